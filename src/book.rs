@@ -0,0 +1,253 @@
+//! Books of named top-level definitions.
+//!
+//! A program is a sequence of `def name = term;` declarations, optionally
+//! followed by a main term:
+//!
+//! ```text
+//! def id = λx x;
+//! def self = λf (f f);
+//! (self id)
+//! ```
+//!
+//! Once loaded into a [`Book`], a free variable named after a definition is a
+//! [`Term::Ref`] to it, so definitions can refer to each other and to
+//! themselves. A graph built with [`TermGraph::from_book`] keeps references
+//! as leaves, and only builds the graph of a definition when a reference to
+//! it is applied, duplicated, or is a root, so recursive definitions only
+//! unfold as far as reduction needs them to.
+//!
+//! [`TermGraph::from_book`]: crate::vm::TermGraph::from_book
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::hvm::parse_hvm;
+use crate::intern::{IStr, Intern};
+use crate::parse::{parse_defs, parse_program, Program};
+use crate::syntax::Term;
+use crate::vm::Header;
+
+/// A definition of a [`Book`].
+///
+/// Graphs point at the definitions of their book, so a definition starts with
+/// the header that tells a graph's `Ref` pointers from its other pointers.
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub(crate) struct Def {
+    header: Header,
+    pub(crate) name: IStr,
+    pub(crate) term: Term,
+}
+
+/// A set of named terms that refer to each other by [`Term::Ref`].
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    defs: Vec<Def>,
+    /// The index in `defs` of each definition.
+    index: HashMap<IStr, usize>,
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines (or redefines) `name` as `term`.
+    ///
+    /// The free variables of `term` are kept as they are. Use
+    /// [`Book::resolve`] to turn those naming definitions into references.
+    pub fn define(&mut self, name: &str, term: Term) {
+        self.insert(name.intern(), term);
+    }
+
+    fn insert(&mut self, name: IStr, term: Term) {
+        match self.index.get(&name) {
+            Some(&i) => self.defs[i].term = term,
+            None => {
+                self.index.insert(name, self.defs.len());
+                self.defs.push(Def {
+                    header: Header::REF,
+                    name,
+                    term,
+                });
+            }
+        }
+    }
+
+    /// Returns the term defined as `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<&Term> {
+        self.def(name.intern()).map(|def| &def.term)
+    }
+
+    /// Returns the names of the definitions, in the order they were first
+    /// defined.
+    pub fn names(&self) -> impl Iterator<Item = IStr> + '_ {
+        self.defs.iter().map(|def| def.name)
+    }
+
+    /// Returns the number of definitions.
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    pub(crate) fn def(&self, name: IStr) -> Option<&Def> {
+        self.index.get(&name).map(|&i| &self.defs[i])
+    }
+
+    /// Returns the name and term of each definition, in the order they were
+    /// first defined, for editing the terms in place.
+    pub(crate) fn defs_mut(&mut self) -> impl Iterator<Item = (IStr, &mut Term)> {
+        self.defs.iter_mut().map(|def| (def.name, &mut def.term))
+    }
+
+    /// Defines (or redefines) each name of `defs` as its term, then
+    /// [resolves](Book::resolve) every definition, as [`Book::load`] does.
+    ///
+    /// A definition that named one of `defs` as a free variable before refers
+    /// to it from then on.
+    pub fn add_definitions(&mut self, defs: impl IntoIterator<Item = (IStr, Term)>) {
+        for (name, term) in defs {
+            self.insert(name, term);
+        }
+        for i in 0..self.defs.len() {
+            self.defs[i].term = self.resolve(&self.defs[i].term);
+        }
+    }
+
+    /// Returns `term` with each free variable that names a definition of the
+    /// book replaced by a reference to it.
+    pub fn resolve(&self, term: &Term) -> Term {
+        fn go(book: &Book, term: &Term, bound: &mut Vec<IStr>) -> Term {
+            match term {
+                Term::Var(x) if !bound.contains(x) && book.index.contains_key(x) => Term::Ref(*x),
+                Term::Var(_) | Term::Ref(_) | Term::Num(_) => term.clone(),
+                Term::Lam(x, e) => {
+                    bound.push(*x);
+                    let e = go(book, e, bound);
+                    bound.pop();
+                    Term::Lam(*x, Box::new(e))
+                }
+                Term::App(e1, e2) => {
+                    let e1 = go(book, e1, bound);
+                    let e2 = go(book, e2, bound);
+                    Term::App(Box::new(e1), Box::new(e2))
+                }
+                Term::Sup(l, e1, e2) => {
+                    let e1 = go(book, e1, bound);
+                    let e2 = go(book, e2, bound);
+                    Term::Sup(*l, Box::new(e1), Box::new(e2))
+                }
+                Term::Op2(op, e1, e2) => {
+                    let e1 = go(book, e1, bound);
+                    let e2 = go(book, e2, bound);
+                    Term::Op2(*op, Box::new(e1), Box::new(e2))
+                }
+                Term::Dup(l, a, b, e, cont) => {
+                    let e = go(book, e, bound);
+                    bound.push(*a);
+                    bound.push(*b);
+                    let cont = go(book, cont, bound);
+                    bound.truncate(bound.len() - 2);
+                    Term::Dup(*l, *a, *b, Box::new(e), Box::new(cont))
+                }
+                Term::Let(x, e, cont) => {
+                    let e = go(book, e, bound);
+                    bound.push(*x);
+                    let cont = go(book, cont, bound);
+                    bound.pop();
+                    Term::Let(*x, Box::new(e), Box::new(cont))
+                }
+            }
+        }
+        go(self, term, &mut vec![])
+    }
+
+    /// Parses a program (see the [module docs](self)), adds its definitions
+    /// to the book, and returns its main term, if it has one.
+    ///
+    /// Definitions replace any of the same name already in the book, and every
+    /// definition and the main term are then [resolved](Book::resolve), so
+    /// the definitions of a program can refer to each other in any order.
+    /// Returns an error if the program does not parse, or defines a name
+    /// twice.
+    pub fn load(&mut self, src: &str) -> Result<Option<Term>, Error> {
+        Ok(self.add_program(parse_program(src)?))
+    }
+
+    /// Like [`Book::load`], but parses an HVM1 program (see [`crate::hvm`]),
+    /// whose rules are defined under their own names.
+    pub fn load_hvm(&mut self, src: &str) -> Result<Option<Term>, Error> {
+        Ok(self.add_program(parse_hvm(src)?))
+    }
+
+    fn add_program(&mut self, (defs, main): Program) -> Option<Term> {
+        self.add_definitions(defs);
+        main.map(|term| self.resolve(&term))
+    }
+}
+
+/// Parses a program of definitions only, without a main term.
+impl FromStr for Book {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut book = Book::new();
+        book.add_definitions(parse_defs(s)?);
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::intern::InternStatic;
+
+    #[test]
+    fn test_load() {
+        let mut book = Book::new();
+        let main = book
+            .load("def self = λf (f f); def id = λx x;\n(self id)")
+            .unwrap()
+            .unwrap();
+        let names: Vec<_> = book.names().map(|name| name.to_string()).collect();
+        assert_eq!(names, ["self", "id"]);
+        assert_eq!(
+            main,
+            Term::App(
+                Box::new(Term::Ref("self".intern_static())),
+                Box::new(Term::Ref("id".intern_static())),
+            )
+        );
+        // A bound variable shadows a definition.
+        let id = book.resolve(&"λid id".parse().unwrap());
+        assert!(id.refs().is_empty());
+        assert_eq!(book.get("id"), Some(&"λx x".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let syntax_error = |src: &str| match src.parse::<Book>() {
+            Err(Error::Syntax(err)) => (err.line, err.column, err.expected, err.found),
+            result => panic!("{}: {:?}", src, result),
+        };
+        let (line, column, _, found) = syntax_error("def a = λx x;\ndef a = λy y;");
+        assert_eq!((line, column, found.as_str()), (2, 5, "`a`"));
+        assert_eq!(
+            syntax_error("def a = λx x; a"),
+            (1, 15, "`def`".to_string(), "`a`".to_string())
+        );
+        let mut book = Book::new();
+        assert!(matches!(
+            book.load("def a = λx x; def b = a; def a = λy y; b"),
+            Err(Error::Syntax(_))
+        ));
+        assert!(matches!("def a = ;".parse::<Book>(), Err(Error::Syntax(_))));
+        let book: Book = "def loop = λx (loop x);".parse().unwrap();
+        assert_eq!(book.get("loop").unwrap().refs().len(), 1);
+    }
+}
@@ -0,0 +1,275 @@
+//! Turns ordinary lambda terms, whose variables may be used any number of
+//! times, into affine terms that can be turned into graphs.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::intern::{IStr, Intern};
+use crate::syntax::{LabelGen, Term};
+
+/// Returns `term` with each variable that is used more than once copied by a
+/// chain of dups, one per extra use, so that every copy is used once.
+///
+/// A variable used `n` times is copied right where it is bound, e.g.
+/// `λx (x x x)` becomes
+/// `λx dup #0{x0 x3} = x; dup #1{x1 x2} = x3; ((x0 x1) x2)`, and a free
+/// variable used more than once is copied at the root. The copies take fresh
+/// names, and the dups take labels that the term does not use. Other
+/// variables are left alone, so an affine term is returned unchanged.
+///
+/// Each inserted dup has its own label, which is enough to copy values that
+/// are used independently, but, as always with dups, not every lambda term
+/// reduces to its normal form this way: a copy of a function that is applied
+/// to another copy of itself is taken apart by the dups of the original.
+pub fn desugar(term: &Term) -> Term {
+    desugar_with(term, &mut LabelGen::avoiding(term))
+}
+
+/// Like [`desugar`], but takes the labels of the inserted dups from `labels`,
+/// e.g. to desugar the definitions of a program without their labels
+/// clashing.
+pub fn desugar_with(term: &Term, labels: &mut LabelGen) -> Term {
+    let mut desugar = Desugar {
+        labels,
+        uses: vec![],
+        free: vec![],
+        taken: HashSet::new(),
+        scope: vec![],
+        copies: HashMap::new(),
+    };
+    desugar.count(term);
+    // Free variables are bound at the root, numbered after the binders of the
+    // term.
+    let free = std::mem::take(&mut desugar.free)
+        .into_iter()
+        .map(|(name, uses)| {
+            desugar.uses.push(uses);
+            (name, desugar.uses.len() - 1)
+        })
+        .collect();
+    let mut next = 0;
+    desugar.bind(free, term, &mut next)
+}
+
+struct Desugar<'l> {
+    labels: &'l mut LabelGen,
+    /// The number of uses of each binder, in the order they are visited.
+    uses: Vec<usize>,
+    /// The free variables and their numbers of uses, in the order they are
+    /// first used.
+    free: Vec<(IStr, usize)>,
+    /// Every name used in the term, and every name made up for it so far.
+    taken: HashSet<IStr>,
+    /// The binders in scope, innermost last.
+    scope: Vec<(IStr, usize)>,
+    /// For each binder used more than once, the names of its copies that have
+    /// not been used yet, last first.
+    copies: HashMap<usize, Vec<IStr>>,
+}
+
+impl Desugar<'_> {
+    /// Counts the uses of each binder of `term`, and collects its names.
+    fn count(&mut self, term: &Term) {
+        match term {
+            Term::Var(x) => {
+                self.taken.insert(*x);
+                match self.scope.iter().rev().find(|(name, _)| name == x) {
+                    Some(&(_, id)) => self.uses[id] += 1,
+                    None => match self.free.iter_mut().find(|(name, _)| name == x) {
+                        Some((_, uses)) => *uses += 1,
+                        None => self.free.push((*x, 1)),
+                    },
+                }
+            }
+            Term::Ref(_) | Term::Num(_) => {}
+            Term::Lam(x, e) => self.count_bind(&[*x], e),
+            Term::App(e1, e2) | Term::Sup(_, e1, e2) | Term::Op2(_, e1, e2) => {
+                self.count(e1);
+                self.count(e2);
+            }
+            Term::Dup(_, a, b, e, cont) => {
+                self.count(e);
+                self.count_bind(&[*a, *b], cont);
+            }
+            Term::Let(x, e, cont) => {
+                self.count(e);
+                self.count_bind(&[*x], cont);
+            }
+        }
+    }
+
+    fn count_bind(&mut self, names: &[IStr], body: &Term) {
+        let depth = self.scope.len();
+        for name in names {
+            self.taken.insert(*name);
+            self.scope.push((*name, self.uses.len()));
+            self.uses.push(0);
+        }
+        self.count(body);
+        self.scope.truncate(depth);
+    }
+
+    /// Returns `term` with its variables renamed to their copies, and dups
+    /// inserted under its binders. `next` is the id of the next binder, in
+    /// the order of [`count`](Self::count).
+    fn rewrite(&mut self, term: &Term, next: &mut usize) -> Term {
+        fn binders(names: &[IStr], next: &mut usize) -> Vec<(IStr, usize)> {
+            let ids = *next..*next + names.len();
+            *next += names.len();
+            names.iter().copied().zip(ids).collect()
+        }
+        match term {
+            Term::Var(x) => match self.scope.iter().rev().find(|(name, _)| name == x) {
+                Some((_, id)) => match self.copies.get_mut(id) {
+                    Some(copies) => Term::Var(copies.pop().unwrap()),
+                    None => Term::Var(*x),
+                },
+                None => Term::Var(*x),
+            },
+            Term::Ref(name) => Term::Ref(*name),
+            Term::Num(n) => Term::Num(*n),
+            Term::Lam(x, e) => {
+                let bound = binders(&[*x], next);
+                let e = self.bind(bound, e, next);
+                Term::Lam(*x, Box::new(e))
+            }
+            Term::App(e1, e2) => {
+                let e1 = self.rewrite(e1, next);
+                Term::App(Box::new(e1), Box::new(self.rewrite(e2, next)))
+            }
+            Term::Sup(l, e1, e2) => {
+                let e1 = self.rewrite(e1, next);
+                Term::Sup(*l, Box::new(e1), Box::new(self.rewrite(e2, next)))
+            }
+            Term::Op2(op, e1, e2) => {
+                let e1 = self.rewrite(e1, next);
+                Term::Op2(*op, Box::new(e1), Box::new(self.rewrite(e2, next)))
+            }
+            Term::Dup(l, a, b, e, cont) => {
+                let e = self.rewrite(e, next);
+                let bound = binders(&[*a, *b], next);
+                let cont = self.bind(bound, cont, next);
+                Term::Dup(*l, *a, *b, Box::new(e), Box::new(cont))
+            }
+            Term::Let(x, e, cont) => {
+                let e = self.rewrite(e, next);
+                let bound = binders(&[*x], next);
+                let cont = self.bind(bound, cont, next);
+                Term::Let(*x, Box::new(e), Box::new(cont))
+            }
+        }
+    }
+
+    /// Rewrites `body` in the scope of `bound`, and copies each of those
+    /// binders that is used more than once with a chain of dups wrapped around
+    /// the result.
+    fn bind(&mut self, bound: Vec<(IStr, usize)>, body: &Term, next: &mut usize) -> Term {
+        let depth = self.scope.len();
+        let mut chains = vec![];
+        for &(name, id) in &bound {
+            if self.uses[id] > 1 {
+                let copies: Vec<IStr> = (0..self.uses[id]).map(|_| self.fresh_name(name)).collect();
+                self.copies
+                    .insert(id, copies.iter().rev().copied().collect());
+                chains.push((name, copies));
+            }
+            self.scope.push((name, id));
+        }
+        let mut body = self.rewrite(body, next);
+        self.scope.truncate(depth);
+        for (name, copies) in chains.into_iter().rev() {
+            body = self.chain(name, &copies, body);
+        }
+        body
+    }
+
+    /// Returns `body` under a chain of dups that copies `name` into `copies`.
+    fn chain(&mut self, name: IStr, copies: &[IStr], body: Term) -> Term {
+        let last = copies.len() - 1;
+        let mut dups = vec![];
+        let mut source = name;
+        for (i, copy) in copies[..last].iter().enumerate() {
+            let rest = match i + 1 == last {
+                true => copies[last],
+                false => self.fresh_name(name),
+            };
+            dups.push((self.labels.fresh(), *copy, rest, source));
+            source = rest;
+        }
+        dups.into_iter()
+            .rev()
+            .fold(body, |body, (l, a, b, source)| {
+                Term::Dup(l, a, b, Box::new(Term::Var(source)), Box::new(body))
+            })
+    }
+
+    /// Returns a name made of `base` and a number that is not used anywhere
+    /// in the term.
+    fn fresh_name(&mut self, base: IStr) -> IStr {
+        (0..)
+            .map(|n| format!("{}{}", base, n).intern())
+            .find(|name| self.taken.insert(*name))
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vm::TermGraph;
+
+    fn desugared(src: &str) -> Term {
+        desugar(&src.parse().unwrap())
+    }
+
+    #[test]
+    fn test_desugar() {
+        let parse = |src: &str| src.parse::<Term>().unwrap();
+        assert_eq!(
+            desugared("λx (x x x)"),
+            parse("λx dup #0{x0 x3} = x; dup #1{x1 x2} = x3; ((x0 x1) x2)")
+        );
+        assert_eq!(
+            desugared("λf λx (f (f x))"),
+            parse("λf dup #0{f0 f1} = f; λx (f0 (f1 x))")
+        );
+        // Free variables are copied at the root, and fresh names and labels
+        // avoid those already in the term.
+        assert_eq!(
+            desugared("#3{(y y) y0}"),
+            parse("dup #4{y1 y2} = y; #3{(y1 y2) y0}")
+        );
+        // Shadowed binders are counted separately.
+        assert_eq!(
+            desugared("λx (x λx (x x))"),
+            parse("λx (x λx dup #0{x0 x1} = x; (x0 x1))")
+        );
+        // Affine terms are unchanged.
+        let affine = parse("λx dup #0{a b} = x; let y = a; (y b)");
+        assert_eq!(desugar(&affine), affine);
+    }
+
+    #[test]
+    fn test_desugar_shared_labels() {
+        let mut labels = LabelGen::new();
+        let two = desugar_with(&"λf λx (f (f x))".parse().unwrap(), &mut labels);
+        let square = desugar_with(&"λn λf (n (n f))".parse().unwrap(), &mut labels);
+        assert_eq!(two, "λf dup #0{f0 f1} = f; λx (f0 (f1 x))".parse().unwrap());
+        assert_eq!(
+            square,
+            "λn dup #1{n0 n1} = n; λf (n0 (n1 f))".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_desugar_reduce() {
+        for (src, expected) in [
+            ("((λf λx (f (f x)) λy y) z)", "z"),
+            ("(λx #0{x x} λy y)", "#0{(λv1 v1) (λv2 v2)}"),
+            ("(λn (+ n (* n n)) 3)", "12"),
+        ] {
+            let mut term_graph = TermGraph::from(&desugared(src));
+            while term_graph.naive_reduce_step().is_some() {}
+            assert_eq!(format!("{}", Term::from(&term_graph)), expected, "{}", src);
+        }
+    }
+}
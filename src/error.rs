@@ -0,0 +1,211 @@
+use std::{fmt, io};
+
+use crate::intern::IStr;
+use crate::syntax::Label;
+use crate::vm::MAX_LABEL;
+
+/// The errors returned by this crate's fallible operations.
+#[derive(Debug)]
+pub enum Error {
+    /// Source text, such as a term, a program, or a line-oriented format like
+    /// a session or a script, could not be parsed.
+    Syntax(ParseError),
+    /// A term is not well formed, so no graph can be built for it.
+    Build(GraphBuildError),
+    /// A graph could not be built or edited as asked, for example because of
+    /// duplicate roots or an unknown node.
+    Graph(String),
+    /// Reduction was stopped because the next rewrite would have taken its
+    /// total cost above `limit`, after `spent` had been spent.
+    CostLimit { limit: u64, spent: u64 },
+    /// Reduction was stopped after `step` steps because the graph had come
+    /// back to an earlier state, so it would repeat every `period` steps
+    /// forever.
+    Cycle { step: usize, period: usize },
+    /// Reading or writing failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(err) => write!(f, "parse error: {}", err),
+            Error::Build(err) => write!(f, "graph error: {}", err),
+            Error::Graph(message) => write!(f, "graph error: {}", message),
+            Error::CostLimit { limit, spent } => {
+                write!(f, "cost limit {} exceeded after spending {}", limit, spent)
+            }
+            Error::Cycle { step, period } => write!(
+                f,
+                "reduction is cycling with period {} (detected after {} steps)",
+                period, step
+            ),
+            Error::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+impl Error {
+    /// Returns this error, from parsing `part` on its own, with a syntax
+    /// error moved to where `part` is in `code` (see [`ParseError::within`]).
+    pub(crate) fn within(self, code: &str, part: &str) -> Self {
+        match self {
+            Error::Syntax(err) => Error::Syntax(err.within(code, part)),
+            err => err,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Syntax(err) => Some(err),
+            Error::Build(err) => Some(err),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Syntax(err)
+    }
+}
+
+impl From<GraphBuildError> for Error {
+    fn from(err: GraphBuildError) -> Self {
+        Error::Build(err)
+    }
+}
+
+/// Where and why source text failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset of the failure in the source.
+    pub offset: usize,
+    /// The line of the failure, counting from 1.
+    pub line: usize,
+    /// The column of the failure in characters, counting from 1.
+    pub column: usize,
+    /// What the parser expected at `offset`, e.g. "`)`" or "term".
+    pub expected: String,
+    /// What it found there instead, e.g. "`x`" or "end of input".
+    pub found: String,
+}
+
+impl ParseError {
+    /// Returns the error for failing at byte `offset` of `code`.
+    pub fn new(code: &str, offset: usize, expected: String, found: String) -> Self {
+        let before = &code[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        ParseError {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            expected,
+            found,
+        }
+    }
+
+    /// Returns the error for failing at the start of `part`, which must be a
+    /// slice of `code`.
+    pub fn at(code: &str, part: &str, expected: String, found: String) -> Self {
+        Self::new(code, offset_in(code, part), expected, found)
+    }
+
+    /// Returns this error, from parsing `part` on its own, moved to where
+    /// `part` is in `code`, of which it must be a slice.
+    pub fn within(self, code: &str, part: &str) -> Self {
+        let offset = offset_in(code, part) + self.offset;
+        Self::new(code, offset, self.expected, self.found)
+    }
+}
+
+/// Returns the byte offset of `part`, a slice of `code`, in `code`.
+fn offset_in(code: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize).wrapping_sub(code.as_ptr() as usize);
+    assert!(
+        offset <= code.len().saturating_sub(part.len()),
+        "not a slice of the code"
+    );
+    offset
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found {}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why no graph can be built for a term, with the name at fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphBuildError {
+    /// A bound variable is used more than once.
+    UsedTwice(IStr),
+    /// A dup binds the same variable as both of its variables.
+    BoundTwice(IStr),
+    /// A variable of a dup is used in the expression it is bound to.
+    UsedInOwnExpression(IStr),
+    /// A reference names a definition that the book does not define.
+    UndefinedRef(IStr),
+    /// A label is larger than a node can hold (see
+    /// [`MAX_LABEL`](MAX_LABEL)).
+    LabelTooLarge(Label),
+}
+
+impl fmt::Display for GraphBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphBuildError::UsedTwice(x) => write!(f, "variable {} is used more than once", x),
+            GraphBuildError::BoundTwice(x) => write!(f, "dup binds {} twice", x),
+            GraphBuildError::UsedInOwnExpression(x) => {
+                write!(f, "variable {} is used in the expression it is bound to", x)
+            }
+            GraphBuildError::UndefinedRef(name) => {
+                write!(f, "reference to undefined definition {}", name)
+            }
+            GraphBuildError::LabelTooLarge(l) => {
+                write!(f, "label {} is larger than {}", l, MAX_LABEL)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphBuildError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_question_mark() {
+        fn parse_and_write(src: &str, out: &mut impl io::Write) -> Result<(), Error> {
+            let term: Term = src.parse()?;
+            write!(out, "{}", term)?;
+            Ok(())
+        }
+        let mut out = vec![];
+        parse_and_write("λx x", &mut out).unwrap();
+        assert_eq!(out, "(λx x)".as_bytes());
+        let err = parse_and_write("(x", &mut out).unwrap_err();
+        assert!(matches!(err, Error::Syntax(_)));
+        assert!(err.to_string().starts_with("parse error: "));
+        let err = parse_and_write("x", &mut [0u8; 0].as_mut_slice()).unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
@@ -0,0 +1,699 @@
+//! A reference evaluator, for differential testing of the graph reducer.
+//!
+//! [`eval`] reduces a [`Term`] by rewriting it as a tree and substituting
+//! variables, with none of the pointer machinery of [`crate::vm`]. It is slow,
+//! but simple enough to check by reading, so a term on which it and
+//! [`TermGraph`] disagree points at a bug in a rewrite rule of the VM.
+//!
+//! The variables of the interaction calculus are global: after a `DupLam`, the
+//! variable of a lambda is used in a superposition outside of its body. So the
+//! evaluator renames every binder apart, keeps dups in a table of their own
+//! rather than in the tree, and substitutes a variable wherever in the term or
+//! the table it is used. Dups are only put back into the tree when the normal
+//! form is read back, around the smallest subterm that holds every use of
+//! their variables.
+//!
+//! [`TermGraph`]: crate::vm::TermGraph
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::book::Book;
+use crate::error::Error;
+use crate::intern::{IStr, Intern, InternStatic};
+use crate::syntax::{Label, Term};
+use crate::vm::{check_refs, validate};
+
+/// Reduces `term` to normal form, performing at most `max_steps` rewrites.
+///
+/// The rewrites are the same as those of [`TermGraph`], including the erasure
+/// of the subterms they discard, so the normal form is the one the graph
+/// reads back, up to the names of variables and where dups are placed.
+/// Comparing the [structural hashes] of the two graphs built from the normal
+/// forms is a way to check that they agree.
+///
+/// Returns an error if `term` is not well formed (see
+/// [`TermGraph::try_from_term`]), or if it is not in normal form after
+/// `max_steps` rewrites.
+///
+/// [`TermGraph`]: crate::vm::TermGraph
+/// [`TermGraph::try_from_term`]: crate::vm::TermGraph::try_from_term
+/// [structural hashes]: crate::vm::TermGraph::structural_hash
+pub fn eval(term: &Term, max_steps: u64) -> Result<Term, Error> {
+    eval_in_book(&Book::default(), term, max_steps)
+}
+
+/// Reduces `term`, whose references are to the definitions of `book`, to
+/// normal form, like [`eval`].
+///
+/// References are unfolded where [`TermGraph::from_book`] unfolds them: at the
+/// root, in the function of an application, in the expression of a dup, and in
+/// an operand of an operation that needs its value.
+///
+/// [`TermGraph::from_book`]: crate::vm::TermGraph::from_book
+pub fn eval_in_book(book: &Book, term: &Term, max_steps: u64) -> Result<Term, Error> {
+    let mut taken = term.free_vars();
+    for name in book.names() {
+        let def = &book.def(name).unwrap().term;
+        validate(def)?;
+        check_refs(book, def)?;
+        taken.extend(def.free_vars());
+    }
+    validate(term)?;
+    check_refs(book, term)?;
+    let mut evaluator = Evaluator {
+        book,
+        main: Term::Var(IStr::EMPTY),
+        dups: BTreeMap::new(),
+        dup_vars: HashMap::new(),
+        taken,
+        pending: vec![],
+        next_var: 0,
+        next_dup: 0,
+    };
+    evaluator.main = evaluator.load(term, &mut vec![]);
+    let mut steps = 0;
+    while let Some(site) = evaluator.find_redex() {
+        if steps == max_steps {
+            return Err(Error::CostLimit {
+                limit: max_steps,
+                spent: steps,
+            });
+        }
+        evaluator.rewrite(site);
+        steps += 1;
+    }
+    Ok(evaluator.read_back())
+}
+
+/// A dup of the table: `dup #label{vars[0] vars[1]} = e`.
+struct Dup {
+    label: Label,
+    vars: [IStr; 2],
+    /// Whether each variable is still used somewhere.
+    used: [bool; 2],
+    e: Term,
+}
+
+/// Where a redex is.
+enum Site {
+    /// At the end of a path of child indices from the root of the term.
+    Main(Vec<usize>),
+    /// At the end of a path of child indices from the expression of a dup.
+    Expr(usize, Vec<usize>),
+    /// The dup itself, whose expression is a lambda, superposition, number,
+    /// or reference.
+    Dup(usize),
+}
+
+struct Evaluator<'b> {
+    book: &'b Book,
+    main: Term,
+    dups: BTreeMap<usize, Dup>,
+    /// The dup binding each variable of a dup, and which of its two variables
+    /// it is.
+    dup_vars: HashMap<IStr, (usize, usize)>,
+    /// The free variables of the term and the definitions, which fresh
+    /// variables must not be named after.
+    taken: HashSet<IStr>,
+    /// The substitutions left to perform for the last rewrite. The value of
+    /// one may hold the use of the variable of another, so they are searched
+    /// for uses too.
+    pending: Vec<(IStr, Term)>,
+    next_var: usize,
+    next_dup: usize,
+}
+
+impl Evaluator<'_> {
+    fn fresh(&mut self) -> IStr {
+        loop {
+            let name = format!("x{}", self.next_var).intern();
+            self.next_var += 1;
+            if !self.taken.contains(&name) {
+                return name;
+            }
+        }
+    }
+
+    fn new_dup(&mut self, label: Label, vars: [IStr; 2], used: [bool; 2], e: Term) -> usize {
+        let id = self.next_dup;
+        self.next_dup += 1;
+        self.dup_vars.insert(vars[0], (id, 0));
+        self.dup_vars.insert(vars[1], (id, 1));
+        self.dups.insert(
+            id,
+            Dup {
+                label,
+                vars,
+                used,
+                e,
+            },
+        );
+        id
+    }
+
+    fn remove_dup(&mut self, id: usize) -> Dup {
+        let dup = self.dups.remove(&id).unwrap();
+        for var in dup.vars {
+            self.dup_vars.remove(&var);
+        }
+        dup
+    }
+
+    /// Returns `term` with its binders renamed apart and its dups moved to the
+    /// table. `scope` maps the binders in scope to their new names.
+    fn load(&mut self, term: &Term, scope: &mut Vec<(IStr, IStr)>) -> Term {
+        match term {
+            Term::Var(x) => match scope.iter().rev().find(|(name, _)| name == x) {
+                Some(&(_, var)) => {
+                    if let Some(&(id, side)) = self.dup_vars.get(&var) {
+                        self.dups.get_mut(&id).unwrap().used[side] = true;
+                    }
+                    Term::Var(var)
+                }
+                None => Term::Var(*x),
+            },
+            Term::Ref(_) | Term::Num(_) => term.clone(),
+            Term::Lam(x, e) => {
+                let var = self.fresh();
+                scope.push((*x, var));
+                let e = self.load(e, scope);
+                scope.pop();
+                Term::Lam(var, Box::new(e))
+            }
+            Term::App(e1, e2) => {
+                let e1 = self.load(e1, scope);
+                let e2 = self.load(e2, scope);
+                Term::App(Box::new(e1), Box::new(e2))
+            }
+            Term::Sup(l, e1, e2) => {
+                let e1 = self.load(e1, scope);
+                let e2 = self.load(e2, scope);
+                Term::Sup(*l, Box::new(e1), Box::new(e2))
+            }
+            Term::Op2(op, e1, e2) => {
+                let e1 = self.load(e1, scope);
+                let e2 = self.load(e2, scope);
+                Term::Op2(*op, Box::new(e1), Box::new(e2))
+            }
+            Term::Dup(l, a, b, e, cont) => {
+                let e = self.load(e, scope);
+                let vars = [self.fresh(), self.fresh()];
+                let id = self.new_dup(*l, vars, [false, false], e);
+                scope.push((*a, vars[0]));
+                scope.push((*b, vars[1]));
+                let cont = self.load(cont, scope);
+                scope.truncate(scope.len() - 2);
+                if self.dups[&id].used == [false, false] {
+                    let dup = self.remove_dup(id);
+                    self.erase(dup.e);
+                }
+                cont
+            }
+            Term::Let(x, e, cont) => {
+                let e = self.load(e, scope);
+                let var = self.fresh();
+                scope.push((*x, var));
+                let cont = self.load(cont, scope);
+                scope.pop();
+                Term::Let(var, Box::new(e), Box::new(cont))
+            }
+        }
+    }
+
+    /// Returns a fresh copy of the definition named `name`.
+    fn unfold(&mut self, name: IStr) -> Term {
+        let book = self.book;
+        self.load(&book.def(name).unwrap().term, &mut vec![])
+    }
+
+    /// Returns the outermost redex of the term, or else of the expression of
+    /// the first dup that has one.
+    fn find_redex(&self) -> Option<Site> {
+        fn find(term: &Term, path: &mut Vec<usize>) -> bool {
+            let is_redex = match term {
+                Term::App(f, _) => matches!(**f, Term::Lam(..) | Term::Sup(..) | Term::Ref(_)),
+                Term::Op2(_, e1, e2) => match (&**e1, &**e2) {
+                    (Term::Sup(..) | Term::Ref(_), _) => true,
+                    (Term::Num(_), e2) => {
+                        matches!(e2, Term::Num(_) | Term::Sup(..) | Term::Ref(_))
+                    }
+                    _ => false,
+                },
+                Term::Let(..) => true,
+                _ => false,
+            };
+            if is_redex {
+                return true;
+            }
+            for (i, child) in term.children().enumerate() {
+                path.push(i);
+                if find(child, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+
+        let mut path = vec![];
+        if matches!(self.main, Term::Ref(_)) || find(&self.main, &mut path) {
+            return Some(Site::Main(path));
+        }
+        for (&id, dup) in &self.dups {
+            if matches!(
+                dup.e,
+                Term::Lam(..) | Term::Sup(..) | Term::Num(_) | Term::Ref(_)
+            ) {
+                return Some(Site::Dup(id));
+            }
+            if find(&dup.e, &mut path) {
+                return Some(Site::Expr(id, path));
+            }
+        }
+        None
+    }
+
+    fn rewrite(&mut self, site: Site) {
+        match site {
+            Site::Main(path) => {
+                let term = take(subterm(&mut self.main, &path));
+                let term = self.rewrite_term(term);
+                *subterm(&mut self.main, &path) = term;
+            }
+            Site::Expr(id, path) => {
+                let term = take(subterm(&mut self.dups.get_mut(&id).unwrap().e, &path));
+                let term = self.rewrite_term(term);
+                *subterm(&mut self.dups.get_mut(&id).unwrap().e, &path) = term;
+            }
+            Site::Dup(id) => self.rewrite_dup(id),
+        }
+        while !self.pending.is_empty() {
+            let (x, value) = self.pending.remove(0);
+            self.subst(x, value);
+        }
+    }
+
+    /// Rewrites a redex of the tree, returning the subterm to replace it with,
+    /// and adding the substitutions to perform to `pending`.
+    fn rewrite_term(&mut self, mut term: Term) -> Term {
+        match &mut term {
+            // @f
+            // ---- Ref
+            // (the definition of f)
+            Term::Ref(name) => self.unfold(*name),
+            // let x = e; cont
+            // --------------- AppLam
+            // x <- e
+            // cont
+            Term::Let(x, e, cont) => {
+                self.pending.push((*x, take(e)));
+                take(cont)
+            }
+            Term::App(f, arg) => match &mut **f {
+                // ((λx body) arg)
+                // --------------- AppLam
+                // x <- arg
+                // body
+                Term::Lam(x, body) => {
+                    self.pending.push((*x, take(arg)));
+                    take(body)
+                }
+                // (#l{f0 f1} arg)
+                // ------------------------------ AppSup
+                // dup #l{a0 a1} = arg
+                // #l{(f0 a0) (f1 a1)}
+                Term::Sup(l, f0, f1) => {
+                    let vars = [self.fresh(), self.fresh()];
+                    self.new_dup(*l, vars, [true, true], take(arg));
+                    let app0 = Term::App(Box::new(take(f0)), Box::new(Term::Var(vars[0])));
+                    let app1 = Term::App(Box::new(take(f1)), Box::new(Term::Var(vars[1])));
+                    Term::Sup(*l, Box::new(app0), Box::new(app1))
+                }
+                Term::Ref(name) => {
+                    let name = *name;
+                    Term::App(Box::new(self.unfold(name)), Box::new(take(arg)))
+                }
+                _ => unreachable!("{}", term),
+            },
+            Term::Op2(op, e1, e2) => match (&mut **e1, &mut **e2) {
+                (Term::Ref(name), _) => {
+                    let name = *name;
+                    Term::Op2(*op, Box::new(self.unfold(name)), Box::new(take(e2)))
+                }
+                (Term::Num(_), Term::Ref(name)) => {
+                    let name = *name;
+                    Term::Op2(*op, Box::new(take(e1)), Box::new(self.unfold(name)))
+                }
+                // (op n m)
+                // -------- OpNum
+                // (n op m)
+                (Term::Num(n), Term::Num(m)) => Term::Num(op.apply(*n, *m)),
+                // (op #l{e1 e2} e3)
+                // ----------------------- OpSup
+                // dup #l{a0 a1} = e3
+                // #l{(op e1 a0) (op e2 a1)}
+                (Term::Sup(l, f0, f1), e3) => {
+                    let vars = [self.fresh(), self.fresh()];
+                    self.new_dup(*l, vars, [true, true], take(e3));
+                    let op0 = Term::Op2(*op, Box::new(take(f0)), Box::new(Term::Var(vars[0])));
+                    let op1 = Term::Op2(*op, Box::new(take(f1)), Box::new(Term::Var(vars[1])));
+                    Term::Sup(*l, Box::new(op0), Box::new(op1))
+                }
+                // (op n #l{e1 e2})
+                // ----------------------- OpSup
+                // #l{(op n e1) (op n e2)}
+                (Term::Num(n), Term::Sup(l, f0, f1)) => {
+                    let num = || Box::new(Term::Num(*n));
+                    let op0 = Term::Op2(*op, num(), Box::new(take(f0)));
+                    let op1 = Term::Op2(*op, num(), Box::new(take(f1)));
+                    Term::Sup(*l, Box::new(op0), Box::new(op1))
+                }
+                _ => unreachable!("{}", term),
+            },
+            _ => unreachable!("{}", term),
+        }
+    }
+
+    /// Rewrites a dup whose expression is a lambda, superposition, number, or
+    /// reference, adding the substitutions to perform to `pending`.
+    fn rewrite_dup(&mut self, id: usize) {
+        if let Term::Ref(name) = self.dups[&id].e {
+            self.dups.get_mut(&id).unwrap().e = self.unfold(name);
+            return;
+        }
+        let mut dup = self.remove_dup(id);
+        let l = dup.label;
+        let [a, b] = dup.vars;
+        match &mut dup.e {
+            // dup #l{a b} = λx f
+            // ------------------ DupLam
+            // a <- λx0 f0
+            // b <- λx1 f1
+            // x <- #l{x0 x1}
+            // dup #l{f0 f1} = f
+            Term::Lam(x, f) => {
+                let [x0, x1, f0, f1] = [self.fresh(), self.fresh(), self.fresh(), self.fresh()];
+                self.new_dup(l, [f0, f1], [true, true], take(f));
+                let var = |x| Box::new(Term::Var(x));
+                self.pending.push((*x, Term::Sup(l, var(x0), var(x1))));
+                self.pending.push((a, Term::Lam(x0, var(f0))));
+                self.pending.push((b, Term::Lam(x1, var(f1))));
+            }
+            // dup #l{a b} = #l{e1 e2}
+            // ----------------------- DupSupSame
+            // a <- e1
+            // b <- e2
+            Term::Sup(m, e1, e2) if *m == l => {
+                self.pending.push((a, take(e1)));
+                self.pending.push((b, take(e2)));
+            }
+            // dup #l{a b} = #m{e1 e2}
+            // ----------------------- DupSupDiff
+            // a <- #m{a0 b0}
+            // b <- #m{a1 b1}
+            // dup #l{a0 a1} = e1
+            // dup #l{b0 b1} = e2
+            Term::Sup(m, e1, e2) => {
+                let m = *m;
+                let [a0, a1, b0, b1] = [self.fresh(), self.fresh(), self.fresh(), self.fresh()];
+                self.new_dup(l, [a0, a1], [true, true], take(e1));
+                self.new_dup(l, [b0, b1], [true, true], take(e2));
+                let var = |x| Box::new(Term::Var(x));
+                self.pending.push((a, Term::Sup(m, var(a0), var(b0))));
+                self.pending.push((b, Term::Sup(m, var(a1), var(b1))));
+            }
+            // dup #l{a b} = n
+            // --------------- DupNum
+            // a <- n
+            // b <- n
+            Term::Num(n) => {
+                self.pending.push((a, Term::Num(*n)));
+                self.pending.push((b, Term::Num(*n)));
+            }
+            e => unreachable!("{}", e),
+        }
+    }
+
+    /// Replaces the use of `x` with `value`, or erases `value` if `x` is not
+    /// used.
+    fn subst(&mut self, x: IStr, value: Term) {
+        let trees = std::iter::once(&mut self.main)
+            .chain(self.dups.values_mut().map(|dup| &mut dup.e))
+            .chain(self.pending.iter_mut().map(|(_, value)| value));
+        for tree in trees {
+            let mut stack = vec![tree];
+            while let Some(term) = stack.pop() {
+                if matches!(term, Term::Var(y) if *y == x) {
+                    *term = value;
+                    return;
+                }
+                stack.extend(children_mut(term));
+            }
+        }
+        self.erase(value);
+    }
+
+    /// Erases `term`, like the erase rules of the graph: a dup is erased
+    /// along with its expression once neither of its variables is used, and
+    /// the uses of the variables of erased lambdas that were outside of them
+    /// become free variables.
+    fn erase(&mut self, term: Term) {
+        let mut binders = vec![];
+        let mut stack = vec![term];
+        while let Some(mut term) = stack.pop() {
+            match &term {
+                Term::Var(x) => {
+                    if let Some(&(id, side)) = self.dup_vars.get(x) {
+                        let dup = self.dups.get_mut(&id).unwrap();
+                        dup.used[side] = false;
+                        if dup.used == [false, false] {
+                            stack.push(self.remove_dup(id).e);
+                        }
+                    }
+                }
+                Term::Lam(x, _) | Term::Let(x, _, _) => binders.push(*x),
+                _ => {}
+            }
+            stack.extend(children_mut(&mut term).into_iter().map(take));
+        }
+        for x in binders {
+            let free = Term::Var(self.fresh());
+            self.subst(x, free);
+        }
+    }
+
+    /// Returns the normal form, with each dup placed around the smallest
+    /// subterm that holds every use of its variables.
+    fn read_back(mut self) -> Term {
+        let mut uses: HashMap<IStr, usize> = HashMap::new();
+        let trees = std::iter::once(&self.main).chain(self.dups.values().map(|dup| &dup.e));
+        for tree in trees {
+            let mut stack = vec![tree];
+            while let Some(term) = stack.pop() {
+                if let Term::Var(x) = term {
+                    *uses.entry(*x).or_default() += 1;
+                }
+                stack.extend(term.children());
+            }
+        }
+        let main = take(&mut self.main);
+        let (term, mut counts) = self.place_dups(main, &uses);
+        self.wrap_dups(term, &mut counts, &uses, true)
+    }
+
+    /// Returns `term` with the dups of the table placed in it, and the number
+    /// of uses in it of the variables of each dup still in the table.
+    fn place_dups(
+        &mut self,
+        mut term: Term,
+        uses: &HashMap<IStr, usize>,
+    ) -> (Term, HashMap<usize, usize>) {
+        let mut counts = HashMap::new();
+        match &mut term {
+            Term::Var(x) => {
+                if let Some(&(id, _)) = self.dup_vars.get(x) {
+                    counts.insert(id, 1);
+                }
+            }
+            Term::Lam(x, _) | Term::Let(x, _, _) if !uses.contains_key(x) => {
+                *x = "_".intern_static();
+            }
+            _ => {}
+        }
+        for child in children_mut(&mut term) {
+            let (placed, child_counts) = self.place_dups(take(child), uses);
+            *child = placed;
+            for (id, count) in child_counts {
+                *counts.entry(id).or_default() += count;
+            }
+        }
+        let term = self.wrap_dups(term, &mut counts, uses, false);
+        (term, counts)
+    }
+
+    /// Wraps `term` in the dups whose variables are only used in it, according
+    /// to `counts`, or in all of those used in it if `all` is set, and updates
+    /// `counts` to match.
+    fn wrap_dups(
+        &mut self,
+        mut term: Term,
+        counts: &mut HashMap<usize, usize>,
+        uses: &HashMap<IStr, usize>,
+        all: bool,
+    ) -> Term {
+        loop {
+            let next = counts
+                .iter()
+                .filter(|&(id, &count)| match self.dups.get(id) {
+                    Some(dup) => {
+                        all || count == dup.vars.iter().filter_map(|x| uses.get(x)).sum::<usize>()
+                    }
+                    None => false,
+                })
+                .map(|(&id, _)| id)
+                .min();
+            let Some(id) = next else {
+                return term;
+            };
+            counts.remove(&id);
+            let dup = self.remove_dup(id);
+            let (e, e_counts) = self.place_dups(dup.e, uses);
+            for (id, count) in e_counts {
+                *counts.entry(id).or_default() += count;
+            }
+            let [a, b] = dup.vars.map(|x| {
+                if uses.contains_key(&x) {
+                    x
+                } else {
+                    "_".intern_static()
+                }
+            });
+            term = Term::Dup(dup.label, a, b, Box::new(e), Box::new(term));
+        }
+    }
+}
+
+/// Replaces `term` with a placeholder, returning it.
+fn take(term: &mut Term) -> Term {
+    std::mem::replace(term, Term::Var(IStr::EMPTY))
+}
+
+fn subterm<'t>(mut term: &'t mut Term, path: &[usize]) -> &'t mut Term {
+    for &i in path {
+        term = children_mut(term).swap_remove(i);
+    }
+    term
+}
+
+fn children_mut(term: &mut Term) -> Vec<&mut Term> {
+    match term {
+        Term::Var(_) | Term::Ref(_) | Term::Num(_) => vec![],
+        Term::Lam(_, e) => vec![&mut **e],
+        Term::App(e1, e2)
+        | Term::Sup(_, e1, e2)
+        | Term::Op2(_, e1, e2)
+        | Term::Dup(_, _, _, e1, e2)
+        | Term::Let(_, e1, e2) => vec![&mut **e1, &mut **e2],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::examples;
+    use crate::vm::TermGraph;
+
+    /// Asserts that the evaluator and the graph reduce the program `src` to the
+    /// same normal form.
+    fn assert_agrees(src: &str) {
+        let mut book = Book::new();
+        let term = book.load(src).unwrap().unwrap();
+        let book = Arc::new(book);
+        let normal_form = eval_in_book(&book, &term, 10_000).unwrap();
+        let mut term_graph = TermGraph::from_book(book.clone(), &term).unwrap();
+        while term_graph.naive_reduce_step().is_some() {}
+        let expected = TermGraph::from_book(book, &normal_form).unwrap();
+        assert_eq!(
+            expected.structural_hash(),
+            term_graph.structural_hash(),
+            "{}: {} != {}",
+            src,
+            normal_form,
+            Term::from(&term_graph)
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let term: Term = "((λx λy (y x)) z)".parse().unwrap();
+        assert_eq!(format!("{}", eval(&term, 10).unwrap()), "(λx1 (x1 z))");
+        let term: Term = "(λf dup #0{f1 f2} = f; λx (f1 (f2 x)) λy y)"
+            .parse()
+            .unwrap();
+        assert_eq!(format!("{}", eval(&term, 10).unwrap()), "(λx3 x3)");
+    }
+
+    #[test]
+    fn test_eval_agrees() {
+        let cases = [
+            "((λx λy (y x)) z)",
+            "(λf λx dup #0{f1 f2} = f; (f1 (f2 x)) λy dup #1{y1 y2} = y; #2{y1 y2})",
+            "(#0{λx x λy (y y0)} z)",
+            "dup #0{a b} = #0{x y}; #1{b a}",
+            "dup #0{a b} = #1{x y}; #2{a b}",
+            "dup #0{a b} = λx λy #1{x y}; #2{a b}",
+            "dup #0{a b} = λx (x z); (a b)",
+            "dup #0{a b} = λx x; a",
+            "λx dup #0{a b} = x; #1{b a}",
+            "let x = λy y; (x z)",
+            "((λa λb b) (λx dup #0{p q} = x; (p q)))",
+            "def two = λf dup #0{f1 f2} = f; λx (f1 (f2 x));\n(two λy λz (z y))",
+            "def step = λf (f step);\nλz (step λg λx (x g))",
+            "(* #0{2 3} (+ 1 #1{4 5}))",
+            "dup #0{a b} = (- 10 3); #1{(< a 8) (== b 7)}",
+            "def double = λn dup #2{a b} = n; (+ a b);\n(double (double 5))",
+        ];
+        for src in cases {
+            assert_agrees(src);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_eval_agrees_examples() {
+        for name in examples::names() {
+            let term = examples::load(name).unwrap();
+            let normal_form = eval(&term, 1_000_000).unwrap();
+            let mut term_graph = TermGraph::from(&term);
+            while term_graph.naive_reduce_step().is_some() {}
+            assert_eq!(
+                TermGraph::from(&normal_form).structural_hash(),
+                term_graph.structural_hash(),
+                "{}: {} != {}",
+                name,
+                normal_form,
+                Term::from(&term_graph)
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let term: Term = "λx (x x)".parse().unwrap();
+        assert!(matches!(eval(&term, 10), Err(Error::Build(_))));
+        let mut book = Book::new();
+        let term = book
+            .load("def loop = λx (loop x);\n(loop y)")
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            eval_in_book(&book, &term, 100),
+            Err(Error::CostLimit {
+                limit: 100,
+                spent: 100
+            })
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+//! Example programs, shared by tests, benchmarks, the REPL's `:example`
+//! command, and anyone new to the calculus.
+//!
+//! Each example is a `.ic` file in `src/examples`, embedded at compile time.
+//! The terms are closed, so they can be evaluated without the prelude, and
+//! their directives (see [`TestFile`]) state the expected results, so
+//! `ictest test src/examples` checks them too.
+
+use crate::runtime::TestFile;
+use crate::syntax::Term;
+
+/// The names and sources of the examples.
+const SOURCES: &[(&str, &str)] = &[
+    ("church_arith", include_str!("examples/church_arith.ic")),
+    ("interpreter", include_str!("examples/interpreter.ic")),
+    ("sort", include_str!("examples/sort.ic")),
+];
+
+/// Returns the names of all examples.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    SOURCES.iter().map(|(name, _)| *name)
+}
+
+/// Returns the source of the example named `name`, including its comments
+/// and directives, if there is one.
+pub fn source(name: &str) -> Option<&'static str> {
+    SOURCES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, src)| *src)
+}
+
+/// Returns the term of the example named `name`, if there is one.
+pub fn load(name: &str) -> Option<Term> {
+    source(name).map(|src| TestFile::parse(src).unwrap().term)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_examples_pass() {
+        let runtime = Runtime::new();
+        for name in names() {
+            let test = TestFile::parse(source(name).unwrap()).unwrap();
+            assert!(!test.directives.is_empty(), "{}", name);
+            assert_eq!(runtime.run_test(&test).unwrap(), [], "{}", name);
+        }
+        assert!(load("nope").is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_church_arith() {
+        let mut runtime = Runtime::new();
+        let normal_form = runtime.eval(&load("church_arith").unwrap()).unwrap();
+        // A Scott numeral `λs λz (s n)` is one more than `n`.
+        let mut value = 0;
+        let mut term = &normal_form;
+        while let Term::Lam(_, body) = term {
+            let Term::Lam(_, body) = &**body else { break };
+            let Term::App(_, n) = &**body else { break };
+            term = n;
+            value += 1;
+        }
+        assert_eq!(value, 50);
+    }
+}
@@ -0,0 +1,576 @@
+//! Conversion between terms and the textual syntax of HVM1, so that programs
+//! written for HVM can be run here and their results compared.
+//!
+//! An HVM1 program is a list of rules:
+//!
+//! ```text
+//! // Applies a function twice.
+//! (Twice f x) = dup f0 f1 = f; (f0 (f1 x))
+//! (Main) = (Twice λn (+ n 1) 5)
+//! ```
+//!
+//! A rule whose parameters are all variables is read as a definition of the
+//! function of those parameters, e.g. `Twice` above as
+//! `λf λx dup #0{f0 f1} = f; (f0 (f1 x))`, and a call as the application of
+//! the definition to its arguments. The rule `(Main)` is the main term of the
+//! program. Rules that match patterns, and constructors, have no counterpart
+//! here, and are reported as errors.
+//!
+//! HVM1 does not write labels, so every dup and superposition `{a b}` is
+//! given a label of its own, in the order they appear. Variables may be used
+//! more than once, as in HVM, and are copied with dups (see
+//! [`desugar`](crate::desugar::desugar)). Going the other way,
+//! labels are dropped, so a term whose dups and superpositions rely on
+//! sharing labels may reduce differently in HVM.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::desugar::desugar_with;
+use crate::error::{Error, ParseError};
+use crate::intern::{IStr, Intern};
+use crate::parse::{self, DepthGuard, Program};
+use crate::parser;
+use crate::syntax::{Label, LabelGen, Op, Term};
+
+/// The symbols of the operations of HVM1, longest first, so that none is
+/// taken for the start of a longer one.
+const HVM_OPS: [&str; 16] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "&", "|", "^", "<", ">",
+];
+
+thread_local! {
+    /// The name and offset of each call parsed so far, checked against the
+    /// rules once the whole program has been parsed.
+    static CALLS: RefCell<Vec<(IStr, usize)>> = const { RefCell::new(vec![]) };
+}
+
+fn is_rule_start(chr: char) -> bool {
+    chr.is_ascii_uppercase()
+}
+
+/// Checks if the next character after skipping is `(`, followed by `cond`.
+fn paren_then<'a>(cond: fn(parser::State) -> parser::Answer<bool>) -> parser::Parser<'a, bool> {
+    Box::new(move |state| {
+        let (state, matched) = parser::text("(", state)?;
+        match matched {
+            true => cond(state),
+            false => Ok((state, false)),
+        }
+    })
+}
+
+fn parse_lam(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    let parse_symbol =
+        |x| parser::parser_or(&[parser::text_parser("λ"), parser::text_parser("@")], x);
+    parser::guard(
+        Box::new(parse_symbol),
+        Box::new(move |state| {
+            let (state, _) = parse_symbol(state)?;
+            let (state, name) = parser::name1(state)?;
+            let (state, body) = parse_term(state)?;
+            Ok((state, Box::new(Term::Lam(name.intern(), body))))
+        }),
+        state,
+    )
+}
+
+/// Parses an application, e.g. `(f a b)`.
+fn parse_app(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        parser::text_parser("("),
+        Box::new(|state| {
+            let (next, _) = parser::consume("(", state)?;
+            let (next, args) = parser::until(parser::text_parser(")"), Box::new(parse_term), next)?;
+            match args.into_iter().reduce(|a, b| Box::new(Term::App(a, b))) {
+                Some(app) => Ok((next, app)),
+                None => {
+                    let (state, _) = parser::consume("(", state)?;
+                    parser::expected("term", state)
+                }
+            }
+        }),
+        state,
+    )
+}
+
+/// Parses a call of a rule, e.g. `(Twice f x)`, as the application of a
+/// variable named after the rule.
+fn parse_call(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        paren_then(|state| {
+            let (state, _) = parser::skip(state)?;
+            Ok((state, parser::head(state).is_some_and(is_rule_start)))
+        }),
+        Box::new(|state| {
+            let (state, _) = parser::consume("(", state)?;
+            let (state, _) = parser::skip(state)?;
+            let offset = state.index;
+            let (state, name) = parser::name1(state)?;
+            let name = name.intern();
+            CALLS.with(|calls| calls.borrow_mut().push((name, offset)));
+            let (state, args) =
+                parser::until(parser::text_parser(")"), Box::new(parse_term), state)?;
+            let call = args.into_iter().fold(Box::new(Term::Var(name)), |f, arg| {
+                Box::new(Term::App(f, arg))
+            });
+            Ok((state, call))
+        }),
+        state,
+    )
+}
+
+/// Parses the symbol of an HVM1 operation, if there is one after skipping.
+fn parse_op(state: parser::State) -> parser::Answer<Option<&'static str>> {
+    let (state, _) = parser::skip(state)?;
+    for symbol in HVM_OPS {
+        let (next, matched) = parser::text_here(symbol, state)?;
+        if matched {
+            return Ok((next, Some(symbol)));
+        }
+    }
+    Ok((state, None))
+}
+
+/// Parses a binary operation, e.g. `(+ x 1)`, failing on the operations of
+/// HVM1 that this crate does not have.
+fn parse_op2(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        paren_then(|state| {
+            let (state, symbol) = parse_op(state)?;
+            Ok((state, symbol.is_some()))
+        }),
+        Box::new(|state| {
+            let (state, _) = parser::consume("(", state)?;
+            let (state, _) = parser::skip(state)?;
+            let (next, symbol) = parse_op(state)?;
+            let symbol = symbol.unwrap();
+            let Some(op) = Op::ALL.into_iter().find(|op| op.symbol() == symbol) else {
+                let symbols: Vec<_> = Op::ALL
+                    .iter()
+                    .map(|op| format!("`{}`", op.symbol()))
+                    .collect();
+                let expected = format!("one of the operations {}", symbols.join(", "));
+                let found = format!("`{}`", symbol);
+                return Err(ParseError::new(state.code, state.index, expected, found));
+            };
+            let (state, val0) = parse_term(next)?;
+            let (state, val1) = parse_term(state)?;
+            let (state, _) = parser::consume(")", state)?;
+            Ok((state, Box::new(Term::Op2(op, val0, val1))))
+        }),
+        state,
+    )
+}
+
+/// Parses a superposition, e.g. `{a b}`, with a label to be assigned later.
+fn parse_sup(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        parser::text_parser("{"),
+        Box::new(|state| {
+            let (state, _) = parser::consume("{", state)?;
+            let (state, val0) = parse_term(state)?;
+            let (state, val1) = parse_term(state)?;
+            let (state, _) = parser::consume("}", state)?;
+            Ok((state, Box::new(Term::Sup(0, val0, val1))))
+        }),
+        state,
+    )
+}
+
+/// Parses a dup, e.g. `dup a b = x; body`, with a label to be assigned later.
+fn parse_dup(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        parser::text_parser("dup "),
+        Box::new(|state| {
+            let (state, _) = parser::consume("dup ", state)?;
+            let (state, nam0) = parser::name1(state)?;
+            let (state, nam1) = parser::name1(state)?;
+            let (state, _) = parser::consume("=", state)?;
+            let (state, expr) = parse_term(state)?;
+            let (state, _) = parser::text(";", state)?;
+            let (state, body) = parse_term(state)?;
+            Ok((
+                state,
+                Box::new(Term::Dup(0, nam0.intern(), nam1.intern(), expr, body)),
+            ))
+        }),
+        state,
+    )
+}
+
+/// Parses a let, e.g. `let x = expr; body`.
+fn parse_let(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        parser::text_parser("let "),
+        Box::new(|state| {
+            let (state, _) = parser::consume("let ", state)?;
+            let (state, name) = parser::name1(state)?;
+            let (state, _) = parser::consume("=", state)?;
+            let (state, expr) = parse_term(state)?;
+            let (state, _) = parser::text(";", state)?;
+            let (state, body) = parse_term(state)?;
+            Ok((state, Box::new(Term::Let(name.intern(), expr, body))))
+        }),
+        state,
+    )
+}
+
+fn parse_term(state: parser::State) -> parser::Answer<Box<Term>> {
+    let _guard = DepthGuard::enter(state)?;
+    parser::grammar(
+        "term",
+        &[
+            Box::new(parse_let),
+            Box::new(parse_dup),
+            Box::new(parse_lam),
+            Box::new(parse_op2),
+            Box::new(parse_call),
+            Box::new(parse_app),
+            Box::new(parse_sup),
+            Box::new(parse::parse_var),
+            Box::new(parse::parse_num),
+            Box::new(|state| Ok((state, None))),
+        ],
+        state,
+    )
+}
+
+/// Parses a parameter of a rule, which must be a variable.
+fn parse_param(state: parser::State) -> parser::Answer<IStr> {
+    let (state, _) = parser::skip(state)?;
+    match parser::head(state) {
+        Some(chr) if chr.is_ascii_lowercase() || chr == '_' => {
+            let (state, name) = parser::name1(state)?;
+            Ok((state, name.intern()))
+        }
+        _ => parser::expected(
+            "a variable (rules that match patterns are not supported)",
+            state,
+        ),
+    }
+}
+
+/// A rule: its name and where it starts, its parameters and its body.
+type Rule = ((IStr, usize), Vec<IStr>, Box<Term>);
+
+/// Parses a rule, e.g. `(Twice f x) = (f (f x))`.
+fn parse_rule(state: parser::State) -> parser::Answer<Option<Rule>> {
+    parser::guard(
+        parser::text_parser("("),
+        Box::new(|state| {
+            let (state, _) = parser::consume("(", state)?;
+            let (state, _) = parser::skip(state)?;
+            if !parser::head(state).is_some_and(is_rule_start) {
+                return parser::expected("a rule name", state);
+            }
+            let offset = state.index;
+            let (state, name) = parser::name1(state)?;
+            let (state, params) =
+                parser::until(parser::text_parser(")"), Box::new(parse_param), state)?;
+            let (state, _) = parser::consume("=", state)?;
+            let (state, body) = parse_term(state)?;
+            Ok((state, ((name.intern(), offset), params, body)))
+        }),
+        state,
+    )
+}
+
+/// Parses an HVM1 program (see the [module docs](self)) into the definitions
+/// of its rules, in order, and its main term, if it has a `(Main)` rule.
+///
+/// Definitions keep the names of their rules, and calls are left as
+/// variables named after them, as with [`parse::parse_program`] (see
+/// [`Book::load_hvm`](crate::book::Book::load_hvm)).
+pub fn parse_hvm(s: &str) -> Result<Program, Error> {
+    parse::with_limits(parse::DEFAULT_MAX_DEPTH, Label::MAX, || {
+        CALLS.with(|calls| calls.take());
+        let mut defs = vec![];
+        let mut main = None;
+        let mut seen = HashSet::new();
+        let mut state = parser::State::new(s);
+        while !parser::done(state)?.1 {
+            let (next, rule) = parse_rule(state)?;
+            let Some(((name, offset), params, body)) = rule else {
+                let (state, _) = parser::skip(state)?;
+                return Err(parser::error("rule", state).into());
+            };
+            state = next;
+            if !seen.insert(name) {
+                let expected = "a new rule name (rules that match patterns are not supported)";
+                let found = format!("`{}`", name);
+                return Err(ParseError::new(s, offset, expected.to_string(), found).into());
+            }
+            let is_main = name.to_string() == "Main" && params.is_empty();
+            let term = params
+                .into_iter()
+                .rev()
+                .fold(*body, |body, param| Term::Lam(param, Box::new(body)));
+            match is_main {
+                true => main = Some(term),
+                false => defs.push((name, term)),
+            }
+        }
+        if main.is_some() {
+            seen.remove(&"Main".intern());
+        }
+        for (name, offset) in CALLS.with(|calls| calls.take()) {
+            if !seen.contains(&name) {
+                let expected = "the name of a rule (constructors are not supported)";
+                let found = format!("`{}`", name);
+                return Err(ParseError::new(s, offset, expected.to_string(), found).into());
+            }
+        }
+        let mut labels = LabelGen::new();
+        for term in defs.iter_mut().map(|(_, term)| term).chain(&mut main) {
+            relabel(term, &mut labels);
+        }
+        for term in defs.iter_mut().map(|(_, term)| term).chain(&mut main) {
+            *term = desugar_with(term, &mut labels);
+        }
+        Ok((defs, main))
+    })
+}
+
+/// Gives every superposition and dup of `term` a fresh label from `labels`,
+/// in the order they appear.
+fn relabel(term: &mut Term, labels: &mut LabelGen) {
+    let mut stack = vec![term];
+    while let Some(term) = stack.pop() {
+        match term {
+            Term::Var(_) | Term::Ref(_) | Term::Num(_) => {}
+            Term::Lam(_, e) => stack.push(e),
+            Term::Sup(l, e1, e2) | Term::Dup(l, _, _, e1, e2) => {
+                *l = labels.fresh();
+                stack.extend([e2, e1].map(|e| &mut **e));
+            }
+            Term::App(e1, e2) | Term::Op2(_, e1, e2) | Term::Let(_, e1, e2) => {
+                stack.extend([e2, e1].map(|e| &mut **e));
+            }
+        }
+    }
+}
+
+/// Writes `program` as HVM1 rules: each definition as a rule without
+/// parameters, named with its first letter in upper case, and then the main
+/// term, if there is one, as the rule `(Main)`.
+///
+/// A reference, or a free variable that names a definition, is written as a
+/// call to its rule. Labels are dropped (see the [module docs](self)).
+pub fn to_hvm(program: &Program) -> String {
+    let (defs, main) = program;
+    let names: HashSet<IStr> = defs.iter().map(|(name, _)| *name).collect();
+    let mut out = String::new();
+    let main = main.iter().map(|term| ("Main".intern(), term));
+    for (name, term) in defs.iter().map(|(name, term)| (*name, term)).chain(main) {
+        write!(out, "({}) = ", rule_name(name)).unwrap();
+        write_term(&mut out, term, &names);
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes `term` in HVM1 syntax, without labels.
+pub fn term_to_hvm(term: &Term) -> String {
+    let mut out = String::new();
+    write_term(&mut out, term, &HashSet::new());
+    out
+}
+
+/// Returns the name of the rule for the definition `name`.
+fn rule_name(name: IStr) -> String {
+    let name = name.to_string();
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+/// Writes `term` to `out`, with the free variables in `defs` as calls.
+fn write_term(out: &mut String, term: &Term, defs: &HashSet<IStr>) {
+    // NOTE: Uses an explicit stack, so that deep terms can be written.
+    enum Item<'t> {
+        Term(&'t Term),
+        Text(&'static str),
+        /// Brings a binder into scope.
+        Bind(IStr),
+        /// Takes the last `n` binders out of scope.
+        Unbind(usize),
+    }
+
+    let mut bound = vec![];
+    let mut stack = vec![Item::Term(term)];
+    while let Some(item) = stack.pop() {
+        let term = match item {
+            Item::Term(term) => term,
+            Item::Text(text) => {
+                out.push_str(text);
+                continue;
+            }
+            Item::Bind(x) => {
+                bound.push(x);
+                continue;
+            }
+            Item::Unbind(n) => {
+                bound.truncate(bound.len() - n);
+                continue;
+            }
+        };
+        match term {
+            Term::Var(x) if defs.contains(x) && !bound.contains(x) => {
+                write!(out, "({})", rule_name(*x)).unwrap();
+            }
+            Term::Ref(name) => write!(out, "({})", rule_name(*name)).unwrap(),
+            Term::Var(x) => write!(out, "{}", x).unwrap(),
+            Term::Num(n) => write!(out, "{}", n).unwrap(),
+            Term::Lam(x, body) => {
+                write!(out, "λ{} ", x).unwrap();
+                stack.extend([Item::Unbind(1), Item::Term(body), Item::Bind(*x)]);
+            }
+            Term::App(fun, arg) => {
+                out.push('(');
+                stack.extend([
+                    Item::Text(")"),
+                    Item::Term(arg),
+                    Item::Text(" "),
+                    Item::Term(fun),
+                ]);
+            }
+            Term::Sup(_, left, right) => {
+                out.push('{');
+                stack.extend([
+                    Item::Text("}"),
+                    Item::Term(right),
+                    Item::Text(" "),
+                    Item::Term(left),
+                ]);
+            }
+            Term::Dup(_, a, b, expr, body) => {
+                write!(out, "dup {} {} = ", a, b).unwrap();
+                stack.extend([
+                    Item::Unbind(2),
+                    Item::Term(body),
+                    Item::Bind(*b),
+                    Item::Bind(*a),
+                    Item::Text("; "),
+                    Item::Term(expr),
+                ]);
+            }
+            Term::Op2(op, left, right) => {
+                write!(out, "({} ", op.symbol()).unwrap();
+                stack.extend([
+                    Item::Text(")"),
+                    Item::Term(right),
+                    Item::Text(" "),
+                    Item::Term(left),
+                ]);
+            }
+            Term::Let(x, expr, body) => {
+                write!(out, "let {} = ", x).unwrap();
+                stack.extend([
+                    Item::Unbind(1),
+                    Item::Term(body),
+                    Item::Bind(*x),
+                    Item::Text("; "),
+                    Item::Term(expr),
+                ]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::book::Book;
+    use crate::vm::TermGraph;
+    use std::sync::Arc;
+
+    const TWICE: &str = "
+        // Applies a function twice.
+        (Twice f x) = dup f0 f1 = f; (f0 (f1 x))
+        (Inc n) = (+ n 1)
+        (Main) = (Twice @n (Inc n) {5 let y = 2; (* y y)})
+    ";
+
+    #[test]
+    fn test_parse_hvm() {
+        let (defs, main) = parse_hvm(TWICE).unwrap();
+        let defs: Vec<_> = defs
+            .iter()
+            .map(|(name, term)| format!("{} = {}", name, term))
+            .collect();
+        assert_eq!(
+            defs,
+            [
+                "Twice = (λf (λx (dup #0{f0 f1} = f; (f0 (f1 x)))))",
+                "Inc = (λn (+ n 1))",
+            ]
+        );
+        assert_eq!(
+            main.unwrap().to_string(),
+            "((Twice (λn (Inc n))) #1{5 (let y = 2; (dup #2{y0 y1} = y; (* y0 y1)))})"
+        );
+    }
+
+    #[test]
+    fn test_load_hvm() {
+        let mut book = Book::new();
+        let main = book.load_hvm(TWICE).unwrap().unwrap();
+        let mut term_graph = TermGraph::from_book(Arc::new(book), &main).unwrap();
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(Term::from(&term_graph).to_string(), "#1{7 6}");
+    }
+
+    #[test]
+    fn test_parse_hvm_errors() {
+        let error = |src: &str| parse_hvm(src).unwrap_err().to_string();
+        assert_eq!(
+            error("(Len (Cons x xs)) = (+ 1 (Len xs))"),
+            "parse error: line 1, column 6: expected a variable (rules that match patterns are not supported), found `(`"
+        );
+        assert_eq!(
+            error("(Not x) = x\n(Not x) = x"),
+            "parse error: line 2, column 2: expected a new rule name (rules that match patterns are not supported), found `Not`"
+        );
+        assert_eq!(
+            error("(Main) = (Cons 1 (Nil))"),
+            "parse error: line 1, column 11: expected the name of a rule (constructors are not supported), found `Cons`"
+        );
+        assert_eq!(
+            error("(Main) = (% 7 2)"),
+            "parse error: line 1, column 11: expected one of the operations `+`, `-`, `*`, `/`, `==`, `<`, found `%`"
+        );
+        assert_eq!(
+            error("(main) = 1"),
+            "parse error: line 1, column 2: expected a rule name, found `main`"
+        );
+        assert_eq!(
+            error("(Main) = 1 2"),
+            "parse error: line 1, column 12: expected rule, found `2`"
+        );
+    }
+
+    #[test]
+    fn test_to_hvm() {
+        let program = parse::parse_program(
+            "def id = λx x;\ndef two = λf dup #0{f0 f1} = f; λx (f0 (f1 x));\nλid (two id #1{3 4})",
+        )
+        .unwrap();
+        let hvm = to_hvm(&program);
+        assert_eq!(
+            hvm,
+            "(Id) = λx x\n\
+             (Two) = λf dup f0 f1 = f; λx (f0 (f1 x))\n\
+             (Main) = λid (((Two) id) {3 4})\n"
+        );
+        let (defs, main) = parse_hvm(&hvm).unwrap();
+        assert_eq!(to_hvm(&(defs, main)), hvm);
+        assert_eq!(
+            term_to_hvm(&"let x = #3{1 2}; (+ x (id 1))".parse().unwrap()),
+            "let x = {1 2}; (+ x (id 1))"
+        );
+    }
+}
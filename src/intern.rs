@@ -8,6 +8,9 @@ static INTERNED: Lazy<RwLock<HashSet<&'static str>>> = Lazy::new(|| RwLock::new(
 pub struct IStr(&'static str);
 
 impl IStr {
+    /// The empty string, usable without looking it up.
+    pub(crate) const EMPTY: IStr = IStr("");
+
     #[allow(dead_code)]
     pub fn as_ptr(&self) -> *const str {
         self.0
@@ -20,6 +23,22 @@ impl std::fmt::Display for IStr {
     }
 }
 
+/// Serialized as the string it holds.
+#[cfg(feature = "serde")]
+impl serde::Serialize for IStr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+/// Deserialized from a string, which is interned.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IStr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(String::deserialize(deserializer)?.intern())
+    }
+}
+
 pub trait Intern {
     fn intern(&self) -> IStr;
 }
@@ -1,5 +1,23 @@
+// Unsafe operations must be inside an explicit `unsafe` block, even in an
+// `unsafe fn`, so that the body of an `unsafe fn` is not implicitly unsafe.
+#![deny(unsafe_op_in_unsafe_fn)]
+
+pub mod book;
+pub mod desugar;
+mod error;
+pub mod eval;
+pub mod examples;
+pub mod hvm;
 mod intern;
+pub mod lint;
+pub mod nets;
 pub mod parse;
 mod parser;
+pub mod prelude;
+pub mod runtime;
 pub mod syntax;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod vm;
+
+pub use error::{Error, GraphBuildError, ParseError};
@@ -0,0 +1,523 @@
+//! Static checks of terms that point out likely mistakes.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::intern::{IStr, Intern};
+use crate::syntax::{Label, LabelGen, Term};
+
+/// The kind of node a label is used by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SiteKind {
+    Sup,
+    Dup,
+}
+
+/// Where a label is used: a superposition or dup, identified by the
+/// definition it is in (if any) and its path from the root of that term.
+///
+/// A path lists the index of the child taken at each step, in the order of
+/// [`Term::children`]. Terms do not keep source positions, so paths are the
+/// most precise location available.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LabelSite {
+    pub definition: Option<IStr>,
+    pub path: Vec<usize>,
+    pub kind: SiteKind,
+}
+
+impl fmt::Display for LabelSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            SiteKind::Sup => "sup",
+            SiteKind::Dup => "dup",
+        };
+        write!(f, "{} at ", kind)?;
+        write_location(f, self.definition, &self.path)
+    }
+}
+
+/// Writes a location as the definition name (if any) followed by the path,
+/// e.g. `two/0/1`, or `/` for the root of an anonymous term.
+fn write_location(
+    f: &mut fmt::Formatter<'_>,
+    definition: Option<IStr>,
+    path: &[usize],
+) -> fmt::Result {
+    if let Some(definition) = definition {
+        write!(f, "{}", definition)?;
+    }
+    if path.is_empty() {
+        return f.write_str("/");
+    }
+    for index in path {
+        write!(f, "/{}", index)?;
+    }
+    Ok(())
+}
+
+/// Two dups that use the same label, as found by [`LabelReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LabelConflict {
+    pub label: Label,
+    /// The first dup with the label.
+    pub first: LabelSite,
+    /// A later dup with the label.
+    pub other: LabelSite,
+    /// An unused label that `other` could use instead.
+    pub suggested: Label,
+}
+
+impl fmt::Display for LabelConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "label #{} of the {} is also used by the {}; consider relabeling it to #{}",
+            self.label, self.other, self.first, self.suggested
+        )
+    }
+}
+
+/// Which labels are used where, and which of them are likely to clash.
+///
+/// Two superpositions and dups with the same label annihilate when they
+/// meet, which is only correct when the superposition was made by that dup
+/// (or is meant to be taken apart by it). Two different dups with the same
+/// label break that: the copies made by one are taken apart by the other, as
+/// a `DupSupSame` interaction, instead of being copied again. So every dup
+/// after the first to use a label is reported as a conflict, with a fresh
+/// label to use instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelReport {
+    /// The sites that use each label, in the order they were found.
+    pub uses: BTreeMap<Label, Vec<LabelSite>>,
+    pub conflicts: Vec<LabelConflict>,
+}
+
+impl LabelReport {
+    /// Reports the labels used by `term`.
+    pub fn of_term(term: &Term) -> Self {
+        Self::collect([(None, term)])
+    }
+
+    /// Reports the labels used by several definitions, as if they were one
+    /// program: a label used by dups in two different definitions is a
+    /// conflict too.
+    pub fn of_definitions<'t>(definitions: impl IntoIterator<Item = (&'t str, &'t Term)>) -> Self {
+        Self::collect(
+            definitions
+                .into_iter()
+                .map(|(name, term)| (Some(name.intern()), term)),
+        )
+    }
+
+    fn collect<'t>(terms: impl IntoIterator<Item = (Option<IStr>, &'t Term)>) -> Self {
+        let mut report = LabelReport::default();
+        for (definition, term) in terms {
+            let mut stack = vec![(term, vec![])];
+            while let Some((term, path)) = stack.pop() {
+                let site = match term {
+                    Term::Sup(l, _, _) => Some((*l, SiteKind::Sup)),
+                    Term::Dup(l, _, _, _, _) => Some((*l, SiteKind::Dup)),
+                    _ => None,
+                };
+                for (index, child) in term
+                    .children()
+                    .enumerate()
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                {
+                    let mut child_path = path.clone();
+                    child_path.push(index);
+                    stack.push((child, child_path));
+                }
+                if let Some((label, kind)) = site {
+                    report.uses.entry(label).or_default().push(LabelSite {
+                        definition,
+                        path,
+                        kind,
+                    });
+                }
+            }
+        }
+        let mut labels = LabelGen::new();
+        if let Some(last) = report.uses.keys().last() {
+            labels.reserve(*last);
+        }
+        for (label, sites) in &report.uses {
+            let mut dups = sites.iter().filter(|site| site.kind == SiteKind::Dup);
+            let Some(first) = dups.next() else {
+                continue;
+            };
+            for other in dups {
+                report.conflicts.push(LabelConflict {
+                    label: *label,
+                    first: first.clone(),
+                    other: other.clone(),
+                    suggested: labels.fresh(),
+                });
+            }
+        }
+        report
+    }
+}
+
+/// How serious a [`BinderLint`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Often intended, such as a function that ignores its argument.
+    Note,
+    /// Legal, but likely a mistake.
+    Warning,
+    /// The term cannot be turned into a graph.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// What is wrong with a binder, as found by [`BinderReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BinderIssue {
+    /// The binder hides one with the same name, bound by the node at
+    /// `outer`, for the rest of its scope. This includes a binder that hides
+    /// one of the variables of an enclosing dup.
+    Shadowed { outer: Vec<usize> },
+    /// Both variables of a dup have the same name.
+    DuplicateDup,
+    /// The variable is never used. Names starting with `_` are never
+    /// reported as unused.
+    Unused,
+    /// The variable is used `uses` times, more than the once that affine
+    /// variables allow (see [`desugar`](crate::desugar::desugar)).
+    Reused { uses: usize },
+    /// The variable of a dup or `let` is used in the expression it is bound
+    /// to.
+    UsedInOwnExpression,
+    /// The variable is not bound by any binder. It stays free in the graph,
+    /// unless it names a definition. Reported once per name, at its first
+    /// use.
+    Unbound,
+}
+
+/// A binder reported by [`BinderReport`]: the lambda, dup or `let` at `path`
+/// in `definition` (if any), binding `name`, or for
+/// [`BinderIssue::Unbound`], the first use of `name` that nothing binds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinderLint {
+    pub severity: Severity,
+    pub name: IStr,
+    pub definition: Option<IStr>,
+    pub path: Vec<usize>,
+    pub issue: BinderIssue,
+}
+
+impl fmt::Display for BinderLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        match &self.issue {
+            BinderIssue::Shadowed { outer } => {
+                write!(f, "{} shadows the binder at ", self.name)?;
+                write_location(f, self.definition, outer)?;
+            }
+            BinderIssue::DuplicateDup => write!(f, "dup binds {} twice", self.name)?,
+            BinderIssue::Unused => write!(f, "{} is never used", self.name)?,
+            BinderIssue::Reused { uses } => write!(f, "{} is used {} times", self.name, uses)?,
+            BinderIssue::UsedInOwnExpression => {
+                write!(f, "{} is used in the expression it is bound to", self.name)?
+            }
+            BinderIssue::Unbound => write!(f, "{} is not bound", self.name)?,
+        }
+        f.write_str(" (at ")?;
+        write_location(f, self.definition, &self.path)?;
+        f.write_str(")")
+    }
+}
+
+/// Binders that are shadowed, duplicated, unused or used too often, and
+/// variables that are not bound.
+///
+/// A dup that binds the same name twice, a variable that is used more than
+/// once, and a variable of a dup or `let` that is used in its own expression
+/// are errors, since no graph can be built for them. A binder that shadows
+/// another is legal, but since variables are affine it usually means the
+/// outer one was meant. So is an unbound variable, which stays free, but is
+/// more often a typo. An unused binder erases its argument, which is often
+/// intended, so it is only a note.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BinderReport {
+    /// The lints, in the order their binders appear.
+    pub lints: Vec<BinderLint>,
+}
+
+impl BinderReport {
+    /// Reports the binders of `term`.
+    pub fn of_term(term: &Term) -> Self {
+        Self::collect([(None, term)])
+    }
+
+    /// Reports the binders of several definitions. Each definition has its
+    /// own scope, so binders are never shadowed across definitions.
+    pub fn of_definitions<'t>(definitions: impl IntoIterator<Item = (&'t str, &'t Term)>) -> Self {
+        Self::collect(
+            definitions
+                .into_iter()
+                .map(|(name, term)| (Some(name.intern()), term)),
+        )
+    }
+
+    /// Returns the most serious severity of the lints, if there are any.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.lints.iter().map(|lint| lint.severity).max()
+    }
+
+    fn collect<'t>(terms: impl IntoIterator<Item = (Option<IStr>, &'t Term)>) -> Self {
+        /// A binder in scope: its name, the path of its node, how often it
+        /// has been used, and whether it may be used yet, which it may not in
+        /// the expression of its dup or `let`.
+        struct Binder {
+            name: IStr,
+            path: Vec<usize>,
+            uses: usize,
+            usable: bool,
+        }
+
+        struct Collector {
+            definition: Option<IStr>,
+            scope: Vec<Binder>,
+            /// The unbound names reported so far.
+            unbound: HashSet<IStr>,
+            lints: Vec<BinderLint>,
+        }
+
+        impl Collector {
+            fn lint(&mut self, severity: Severity, name: IStr, path: &[usize], issue: BinderIssue) {
+                self.lints.push(BinderLint {
+                    severity,
+                    name,
+                    definition: self.definition,
+                    path: path.to_vec(),
+                    issue,
+                });
+            }
+
+            /// Visits `body`, the child at `index`, with `names` bound by the
+            /// node at `path`, after visiting `expr`, the expression of a dup
+            /// or `let` (always the child at 0), if any.
+            fn bind(
+                &mut self,
+                names: &[IStr],
+                path: &mut Vec<usize>,
+                expr: Option<&Term>,
+                index: usize,
+                body: &Term,
+            ) {
+                for name in names {
+                    if let Some(outer) = self.scope.iter().rev().find(|b| b.name == *name) {
+                        let outer = outer.path.clone();
+                        self.lint(
+                            Severity::Warning,
+                            *name,
+                            path,
+                            BinderIssue::Shadowed { outer },
+                        );
+                    }
+                }
+                let depth = self.scope.len();
+                self.scope.extend(names.iter().map(|name| Binder {
+                    name: *name,
+                    path: path.clone(),
+                    uses: 0,
+                    usable: expr.is_none(),
+                }));
+                if let Some(expr) = expr {
+                    path.push(0);
+                    self.visit(expr, path);
+                    path.pop();
+                    for binder in &mut self.scope[depth..] {
+                        binder.usable = true;
+                    }
+                }
+                path.push(index);
+                self.visit(body, path);
+                path.pop();
+                for binder in self.scope.split_off(depth) {
+                    match binder.uses {
+                        0 if !binder.name.to_string().starts_with('_') => {
+                            self.lint(Severity::Note, binder.name, path, BinderIssue::Unused)
+                        }
+                        0 | 1 => {}
+                        uses => self.lint(
+                            Severity::Error,
+                            binder.name,
+                            path,
+                            BinderIssue::Reused { uses },
+                        ),
+                    }
+                }
+            }
+
+            fn visit(&mut self, term: &Term, path: &mut Vec<usize>) {
+                match term {
+                    Term::Var(x) => match self.scope.iter_mut().rev().find(|b| b.name == *x) {
+                        Some(binder) => {
+                            binder.uses += 1;
+                            if !binder.usable && binder.uses == 1 {
+                                let binder_path = binder.path.clone();
+                                self.lint(
+                                    Severity::Error,
+                                    *x,
+                                    &binder_path,
+                                    BinderIssue::UsedInOwnExpression,
+                                );
+                            }
+                        }
+                        None => {
+                            if self.unbound.insert(*x) {
+                                self.lint(Severity::Warning, *x, path, BinderIssue::Unbound);
+                            }
+                        }
+                    },
+                    Term::Ref(_) | Term::Num(_) => {}
+                    Term::Lam(x, e) => self.bind(&[*x], path, None, 0, e),
+                    Term::App(e1, e2) | Term::Sup(_, e1, e2) | Term::Op2(_, e1, e2) => {
+                        for (index, e) in [e1, e2].into_iter().enumerate() {
+                            path.push(index);
+                            self.visit(e, path);
+                            path.pop();
+                        }
+                    }
+                    Term::Dup(_, a, b, e, cont) => {
+                        if a == b {
+                            self.lint(Severity::Error, *a, path, BinderIssue::DuplicateDup);
+                            self.bind(&[*a], path, Some(e), 1, cont);
+                        } else {
+                            self.bind(&[*a, *b], path, Some(e), 1, cont);
+                        }
+                    }
+                    Term::Let(x, e, cont) => self.bind(&[*x], path, Some(e), 1, cont),
+                }
+            }
+        }
+
+        let mut lints = vec![];
+        for (definition, term) in terms {
+            let mut collector = Collector {
+                definition,
+                scope: vec![],
+                unbound: HashSet::new(),
+                lints,
+            };
+            collector.visit(term, &mut vec![]);
+            lints = collector.lints;
+        }
+        BinderReport { lints }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_label_report() {
+        let term: Term = "λx dup #0{a b} = x; #0{(dup #0{c d} = a; (c d)) #1{b y}}"
+            .parse()
+            .unwrap();
+        let report = LabelReport::of_term(&term);
+        assert_eq!(report.uses[&0].len(), 3);
+        assert_eq!(report.uses[&1].len(), 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(
+            report.conflicts[0].to_string(),
+            "label #0 of the dup at /0/1/0 is also used by the dup at /0; \
+             consider relabeling it to #2"
+        );
+    }
+
+    #[test]
+    fn test_label_report_of_definitions() {
+        let two: Term = "λf dup #7{f1 f2} = f; λx (f1 (f2 x))".parse().unwrap();
+        let three: Term = "λf dup #7{f1 f2} = f; dup #8{f3 f4} = f2; λx (f1 (f3 (f4 x)))"
+            .parse()
+            .unwrap();
+        let report = LabelReport::of_definitions([("two", &two), ("three", &three)]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].first.to_string(), "dup at two/0");
+        assert_eq!(report.conflicts[0].other.to_string(), "dup at three/0");
+        assert_eq!(report.conflicts[0].suggested, 9);
+
+        let prelude = crate::prelude::all();
+        let report = LabelReport::of_definitions(prelude.iter().map(|(n, t)| (*n, t)));
+        assert_eq!(report.conflicts, []);
+    }
+
+    #[test]
+    fn test_binder_report() {
+        let term: Term = "λx λy dup #0{a a} = x; λx (a λ_z λ_ x)".parse().unwrap();
+        let lints: Vec<String> = BinderReport::of_term(&term)
+            .lints
+            .iter()
+            .map(|lint| lint.to_string())
+            .collect();
+        assert_eq!(
+            lints,
+            [
+                "error: dup binds a twice (at /0/0)",
+                "warning: x shadows the binder at / (at /0/0/1)",
+                "note: y is never used (at /0)",
+            ]
+        );
+        let report = BinderReport::of_term(&term);
+        assert_eq!(report.max_severity(), Some(Severity::Error));
+
+        let prelude = crate::prelude::all();
+        let report = BinderReport::of_definitions(prelude.iter().map(|(n, t)| (*n, t)));
+        assert_eq!(report.max_severity(), Some(Severity::Note));
+        assert_eq!(
+            report.lints[0].to_string(),
+            "note: y is never used (at const/0)"
+        );
+    }
+
+    #[test]
+    fn test_binder_report_linearity() {
+        let term: Term = "λx λy dup #0{a b} = (a y); let z = (x x x); (b (z w (w v)))"
+            .parse()
+            .unwrap();
+        let lints: Vec<String> = BinderReport::of_term(&term)
+            .lints
+            .iter()
+            .map(|lint| lint.to_string())
+            .collect();
+        assert_eq!(
+            lints,
+            [
+                "error: a is used in the expression it is bound to (at /0/0)",
+                "warning: w is not bound (at /0/0/1/1/1/0/1)",
+                "warning: v is not bound (at /0/0/1/1/1/1/1)",
+                "error: x is used 3 times (at /)",
+            ]
+        );
+        // Every term that no graph can be built for has an error.
+        for src in [
+            "λx (x x)",
+            "dup #0{a b} = x; (a a)",
+            "let x = y; (x x)",
+            "dup #0{a a} = y; a",
+            "dup #0{a b} = a; b",
+            "λx let x = x; x",
+        ] {
+            let report = BinderReport::of_term(&src.parse().unwrap());
+            assert_eq!(report.max_severity(), Some(Severity::Error), "{}", src);
+        }
+    }
+}
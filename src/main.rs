@@ -0,0 +1,319 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use ictest::examples;
+use ictest::lint::Severity;
+use ictest::parse::parse_defs;
+use ictest::runtime::{load_source, Runtime, TestFile};
+use ictest::syntax::Term;
+use ictest::vm::{Strategy, StrategyConfig};
+
+const USAGE: &str = "\
+usage: ictest reduce FILE [--strategy deterministic|normal] [--max-steps N]
+       ictest test DIR
+       ictest run-dir DIR [--jobs N] [--report FILE]
+       ictest check FILE
+       ictest repl";
+
+/// The file the REPL's input lines are appended to, in the home directory.
+const HISTORY_FILE: &str = ".ictest_history";
+
+const REPL_HELP: &str = "\
+TERM                   evaluate TERM
+def NAME = TERM;       define NAME as TERM (several may share a line)
+:def NAME = TERM       define NAME as TERM
+:save [--cache] FILE   save the definitions (and cached normal forms)
+:load FILE             load definitions saved with :save
+:example [NAME]        evaluate an example program, or list them
+:history               list the previous inputs
+:quit                  exit";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [command, file, options @ ..] if command == "reduce" => match reduce_options(options) {
+            Some((strategy, max_steps)) => reduce(Path::new(file), strategy, max_steps),
+            None => {
+                eprintln!("{}", USAGE);
+                ExitCode::from(2)
+            }
+        },
+        [command, dir] if command == "test" => test(Path::new(dir)),
+        [command, dir, options @ ..] if command == "run-dir" => match run_dir_options(options) {
+            Some((jobs, report)) => run_dir(Path::new(dir), jobs, report),
+            None => {
+                eprintln!("{}", USAGE);
+                ExitCode::from(2)
+            }
+        },
+        [command, file] if command == "check" => check(Path::new(file)),
+        [command] if command == "repl" => repl(),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Parses the options of `reduce`: the strategy (by default,
+/// [`Strategy::Deterministic`]) and the step limit, if any.
+fn reduce_options(options: &[String]) -> Option<(Strategy, Option<usize>)> {
+    let mut strategy = Strategy::default();
+    let mut max_steps = None;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--strategy" => {
+                strategy = match options.next()?.as_str() {
+                    "deterministic" => Strategy::Deterministic,
+                    "normal" => Strategy::NormalOrder,
+                    _ => return None,
+                }
+            }
+            "--max-steps" => max_steps = Some(options.next()?.parse().ok()?),
+            _ => return None,
+        }
+    }
+    Some((strategy, max_steps))
+}
+
+/// Reduces the term of the `.ic` file at `path` with `strategy`, with the
+/// prelude and the file's definitions in scope, and prints its readback. Stops after `max_steps` rewrites
+/// (by default, the runtime's limit), and fails with a diagnosis of the
+/// reduction if the term is not in normal form by then.
+fn reduce(path: &Path, strategy: Strategy, max_steps: Option<usize>) -> ExitCode {
+    let mut runtime = Runtime::new();
+    runtime.load_prelude();
+    let term_graph = load_source(path)
+        .and_then(|src| TestFile::parse(&src))
+        .and_then(|test| {
+            runtime.define_all(test.defs);
+            runtime.graph(&test.term)
+        });
+    let mut term_graph = match term_graph {
+        Ok(term_graph) => term_graph,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+    let max_steps = max_steps.unwrap_or(runtime.max_steps() as usize);
+    let diagnosis = term_graph
+        .reduce_diagnosed(strategy, &StrategyConfig::default(), max_steps)
+        .err();
+    println!("{}", Term::from(&term_graph));
+    match diagnosis {
+        None => ExitCode::SUCCESS,
+        Some(diagnosis) => {
+            eprintln!("{}: {}", path.display(), diagnosis);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the test directives of every `.ic` file under `dir`, with the prelude
+/// in scope.
+fn test(dir: &Path) -> ExitCode {
+    let mut runtime = Runtime::new();
+    runtime.load_prelude();
+    let reports = match runtime.run_test_dir(dir) {
+        Ok(reports) => reports,
+        Err(error) => {
+            eprintln!("{}: {}", dir.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+    let mut failed = 0;
+    for report in &reports {
+        match &report.result {
+            Ok(failures) if failures.is_empty() => println!("PASS {}", report.path.display()),
+            Ok(failures) => {
+                failed += 1;
+                println!("FAIL {}", report.path.display());
+                for failure in failures {
+                    for line in failure.to_string().lines() {
+                        println!("    {}", line);
+                    }
+                }
+            }
+            Err(error) => {
+                failed += 1;
+                println!("FAIL {}", report.path.display());
+                println!("    {}", error);
+            }
+        }
+    }
+    println!("{} passed, {} failed", reports.len() - failed, failed);
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Parses the options of `run-dir`: the number of jobs (by default, the
+/// available parallelism) and the report file, if any.
+fn run_dir_options(options: &[String]) -> Option<(usize, Option<&Path>)> {
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut report = None;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--jobs" => jobs = options.next()?.parse().ok().filter(|jobs| *jobs > 0)?,
+            "--report" => report = Some(Path::new(options.next()?)),
+            _ => return None,
+        }
+    }
+    Some((jobs, report))
+}
+
+/// Evaluates every `.ic` file under `dir` on `jobs` threads, with the prelude
+/// in scope, and writes the report to `report` (or standard output). Fails if
+/// a file could not be evaluated.
+fn run_dir(dir: &Path, jobs: usize, report: Option<&Path>) -> ExitCode {
+    let mut runtime = Runtime::new();
+    runtime.load_prelude();
+    let run = match runtime.eval_dir(dir, jobs) {
+        Ok(run) => run,
+        Err(error) => {
+            eprintln!("{}: {}", dir.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+    let written = match report {
+        Some(path) => fs::File::create(path).and_then(|mut file| run.write_tsv(&mut file)),
+        None => run.write_tsv(&mut io::stdout().lock()),
+    };
+    if let Err(error) = written {
+        eprintln!("{}: {}", report.unwrap_or(dir).display(), error);
+        return ExitCode::from(2);
+    }
+    if report.is_some() {
+        println!(
+            "{} files, {} errors, {} rewrites in {:?}",
+            run.stats.items, run.stats.errors, run.stats.rewrites, run.stats.elapsed
+        );
+    }
+    if run.stats.errors == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Prints the diagnostics of the `.ic` file at `path` (see
+/// [`Runtime::check`]), with the prelude in scope, without reducing it. Fails
+/// if there is an error.
+fn check(path: &Path) -> ExitCode {
+    let src = match load_source(path) {
+        Ok(src) => src,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            return ExitCode::from(2);
+        }
+    };
+    let mut runtime = Runtime::new();
+    runtime.load_prelude();
+    let diagnostics = runtime.check(&src);
+    for diagnostic in &diagnostics {
+        println!("{}: {}", path.display(), diagnostic);
+    }
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity() == Severity::Error)
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Reads and evaluates lines from standard input, with the prelude in scope
+/// and the normal-form cache enabled. Every input is appended to the history
+/// file, so `:history` also lists the inputs of earlier sessions.
+fn repl() -> ExitCode {
+    let mut runtime = Runtime::new();
+    runtime.load_prelude();
+    runtime.enable_cache(1024);
+    let history_path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    let mut history: Vec<String> = history_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|src| src.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    let mut history_file = history_path
+        .as_ref()
+        .and_then(|path| OpenOptions::new().create(true).append(true).open(path).ok());
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return ExitCode::SUCCESS,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(file) = &mut history_file {
+            writeln!(file, "{}", line).ok();
+        }
+        history.push(line.to_string());
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        match command {
+            ":quit" | ":q" => return ExitCode::SUCCESS,
+            ":help" => println!("{}", REPL_HELP),
+            ":history" => {
+                for (i, input) in history.iter().enumerate() {
+                    println!("{:5}  {}", i + 1, input);
+                }
+            }
+            ":def" => match arg.split_once('=') {
+                Some((name, term)) => match term.parse() {
+                    Ok(term) => runtime.define(name.trim(), term),
+                    Err(error) => println!("{}", error),
+                },
+                None => println!("usage: :def NAME = TERM"),
+            },
+            ":save" => {
+                let (with_cache, path) = match arg.strip_prefix("--cache") {
+                    Some(path) => (true, path.trim()),
+                    None => (false, arg),
+                };
+                if path.is_empty() {
+                    println!("usage: :save [--cache] FILE");
+                } else if let Err(error) = runtime.save_session(Path::new(path), with_cache) {
+                    println!("{}: {}", path, error);
+                }
+            }
+            ":load" => match runtime.load_session(Path::new(arg)) {
+                Ok(count) => println!("loaded {} definitions", count),
+                Err(error) => println!("{}: {}", arg, error),
+            },
+            ":example" if arg.is_empty() => {
+                println!("{}", examples::names().collect::<Vec<_>>().join(" "))
+            }
+            ":example" => match examples::load(arg) {
+                Some(term) => match runtime.eval(&term) {
+                    Ok(term) => println!("{}", term),
+                    Err(error) => println!("{}", error),
+                },
+                None => println!("unknown example {}; try :example", arg),
+            },
+            "def" => match parse_defs(line) {
+                Ok(defs) => runtime.define_all(defs),
+                Err(error) => println!("{}", error),
+            },
+            _ if command.starts_with(':') => println!("unknown command {}; try :help", command),
+            _ => match runtime.eval_str(line) {
+                Ok(term) => println!("{}", term),
+                Err(error) => println!("{}", error),
+            },
+        }
+    }
+}
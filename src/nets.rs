@@ -0,0 +1,639 @@
+//! Graphs as interaction nets of agents and wires, in a textual format that
+//! other interaction-net tools can check the encoding against.
+//!
+//! A graph is lowered to the symmetric interaction combinators, extended with
+//! numbers, operations and references: lambdas and applications are both
+//! constructors (`con`), superpositions and dups are both labelled
+//! duplicators (`fan`), and unused variables are erasers (`era`). Which of
+//! the two a constructor or duplicator is follows from the direction of its
+//! wires, which [`Net::to_graph`] works out from the root.
+//!
+//! In the text of a net, each line is a free port of the net or an agent,
+//! with its principal port first. Wires are named, and every wire appears
+//! exactly twice:
+//!
+//! ```text
+//! root w0
+//! free w1 y
+//! con w0 w2 w3
+//! con w2 w1 w3
+//! ```
+//!
+//! is `λx (x y)`: a lambda with its value on the root, its variable on `w2`
+//! and its body on `w3`, and an application of `w2` to the free variable `y`,
+//! whose result is `w3`. The lines are:
+//!
+//! - `root WIRE [NAME]`: a root of the graph, with its name unless it is the
+//!   only root of an ordinary graph;
+//! - `free WIRE [NAME]`: a free variable, with its name if it has one;
+//! - `con VALUE VAR BODY` for a lambda, or `con FUN ARG RESULT` for an
+//!   application;
+//! - `fan LABEL VALUE LEFT RIGHT` for a superposition, or
+//!   `fan LABEL EXPR A B` for a dup;
+//! - `era WIRE`: an eraser;
+//! - `num N WIRE`: a number;
+//! - `op2 OP LEFT RIGHT RESULT`: a binary operation, e.g. `op2 + w0 w1 w2`;
+//! - `ref NAME WIRE`: a reference to a definition.
+//!
+//! Blank lines and lines starting with `//` are ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, ParseError};
+use crate::intern::{IStr, Intern};
+use crate::syntax::{Label, Op};
+use crate::vm::{ChildRecord, NodeRecord, RecordTag, TermGraph, UseRecord};
+
+/// A wire of a [`Net`], by number.
+pub type Wire = usize;
+
+/// The type of an [`Agent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AgentKind {
+    /// A constructor: a lambda, whose ports are its value, its variable and
+    /// its body, or an application, whose ports are the function, the
+    /// argument and the result.
+    Con,
+    /// A duplicator with a label: a superposition, whose ports are its value
+    /// and its two branches, or a dup, whose ports are the expression and its
+    /// two copies.
+    Fan(Label),
+    /// An eraser, on a variable that is not used.
+    Era,
+    /// A number, with its value.
+    Num(u64),
+    /// A binary operation, whose ports are its two operands and its result.
+    Op2(Op),
+    /// A reference to the definition with this name.
+    Ref(IStr),
+}
+
+impl AgentKind {
+    /// Returns the number of ports of an agent of this kind.
+    pub fn arity(self) -> usize {
+        match self {
+            AgentKind::Con | AgentKind::Fan(_) | AgentKind::Op2(_) => 3,
+            AgentKind::Era | AgentKind::Num(_) | AgentKind::Ref(_) => 1,
+        }
+    }
+}
+
+/// An agent of a [`Net`], with the wire on each of its ports, principal port
+/// first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Agent {
+    pub kind: AgentKind,
+    pub ports: Vec<Wire>,
+}
+
+/// An interaction net: agents, and the free ports that connect it to the
+/// outside (see the [module docs](self)).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Net {
+    pub agents: Vec<Agent>,
+    /// The wire of each root, with the name of the root.
+    pub roots: Vec<(String, Wire)>,
+    /// The wire of each free variable, with its name if it has one.
+    pub free: Vec<(Wire, Option<IStr>)>,
+}
+
+impl From<&TermGraph> for Net {
+    /// Lowers the graph to a net, with its wires numbered in the order they
+    /// are written.
+    fn from(term_graph: &TermGraph) -> Net {
+        let records = term_graph.dump();
+        let mut net = Net::default();
+        let mut next = 0;
+        // The wire of the value of each node but dups, and of each variable
+        // it binds.
+        let mut values = vec![];
+        let mut vars = vec![];
+        for record in &records {
+            let mut wire = || {
+                next += 1;
+                next - 1
+            };
+            values.push(match record.tag {
+                RecordTag::Dup => None,
+                _ => Some(wire()),
+            });
+            vars.push(record.uses.iter().map(|_| wire()).collect::<Vec<_>>());
+        }
+        let mut wire_of = |child: ChildRecord, net: &mut Net| match child {
+            ChildRecord::Node(id) => values[id].unwrap(),
+            ChildRecord::LamVar(id) | ChildRecord::DupAVar(id) => vars[id][0],
+            ChildRecord::DupBVar(id) => vars[id][1],
+            ChildRecord::FreeVar(name) => {
+                next += 1;
+                net.free.push((next - 1, name));
+                next - 1
+            }
+            ChildRecord::Ref(name) => {
+                next += 1;
+                net.agents.push(Agent {
+                    kind: AgentKind::Ref(name),
+                    ports: vec![next - 1],
+                });
+                next - 1
+            }
+        };
+        for (name, child) in term_graph.root_entries() {
+            let wire = wire_of(child, &mut net);
+            net.roots.push((name, wire));
+        }
+        for record in &records {
+            let c: Vec<Wire> = record
+                .children
+                .iter()
+                .map(|child| wire_of(*child, &mut net))
+                .collect();
+            let (value, vars) = (values[record.id], &vars[record.id]);
+            let (kind, ports) = match record.tag {
+                RecordTag::Lam => (AgentKind::Con, vec![value.unwrap(), vars[0], c[0]]),
+                RecordTag::App => (AgentKind::Con, vec![c[0], c[1], value.unwrap()]),
+                RecordTag::Sup => (
+                    AgentKind::Fan(record.label.unwrap()),
+                    vec![value.unwrap(), c[0], c[1]],
+                ),
+                RecordTag::Dup => (
+                    AgentKind::Fan(record.label.unwrap()),
+                    vec![c[0], vars[0], vars[1]],
+                ),
+                RecordTag::Num(n) => (AgentKind::Num(n), vec![value.unwrap()]),
+                RecordTag::Op2(op) => (AgentKind::Op2(op), vec![c[0], c[1], value.unwrap()]),
+            };
+            net.agents.push(Agent { kind, ports });
+            for (var, use_) in vars.iter().zip(&record.uses) {
+                if *use_ == UseRecord::Unused {
+                    net.agents.push(Agent {
+                        kind: AgentKind::Era,
+                        ports: vec![*var],
+                    });
+                }
+            }
+        }
+        net.renumbered()
+    }
+}
+
+/// One end of a wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum End {
+    Root,
+    Free(usize),
+    /// A port of an agent: the index of the agent, and of the port.
+    Port(usize, usize),
+}
+
+fn invalid(message: String) -> Error {
+    Error::Graph(format!("invalid net: {}", message))
+}
+
+impl Net {
+    /// Returns the net with its wires numbered from 0 in the order they first
+    /// appear in its text.
+    fn renumbered(mut self) -> Net {
+        let mut numbers = HashMap::new();
+        let mut renumber = |wire: &mut Wire| {
+            let next = numbers.len();
+            *wire = *numbers.entry(*wire).or_insert(next);
+        };
+        self.roots.iter_mut().for_each(|(_, wire)| renumber(wire));
+        self.free.iter_mut().for_each(|(wire, _)| renumber(wire));
+        for agent in &mut self.agents {
+            agent.ports.iter_mut().for_each(&mut renumber);
+        }
+        self
+    }
+
+    /// Reads the net back as a graph, working out which constructors are
+    /// lambdas and which are applications, and which duplicators are
+    /// superpositions and which are dups, from the directions of the wires.
+    ///
+    /// Returns [`Error::Graph`] if the net does not have exactly one root, if
+    /// a wire does not have exactly two ends, if the wires cannot all be
+    /// directed from a value to its use, if an agent is not reached from the
+    /// root, or if the net holds a reference, since a graph is read back
+    /// without definitions. A free variable on the root of a net without
+    /// agents is read back as an anonymous one, as by
+    /// [`TermGraph::from_dump`].
+    pub fn to_graph(&self) -> Result<TermGraph, Error> {
+        let [(_, root)] = self.roots[..] else {
+            return Err(invalid(format!(
+                "a graph has one root, but the net has {}",
+                self.roots.len()
+            )));
+        };
+        let mut ends: HashMap<Wire, Vec<End>> = HashMap::new();
+        ends.entry(root).or_default().push(End::Root);
+        for (index, (wire, _)) in self.free.iter().enumerate() {
+            ends.entry(*wire).or_default().push(End::Free(index));
+        }
+        for (index, agent) in self.agents.iter().enumerate() {
+            if agent.ports.len() != agent.kind.arity() {
+                return Err(invalid(format!(
+                    "agent {} has {} ports, but {:?} has {}",
+                    index,
+                    agent.ports.len(),
+                    agent.kind,
+                    agent.kind.arity()
+                )));
+            }
+            for (port, wire) in agent.ports.iter().enumerate() {
+                ends.entry(*wire).or_default().push(End::Port(index, port));
+            }
+        }
+        let mut wires: Vec<(Wire, &Vec<End>)> = ends.iter().map(|(w, e)| (*w, e)).collect();
+        wires.sort_by_key(|(wire, _)| *wire);
+        let mut pending: Vec<(Wire, [End; 2])> = vec![];
+        for (wire, ends) in wires {
+            match ends[..] {
+                [a, b] => pending.push((wire, [a, b])),
+                _ => return Err(invalid(format!("wire {} has {} ends", wire, ends.len()))),
+            }
+        }
+        let other_end = |wire: Wire, end: End| {
+            let [a, b] = ends[&wire][..] else {
+                unreachable!()
+            };
+            if a == end {
+                b
+            } else {
+                a
+            }
+        };
+
+        // Whether each constructor is a lambda, and each duplicator a
+        // superposition: whether its principal port is a value.
+        let mut values: Vec<Option<bool>> = self
+            .agents
+            .iter()
+            .map(|agent| match agent.kind {
+                AgentKind::Con | AgentKind::Fan(_) => None,
+                _ => Some(true),
+            })
+            .collect();
+        // Whether the end is a value rather than a use, if that is known yet.
+        let is_value = |values: &[Option<bool>], end: End| match end {
+            End::Root => Some(false),
+            End::Free(_) => Some(true),
+            End::Port(index, port) => {
+                let agent = &self.agents[index];
+                match agent.kind {
+                    AgentKind::Con => values[index].map(|lam| lam == (port != 2)),
+                    AgentKind::Fan(_) => values[index].map(|sup| sup == (port == 0)),
+                    AgentKind::Era => Some(false),
+                    AgentKind::Num(_) | AgentKind::Ref(_) => Some(true),
+                    AgentKind::Op2(_) => Some(port == 2),
+                }
+            }
+        };
+        while let Some((wire, [a, b])) = pending.pop() {
+            let (end, value) = match (is_value(&values, a), is_value(&values, b)) {
+                (Some(x), Some(y)) if x == y => {
+                    let ends = match x {
+                        true => "values",
+                        false => "uses",
+                    };
+                    return Err(invalid(format!("wire {} connects two {}", wire, ends)));
+                }
+                (Some(_), Some(_)) | (None, None) => continue,
+                (Some(x), None) => (b, !x),
+                (None, Some(y)) => (a, !y),
+            };
+            let End::Port(index, port) = end else {
+                unreachable!()
+            };
+            values[index] = Some(match self.agents[index].kind {
+                AgentKind::Con => value == (port != 2),
+                _ => value == (port == 0),
+            });
+            for wire in &self.agents[index].ports {
+                pending.push((*wire, [ends[wire][0], ends[wire][1]]));
+            }
+        }
+        if let Some(index) = values.iter().position(Option::is_none) {
+            return Err(invalid(format!(
+                "agent {} is not reached from the root",
+                index
+            )));
+        }
+
+        // Number the nodes, with the node on the root first, as `from_dump`
+        // expects.
+        let is_node = |agent: &Agent| {
+            matches!(
+                agent.kind,
+                AgentKind::Con | AgentKind::Fan(_) | AgentKind::Num(_) | AgentKind::Op2(_)
+            )
+        };
+        // Whether the value on the port is the variable of a lambda or dup.
+        let is_var = |index: usize, port: usize| {
+            matches!(
+                (self.agents[index].kind, values[index] == Some(true), port),
+                (AgentKind::Con, true, 1) | (AgentKind::Fan(_), false, 1 | 2)
+            )
+        };
+        let root_end = other_end(root, End::Root);
+        let first = match root_end {
+            End::Port(index, port) => match self.agents[index].kind {
+                AgentKind::Ref(name) => {
+                    return Err(invalid(format!(
+                        "the root holds reference {}, but a graph is read back without definitions",
+                        name
+                    )));
+                }
+                _ if is_var(index, port) => None,
+                _ => Some(index),
+            },
+            _ => None,
+        };
+        let order: Vec<usize> = first
+            .into_iter()
+            .chain((0..self.agents.len()).filter(|&i| is_node(&self.agents[i]) && Some(i) != first))
+            .collect();
+        if order.is_empty() {
+            // A net without nodes is a free variable on the root.
+            return TermGraph::from_dump(&[]);
+        }
+        if let End::Free(_) = root_end {
+            return Err(invalid(
+                "the root holds a free variable, but the net has other agents".to_string(),
+            ));
+        }
+        let mut ids = vec![usize::MAX; self.agents.len()];
+        for (id, index) in order.iter().enumerate() {
+            ids[*index] = id;
+        }
+
+        // What the use at `end` of `wire` holds.
+        let child = |wire: Wire, end: End| match other_end(wire, end) {
+            End::Free(index) => ChildRecord::FreeVar(self.free[index].1),
+            End::Port(index, port) => match (self.agents[index].kind, port) {
+                (AgentKind::Ref(name), _) => ChildRecord::Ref(name),
+                (AgentKind::Con, _) if is_var(index, port) => ChildRecord::LamVar(ids[index]),
+                (_, 1) if is_var(index, port) => ChildRecord::DupAVar(ids[index]),
+                (_, 2) if is_var(index, port) => ChildRecord::DupBVar(ids[index]),
+                _ => ChildRecord::Node(ids[index]),
+            },
+            End::Root => unreachable!(),
+        };
+        let use_of =
+            |wire: Wire, index: usize, port: usize| match other_end(wire, End::Port(index, port)) {
+                End::Root => UseRecord::Root,
+                End::Port(other, _) if self.agents[other].kind == AgentKind::Era => {
+                    UseRecord::Unused
+                }
+                End::Port(other, _) => UseRecord::Node(ids[other]),
+                End::Free(_) => unreachable!(),
+            };
+        let records = order
+            .iter()
+            .enumerate()
+            .map(|(id, &index)| {
+                let agent = &self.agents[index];
+                let value = values[index] == Some(true);
+                let p = &agent.ports;
+                // The ports that hold the children, and the variables.
+                let (tag, label, children, uses) = match (agent.kind, value) {
+                    (AgentKind::Con, true) => (RecordTag::Lam, None, vec![2], vec![1]),
+                    (AgentKind::Con, false) => (RecordTag::App, None, vec![0, 1], vec![]),
+                    (AgentKind::Fan(l), true) => (RecordTag::Sup, Some(l), vec![1, 2], vec![]),
+                    (AgentKind::Fan(l), false) => (RecordTag::Dup, Some(l), vec![0], vec![1, 2]),
+                    (AgentKind::Num(n), _) => (RecordTag::Num(n), None, vec![], vec![]),
+                    (AgentKind::Op2(op), _) => (RecordTag::Op2(op), None, vec![0, 1], vec![]),
+                    _ => unreachable!(),
+                };
+                NodeRecord {
+                    id,
+                    tag,
+                    label,
+                    children: children
+                        .into_iter()
+                        .map(|port| child(p[port], End::Port(index, port)))
+                        .collect(),
+                    uses: uses
+                        .into_iter()
+                        .map(|port| use_of(p[port], index, port))
+                        .collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+        TermGraph::from_dump(&records)
+    }
+}
+
+impl fmt::Display for Net {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, wire) in &self.roots {
+            match self.roots.len() == 1 && name == "root" {
+                true => writeln!(f, "root w{}", wire)?,
+                false => writeln!(f, "root w{} {}", wire, name)?,
+            }
+        }
+        for (wire, name) in &self.free {
+            match name {
+                Some(name) => writeln!(f, "free w{} {}", wire, name)?,
+                None => writeln!(f, "free w{}", wire)?,
+            }
+        }
+        for agent in &self.agents {
+            match agent.kind {
+                AgentKind::Con => write!(f, "con")?,
+                AgentKind::Fan(label) => write!(f, "fan {}", label)?,
+                AgentKind::Era => write!(f, "era")?,
+                AgentKind::Num(n) => write!(f, "num {}", n)?,
+                AgentKind::Op2(op) => write!(f, "op2 {}", op.symbol())?,
+                AgentKind::Ref(name) => write!(f, "ref {}", name)?,
+            }
+            for wire in &agent.ports {
+                write!(f, " w{}", wire)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of the wire named `field`, numbering wires in the order
+/// they are first named.
+fn wire_number<'s>(wires: &mut HashMap<&'s str, Wire>, field: &'s str) -> Wire {
+    let next = wires.len();
+    *wires.entry(field).or_insert(next)
+}
+
+/// Parses a net in the format written by `Display`, with wires numbered in
+/// the order they first appear.
+impl FromStr for Net {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut net = Net::default();
+        let mut wires: HashMap<&str, Wire> = HashMap::new();
+        let error = |part: &str, expected: String| -> Error {
+            ParseError::at(s, part, expected, format!("`{}`", part)).into()
+        };
+        for line in s.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let mut wire = |field| wire_number(&mut wires, field);
+            let (kind, ports) = match fields[..] {
+                ["root", w] => {
+                    net.roots.push(("root".to_string(), wire(w)));
+                    continue;
+                }
+                ["root", w, name] => {
+                    net.roots.push((name.to_string(), wire(w)));
+                    continue;
+                }
+                ["free", w] => {
+                    net.free.push((wire(w), None));
+                    continue;
+                }
+                ["free", w, name] => {
+                    net.free.push((wire(w), Some(name.intern())));
+                    continue;
+                }
+                ["con", ref ports @ ..] => (AgentKind::Con, ports),
+                ["fan", label, ref ports @ ..] => match label.parse() {
+                    Ok(label) => (AgentKind::Fan(label), ports),
+                    Err(_) => return Err(error(label, "a label".to_string())),
+                },
+                ["era", ref ports @ ..] => (AgentKind::Era, ports),
+                ["num", n, ref ports @ ..] => match n.parse() {
+                    Ok(n) => (AgentKind::Num(n), ports),
+                    Err(_) => return Err(error(n, "a number".to_string())),
+                },
+                ["op2", symbol, ref ports @ ..] => {
+                    match Op::ALL.into_iter().find(|op| op.symbol() == symbol) {
+                        Some(op) => (AgentKind::Op2(op), ports),
+                        None => return Err(error(symbol, "an operation".to_string())),
+                    }
+                }
+                ["ref", name, ref ports @ ..] => (AgentKind::Ref(name.intern()), ports),
+                [kind, ..] if ["root", "free"].contains(&kind) => {
+                    return Err(error(line, format!("`{} wire [name]`", kind)))
+                }
+                [kind, ..] => return Err(error(kind, "an agent".to_string())),
+                [] => unreachable!(),
+            };
+            if ports.len() != kind.arity() {
+                let expected = format!("{} wires for `{}`", kind.arity(), fields[0]);
+                let found = ports.len().to_string();
+                return Err(ParseError::at(s, line, expected, found).into());
+            }
+            let ports = ports.iter().map(|w| wire(w)).collect();
+            net.agents.push(Agent { kind, ports });
+        }
+        Ok(net)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    fn net(src: &str) -> Net {
+        Net::from(&TermGraph::from(&src.parse::<Term>().unwrap()))
+    }
+
+    #[test]
+    fn test_net_lower() {
+        assert_eq!(
+            net("λx (x y)").to_string(),
+            "root w0\nfree w1 y\ncon w0 w2 w3\ncon w2 w1 w3\n"
+        );
+        assert_eq!(
+            net("λx λy dup #3{a b} = y; #0{(+ a 1) b}").to_string(),
+            "root w0\n\
+             con w0 w1 w2\n\
+             era w1\n\
+             con w2 w3 w4\n\
+             fan 0 w4 w5 w6\n\
+             op2 + w7 w8 w5\n\
+             fan 3 w3 w7 w6\n\
+             num 1 w8\n"
+        );
+        assert_eq!(net("x").to_string(), "root w0\nfree w0 x\n");
+    }
+
+    #[test]
+    fn test_net_round_trip() {
+        for src in [
+            "λx (x y)",
+            "λx λy dup #3{a b} = y; #0{(+ a 1) b}",
+            "dup #0{a b} = λx x; (a b)",
+            "λf dup #1{f0 f1} = f; λx (f0 (f1 x))",
+            "(λx λy #2{x y} 4 5)",
+            "42",
+        ] {
+            let term_graph = TermGraph::from(&src.parse::<Term>().unwrap());
+            let net = Net::from(&term_graph);
+            let parsed: Net = net.to_string().parse().unwrap();
+            assert_eq!(parsed, net, "{}", src);
+            let read_back = parsed.to_graph().unwrap();
+            assert_eq!(read_back.dump(), term_graph.dump(), "{}", src);
+        }
+        // A partially reduced graph, whose root holds a dup's variable.
+        let mut term_graph =
+            TermGraph::from(&"dup #0{a b} = (λx #1{x x0} 1); a".parse::<Term>().unwrap());
+        term_graph.naive_reduce_step().unwrap();
+        let read_back = Net::from(&term_graph).to_graph().unwrap();
+        assert_eq!(read_back.dump(), term_graph.dump());
+        assert_eq!(Term::from(&read_back), Term::from(&term_graph));
+    }
+
+    #[test]
+    fn test_net_errors() {
+        let error = |src: &str| {
+            src.parse::<Net>()
+                .unwrap()
+                .to_graph()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("root w0\nnum 1 w0\nnum 2 w1\nera w1\nera w2"),
+            "graph error: invalid net: wire 2 has 1 ends"
+        );
+        assert_eq!(
+            error("root w0\nnum 1 w1\nnum 2 w1\nera w0"),
+            "graph error: invalid net: wire 1 connects two values"
+        );
+        assert_eq!(
+            error("root w0\nera w0"),
+            "graph error: invalid net: wire 0 connects two uses"
+        );
+        assert_eq!(
+            error("root w0\nref id w0"),
+            "graph error: invalid net: the root holds reference id, but a graph is read back without definitions"
+        );
+        assert_eq!(
+            error("root w0\nnum 1 w0\ncon w1 w2 w3\ncon w1 w2 w3"),
+            "graph error: invalid net: agent 1 is not reached from the root"
+        );
+        assert_eq!(
+            error("root w0\nroot w1 r\nnum 1 w0\nnum 2 w1"),
+            "graph error: invalid net: a graph has one root, but the net has 2"
+        );
+        let error = |src: &str| src.parse::<Net>().unwrap_err().to_string();
+        assert_eq!(
+            error("root w0\n// comment\n\ncon w0 w1"),
+            "parse error: line 4, column 1: expected 3 wires for `con`, found 2"
+        );
+        assert_eq!(
+            error("fan x w0 w1 w2"),
+            "parse error: line 1, column 5: expected a label, found `x`"
+        );
+        assert_eq!(
+            error("lam w0"),
+            "parse error: line 1, column 1: expected an agent, found `lam`"
+        );
+    }
+}
@@ -23,11 +23,14 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::str::FromStr;
 
-use crate::intern::Intern;
+use crate::error::{Error, ParseError};
+use crate::intern::{IStr, Intern};
 use crate::parser;
-use crate::syntax::{Label, Term};
+use crate::syntax::{Label, LabelGen, Op, Term};
 
 pub fn parse_var(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
     parser::guard(
@@ -65,22 +68,21 @@ pub fn parse_app(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
     return parser::guard(
         parser::text_parser("("),
         Box::new(|state| {
-            parser::list(
+            let (new_state, args) = parser::list(
                 parser::text_parser("("),
                 parser::text_parser(""),
                 parser::text_parser(")"),
                 Box::new(parse_term),
-                Box::new(|args| {
-                    if !args.is_empty() {
-                        args.into_iter()
-                            .reduce(|a, b| Box::new(Term::App(a, b)))
-                            .unwrap()
-                    } else {
-                        todo!()
-                    }
-                }),
+                Box::new(|args| args.into_iter().reduce(|a, b| Box::new(Term::App(a, b)))),
                 state,
-            )
+            )?;
+            match args {
+                Some(app) => Ok((new_state, app)),
+                None => {
+                    let (state, _) = parser::consume("(", state)?;
+                    parser::expected("term", state)
+                }
+            }
         }),
         state,
     );
@@ -101,12 +103,79 @@ fn num_here(state: parser::State) -> parser::Answer<String> {
     Ok((state, name))
 }
 
+/// Parses a number, e.g. `42`.
+pub fn parse_num(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        Box::new(|state| {
+            let (state, head) = parser::get_char(state)?;
+            Ok((state, head.is_ascii_digit()))
+        }),
+        Box::new(|state| {
+            let (state, text) = num_here(state)?;
+            match text.parse::<u64>() {
+                Ok(num) => Ok((state, Box::new(Term::Num(num)))),
+                Err(_) => Err(ParseError::new(
+                    state.code,
+                    state.index - text.len(),
+                    format!("a number at most {}", u64::MAX),
+                    format!("`{}`", text),
+                )),
+            }
+        }),
+        state,
+    )
+}
+
+/// Parses the symbol of an operation, if there is one after skipping.
+fn parse_op(state: parser::State) -> parser::Answer<Option<Op>> {
+    let (state, _) = parser::skip(state)?;
+    for op in Op::ALL {
+        let (next, matched) = parser::text_here(op.symbol(), state)?;
+        if matched {
+            return Ok((next, Some(op)));
+        }
+    }
+    Ok((state, None))
+}
+
+/// Parses a binary operation, e.g. `(+ x 1)`.
+pub fn parse_op2(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
+    parser::guard(
+        Box::new(|state| {
+            let (state, matched) = parser::text("(", state)?;
+            if !matched {
+                return Ok((state, false));
+            }
+            let (state, op) = parse_op(state)?;
+            Ok((state, op.is_some()))
+        }),
+        Box::new(|state| {
+            let (state, _) = parser::consume("(", state)?;
+            let (state, op) = parse_op(state)?;
+            let (state, val0) = parse_term(state)?;
+            let (state, val1) = parse_term(state)?;
+            let (state, _) = parser::consume(")", state)?;
+            Ok((state, Box::new(Term::Op2(op.unwrap(), val0, val1))))
+        }),
+        state,
+    )
+}
+
 /// Parses a label, e.g. `#0`, `#1`, `#2`, etc.
 pub fn parse_label(state: parser::State) -> parser::Answer<Label> {
     let (state, _) = parser::consume("#", state)?;
-    let (state, text) = num_here(state)?;
-    let num = text.parse::<u64>().map_err(|_| "label out of range")?;
-    Ok((state, num))
+    let (next, text) = num_here(state)?;
+    let max = MAX_LABEL.with(Cell::get);
+    match text.parse::<Label>() {
+        Ok(num) if num <= max => Ok((next, num)),
+        _ if text.is_empty() => Err(parser::error("label number", state)),
+        _ => Err(ParseError::new(
+            state.code,
+            state.index - 1,
+            format!("a label at most #{}", max),
+            format!("`#{}`", text),
+        )),
+    }
 }
 
 pub fn parse_sup(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
@@ -124,12 +193,21 @@ pub fn parse_sup(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
     )
 }
 
+/// Parses a dup, e.g. `dup #0{a b} = x; body`. Without a label, as in
+/// `dup {a b} = x; body`, the dup is given a fresh one once the whole term or
+/// program has been parsed (see `label_dups`).
 pub fn parse_dup(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
     return parser::guard(
         parser::text_parser("dup "),
         Box::new(|state| {
             let (state, _) = parser::consume("dup ", state)?;
-            let (state, label) = parse_label(state)?;
+            let (state, _) = parser::skip(state)?;
+            let unlabelled = (parser::head(state) == Some('{')).then_some(state.index);
+            let (state, label) = match unlabelled {
+                Some(_) => (state, 0),
+                None => parse_label(state)?,
+            };
+            DUPS.with(|dups| dups.borrow_mut().push(unlabelled));
             let (state, _) = parser::consume("{", state)?;
             let (state, nam0) = parser::name1(state)?;
             let (state, nam1) = parser::name1(state)?;
@@ -147,56 +225,307 @@ pub fn parse_dup(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
     );
 }
 
+/// Parses the keyword `in`, if it comes next after skipping, and is not just
+/// the start of a longer name.
+fn parse_in(state: parser::State) -> parser::Answer<bool> {
+    let (next, matched) = parser::text("in", state)?;
+    match matched && !parser::head(next).is_some_and(parser::is_letter) {
+        true => Ok((next, true)),
+        false => Ok((state, false)),
+    }
+}
+
+/// Parses a let, e.g. `let x = expr; body` or `let x = expr in body`. The body
+/// can itself be a let, so bindings can be chained without parentheses, as in
+/// `let x = a in let y = b in (x y)`.
 pub fn parse_let(state: parser::State) -> parser::Answer<Option<Box<Term>>> {
-    return parser::guard(
+    parser::guard(
         parser::text_parser("let "),
         Box::new(|state| {
             let (state, _) = parser::consume("let ", state)?;
             let (state, name) = parser::name1(state)?;
             let (state, _) = parser::consume("=", state)?;
             let (state, expr) = parse_term(state)?;
-            let (state, _) = parser::text(";", state)?;
+            let (state, _) =
+                parser::parser_or(&[parser::text_parser(";"), Box::new(parse_in)], state)?;
             let (state, body) = parse_term(state)?;
             Ok((state, Box::new(Term::Let(name.intern(), expr, body))))
         }),
         state,
-    );
+    )
+}
+
+/// The maximum nesting depth of terms parsed with `Term::from_str`.
+///
+/// Each level of nesting takes several kilobytes of stack in the parser (more
+/// in debug builds), so this keeps deeply nested input from overflowing even
+/// the 2 MiB stack of a spawned thread. Use [`parse_with_max_depth`] to pick a
+/// different limit.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// The range of labels accepted by [`parse_with_label_width`], for programs
+/// meant to run where labels are stored in fewer bits.
+///
+/// A graph packs each label into 32 bits of a node's header, so `U32` rejects
+/// while parsing the labels that building a graph would reject with
+/// [`GraphBuildError::LabelTooLarge`](crate::error::GraphBuildError::LabelTooLarge).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LabelWidth {
+    U16,
+    U32,
+    /// Any label; the width of `Term::from_str`.
+    #[default]
+    U64,
+}
+
+impl LabelWidth {
+    /// Returns the largest label of this width.
+    pub fn max(self) -> Label {
+        match self {
+            LabelWidth::U16 => u16::MAX.into(),
+            LabelWidth::U32 => u32::MAX.into(),
+            LabelWidth::U64 => u64::MAX,
+        }
+    }
+}
+
+thread_local! {
+    /// The current nesting depth of `parse_term`, and the maximum allowed.
+    static DEPTH: Cell<(usize, usize)> = const { Cell::new((0, usize::MAX)) };
+    /// The largest label `parse_label` accepts.
+    static MAX_LABEL: Cell<Label> = const { Cell::new(Label::MAX) };
+    /// For each dup parsed so far, in order, the offset of its `{` if it was
+    /// written without a label.
+    static DUPS: RefCell<Vec<Option<usize>>> = const { RefCell::new(vec![]) };
+}
+
+/// Tracks one level of nesting in `parse_term`, until dropped.
+pub(crate) struct DepthGuard;
+
+impl DepthGuard {
+    pub(crate) fn enter(state: parser::State) -> Result<Self, ParseError> {
+        DEPTH.with(|depth| {
+            let (current, max) = depth.get();
+            if current >= max {
+                let (state, _) = parser::skip(state)?;
+                let expected = format!("a term nested at most {} levels deep", max);
+                return Err(parser::error(&expected, state));
+            }
+            depth.set((current + 1, max));
+            Ok(DepthGuard)
+        })
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| {
+            let (current, max) = depth.get();
+            depth.set((current - 1, max));
+        });
+    }
 }
 
 pub fn parse_term(state: parser::State) -> parser::Answer<Box<Term>> {
+    let _guard = DepthGuard::enter(state)?;
     parser::grammar(
-        "Term",
+        "term",
         &[
             Box::new(parse_let),
             Box::new(parse_dup),
             Box::new(parse_lam),
+            Box::new(parse_op2),
             Box::new(parse_app),
             Box::new(parse_sup),
             Box::new(parse_var),
+            Box::new(parse_num),
             Box::new(|state| Ok((state, None))),
         ],
         state,
     )
 }
 
+/// Parses `s` as a term, failing with an error instead of overflowing the
+/// stack if it is nested more than `max_depth` levels deep.
+pub fn parse_with_max_depth(s: &str, max_depth: usize) -> Result<Term, Error> {
+    parse_with_limits(s, max_depth, Label::MAX)
+}
+
+/// Parses `s` as a term, failing with an error if it has a label that does
+/// not fit in `width`.
+pub fn parse_with_label_width(s: &str, width: LabelWidth) -> Result<Term, Error> {
+    parse_with_limits(s, DEFAULT_MAX_DEPTH, width.max())
+}
+
+fn parse_with_limits(s: &str, max_depth: usize, max_label: Label) -> Result<Term, Error> {
+    with_limits(max_depth, max_label, || {
+        let (state, mut term) = parse_term(parser::State::new(s))?;
+        expect_done(state)?;
+        label_dups(s, [&mut *term])?;
+        Ok(*term)
+    })
+}
+
+/// Runs `parse` with the nesting depth and labels limited to `max_depth` and
+/// `max_label`, and no dups parsed yet, restoring the outer limits and dups
+/// afterwards.
+pub(crate) fn with_limits<A>(max_depth: usize, max_label: Label, parse: impl FnOnce() -> A) -> A {
+    let outer = DEPTH.with(|depth| depth.replace((0, max_depth)));
+    let outer_max_label = MAX_LABEL.with(|max| max.replace(max_label));
+    let outer_dups = DUPS.with(|dups| dups.take());
+    let result = parse();
+    DEPTH.with(|depth| depth.set(outer));
+    MAX_LABEL.with(|max| max.set(outer_max_label));
+    DUPS.with(|dups| dups.replace(outer_dups));
+    result
+}
+
+/// Gives each dup parsed without a label a fresh one, above every label used
+/// by `terms`, which must be every term parsed from `s`, in order.
+fn label_dups<'t>(s: &str, terms: impl IntoIterator<Item = &'t mut Term>) -> Result<(), Error> {
+    let unlabelled = DUPS.with(|dups| dups.take());
+    if unlabelled.iter().all(Option::is_none) {
+        return Ok(());
+    }
+    // The labels of the dups in the order they were parsed, which is the
+    // order they are visited in here.
+    let mut dup_labels = vec![];
+    let mut labels = LabelGen::new();
+    for term in terms {
+        let mut stack = vec![term];
+        while let Some(term) = stack.pop() {
+            match term {
+                Term::Var(_) | Term::Ref(_) | Term::Num(_) => {}
+                Term::Lam(_, e) => stack.push(e),
+                Term::Sup(l, e1, e2) => {
+                    labels.reserve(*l);
+                    stack.extend([e2, e1].map(|e| &mut **e));
+                }
+                Term::Dup(l, _, _, e1, e2) => {
+                    dup_labels.push(l);
+                    stack.extend([e2, e1].map(|e| &mut **e));
+                }
+                Term::App(e1, e2) | Term::Op2(_, e1, e2) | Term::Let(_, e1, e2) => {
+                    stack.extend([e2, e1].map(|e| &mut **e));
+                }
+            }
+        }
+    }
+    for (label, offset) in dup_labels.iter().zip(&unlabelled) {
+        if offset.is_none() {
+            labels.reserve(**label);
+        }
+    }
+    let max = MAX_LABEL.with(Cell::get);
+    for (label, offset) in dup_labels.into_iter().zip(unlabelled) {
+        if let Some(offset) = offset {
+            *label = match labels.peek() {
+                Some(fresh) if fresh <= max => labels.fresh(),
+                _ => {
+                    let expected = format!("a fresh label at most #{}", max);
+                    return Err(ParseError::new(s, offset, expected, "`{`".to_string()).into());
+                }
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Fails unless only whitespace and comments are left after `state`.
+pub(crate) fn expect_done(state: parser::State) -> Result<(), Error> {
+    let (state, is_done) = parser::done(state)?;
+    if !is_done {
+        Err(parser::error("end of input", state).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// A top-level definition: its name and where the name starts, and its term.
+pub type Def = ((IStr, usize), Box<Term>);
+
+/// Parses a top-level definition, e.g. `def name = term;`.
+pub fn parse_def(state: parser::State) -> parser::Answer<Option<Def>> {
+    parser::guard(
+        parser::text_parser("def "),
+        Box::new(|state| {
+            let (state, _) = parser::consume("def ", state)?;
+            let (state, _) = parser::skip(state)?;
+            let offset = state.index;
+            let (state, name) = parser::name1(state)?;
+            let (state, _) = parser::consume("=", state)?;
+            let (state, term) = parse_term(state)?;
+            let (state, _) = parser::consume(";", state)?;
+            Ok((state, ((name.intern(), offset), term)))
+        }),
+        state,
+    )
+}
+
+/// The definitions of a program, in order, and its term, if it has one.
+pub type Program = (Vec<(IStr, Term)>, Option<Term>);
+
+/// Parses a program: any number of top-level definitions, followed by a term
+/// unless the program only defines names. Variables are not resolved to
+/// references (see [`Book::load`](crate::book::Book::load)).
+///
+/// Fails if the program defines a name twice.
+pub fn parse_program(s: &str) -> Result<Program, Error> {
+    parse_program_with(s, true)
+}
+
+/// Parses a program of definitions only, like [`parse_program`], failing at
+/// the term if there is one.
+pub fn parse_defs(s: &str) -> Result<Vec<(IStr, Term)>, Error> {
+    Ok(parse_program_with(s, false)?.0)
+}
+
+fn parse_program_with(s: &str, allow_main: bool) -> Result<Program, Error> {
+    with_limits(DEFAULT_MAX_DEPTH, Label::MAX, || {
+        let mut defs = vec![];
+        let mut seen = HashSet::new();
+        let mut state = parser::State::new(s);
+        loop {
+            let (next, def) = parse_def(state)?;
+            state = next;
+            let Some(((name, offset), term)) = def else {
+                break;
+            };
+            if !seen.insert(name) {
+                let expected = "a name that is not defined yet";
+                let found = format!("`{}`", name);
+                return Err(ParseError::new(s, offset, expected.to_string(), found).into());
+            }
+            defs.push((name, *term));
+        }
+        let mut main = None;
+        if !parser::done(state)?.1 {
+            if !allow_main {
+                return Err(parser::error("`def`", parser::skip(state)?.0).into());
+            }
+            let (state, term) = parse_term(state)?;
+            expect_done(state)?;
+            main = Some(*term);
+        }
+        let terms = defs.iter_mut().map(|(_, term)| term).chain(&mut main);
+        label_dups(s, terms)?;
+        Ok((defs, main))
+    })
+}
+
 impl FromStr for Term {
-    type Err = String;
+    type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (state, term) = parse_term(parser::State::new(s))?;
-        let (state, is_done) = parser::done(state).unwrap();
-        if !is_done {
-            Err(format!("unexpected input: {}", &s[state.index..]))
-        } else {
-            Ok(*term)
-        }
+        parse_with_max_depth(s, DEFAULT_MAX_DEPTH)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::intern::{IStr, InternStatic};
+    use crate::intern::InternStatic;
+    use crate::testing::arb_term;
     use proptest::prelude::*;
 
     #[test]
@@ -232,6 +561,18 @@ mod test {
                 "let x = y;\nz",
                 Box::new(Term::Let(x, Box::new(Term::Var(y)), Box::new(Term::Var(z)))),
             ),
+            (
+                "(== (* x 2) 42)",
+                Box::new(Term::Op2(
+                    Op::Eq,
+                    Box::new(Term::Op2(
+                        Op::Mul,
+                        Box::new(Term::Var(x)),
+                        Box::new(Term::Num(2)),
+                    )),
+                    Box::new(Term::Num(42)),
+                )),
+            ),
         ];
         for (input, expected) in test_cases {
             let state = parser::State::new(input);
@@ -277,47 +618,141 @@ mod test {
         }
     }
 
-    fn arb_var_name() -> impl Strategy<Value = IStr> {
-        "[_a-z][_a-zA-Z0-9]*".prop_map(|s| s.into())
+    #[test]
+    fn test_parse_label_width() {
+        let src = "dup #65536{a b} = #65535{x y}; (a b)";
+        assert!(parse_with_label_width(src, LabelWidth::U32).is_ok());
+        assert_eq!(
+            parse_with_label_width(src, LabelWidth::U16)
+                .unwrap_err()
+                .to_string(),
+            "parse error: line 1, column 5: expected a label at most #65535, found `#65536`"
+        );
+        assert!(parse_with_label_width("#18446744073709551616{x y}", LabelWidth::U64).is_err());
+        // The limit is reset afterwards.
+        assert!(src.parse::<Term>().is_ok());
     }
 
-    fn arb_label() -> impl Strategy<Value = Label> {
-        prop::num::u64::ANY
+    #[test]
+    fn test_parse_max_depth() {
+        let nested = |depth| format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse_with_max_depth(&nested(9), 10).is_ok());
+        assert_eq!(
+            parse_with_max_depth(&nested(10), 10)
+                .unwrap_err()
+                .to_string(),
+            "parse error: line 1, column 11: expected a term nested at most 10 levels deep, found `x`"
+        );
+        assert!(nested(DEFAULT_MAX_DEPTH - 1).parse::<Term>().is_ok());
+        // Under Miri, which is far slower, a term just past the limit has to do.
+        let depth = if cfg!(miri) {
+            DEFAULT_MAX_DEPTH + 1
+        } else {
+            100_000
+        };
+        assert!("λx ".repeat(depth).parse::<Term>().is_err());
+        // The limit is reset after an error.
+        assert!(nested(9).parse::<Term>().is_ok());
     }
 
-    fn arb_term() -> impl Strategy<Value = Term> {
-        let leaf = arb_var_name().prop_map(|v| Term::Var(v));
-        leaf.prop_recursive(8, 256, 5, |inner| {
-            prop_oneof![
-                (arb_var_name(), inner.clone()).prop_map(|(v, t)| Term::Lam(v, Box::new(t))),
-                (inner.clone(), inner.clone())
-                    .prop_map(|(f, arg)| Term::App(Box::new(f), Box::new(arg))),
-                (arb_label(), inner.clone(), inner.clone()).prop_map(|(l, a, b)| Term::Sup(
-                    l,
-                    Box::new(a),
-                    Box::new(b)
-                )),
-                (
-                    arb_label(),
-                    arb_var_name(),
-                    arb_var_name(),
-                    inner.clone(),
-                    inner.clone()
-                )
-                    .prop_map(|(l, a, b, expr, cont)| Term::Dup(
-                        l,
-                        a,
-                        b,
-                        Box::new(expr),
-                        Box::new(cont)
-                    )),
-                (arb_var_name(), inner.clone(), inner.clone()).prop_map(|(v, x, y)| Term::Let(
-                    v,
-                    Box::new(x),
-                    Box::new(y)
-                )),
-            ]
-        })
+    #[test]
+    fn test_parse_num() {
+        assert_eq!(
+            "(- 18446744073709551615 x)"
+                .parse::<Term>()
+                .unwrap()
+                .to_string(),
+            "(- 18446744073709551615 x)"
+        );
+        assert_eq!(
+            "18446744073709551616".parse::<Term>().unwrap_err().to_string(),
+            "parse error: line 1, column 1: expected a number at most 18446744073709551615, found `18446744073709551616`"
+        );
+        assert!("(+ x)".parse::<Term>().is_err());
+    }
+
+    #[test]
+    fn test_parse_empty_app() {
+        assert!("()".parse::<Term>().is_err());
+        assert!("(f ( ))".parse::<Term>().is_err());
+    }
+
+    #[test]
+    fn test_parse_unlabelled_dup() {
+        let parse = |src: &str| src.parse::<Term>().unwrap();
+        assert_eq!(
+            parse("λf dup {f0 f1} = f; dup {g0 g1} = f1; λx (f0 (g0 (g1 x)))"),
+            parse("λf dup #0{f0 f1} = f; dup #1{g0 g1} = f1; λx (f0 (g0 (g1 x)))")
+        );
+        // Fresh labels avoid every label written anywhere in the term.
+        assert_eq!(
+            parse("#0{dup {a b} = x; (a b) dup #4{c d} = #2{y z}; (c d)}"),
+            parse("#0{dup #5{a b} = x; (a b) dup #4{c d} = #2{y z}; (c d)}")
+        );
+        let (defs, main) =
+            parse_program("def f = λx dup #3{a b} = x; (a b);\ndup {a b} = f; (a b)").unwrap();
+        assert_eq!(defs.len(), 1);
+        assert_eq!(main, Some(parse("dup #4{a b} = f; (a b)")));
+        let err = parse_with_label_width("dup #65535{a b} = x; dup {c d} = a; b", LabelWidth::U16)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error: line 1, column 26: expected a fresh label at most #65535, found `{`"
+        );
+    }
+
+    #[test]
+    fn test_parse_let_in() {
+        let parse = |src: &str| src.parse::<Term>().unwrap();
+        assert_eq!(parse("let x = y in x"), parse("let x = y; x"));
+        assert_eq!(
+            parse("let x = a in let y = b in (x y)"),
+            parse("(let x = a; (let y = b; (x y)))")
+        );
+        assert_eq!(
+            parse("let x = a; let y = λz z in\nlet w = b\nin (x y w)"),
+            parse("(let x = a; (let y = λz z; (let w = b; ((x y) w))))")
+        );
+        // A name that only starts with `in` is the body.
+        assert_eq!(parse("let x = y inner"), parse("let x = y; inner"));
+        assert_eq!(parse("let x = y; in"), parse("let x = y; (in)"));
+        assert!("let x = y in".parse::<Term>().is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |src: &str| match src.parse::<Term>() {
+            Err(Error::Syntax(err)) => err,
+            result => panic!("{:?}", result),
+        };
+        let err = error("λx\n  (x\n  y");
+        assert_eq!(
+            (err.offset, err.line, err.column),
+            ("λx\n  (x\n  y".len(), 3, 4)
+        );
+        assert_eq!(err.expected, "term");
+        assert_eq!(err.found, "end of input");
+        assert_eq!(
+            err.to_string(),
+            "line 3, column 4: expected term, found end of input"
+        );
+        let err = error("#0{x y");
+        assert_eq!((err.column, err.expected.as_str()), (7, "`}`"));
+        let err = error("dup #0{a} = x; a");
+        assert_eq!((err.line, err.column), (1, 9));
+        assert_eq!((err.expected.as_str(), err.found.as_str()), ("name", "`}`"));
+        let err = error("(f ())");
+        assert_eq!((err.column, err.expected.as_str()), (5, "term"));
+        assert_eq!(err.found, "`)`");
+        let err = error("#{x y}");
+        assert_eq!((err.column, err.expected.as_str()), (2, "label number"));
+        let err = error("// comment\nλx x y");
+        assert_eq!((err.line, err.column), (2, 6));
+        assert_eq!(err.expected, "end of input");
+        assert_eq!(err.found, "`y`");
+        let err = error("λx ]");
+        assert_eq!((err.column, err.expected.as_str()), (4, "term"));
+        assert_eq!(err.found, "`]`");
     }
 
     proptest! {
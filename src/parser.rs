@@ -26,7 +26,7 @@
 // This parse library is more high-level and functional than existing alternatives.
 // A Parser is defined as (with details omitted):
 //
-//   Answer<A> = Result<(State, A), String>
+//   Answer<A> = Result<(State, A), ParseError>
 //   Parser<A> = Fn(State) -> Answer<A>>
 //
 // Similarly to https://github.com/AndrasKovacs/flatparse, there are 2 ways to fail.
@@ -43,15 +43,18 @@
 //    parse_animal : Parser<Animal>
 //
 // 2. Irrecoverable. Return:
-//    - Err(error_message)
+//    - Err(parse_error)
 //
-//    This will abort the entire parser, like a "throw", and return the error message. Use this
+//    This will abort the entire parser, like a "throw", and return the error, which says where
+//    the parser stopped, what it expected there, and what it found instead. Use this
 //    when you know that only one parsing branch can reach this location, yet the source is wrong.
 //
 // Check the Testree example at the bottom of this file.
 
 #![allow(dead_code)]
 
+use crate::error::ParseError;
+
 // Types
 // =====
 
@@ -71,7 +74,7 @@ impl<'a> State<'a> {
     }
 }
 
-pub type Answer<'a, A> = Result<(State<'a>, A), String>;
+pub type Answer<'a, A> = Result<(State<'a>, A), ParseError>;
 pub type Parser<'a, A> = Box<dyn Fn(State<'a>) -> Answer<'a, A>>;
 
 // Utils
@@ -82,7 +85,7 @@ pub fn find(text: &str, target: &str) -> usize {
         .unwrap_or_else(|| panic!("`{}` not in `{}`.", target, text))
 }
 
-pub fn read<'a, A>(parser: Parser<'a, A>, code: &'a str) -> Result<A, String> {
+pub fn read<'a, A>(parser: Parser<'a, A>, code: &'a str) -> Result<A, ParseError> {
     match parser(State { code, index: 0 }) {
         Ok((_, value)) => Ok(value),
         Err(msg) => Err(msg),
@@ -255,7 +258,7 @@ pub fn consume<'a>(pat: &str, state: State<'a>) -> Answer<'a, ()> {
     if matched {
         Ok((state, ()))
     } else {
-        expected(pat, pat.len(), state)
+        expected(&format!("`{}`", pat), state)
     }
 }
 
@@ -318,7 +321,7 @@ pub fn grammar<'a, A: 'a>(
             return Ok((state, value));
         }
     }
-    expected(name, 1, state)
+    expected(name, state)
 }
 
 // Combinators
@@ -391,7 +394,7 @@ pub fn list<'a, A: 'a, B: 'a>(
 // ====
 
 /// Checks if input is a valid character for names.
-fn is_letter(chr: char) -> bool {
+pub fn is_letter(chr: char) -> bool {
     chr.is_ascii_alphanumeric() || chr == '_' || chr == '.' || chr == '$'
 }
 
@@ -422,19 +425,38 @@ pub fn name1(state: State) -> Answer<String> {
     if !name1.is_empty() {
         Ok((state, name1))
     } else {
-        expected("name", 1, state)
+        expected("name", state)
     }
 }
 
 // Errors
 // ======
 
-pub fn expected<'a, A>(name: &str, size: usize, state: State<'a>) -> Answer<'a, A> {
-    Err(format!(
-        "Expected `{}`:\n{}",
-        name,
-        &highlight_error::highlight_error(state.index, state.index + size, state.code)
-    ))
+/// Aborts at the next token after skipping comments and whitespace, saying
+/// that `name` was expected there.
+pub fn expected<'a, A>(name: &str, state: State<'a>) -> Answer<'a, A> {
+    let (state, _) = skip(state)?;
+    Err(error(name, state))
+}
+
+/// Returns the error for expecting `name` right after the cursor, and finding
+/// the token there instead.
+pub fn error(name: &str, state: State) -> ParseError {
+    ParseError::new(state.code, state.index, name.to_string(), found(state))
+}
+
+/// Describes the token right after the cursor: a whole name if one starts
+/// there, and otherwise a single character, or the end of input.
+pub fn found(state: State) -> String {
+    let rest = state.rest().unwrap_or("");
+    match rest.chars().next() {
+        None => "end of input".to_string(),
+        Some(chr) if is_letter(chr) => {
+            let len = rest.find(|chr| !is_letter(chr)).unwrap_or(rest.len());
+            format!("`{}`", &rest[..len])
+        }
+        Some(chr) => format!("`{}`", chr),
+    }
 }
 
 // Tests
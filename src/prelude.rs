@@ -0,0 +1,98 @@
+//! Ready-made terms for common encodings.
+//!
+//! Since variables are affine, definitions that use an argument more than
+//! once duplicate it explicitly. Each such definition uses its own labels, in
+//! the range 100 and up, so that they don't interfere with each other.
+
+use crate::syntax::Term;
+
+/// The names and sources of the prelude definitions.
+const SOURCES: &[(&str, &str)] = &[
+    // Combinators.
+    ("id", "λx x"),
+    ("const", "λx λy x"),
+    ("compose", "λf λg λx (f (g x))"),
+    (
+        "Y",
+        "λf dup #100{f1 f2} = f; \
+         (λx (f1 dup #101{x1 x2} = x; (x1 x2)) λy (f2 dup #102{y1 y2} = y; (y1 y2)))",
+    ),
+    // Church booleans.
+    ("true", "λt λf t"),
+    ("false", "λt λf f"),
+    ("not", "λb λt λf ((b f) t)"),
+    ("and", "λa λb ((a b) λt λf f)"),
+    ("or", "λa λb ((a λt λf t) b)"),
+    // Pairs.
+    ("pair", "λa λb λp ((p a) b)"),
+    ("fst", "λp (p λa λb a)"),
+    ("snd", "λp (p λa λb b)"),
+    // Church numerals.
+    ("zero", "λf λx x"),
+    ("one", "λf λx (f x)"),
+    ("two", "λf dup #103{f1 f2} = f; λx (f1 (f2 x))"),
+    ("succ", "λn λf dup #104{f1 f2} = f; λx (f1 ((n f2) x))"),
+    (
+        "add",
+        "λm λn λf dup #105{f1 f2} = f; λx ((m f1) ((n f2) x))",
+    ),
+    ("mul", "λm λn λf (m (n f))"),
+    // Scott-encoded lists.
+    ("nil", "λc λn n"),
+    ("cons", "λh λt λc λn ((c h) t)"),
+];
+
+/// Returns the names of all prelude definitions.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    SOURCES.iter().map(|(name, _)| *name)
+}
+
+/// Returns the prelude definition named `name`, if there is one.
+pub fn get(name: &str) -> Option<Term> {
+    SOURCES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, src)| src.parse().unwrap())
+}
+
+/// Returns all prelude definitions.
+pub fn all() -> Vec<(&'static str, Term)> {
+    SOURCES
+        .iter()
+        .map(|(name, src)| (*name, src.parse().unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_all_parse() {
+        assert_eq!(all().len(), names().count());
+        assert!(get("id").is_some());
+        assert!(get("nope").is_none());
+    }
+
+    #[test]
+    fn test_prelude_terms() {
+        let mut runtime = Runtime::new();
+        runtime.load_prelude();
+        let test_cases = [
+            ("(not true)", "(λ_ (λv1 v1))"),
+            ("((and true) false)", "(λ_ (λv1 v1))"),
+            ("((or false) true)", "(λv1 (λ_ v1))"),
+            ("(fst ((pair true) false))", "(λv1 (λ_ v1))"),
+            ("((two not) true)", "(λv1 (λ_ v1))"),
+            ("(((add one two) not) true)", "(λ_ (λv1 v1))"),
+            ("(((mul two two) not) false)", "(λ_ (λv1 v1))"),
+            ("(((succ zero) not) false)", "(λv1 (λ_ v1))"),
+            ("((compose not) not)", "(λv1 (λv2 (λv3 ((v1 v2) v3))))"),
+        ];
+        for (src, expected) in test_cases {
+            let term = runtime.eval_str(src).unwrap();
+            assert_eq!(format!("{}", term), expected, "{}", src);
+        }
+    }
+}
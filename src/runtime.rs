@@ -0,0 +1,403 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::book::Book;
+use crate::error::Error;
+use crate::intern::{IStr, Intern};
+use crate::prelude;
+use crate::syntax::Term;
+use crate::vm::TermGraph;
+
+mod cache;
+mod check;
+mod compile;
+mod corpus;
+mod include;
+mod parallel;
+mod session;
+
+pub use cache::{CacheStats, NormalFormCache};
+pub use check::Diagnostic;
+pub use compile::{CompileOptions, CompileReport};
+pub use corpus::{Directive, Failure, TestFile, TestReport};
+pub use include::load_source;
+pub use parallel::{DirRun, FileRun};
+
+/// The number of rewrites a [`Runtime`] performs on a term, by default,
+/// before giving up on reaching its normal form.
+pub const DEFAULT_MAX_STEPS: u64 = 100_000_000;
+
+/// An evaluation session: a [`Book`] of definitions shared by every term
+/// evaluated with it, plus an optional cache of normal forms.
+#[derive(Debug)]
+pub struct Runtime {
+    /// Shared with the graphs being reduced, and copied on write.
+    book: Arc<Book>,
+    cache: Option<NormalFormCache>,
+    /// The number of rewrites after which evaluating a term fails with
+    /// [`Error::CostLimit`].
+    max_steps: u64,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Runtime {
+            book: Arc::default(),
+            cache: None,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+}
+
+impl Runtime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of rewrites after which evaluating a term fails with
+    /// [`Error::CostLimit`], so that a term without a normal form does not
+    /// run forever. Defaults to [`DEFAULT_MAX_STEPS`].
+    pub fn set_max_steps(&mut self, max_steps: u64) {
+        self.max_steps = max_steps;
+    }
+
+    /// Returns the number of rewrites after which evaluating a term fails.
+    pub fn max_steps(&self) -> u64 {
+        self.max_steps
+    }
+
+    /// Enables the normal-form cache, keeping at most `max_entries` entries.
+    ///
+    /// Replaces (and clears) any existing cache.
+    pub fn enable_cache(&mut self, max_entries: usize) {
+        self.cache = Some(NormalFormCache::new(max_entries));
+    }
+
+    /// Disables and drops the normal-form cache.
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
+    /// Returns the statistics of the normal-form cache, if it is enabled.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Defines (or redefines) `name` as `term`, as with
+    /// [`Runtime::define_all`].
+    pub fn define(&mut self, name: &str, term: Term) {
+        self.define_all([(name.intern(), term)]);
+    }
+
+    /// Defines (or redefines) each name of `defs` as its term, adding them to
+    /// the book with [`Book::add_definitions`].
+    ///
+    /// This invalidates the cached normal forms of the terms that use one of
+    /// the names, directly or through other definitions, including those
+    /// evaluated while it was still undefined. The rest of the cache is kept.
+    pub fn define_all(&mut self, defs: impl IntoIterator<Item = (IStr, Term)>) {
+        let defs: Vec<(IStr, Term)> = defs.into_iter().collect();
+        if let Some(cache) = &mut self.cache {
+            for (name, _) in &defs {
+                cache.invalidate(&name.to_string());
+            }
+        }
+        Arc::make_mut(&mut self.book).add_definitions(defs);
+    }
+
+    /// Returns the names `term` refers to, directly or through the
+    /// definitions it uses, whether they are defined or not.
+    pub fn dependencies(&self, term: &Term) -> HashSet<IStr> {
+        dependencies(&self.book, term)
+    }
+
+    /// Returns the names of the definitions that refer to `name`, directly or
+    /// through other definitions.
+    pub fn dependents(&self, name: &str) -> HashSet<IStr> {
+        let name = name.intern();
+        self.book
+            .names()
+            .filter(|x| {
+                self.dependencies(&self.book.def(*x).unwrap().term)
+                    .contains(&name)
+            })
+            .collect()
+    }
+
+    /// Defines every [`prelude`] term under its name, replacing any existing
+    /// definitions with the same names.
+    pub fn load_prelude(&mut self) {
+        let prelude = prelude::all().into_iter();
+        self.define_all(prelude.map(|(name, term)| (name.intern(), term)));
+    }
+
+    /// Returns the book of definitions.
+    pub fn book(&self) -> &Book {
+        &self.book
+    }
+
+    /// Returns a runtime without a cache, with the definitions of this one
+    /// and then `defs`, for evaluating a file that has its own definitions.
+    fn with_definitions(&self, defs: &[(IStr, Term)]) -> Runtime {
+        let mut runtime = Runtime {
+            book: self.book.clone(),
+            cache: None,
+            max_steps: self.max_steps,
+        };
+        if !defs.is_empty() {
+            runtime.define_all(defs.iter().cloned());
+        }
+        runtime
+    }
+
+    /// Reduces `term` to normal form, with each of its free variables that
+    /// names a definition [resolved](Book::resolve) to a reference to it
+    /// (see [`TermGraph::from_book`]).
+    ///
+    /// If the cache is enabled and already holds the normal form of a term
+    /// that is equal to `term` up to renaming of bound variables and labels,
+    /// that normal form is returned without reducing anything.
+    ///
+    /// Returns an error if `term` or one of the definitions is not well formed
+    /// (see [`TermGraph::try_from_term`](crate::vm::TermGraph::try_from_term)),
+    /// or [`Error::CostLimit`] if `term` is not in normal form after
+    /// [`Runtime::max_steps`] rewrites.
+    pub fn eval(&mut self, term: &Term) -> Result<Term, Error> {
+        Ok(self.eval_counted(term)?.0)
+    }
+
+    /// Like [`Runtime::eval`], but also returns the number of rewrites
+    /// performed, or `None` if the normal form came from the cache.
+    fn eval_counted(&mut self, term: &Term) -> Result<(Term, Option<usize>), Error> {
+        if let Some(cache) = &mut self.cache {
+            if let Some(normal_form) = cache.get(term) {
+                return Ok((normal_form, None));
+            }
+        }
+        let mut term_graph = self.graph(term)?;
+        let mut rewrites = 0;
+        while term_graph.naive_reduce_step().is_some() {
+            rewrites += 1;
+            if rewrites as u64 > self.max_steps {
+                return Err(Error::CostLimit {
+                    limit: self.max_steps,
+                    spent: self.max_steps,
+                });
+            }
+        }
+        let normal_form = Term::from(&term_graph);
+        if let Some(cache) = &mut self.cache {
+            let dependencies = dependencies(&self.book, term);
+            cache.insert_with_dependencies(term, normal_form.clone(), dependencies);
+        }
+        Ok((normal_form, Some(rewrites)))
+    }
+
+    /// Builds the graph for `term` without reducing it, resolving its free
+    /// variables to definitions like [`Runtime::eval`], so that it can be
+    /// reduced step by step.
+    pub fn graph(&self, term: &Term) -> Result<TermGraph, Error> {
+        TermGraph::from_book(self.book.clone(), &self.book.resolve(term))
+    }
+
+    /// Parses `src` and evaluates it with [`Runtime::eval`].
+    pub fn eval_str(&mut self, src: &str) -> Result<Term, Error> {
+        let term: Term = src.parse()?;
+        self.eval(&term)
+    }
+
+    /// Parses and evaluates each of `srcs` with [`Runtime::eval_str`], sharing
+    /// this runtime's definitions and cache between them.
+    ///
+    /// A parse or evaluation error only fails its own item.
+    pub fn eval_batch<'a>(&mut self, srcs: impl IntoIterator<Item = &'a str>) -> Batch {
+        let start = Instant::now();
+        let mut batch = Batch::default();
+        for src in srcs {
+            let result = src
+                .parse::<Term>()
+                .and_then(|term| self.eval_counted(&term));
+            batch.stats.items += 1;
+            match &result {
+                Ok((_, Some(rewrites))) => batch.stats.rewrites += rewrites,
+                Ok((_, None)) => batch.stats.cache_hits += 1,
+                Err(_) => batch.stats.errors += 1,
+            }
+            batch
+                .results
+                .push(result.map(|(normal_form, _)| normal_form));
+        }
+        batch.stats.elapsed = start.elapsed();
+        batch
+    }
+}
+
+/// Returns the names `term` refers to in `book`, as in
+/// [`Runtime::dependencies`].
+///
+/// A free variable counts as well as a reference, since it refers to a
+/// definition as soon as one is added under its name.
+fn dependencies(book: &Book, term: &Term) -> HashSet<IStr> {
+    let names = |term: &Term| &term.free_vars() | &term.refs();
+    let mut dependencies = HashSet::new();
+    let mut stack: Vec<IStr> = names(term).into_iter().collect();
+    while let Some(name) = stack.pop() {
+        if dependencies.insert(name) {
+            if let Some(def) = book.def(name) {
+                stack.extend(names(&def.term));
+            }
+        }
+    }
+    dependencies
+}
+
+/// The results of [`Runtime::eval_batch`].
+#[derive(Debug, Default)]
+pub struct Batch {
+    /// The normal form of each item, or its error.
+    pub results: Vec<Result<Term, Error>>,
+    pub stats: BatchStats,
+}
+
+/// Aggregate statistics of a [`Batch`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    /// The number of items in the batch.
+    pub items: usize,
+    /// The number of items that failed to parse or evaluate.
+    pub errors: usize,
+    /// The number of items whose normal form came from the cache.
+    pub cache_hits: usize,
+    /// The total number of rewrites performed.
+    pub rewrites: usize,
+    /// The time taken by the whole batch.
+    pub elapsed: Duration,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eval_with_definitions() {
+        let mut runtime = Runtime::new();
+        runtime.define("id", "λx x".parse().unwrap());
+        let term = runtime.eval_str("(id (id λy y))").unwrap();
+        assert_eq!(format!("{}", term), "(λv1 v1)");
+        assert!(runtime.eval_str("(id").is_err());
+
+        // A definition can refer to itself, and is only unfolded as needed.
+        runtime.define("ones", "λc ((c a) ones)".parse().unwrap());
+        let term = runtime.eval_str("(ones λh λt h)").unwrap();
+        assert_eq!(format!("{}", term), "a");
+        let term = runtime.eval_str("ones").unwrap();
+        assert_eq!(format!("{}", term), "(λv1 ((v1 a) ones))");
+    }
+
+    #[test]
+    fn test_eval_max_steps() {
+        let mut runtime = Runtime::new();
+        runtime.set_max_steps(1);
+        assert!(runtime.eval_str("((λx x) y)").is_ok());
+        assert!(matches!(
+            runtime.eval_str("((λx x) ((λy y) z))"),
+            Err(Error::CostLimit { limit: 1, spent: 1 })
+        ));
+
+        let batch = runtime.eval_batch(["((λx x) y)", "((λx x) ((λy y) z))"]);
+        assert_eq!(batch.stats.errors, 1);
+    }
+
+    #[test]
+    fn test_graph() {
+        let mut runtime = Runtime::new();
+        runtime.define("id", "λx x".parse().unwrap());
+        let mut term_graph = runtime.graph(&"(id λy y)".parse().unwrap()).unwrap();
+        // The definition is only resolved, not reduced.
+        assert!(term_graph.naive_reduce_step().is_some());
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_eval_cached() {
+        let mut runtime = Runtime::new();
+        runtime.enable_cache(8);
+        runtime.define("id", "λx x".parse().unwrap());
+        runtime.eval_str("(id λa a)").unwrap();
+        // Equal up to renaming of bound variables.
+        let term = runtime.eval_str("(id λb b)").unwrap();
+        assert_eq!(format!("{}", term), "(λv1 v1)");
+        let stats = runtime.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.len), (1, 1, 1));
+
+        runtime.define("id", "λx λy x".parse().unwrap());
+        assert_eq!(runtime.cache_stats().unwrap().len, 0);
+        let term = runtime.eval_str("(id λb b)").unwrap();
+        assert_eq!(format!("{}", term), "(λ_ (λv1 v1))");
+    }
+
+    #[test]
+    fn test_eval_cached_keeps_labels() {
+        let mut cached = Runtime::new();
+        cached.enable_cache(8);
+        let mut uncached = Runtime::new();
+        for runtime in [&mut cached, &mut uncached] {
+            runtime.define("f", "λp dup #0{a b} = p; (a b)".parse().unwrap());
+        }
+        for src in ["(f #0{x y})", "(f #1{x y})", "#5{a b}", "#7{a b}"] {
+            assert_eq!(
+                cached.eval_str(src).unwrap(),
+                uncached.eval_str(src).unwrap()
+            );
+        }
+        assert_eq!(cached.cache_stats().unwrap().hits, 0);
+    }
+
+    #[test]
+    fn test_redefine_invalidates_dependents() {
+        let mut runtime = Runtime::new();
+        runtime.enable_cache(8);
+        runtime.define("id", "λx x".parse().unwrap());
+        runtime.define("wrap", "λx (id x)".parse().unwrap());
+        runtime.define("k", "λx λy x".parse().unwrap());
+        runtime.eval_str("(wrap λa a)").unwrap();
+        runtime.eval_str("(k λa a)").unwrap();
+        runtime.eval_str("(later λa a)").unwrap();
+        let dependents: Vec<IStr> = runtime.dependents("id").into_iter().collect();
+        assert_eq!(dependents, ["wrap".intern()]);
+
+        // Only the entry that uses `id`, through `wrap`, is dropped.
+        runtime.define("id", "λx λy y".parse().unwrap());
+        assert_eq!(runtime.cache_stats().unwrap().len, 2);
+        // Defining a name that was free when an entry was cached drops it too.
+        runtime.define("later", "λx x".parse().unwrap());
+        assert_eq!(runtime.cache_stats().unwrap().len, 1);
+        let term = runtime.eval_str("(later λa a)").unwrap();
+        assert_eq!(format!("{}", term), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_eval_batch() {
+        let mut runtime = Runtime::new();
+        runtime.enable_cache(8);
+        runtime.define("id", "λx x".parse().unwrap());
+        let batch = runtime.eval_batch(["(id λa a)", "(id", "(id λb b)", "((id id) id)"]);
+        let results: Vec<String> = batch
+            .results
+            .iter()
+            .map(|result| match result {
+                Ok(term) => format!("{}", term),
+                Err(_) => "error".to_string(),
+            })
+            .collect();
+        assert_eq!(results, ["(λv1 v1)", "error", "(λv1 v1)", "(λv1 v1)"]);
+        assert_eq!(batch.stats.items, 4);
+        assert_eq!(batch.stats.errors, 1);
+        assert_eq!(batch.stats.cache_hits, 1);
+        // Besides the beta reductions, each `id` that is applied or ends up
+        // in the root is unfolded by a Ref rewrite.
+        assert_eq!(batch.stats.rewrites, 7);
+    }
+}
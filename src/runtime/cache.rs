@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::intern::{IStr, Intern};
+use crate::syntax::Term;
+
+/// Statistics of a [`NormalFormCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of lookups that found a normal form.
+    pub hits: usize,
+    /// The number of lookups that found nothing.
+    pub misses: usize,
+    /// The number of entries dropped to stay within the size bound.
+    pub evictions: usize,
+    /// The number of entries currently in the cache.
+    pub len: usize,
+}
+
+/// A bounded cache from terms to their normal forms.
+///
+/// Terms are looked up by their canonical binders (see
+/// [`Term::canonicalize_binders`]), so a hit only requires the term to be equal
+/// to a cached one up to renaming of bound variables. Labels are part of the
+/// key, since a cached normal form carries the labels of the cached term. When
+/// full, the oldest entry is evicted.
+///
+/// Each entry may record the names of the definitions its normal form depends
+/// on, so that redefining one of them only invalidates the entries that used
+/// it (see [`NormalFormCache::invalidate`]).
+#[derive(Debug)]
+pub struct NormalFormCache {
+    entries: HashMap<Term, (Term, HashSet<IStr>)>,
+    order: VecDeque<Term>,
+    max_entries: usize,
+    stats: CacheStats,
+}
+
+impl NormalFormCache {
+    pub fn new(max_entries: usize) -> Self {
+        NormalFormCache {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            max_entries,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the cached normal form of `term`, if there is one.
+    pub fn get(&mut self, term: &Term) -> Option<Term> {
+        let normal_form = self
+            .entries
+            .get(&term.canonicalize_binders())
+            .map(|(normal_form, _)| normal_form.clone());
+        match normal_form {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        normal_form
+    }
+
+    /// Caches `normal_form` as the normal form of `term`, which does not
+    /// depend on any definitions.
+    pub fn insert(&mut self, term: &Term, normal_form: Term) {
+        self.insert_with_dependencies(term, normal_form, HashSet::new());
+    }
+
+    /// Caches `normal_form` as the normal form of `term`, which depends on the
+    /// definitions named in `dependencies`.
+    pub fn insert_with_dependencies(
+        &mut self,
+        term: &Term,
+        normal_form: Term,
+        dependencies: HashSet<IStr>,
+    ) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let key = term.canonicalize_binders();
+        if self
+            .entries
+            .insert(key.clone(), (normal_form, dependencies))
+            .is_some()
+        {
+            return;
+        }
+        self.order.push_back(key);
+        while self.entries.len() > self.max_entries {
+            let oldest = self.order.pop_front().unwrap();
+            self.entries.remove(&oldest);
+            self.stats.evictions += 1;
+        }
+    }
+
+    /// Removes the entries that depend on the definition named `name`,
+    /// returning how many there were.
+    pub fn invalidate(&mut self, name: &str) -> usize {
+        let name = name.intern();
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, (_, dependencies)| !dependencies.contains(&name));
+        let entries = &self.entries;
+        self.order.retain(|key| entries.contains_key(key));
+        before - self.entries.len()
+    }
+
+    /// Removes every entry, keeping the statistics.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Returns the entries, oldest first, as pairs of a canonical term and
+    /// its normal form.
+    pub fn entries(&self) -> impl Iterator<Item = (&Term, &Term)> {
+        self.order.iter().map(|key| (key, &self.entries[key].0))
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.entries.len(),
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(src: &str) -> Term {
+        src.parse().unwrap()
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut cache = NormalFormCache::new(2);
+        cache.insert(&parse("a"), parse("a"));
+        cache.insert(&parse("b"), parse("b"));
+        cache.insert(&parse("c"), parse("c"));
+        assert!(cache.get(&parse("a")).is_none());
+        assert_eq!(cache.get(&parse("c")), Some(parse("c")));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                evictions: 1,
+                len: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut cache = NormalFormCache::new(4);
+        let uses = |names: &[&str]| names.iter().map(|name| name.intern()).collect();
+        cache.insert_with_dependencies(&parse("(f a)"), parse("a"), uses(&["f"]));
+        cache.insert_with_dependencies(&parse("(g a)"), parse("a"), uses(&["f", "g"]));
+        cache.insert(&parse("a"), parse("a"));
+        assert_eq!(cache.invalidate("g"), 1);
+        assert_eq!(cache.invalidate("f"), 1);
+        assert_eq!(cache.stats().len, 1);
+        assert!(cache.get(&parse("a")).is_some());
+    }
+
+    #[test]
+    fn test_labels_in_key() {
+        let mut cache = NormalFormCache::new(4);
+        cache.insert(&parse("#5{a b}"), parse("#5{a b}"));
+        assert!(cache.get(&parse("#7{a b}")).is_none());
+        assert_eq!(cache.get(&parse("#5{a b}")), Some(parse("#5{a b}")));
+    }
+}
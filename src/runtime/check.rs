@@ -0,0 +1,121 @@
+use std::fmt;
+
+use super::{dependencies, Runtime, TestFile};
+use crate::error::Error;
+use crate::intern::IStr;
+use crate::lint::{BinderIssue, BinderLint, BinderReport, LabelConflict, LabelReport, Severity};
+
+/// A problem found by [`Runtime::check`].
+#[derive(Debug)]
+pub enum Diagnostic {
+    /// The source could not be parsed, so nothing else was checked.
+    Parse(Error),
+    /// The term refers to a name that is neither bound nor defined, directly
+    /// or through the definitions it uses.
+    Unbound(IStr),
+    Binder(BinderLint),
+    Label(LabelConflict),
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Diagnostic::Parse(_) => Severity::Error,
+            Diagnostic::Unbound(_) | Diagnostic::Label(_) => Severity::Warning,
+            Diagnostic::Binder(lint) => lint.severity,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Parse(error) => write!(f, "{}: {}", self.severity(), error),
+            Diagnostic::Unbound(name) => {
+                write!(f, "{}: {} is not defined", self.severity(), name)
+            }
+            Diagnostic::Binder(lint) => write!(f, "{}", lint),
+            Diagnostic::Label(conflict) => write!(f, "{}: {}", self.severity(), conflict),
+        }
+    }
+}
+
+impl Runtime {
+    /// Checks the source of a `.ic` file without reducing it, returning every
+    /// problem found, in order of severity (most serious first).
+    ///
+    /// The program is parsed, then its term is checked for binder and label
+    /// lints (see [`BinderReport`] and [`LabelReport`]), which include every
+    /// use of a variable that keeps a graph from being built, and for names
+    /// that are not defined by the file or the book. Unbound variables are
+    /// only reported if neither defines them.
+    pub fn check(&self, src: &str) -> Vec<Diagnostic> {
+        let (runtime, term) = match TestFile::parse(src) {
+            Ok(file) => (self.with_definitions(&file.defs), file.term),
+            Err(error) => return vec![Diagnostic::Parse(error)],
+        };
+        let binders = BinderReport::of_term(&term);
+        let mut diagnostics = vec![];
+        let mut unbound: Vec<IStr> = dependencies(&runtime.book, &term)
+            .into_iter()
+            .filter(|name| runtime.book.def(*name).is_none())
+            .collect();
+        unbound.sort_by_key(|name| name.to_string());
+        diagnostics.extend(unbound.into_iter().map(Diagnostic::Unbound));
+        diagnostics.extend(
+            binders
+                .lints
+                .into_iter()
+                .filter(|lint| lint.issue != BinderIssue::Unbound)
+                .map(Diagnostic::Binder),
+        );
+        diagnostics.extend(
+            LabelReport::of_term(&term)
+                .conflicts
+                .into_iter()
+                .map(Diagnostic::Label),
+        );
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity()));
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let mut runtime = Runtime::new();
+        runtime.define("id", "λx x".parse().unwrap());
+        let diagnostics: Vec<String> = runtime
+            .check(
+                "-- assert_steps_lt: 10\n\
+                 (λx λy (dup #0{a b} = x; dup #0{c d} = a; (c (d (b (id z))))) λw (w w))",
+            )
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "error: w is used 2 times (at /1)",
+                "warning: z is not defined",
+                "warning: label #0 of the dup at /0/0/0/1 is also used by the dup at /0/0/0; \
+                 consider relabeling it to #1",
+                "note: y is never used (at /0/0)",
+            ]
+        );
+
+        let diagnostics = runtime.check("λx dup #0{a a} = x; a");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error: dup binds a twice (at /0)"
+        );
+        let diagnostics = runtime.check("(λx x");
+        assert!(matches!(diagnostics[..], [Diagnostic::Parse(_)]));
+        assert!(runtime.check("(id λx x)").is_empty());
+        assert!(runtime.check("def k = λx λy x;\n(k id)").is_empty());
+    }
+}
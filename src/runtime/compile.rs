@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use super::Runtime;
+use crate::intern::IStr;
+use crate::syntax::Term;
+use crate::vm::TermGraph;
+
+/// Options for [`Runtime::compile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// Definitions with at most this many nodes (see [`Term::size`]) are
+    /// inlined into the other definitions. 0 disables inlining.
+    pub inline_max_size: usize,
+    /// Whether to reduce closed definitions to normal form.
+    pub normalize_closed: bool,
+    /// The number of rewrites after which the normalization of a closed
+    /// definition is abandoned, leaving the definition as it was.
+    pub normalize_max_steps: usize,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            inline_max_size: 8,
+            normalize_closed: true,
+            normalize_max_steps: 10_000,
+        }
+    }
+}
+
+/// What [`Runtime::compile`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompileReport {
+    /// The number of uses of definitions that were replaced by their terms.
+    pub inlined: usize,
+    /// The number of closed definitions that were reduced to normal form.
+    pub normalized: usize,
+}
+
+impl Runtime {
+    /// Simplifies the book of definitions, so that evaluation starts from
+    /// smaller graphs.
+    ///
+    /// First, small definitions that do not (directly or indirectly) refer to
+    /// themselves are inlined into the other definitions. Then definitions
+    /// without free variables or references are reduced to normal form, giving
+    /// up on those
+    /// that take more than `normalize_max_steps` rewrites. Every definition
+    /// stays defined under its name. Like [`Runtime::define`], this clears the
+    /// normal-form cache.
+    pub fn compile(&mut self, options: &CompileOptions) -> CompileReport {
+        let mut report = CompileReport::default();
+        if options.inline_max_size > 0 {
+            let recursive = self.recursive_definitions();
+            let inlinable: Vec<(IStr, Term)> = self
+                .book
+                .names()
+                .map(|name| (name, &self.book.def(name).unwrap().term))
+                .filter(|(name, term)| {
+                    !recursive.contains(name) && term.size() <= options.inline_max_size
+                })
+                .map(|(name, term)| (name, term.clone()))
+                .collect();
+            let book = Arc::make_mut(&mut self.book);
+            for (name, term) in &inlinable {
+                let free = term.free_vars();
+                for (other, def) in book.defs_mut() {
+                    if other != *name {
+                        *def = inline(def, *name, term, &free, &mut report.inlined);
+                    }
+                }
+            }
+        }
+        if options.normalize_closed {
+            for (_, def) in Arc::make_mut(&mut self.book).defs_mut() {
+                if !def.free_vars().is_empty() || !def.refs().is_empty() {
+                    continue;
+                }
+                if let Some(normal_form) = normalize(def, options.normalize_max_steps) {
+                    *def = normal_form;
+                    report.normalized += 1;
+                }
+            }
+        }
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
+        report
+    }
+
+    /// Returns the names of the definitions that can reach themselves through
+    /// the definitions they use.
+    fn recursive_definitions(&self) -> HashSet<IStr> {
+        let uses: HashMap<IStr, Vec<IStr>> = self
+            .book
+            .names()
+            .map(|name| {
+                let used = self.book.def(name).unwrap().term.refs();
+                (name, used.into_iter().collect())
+            })
+            .collect();
+        let mut recursive = HashSet::new();
+        for name in uses.keys() {
+            let mut seen = HashSet::new();
+            let mut stack = uses[name].clone();
+            while let Some(x) = stack.pop() {
+                if x == *name {
+                    recursive.insert(*name);
+                    break;
+                }
+                if seen.insert(x) {
+                    stack.extend(&uses[&x]);
+                }
+            }
+        }
+        recursive
+    }
+}
+
+/// Replaces the references to `name` in `term` by `def`, whose free variables
+/// are `free`.
+///
+/// References under a binder of one of `free` are left alone, since `def`
+/// would be captured there.
+fn inline(term: &Term, name: IStr, def: &Term, free: &HashSet<IStr>, count: &mut usize) -> Term {
+    let mut go = |e: &Term, binders: &[IStr]| {
+        if binders.iter().any(|x| free.contains(x)) {
+            e.clone()
+        } else {
+            inline(e, name, def, free, count)
+        }
+    };
+    match term {
+        Term::Ref(x) if *x == name => {
+            *count += 1;
+            def.clone()
+        }
+        Term::Var(x) => Term::Var(*x),
+        Term::Ref(x) => Term::Ref(*x),
+        Term::Num(n) => Term::Num(*n),
+        Term::Lam(x, e) => Term::Lam(*x, Box::new(go(e, &[*x]))),
+        Term::App(e1, e2) => Term::App(Box::new(go(e1, &[])), Box::new(go(e2, &[]))),
+        Term::Sup(l, e1, e2) => Term::Sup(*l, Box::new(go(e1, &[])), Box::new(go(e2, &[]))),
+        Term::Op2(op, e1, e2) => Term::Op2(*op, Box::new(go(e1, &[])), Box::new(go(e2, &[]))),
+        Term::Dup(l, a, b, e, cont) => {
+            let e = go(e, &[]);
+            Term::Dup(*l, *a, *b, Box::new(e), Box::new(go(cont, &[*a, *b])))
+        }
+        Term::Let(x, e, cont) => {
+            let e = go(e, &[]);
+            Term::Let(*x, Box::new(e), Box::new(go(cont, &[*x])))
+        }
+    }
+}
+
+/// Reduces `term` to normal form, or returns `None` if that takes more than
+/// `max_steps` rewrites or `term` is not well formed.
+fn normalize(term: &Term, max_steps: usize) -> Option<Term> {
+    let mut term_graph = TermGraph::try_from_term(term).ok()?;
+    for _ in 0..=max_steps {
+        if term_graph.naive_reduce_step().is_none() {
+            return Some(Term::from(&term_graph));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let mut runtime = Runtime::new();
+        runtime.define("id", "λx x".parse().unwrap());
+        runtime.define("k", "λx λy (id x)".parse().unwrap());
+        runtime.define("loop", "λx (loop x)".parse().unwrap());
+        runtime.define("shadow", "λid (id loop)".parse().unwrap());
+        let report = runtime.compile(&CompileOptions::default());
+        assert_eq!(
+            report,
+            CompileReport {
+                inlined: 1,
+                normalized: 2,
+            }
+        );
+        let book = runtime.book();
+        assert_eq!(format!("{}", book.get("k").unwrap()), "(λv1 (λ_ v1))");
+        assert_eq!(format!("{}", book.get("loop").unwrap()), "(λx (loop x))");
+        // `loop` is recursive, and `id` is shadowed.
+        assert_eq!(
+            format!("{}", book.get("shadow").unwrap()),
+            "(λid (id loop))"
+        );
+        assert_eq!(
+            format!("{}", runtime.eval_str("(k λz z)").unwrap()),
+            "(λ_ (λv1 v1))"
+        );
+    }
+
+    #[test]
+    fn test_compile_step_limit() {
+        let mut runtime = Runtime::new();
+        let src = "((λx x) ((λy y) ((λz z) λw w)))";
+        runtime.define("slow", src.parse().unwrap());
+        let options = CompileOptions {
+            normalize_max_steps: 2,
+            ..CompileOptions::default()
+        };
+        assert_eq!(runtime.compile(&options).normalized, 0);
+        assert_eq!(runtime.book().get("slow"), Some(&src.parse().unwrap()));
+        assert_eq!(runtime.compile(&CompileOptions::default()).normalized, 1);
+    }
+}
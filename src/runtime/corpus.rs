@@ -0,0 +1,293 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{load_source, Runtime};
+use crate::error::{Error, ParseError};
+use crate::intern::IStr;
+use crate::parse::parse_program;
+use crate::syntax::Term;
+
+/// The prefix of a test directive line in a `.ic` file.
+pub(super) const DIRECTIVE: &str = "--";
+
+/// An assertion about the term of a `.ic` file, written on its own line as
+/// `-- assert_normalizes_to: <term>` or `-- assert_steps_lt: <n>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    /// The term reduces to this normal form, up to renaming of bound
+    /// variables and labels.
+    NormalizesTo(Term),
+    /// The term reaches its normal form in fewer than this many rewrites.
+    StepsLt(usize),
+}
+
+/// The program of a `.ic` test file, along with its directives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFile {
+    /// The definitions of the program, in order.
+    pub defs: Vec<(IStr, Term)>,
+    /// The term of the program, whose free variables may name `defs`.
+    pub term: Term,
+    pub directives: Vec<Directive>,
+}
+
+impl TestFile {
+    /// Parses a `.ic` file: a program (see [`parse_program`]) that must have a
+    /// term. Directive lines are removed from the source before the program
+    /// is parsed, so they may appear anywhere in the file.
+    pub fn parse(src: &str) -> Result<TestFile, Error> {
+        let mut term_src = String::with_capacity(src.len());
+        let mut directives = vec![];
+        for line in src.lines() {
+            match line.trim_start().strip_prefix(DIRECTIVE) {
+                Some(directive) => directives.push(parse_directive(src, directive.trim())?),
+                None => term_src.push_str(line),
+            }
+            term_src.push('\n');
+        }
+        let (defs, term) = parse_program(&term_src)?;
+        let term = term.ok_or_else(|| {
+            let found = "end of input".to_string();
+            ParseError::new(&term_src, term_src.len(), "term".to_string(), found)
+        })?;
+        Ok(TestFile {
+            defs,
+            term,
+            directives,
+        })
+    }
+}
+
+/// Parses `directive`, the text after the `--` of a directive line of `src`.
+fn parse_directive(src: &str, directive: &str) -> Result<Directive, Error> {
+    let error = |part: &str, expected: &str| {
+        ParseError::at(src, part, expected.to_string(), format!("`{}`", part)).into()
+    };
+    let (name, arg) = directive
+        .split_once(':')
+        .ok_or_else(|| error(directive, "`name: argument`"))?;
+    let arg = arg.trim();
+    match name.trim() {
+        "assert_normalizes_to" => match arg.parse() {
+            Ok(term) => Ok(Directive::NormalizesTo(term)),
+            Err(err) => Err(err.within(src, arg)),
+        },
+        "assert_steps_lt" => arg
+            .parse()
+            .map(Directive::StepsLt)
+            .map_err(|_| error(arg, "a step count")),
+        name => Err(error(name, "`assert_normalizes_to` or `assert_steps_lt`")),
+    }
+}
+
+/// A directive that did not hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The normal form differs from the expected one. Both are canonical (see
+    /// [`Term::canonicalize`]).
+    NormalForm { expected: Term, actual: Term },
+    /// The normal form was not reached in fewer than `limit` rewrites.
+    Steps { limit: usize },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::NormalForm { expected, actual } => {
+                let expected = expected.to_string();
+                let actual = actual.to_string();
+                let column = expected
+                    .chars()
+                    .zip(actual.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                writeln!(f, "normal form differs")?;
+                writeln!(f, "  expected: {}", expected)?;
+                writeln!(f, "    actual: {}", actual)?;
+                write!(f, "            {}^", " ".repeat(column))
+            }
+            Failure::Steps { limit } => {
+                write!(f, "not normalized in fewer than {} steps", limit)
+            }
+        }
+    }
+}
+
+/// The outcome of one `.ic` file, as returned by [`Runtime::run_test_dir`].
+#[derive(Debug)]
+pub struct TestReport {
+    pub path: PathBuf,
+    /// The directives that did not hold, or the error that prevented the file
+    /// from being run.
+    pub result: Result<Vec<Failure>, Error>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_ok_and(|failures| failures.is_empty())
+    }
+}
+
+impl Runtime {
+    /// Checks the directives of a test file, with its definitions added to
+    /// those of the runtime, resolving its free variables to definitions like
+    /// [`Runtime::eval`]. An expected normal form is resolved too, so it may
+    /// name a definition that the reduction left unfolded.
+    ///
+    /// With an `assert_steps_lt` directive, reduction stops once the smallest
+    /// such limit is reached, so a divergent term fails instead of running
+    /// forever. Without one, the test fails with [`Error::CostLimit`] if the
+    /// term is not in normal form after [`Runtime::max_steps`] rewrites.
+    pub fn run_test(&self, test: &TestFile) -> Result<Vec<Failure>, Error> {
+        let limit = test
+            .directives
+            .iter()
+            .filter_map(|directive| match directive {
+                Directive::StepsLt(limit) => Some(*limit),
+                Directive::NormalizesTo(_) => None,
+            })
+            .min();
+        let runtime = self.with_definitions(&test.defs);
+        let mut term_graph = runtime.graph(&test.term)?;
+        let mut steps = 0;
+        let mut normalized = false;
+        while limit.is_none_or(|limit| steps < limit) {
+            if term_graph.naive_reduce_step().is_none() {
+                normalized = true;
+                break;
+            }
+            steps += 1;
+            if limit.is_none() && steps as u64 > runtime.max_steps {
+                return Err(Error::CostLimit {
+                    limit: runtime.max_steps,
+                    spent: runtime.max_steps,
+                });
+            }
+        }
+        let actual = Term::from(&term_graph).canonicalize().0;
+        let mut failures = vec![];
+        for directive in &test.directives {
+            match directive {
+                Directive::NormalizesTo(expected) => {
+                    let expected = runtime.book.resolve(expected).canonicalize().0;
+                    if !normalized || expected != actual {
+                        failures.push(Failure::NormalForm {
+                            expected,
+                            actual: actual.clone(),
+                        });
+                    }
+                }
+                Directive::StepsLt(limit) => {
+                    if !normalized || steps >= *limit {
+                        failures.push(Failure::Steps { limit: *limit });
+                    }
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Runs every `.ic` file in `dir` and its subdirectories with
+    /// [`Runtime::run_test`], in order of their paths.
+    ///
+    /// The files are loaded with [`load_source`], so they may include others.
+    /// Returns an error only if a directory cannot be read; a file that cannot
+    /// be loaded or parsed fails its own report.
+    pub fn run_test_dir(&self, dir: &Path) -> Result<Vec<TestReport>, Error> {
+        Ok(ic_files(dir)?
+            .into_iter()
+            .map(|path| {
+                let result = load_source(&path)
+                    .and_then(|src| TestFile::parse(&src))
+                    .and_then(|test| self.run_test(&test));
+                TestReport { path, result }
+            })
+            .collect())
+    }
+}
+
+/// Returns the paths of the `.ic` files in `dir` and its subdirectories, in
+/// order.
+pub(super) fn ic_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "ic") {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_test() {
+        let mut runtime = Runtime::new();
+        runtime.define("id", "λx x".parse().unwrap());
+        let test = TestFile::parse(
+            "// Identity twice.\n\
+             -- assert_normalizes_to: λa a\n\
+             (id id)\n\
+             -- assert_steps_lt: 10\n",
+        )
+        .unwrap();
+        assert_eq!(test.directives.len(), 2);
+        assert_eq!(runtime.run_test(&test).unwrap(), []);
+
+        let test = TestFile::parse(
+            "-- assert_normalizes_to: λa λb a\n\
+             -- assert_steps_lt: 1\n\
+             ((λf λx (f x)) λy y)",
+        )
+        .unwrap();
+        let failures = runtime.run_test(&test).unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].to_string().lines().collect::<Vec<_>>(),
+            [
+                "normal form differs",
+                "  expected: (λx0 (λx1 x0))",
+                "    actual: (λx0 (let x1 = x0; x1))",
+                "                  ^",
+            ]
+        );
+        assert_eq!(failures[1], Failure::Steps { limit: 1 });
+
+        let test = TestFile::parse(
+            "-- assert_normalizes_to: λa (a wrap)\n\
+             def wrap = λf λx (f x);\n\
+             λa (a wrap)",
+        )
+        .unwrap();
+        assert_eq!(test.defs.len(), 1);
+        assert_eq!(runtime.run_test(&test).unwrap(), []);
+        assert!(runtime.book().get("wrap").is_none());
+    }
+
+    #[test]
+    fn test_parse_directive_errors() {
+        let error = |src: &str| TestFile::parse(src).unwrap_err().to_string();
+        assert_eq!(
+            error("x\n  -- assert_steps_lt: many"),
+            "parse error: line 2, column 23: expected a step count, found `many`"
+        );
+        assert!(TestFile::parse("-- assert_halts: yes\nx").is_err());
+        assert_eq!(
+            error("-- assert_normalizes_to: (x\nx"),
+            "parse error: line 1, column 28: expected term, found end of input"
+        );
+        assert!(TestFile::parse("def id = λx x;\n").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::corpus::DIRECTIVE;
+use crate::error::{Error, ParseError};
+
+/// Reads the `.ic` file at `path`, replacing each `include "relative/path.ic"`
+/// line with the contents of that file, resolved relative to the directory of
+/// the including file.
+///
+/// Each file is included at most once: later includes of a file that was
+/// already included (through any path) are dropped. The test directives of
+/// included files are dropped too, so that only those of `path` itself
+/// remain. Returns an error if a file cannot be read or an include is
+/// malformed or cyclic.
+///
+/// Since a file holds a single program, an included file typically holds
+/// definitions, `def name = ...;`, for the program that includes it. A
+/// malformed or cyclic include is reported at its position in the file that
+/// has it.
+pub fn load_source(path: &Path) -> Result<String, Error> {
+    let mut loader = Loader {
+        included: HashSet::new(),
+        stack: vec![],
+        out: String::new(),
+    };
+    loader.load(path, true)?;
+    Ok(loader.out)
+}
+
+struct Loader {
+    included: HashSet<PathBuf>,
+    /// The files being included, outermost first.
+    stack: Vec<PathBuf>,
+    out: String,
+}
+
+impl Loader {
+    fn load(&mut self, path: &Path, is_root: bool) -> Result<(), Error> {
+        let path = path.canonicalize()?;
+        if !self.included.insert(path.clone()) {
+            return Ok(());
+        }
+        let src = fs::read_to_string(&path)?;
+        self.stack.push(path.clone());
+        for line in src.lines() {
+            let trimmed = line.trim();
+            let include = trimmed
+                .strip_prefix("include")
+                .map(str::trim_start)
+                .filter(|target| target.starts_with('"'));
+            if let Some(quoted) = include {
+                let error = |expected: String| {
+                    let found = format!("`{}`", quoted.trim_end());
+                    ParseError::at(&src, quoted, expected, found)
+                };
+                let target = parse_include(quoted)
+                    .ok_or_else(|| error(format!("a quoted path in {}", path.display())))?;
+                let dir = path.parent().unwrap_or(Path::new("."));
+                let target = dir.join(target).canonicalize()?;
+                if self.stack.contains(&target) {
+                    let cycle: Vec<String> = self
+                        .stack
+                        .iter()
+                        .skip_while(|p| **p != target)
+                        .chain([&target])
+                        .map(|p| p.display().to_string())
+                        .collect();
+                    let expected =
+                        format!("an include outside of the cycle {}", cycle.join(" -> "));
+                    return Err(error(expected).into());
+                }
+                self.load(&target, false)?;
+            } else if is_root || !trimmed.starts_with(DIRECTIVE) {
+                self.out.push_str(line);
+                self.out.push('\n');
+            }
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+/// Parses the quoted path of an include.
+fn parse_include(target: &str) -> Option<&str> {
+    target.trim_end().strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runtime::{Runtime, TestFile};
+
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (name, src) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, src).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_load_source() {
+        let dir = std::env::temp_dir().join(format!("ictest-include-{}", std::process::id()));
+        write_files(
+            &dir,
+            &[
+                (
+                    "main.ic",
+                    "-- assert_normalizes_to: λa a\ninclude \"lib/k.ic\"\ninclude \"lib/i.ic\"\n((k i) y)",
+                ),
+                ("lib/i.ic", "include \"k.ic\"\nlet i = λx x;"),
+                ("lib/k.ic", "-- assert_steps_lt: 0\nlet k = λx λ_ x;"),
+                ("cycle.ic", "include \"lib/../cycle.ic\"\nx"),
+            ],
+        );
+        let src = load_source(&dir.join("main.ic")).unwrap();
+        assert_eq!(
+            src,
+            "-- assert_normalizes_to: λa a\nlet k = λx λ_ x;\nlet i = λx x;\n((k i) y)\n"
+        );
+        let test = TestFile::parse(&src).unwrap();
+        assert_eq!(Runtime::new().run_test(&test).unwrap(), []);
+
+        let error = load_source(&dir.join("cycle.ic")).unwrap_err();
+        let error = error.to_string();
+        assert!(
+            error.starts_with("parse error: line 1, column 9: "),
+            "{}",
+            error
+        );
+        assert!(error.contains("outside of the cycle"), "{}", error);
+        assert!(load_source(&dir.join("missing.ic")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
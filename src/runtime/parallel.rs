@@ -0,0 +1,182 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::corpus::ic_files;
+use super::{load_source, BatchStats, Runtime, TestFile};
+use crate::error::Error;
+use crate::syntax::Term;
+
+/// The outcome of evaluating one `.ic` file with [`Runtime::eval_dir`].
+#[derive(Debug)]
+pub struct FileRun {
+    pub path: PathBuf,
+    /// The normal form of the file's term, or the error that prevented the
+    /// file from being loaded or evaluated.
+    pub result: Result<Term, Error>,
+    /// The number of rewrites performed.
+    pub rewrites: usize,
+    /// The time taken to load and evaluate the file.
+    pub elapsed: Duration,
+}
+
+/// The results of [`Runtime::eval_dir`].
+#[derive(Debug, Default)]
+pub struct DirRun {
+    /// The outcome of each file, in order of their paths.
+    pub files: Vec<FileRun>,
+    /// Aggregate statistics. `elapsed` is the wall-clock time of the whole
+    /// run, and there are never cache hits.
+    pub stats: BatchStats,
+}
+
+impl DirRun {
+    /// Writes the report to `out` as tab-separated values: a header row, one
+    /// row per file with its path, `ok` or `error`, rewrites, time in
+    /// microseconds, and normal form or error message, then a final `total`
+    /// row.
+    pub fn write_tsv(&self, out: &mut impl io::Write) -> io::Result<()> {
+        writeln!(out, "path\tstatus\trewrites\tmicros\tresult")?;
+        for file in &self.files {
+            let (status, result) = match &file.result {
+                Ok(normal_form) => ("ok", normal_form.to_string()),
+                Err(error) => ("error", error.to_string().replace(['\t', '\n'], " ")),
+            };
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                file.path.display(),
+                status,
+                file.rewrites,
+                file.elapsed.as_micros(),
+                result
+            )?;
+        }
+        writeln!(
+            out,
+            "total\t{} files, {} errors\t{}\t{}\t",
+            self.stats.items,
+            self.stats.errors,
+            self.stats.rewrites,
+            self.stats.elapsed.as_micros()
+        )
+    }
+}
+
+impl Runtime {
+    /// Evaluates the term of every `.ic` file in `dir` and its
+    /// subdirectories to normal form, on `jobs` worker threads.
+    ///
+    /// Each file is evaluated with its own definitions added to those of the
+    /// runtime, without the cache, on a graph of its own. Files are loaded
+    /// with [`load_source`], and their directives are ignored. Returns an error only if a directory
+    /// cannot be read; a file that cannot be loaded, parsed or evaluated fails
+    /// its own result.
+    ///
+    /// Like [`Runtime::eval`], a file fails with [`Error::CostLimit`] if its
+    /// term is not in normal form after [`Runtime::max_steps`] rewrites.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `jobs` is 0.
+    pub fn eval_dir(&self, dir: &Path, jobs: usize) -> Result<DirRun, Error> {
+        assert!(jobs > 0, "number of jobs must be positive");
+        let start = Instant::now();
+        let paths = ic_files(dir)?;
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(paths.len()));
+        thread::scope(|scope| {
+            for _ in 0..jobs.min(paths.len()) {
+                scope.spawn(|| {
+                    while let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let start = Instant::now();
+                        let result = load_source(path)
+                            .and_then(|src| TestFile::parse(&src))
+                            .and_then(|file| {
+                                let mut runtime = self.with_definitions(&file.defs);
+                                runtime.eval_counted(&file.term)
+                            });
+                        let (result, rewrites) = match result {
+                            Ok((normal_form, rewrites)) => (Ok(normal_form), rewrites.unwrap_or(0)),
+                            Err(error) => (Err(error), 0),
+                        };
+                        results.lock().unwrap().push(FileRun {
+                            path: path.clone(),
+                            result,
+                            rewrites,
+                            elapsed: start.elapsed(),
+                        });
+                    }
+                });
+            }
+        });
+        let mut run = DirRun {
+            files: results.into_inner().unwrap(),
+            ..DirRun::default()
+        };
+        run.files.sort_by(|a, b| a.path.cmp(&b.path));
+        for file in &run.files {
+            run.stats.items += 1;
+            run.stats.rewrites += file.rewrites;
+            if file.result.is_err() {
+                run.stats.errors += 1;
+            }
+        }
+        run.stats.elapsed = start.elapsed();
+        Ok(run)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_eval_dir() {
+        let dir = std::env::temp_dir().join(format!("ictest-parallel-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.ic"), "(id λx x)").unwrap();
+        fs::write(dir.join("b.ic"), "(λx x").unwrap();
+        fs::write(
+            dir.join("sub/c.ic"),
+            "-- assert_steps_lt: 1\n((id id) λy y)",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a program").unwrap();
+        let mut runtime = Runtime::new();
+        runtime.define("id", "λx x".parse().unwrap());
+        let run = runtime.eval_dir(&dir, 4).unwrap();
+        let names: Vec<_> = run
+            .files
+            .iter()
+            .map(|file| file.path.strip_prefix(&dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            [Path::new("a.ic"), Path::new("b.ic"), Path::new("sub/c.ic")]
+        );
+        assert_eq!(
+            run.files[0].result.as_ref().unwrap().to_string(),
+            "(λv1 v1)"
+        );
+        assert!(run.files[1].result.is_err());
+        assert_eq!((run.stats.items, run.stats.errors), (3, 1));
+        // Besides the beta reductions, each applied `id` is unfolded.
+        assert_eq!(run.stats.rewrites, 6);
+
+        let mut out = vec![];
+        run.write_tsv(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].contains("\tok\t2\t"));
+        assert!(lines[1].ends_with("\t(λv1 v1)"));
+        assert!(lines[2].contains("\terror\t0\t"));
+        assert!(lines[4].starts_with("total\t3 files, 1 errors\t6\t"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use super::corpus::DIRECTIVE;
+use super::{dependencies, Runtime};
+use crate::error::{Error, ParseError};
+use crate::intern::Intern;
+use crate::syntax::Term;
+
+/// The first line of a session file.
+const HEADER: &str = "-- ictest session";
+
+impl Runtime {
+    /// Returns the definitions, and with `with_cache` the cached
+    /// normal forms, in the session format read by
+    /// [`Runtime::load_session_source`].
+    ///
+    /// Each definition is a line `def name = term`, in order of name, and
+    /// each cached normal form is a line `cache term` followed by a line
+    /// `  = normal form`, oldest first. Lines starting with `--` are comments.
+    pub fn session_source(&self, with_cache: bool) -> String {
+        let mut out = format!("{}\n", HEADER);
+        let mut names: Vec<_> = self.book.names().collect();
+        names.sort_by_key(|name| name.to_string());
+        for name in names {
+            writeln!(out, "def {} = {}", name, self.book.def(name).unwrap().term).unwrap();
+        }
+        if let Some(cache) = self.cache.as_ref().filter(|_| with_cache) {
+            for (term, normal_form) in cache.entries() {
+                writeln!(out, "cache {}\n  = {}", term, normal_form).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Writes [`Runtime::session_source`] to the file at `path`.
+    pub fn save_session(&self, path: &Path, with_cache: bool) -> Result<(), Error> {
+        fs::write(path, self.session_source(with_cache))?;
+        Ok(())
+    }
+
+    /// Restores the definitions and cached normal forms of a session saved by
+    /// [`Runtime::session_source`], returning the number of definitions.
+    ///
+    /// The definitions are added to (or replace) the current ones, as with
+    /// [`Runtime::define`]. Cached normal forms are only restored if the
+    /// cache is enabled. Nothing is changed if `src` cannot be parsed.
+    pub fn load_session_source(&mut self, src: &str) -> Result<usize, Error> {
+        let mut definitions = vec![];
+        let mut entries = vec![];
+        let mut lines = src
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with(DIRECTIVE));
+        let end = &src[src.len()..];
+        let error = |line: &str, expected: &str| -> Error {
+            let found = match line {
+                "" => "end of input".to_string(),
+                line => format!("`{}`", line),
+            };
+            ParseError::at(src, line, expected.to_string(), found).into()
+        };
+        let parse = |term: &str| term.parse::<Term>().map_err(|err| err.within(src, term));
+        while let Some(line) = lines.next() {
+            if let Some(definition) = line.strip_prefix("def ") {
+                let (name, term) = definition
+                    .split_once(" = ")
+                    .ok_or_else(|| error(line, "`def name = term`"))?;
+                definitions.push((name.trim().intern(), parse(term)?));
+            } else if let Some(term) = line.strip_prefix("cache ") {
+                let term = parse(term)?;
+                let next = lines.next().unwrap_or(end);
+                let normal_form = next
+                    .strip_prefix("= ")
+                    .ok_or_else(|| error(next, "`= normal form` after `cache`"))?;
+                entries.push((term, parse(normal_form)?));
+            } else {
+                return Err(error(line, "`def` or `cache`"));
+            }
+        }
+        let count = definitions.len();
+        self.define_all(definitions);
+        if let Some(cache) = &mut self.cache {
+            for (term, normal_form) in entries {
+                let dependencies = dependencies(&self.book, &term);
+                cache.insert_with_dependencies(&term, normal_form, dependencies);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Restores a session from the file at `path`, as written by
+    /// [`Runtime::save_session`].
+    pub fn load_session(&mut self, path: &Path) -> Result<usize, Error> {
+        self.load_session_source(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip() {
+        let mut runtime = Runtime::new();
+        runtime.enable_cache(8);
+        runtime.define("id", "λx x".parse().unwrap());
+        runtime.define("k", "λx λy x".parse().unwrap());
+        runtime.eval_str("(k id)").unwrap();
+        let src = runtime.session_source(true);
+        assert_eq!(
+            src,
+            "-- ictest session\n\
+             def id = (λx x)\n\
+             def k = (λx (λy x))\n\
+             cache (k id)\n  = (λ_ id)\n"
+        );
+        assert!(!runtime.session_source(false).contains("cache"));
+
+        let mut restored = Runtime::new();
+        restored.enable_cache(8);
+        assert_eq!(restored.load_session_source(&src).unwrap(), 2);
+        assert_eq!(
+            restored.session_source(false),
+            runtime.session_source(false)
+        );
+        assert_eq!(restored.cache_stats().unwrap().len, 1);
+        // The restored entry still depends on `k`.
+        restored.define("k", "λx λy y".parse().unwrap());
+        assert_eq!(restored.cache_stats().unwrap().len, 0);
+    }
+
+    #[test]
+    fn test_load_session_errors() {
+        let mut runtime = Runtime::new();
+        let err = runtime
+            .load_session_source("def id = λx x\ndef bad = (x\n")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error: line 2, column 13: expected term, found end of input"
+        );
+        // Nothing is defined if any line fails.
+        assert!(runtime.book().is_empty());
+        let err = runtime.load_session_source("cache x\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error: line 2, column 1: expected `= normal form` after `cache`, \
+             found end of input"
+        );
+        assert!(runtime.load_session_source("let x = y\n").is_err());
+    }
+}
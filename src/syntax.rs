@@ -1,9 +1,30 @@
-use crate::intern::IStr;
+use crate::intern::{IStr, Intern};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::io::IsTerminal;
 
 pub type Label = u64;
 
+/// A term of the interaction calculus.
+///
+/// With the `serde` feature, terms implement `Serialize` and `Deserialize`,
+/// so that tools can exchange them as JSON (or any other format serde
+/// supports) instead of source text. A term is an object with a single key,
+/// the name of its variant, whose value is its only field, or an array of its
+/// fields in order. Names are strings, labels and numbers are numbers, and
+/// operations are their symbols, e.g. `"+"`. For example, `λx (+ x 1)` is
+///
+/// ```json
+/// {"Lam": ["x", {"Op2": ["+", {"Var": "x"}, {"Num": 1}]}]}
+/// ```
+///
+/// and `dup #0{a b} = x; (a b)` is
+///
+/// ```json
+/// {"Dup": [0, "a", "b", {"Var": "x"}, {"App": [{"Var": "a"}, {"Var": "b"}]}]}
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Term {
     /// Variable, e.g. `x`
     Var(IStr),
@@ -14,24 +35,596 @@ pub enum Term {
     /// Superposition, e.g. `#0{x y}`
     Sup(Label, Box<Term>, Box<Term>),
     /// Duplication, e.g. `dup #0{x y} = z; body`
+    ///
+    /// Parsed from `dup {x y} = z; body` too, with a fresh label (see
+    /// [`LabelGen`]).
     Dup(Label, IStr, IStr, Box<Term>, Box<Term>),
-    /// Let, e.g. `let x = expr; body`
+    /// Let, e.g. `let x = expr; body` or `let x = expr in body`
     Let(IStr, Box<Term>, Box<Term>),
+    /// Reference to a top-level definition of a [`Book`], e.g. `id`
+    ///
+    /// Displayed as the name of the definition, which [`Book::load`] reads
+    /// back as a reference.
+    ///
+    /// [`Book`]: crate::book::Book
+    /// [`Book::load`]: crate::book::Book::load
+    Ref(IStr),
+    /// Unsigned number, e.g. `42`
+    Num(u64),
+    /// Binary operation on numbers, e.g. `(+ x 1)`
+    Op2(Op, Box<Term>, Box<Term>),
+}
+
+/// A binary operation on numbers.
+///
+/// Arithmetic wraps around on overflow, division by zero gives zero, and
+/// comparisons give `1` for true and `0` for false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Op {
+    #[cfg_attr(feature = "serde", serde(rename = "+"))]
+    Add,
+    #[cfg_attr(feature = "serde", serde(rename = "-"))]
+    Sub,
+    #[cfg_attr(feature = "serde", serde(rename = "*"))]
+    Mul,
+    #[cfg_attr(feature = "serde", serde(rename = "/"))]
+    Div,
+    #[cfg_attr(feature = "serde", serde(rename = "=="))]
+    Eq,
+    #[cfg_attr(feature = "serde", serde(rename = "<"))]
+    Lt,
+}
+
+impl Op {
+    /// All operations, in the order of their discriminants.
+    pub const ALL: [Op; 6] = [Op::Add, Op::Sub, Op::Mul, Op::Div, Op::Eq, Op::Lt];
+
+    /// Returns the symbol of the operation, e.g. `+`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Eq => "==",
+            Op::Lt => "<",
+        }
+    }
+
+    /// Applies the operation to `a` and `b`.
+    pub fn apply(self, a: u64, b: u64) -> u64 {
+        match self {
+            Op::Add => a.wrapping_add(b),
+            Op::Sub => a.wrapping_sub(b),
+            Op::Mul => a.wrapping_mul(b),
+            Op::Div => a.checked_div(b).unwrap_or(0),
+            Op::Eq => (a == b) as u64,
+            Op::Lt => (a < b) as u64,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl Drop for Term {
+    fn drop(&mut self) {
+        // NOTE: The derived drop glue recurses once per level of nesting, so
+        //       the subterms are moved onto an explicit stack instead, leaving
+        //       only leaves behind to be dropped recursively.
+        fn take_children(term: &mut Term, stack: &mut Vec<Term>) {
+            let mut take = |e: &mut Box<Term>| {
+                if !matches!(**e, Term::Var(_)) {
+                    stack.push(std::mem::replace(&mut **e, Term::Var(IStr::EMPTY)));
+                }
+            };
+            match term {
+                Term::Var(_) | Term::Ref(_) | Term::Num(_) => {}
+                Term::Lam(_, e) => take(e),
+                Term::App(e1, e2)
+                | Term::Sup(_, e1, e2)
+                | Term::Op2(_, e1, e2)
+                | Term::Dup(_, _, _, e1, e2)
+                | Term::Let(_, e1, e2) => {
+                    take(e1);
+                    take(e2);
+                }
+            }
+        }
+
+        let mut stack = vec![];
+        take_children(self, &mut stack);
+        while let Some(mut term) = stack.pop() {
+            take_children(&mut term, &mut stack);
+        }
+    }
 }
 
 impl fmt::Display for Term {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_term(f, self, false, DisplayLimits::default())
+    }
+}
+
+/// Whether to color terms printed with [`Term::colored`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    /// Color if stdout is a terminal and the `NO_COLOR` environment variable
+    /// is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns whether this choice colors the output.
+    pub fn enabled(self) -> bool {
         match self {
-            Term::Var(v) => write!(f, "{}", v),
-            Term::Lam(x, body) => write!(f, "(λ{} {})", x, body),
-            Term::App(fun, arg) => write!(f, "({} {})", fun, arg),
-            Term::Sup(label, left, right) => write!(f, "#{}{{{} {}}}", label, left, right),
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Limits on how much of a term is printed by [`Term::limited`].
+///
+/// Each subterm beyond a limit is elided as `…N`, where `N` is its number of
+/// nodes, so that printing a huge term by accident stays cheap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DisplayLimits {
+    /// The number of levels of nesting to print, counting the root as one.
+    pub depth: Option<usize>,
+    /// The number of nodes to print, in the order they are written.
+    pub size: Option<usize>,
+}
+
+/// A term displayed with ANSI colors or within limits, as returned by
+/// [`Term::colored`] and [`Term::limited`].
+#[derive(Debug, Clone, Copy)]
+pub struct Colored<'t> {
+    term: &'t Term,
+    color: bool,
+    limits: DisplayLimits,
+}
+
+impl Colored<'_> {
+    /// Returns the same wrapper, printing only as much of the term as
+    /// `limits` allow.
+    pub fn limited(self, limits: DisplayLimits) -> Self {
+        Colored { limits, ..self }
+    }
+}
+
+impl fmt::Display for Colored<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_term(f, self.term, self.color, self.limits)
+    }
+}
+
+const BINDER: &str = "\x1b[36m";
+const LABEL: &str = "\x1b[33m";
+const KEYWORD: &str = "\x1b[35m";
+const DELIMITER: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Writes `term` in surface syntax. With `color`, binders, labels, and
+/// keywords are colored, and parentheses and braces are dimmed, so that the
+/// structure stands out. Subterms beyond `limits` are elided.
+fn write_term(
+    f: &mut fmt::Formatter,
+    term: &Term,
+    color: bool,
+    limits: DisplayLimits,
+) -> fmt::Result {
+    // NOTE: Uses an explicit stack, so that deep terms can be printed.
+    enum Item<'t> {
+        /// A subterm, with its depth.
+        Term(&'t Term, usize),
+        Text(&'static str),
+        Delimiter(&'static str),
+    }
+
+    let paint = |f: &mut fmt::Formatter, style: &str, text: &dyn fmt::Display| {
+        if color {
+            write!(f, "{}{}{}", style, text, RESET)
+        } else {
+            write!(f, "{}", text)
+        }
+    };
+    let mut stack = vec![Item::Term(term, 1)];
+    let mut printed = 0;
+    while let Some(item) = stack.pop() {
+        let (term, depth) = match item {
+            Item::Term(term, depth) => (term, depth),
+            Item::Text(text) => {
+                f.write_str(text)?;
+                continue;
+            }
+            Item::Delimiter(text) => {
+                paint(f, DELIMITER, &text)?;
+                continue;
+            }
+        };
+        if limits.depth.is_some_and(|d| depth > d) || limits.size.is_some_and(|n| printed >= n) {
+            paint(f, DELIMITER, &format_args!("…{}", term.size()))?;
+            continue;
+        }
+        printed += 1;
+        let depth = depth + 1;
+        match term {
+            Term::Var(v) | Term::Ref(v) => write!(f, "{}", v)?,
+            Term::Num(n) => write!(f, "{}", n)?,
+            Term::Lam(x, body) => {
+                paint(f, DELIMITER, &"(")?;
+                paint(f, KEYWORD, &"λ")?;
+                paint(f, BINDER, x)?;
+                f.write_str(" ")?;
+                stack.extend([Item::Delimiter(")"), Item::Term(body, depth)]);
+            }
+            Term::App(fun, arg) => {
+                paint(f, DELIMITER, &"(")?;
+                stack.extend([
+                    Item::Delimiter(")"),
+                    Item::Term(arg, depth),
+                    Item::Text(" "),
+                    Item::Term(fun, depth),
+                ]);
+            }
+            Term::Sup(label, left, right) => {
+                paint(f, LABEL, &format_args!("#{}", label))?;
+                paint(f, DELIMITER, &"{")?;
+                stack.extend([
+                    Item::Delimiter("}"),
+                    Item::Term(right, depth),
+                    Item::Text(" "),
+                    Item::Term(left, depth),
+                ]);
+            }
             Term::Dup(label, x, y, dup, body) => {
-                write!(f, "(dup #{}{{{} {}}} = {}; {})", label, x, y, dup, body)
+                paint(f, DELIMITER, &"(")?;
+                paint(f, KEYWORD, &"dup")?;
+                f.write_str(" ")?;
+                paint(f, LABEL, &format_args!("#{}", label))?;
+                paint(f, DELIMITER, &"{")?;
+                paint(f, BINDER, x)?;
+                f.write_str(" ")?;
+                paint(f, BINDER, y)?;
+                paint(f, DELIMITER, &"}")?;
+                f.write_str(" = ")?;
+                stack.extend([
+                    Item::Delimiter(")"),
+                    Item::Term(body, depth),
+                    Item::Text("; "),
+                    Item::Term(dup, depth),
+                ]);
+            }
+            Term::Op2(op, left, right) => {
+                paint(f, DELIMITER, &"(")?;
+                paint(f, KEYWORD, op)?;
+                f.write_str(" ")?;
+                stack.extend([
+                    Item::Delimiter(")"),
+                    Item::Term(right, depth),
+                    Item::Text(" "),
+                    Item::Term(left, depth),
+                ]);
+            }
+            Term::Let(x, expr, body) => {
+                paint(f, DELIMITER, &"(")?;
+                paint(f, KEYWORD, &"let")?;
+                f.write_str(" ")?;
+                paint(f, BINDER, x)?;
+                f.write_str(" = ")?;
+                stack.extend([
+                    Item::Delimiter(")"),
+                    Item::Term(body, depth),
+                    Item::Text("; "),
+                    Item::Term(expr, depth),
+                ]);
             }
-            Term::Let(x, expr, body) => write!(f, "(let {} = {}; {})", x, expr, body),
         }
     }
+    Ok(())
+}
+
+/// Hands out fresh labels, for building terms whose dups must not share
+/// labels with each other, or with the superpositions and dups of other terms.
+///
+/// Labels are handed out in increasing order, each above every label handed
+/// out or reserved before it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelGen {
+    /// The largest label handed out or reserved so far, if any.
+    last: Option<Label>,
+}
+
+impl LabelGen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a generator whose labels are not used by `term`.
+    pub fn avoiding(term: &Term) -> Self {
+        let mut labels = Self::new();
+        labels.avoid(term);
+        labels
+    }
+
+    /// Reserves `label`, so that it (and every label below it) is never
+    /// handed out.
+    pub fn reserve(&mut self, label: Label) {
+        self.last = self.last.max(Some(label));
+    }
+
+    /// Reserves every label used by a superposition or dup in `term`.
+    pub fn avoid(&mut self, term: &Term) {
+        let mut stack = vec![term];
+        while let Some(term) = stack.pop() {
+            if let Term::Sup(l, _, _) | Term::Dup(l, _, _, _, _) = term {
+                self.reserve(*l);
+            }
+            stack.extend(term.children());
+        }
+    }
+
+    /// Returns the label [`fresh`](Self::fresh) would hand out next, or
+    /// `None` if even `Label::MAX` is taken.
+    pub fn peek(&self) -> Option<Label> {
+        match self.last {
+            Some(last) => last.checked_add(1),
+            None => Some(0),
+        }
+    }
+
+    /// Hands out a new label.
+    ///
+    /// Panics if every label is taken.
+    pub fn fresh(&mut self) -> Label {
+        let label = self.peek().expect("every label is taken");
+        self.last = Some(label);
+        label
+    }
+}
+
+/// The renamings applied by [`Term::canonicalize`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Renaming {
+    /// Maps each canonical binder name to the original name.
+    pub binders: HashMap<IStr, IStr>,
+    /// Maps each original label to its canonical label.
+    pub labels: HashMap<Label, Label>,
+}
+
+impl Term {
+    /// Returns a wrapper that displays the term like `Display`, but with ANSI
+    /// colors if `choice` enables them.
+    pub fn colored(&self, choice: ColorChoice) -> Colored<'_> {
+        Colored {
+            term: self,
+            color: choice.enabled(),
+            limits: DisplayLimits::default(),
+        }
+    }
+
+    /// Returns a wrapper that displays the term like `Display`, but elides the
+    /// subterms beyond `limits`.
+    pub fn limited(&self, limits: DisplayLimits) -> Colored<'_> {
+        Colored {
+            term: self,
+            color: false,
+            limits,
+        }
+    }
+
+    /// Returns the direct subterms of the term, in order.
+    pub fn children(&self) -> impl Iterator<Item = &Term> {
+        let (first, second) = match self {
+            Term::Var(_) | Term::Ref(_) | Term::Num(_) => (None, None),
+            Term::Lam(_, e) => (Some(e), None),
+            Term::App(e1, e2)
+            | Term::Sup(_, e1, e2)
+            | Term::Op2(_, e1, e2)
+            | Term::Dup(_, _, _, e1, e2)
+            | Term::Let(_, e1, e2) => (Some(e1), Some(e2)),
+        };
+        first.into_iter().chain(second).map(|e| &**e)
+    }
+
+    /// Returns the number of nodes in the term.
+    pub fn size(&self) -> usize {
+        let mut size = 0;
+        let mut stack = vec![self];
+        while let Some(term) = stack.pop() {
+            size += 1;
+            stack.extend(term.children());
+        }
+        size
+    }
+
+    /// Returns the number of nodes on the longest path from the root of the
+    /// term to a leaf.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((term, d)) = stack.pop() {
+            depth = depth.max(d);
+            stack.extend(term.children().map(|e| (e, d + 1)));
+        }
+        depth
+    }
+
+    /// Returns the free variables of the term.
+    pub fn free_vars(&self) -> HashSet<IStr> {
+        fn go(term: &Term, bound: &mut Vec<IStr>, free: &mut HashSet<IStr>) {
+            match term {
+                Term::Var(x) => {
+                    if !bound.contains(x) {
+                        free.insert(*x);
+                    }
+                }
+                Term::Ref(_) | Term::Num(_) => {}
+                Term::Lam(x, e) => {
+                    bound.push(*x);
+                    go(e, bound, free);
+                    bound.pop();
+                }
+                Term::App(e1, e2) | Term::Sup(_, e1, e2) | Term::Op2(_, e1, e2) => {
+                    go(e1, bound, free);
+                    go(e2, bound, free);
+                }
+                Term::Dup(_, a, b, e, cont) => {
+                    go(e, bound, free);
+                    bound.push(*a);
+                    bound.push(*b);
+                    go(cont, bound, free);
+                    bound.truncate(bound.len() - 2);
+                }
+                Term::Let(x, e, cont) => {
+                    go(e, bound, free);
+                    bound.push(*x);
+                    go(cont, bound, free);
+                    bound.pop();
+                }
+            }
+        }
+        let mut free = HashSet::new();
+        go(self, &mut vec![], &mut free);
+        free
+    }
+
+    /// Returns the names of the definitions the term refers to.
+    pub fn refs(&self) -> HashSet<IStr> {
+        let mut refs = HashSet::new();
+        let mut stack = vec![self];
+        while let Some(term) = stack.pop() {
+            if let Term::Ref(name) = term {
+                refs.insert(*name);
+            }
+            stack.extend(term.children());
+        }
+        refs
+    }
+
+    /// Returns a canonical version of the term, along with the renamings used.
+    ///
+    /// Binders are renamed to `x0`, `x1`, ... in the order they appear
+    /// (skipping names of free variables and references), and labels are
+    /// renumbered from `0` in the order they first appear. Free variables keep
+    /// their names. Two terms that only differ in the names of their binders
+    /// and the values of their labels have the same canonical version.
+    pub fn canonicalize(&self) -> (Term, Renaming) {
+        self.canonicalize_with(true)
+    }
+
+    /// Returns a canonical version of the term like [`Term::canonicalize`],
+    /// but with the labels kept as written.
+    ///
+    /// Two terms that only differ in the names of their binders have the same
+    /// canonical binders. Unlike renumbering binders, renumbering labels can
+    /// change the normal form of a term that uses definitions with labels.
+    pub fn canonicalize_binders(&self) -> Term {
+        self.canonicalize_with(false).0
+    }
+
+    fn canonicalize_with(&self, relabel: bool) -> (Term, Renaming) {
+        struct Canonicalizer {
+            relabel: bool,
+            free: HashSet<IStr>,
+            next_binder: usize,
+            scope: Vec<(IStr, IStr)>,
+            renaming: Renaming,
+        }
+
+        impl Canonicalizer {
+            fn bind(&mut self, x: IStr) -> IStr {
+                let name = loop {
+                    let name = format!("x{}", self.next_binder).intern();
+                    self.next_binder += 1;
+                    if !self.free.contains(&name) {
+                        break name;
+                    }
+                };
+                self.scope.push((x, name));
+                self.renaming.binders.insert(name, x);
+                name
+            }
+
+            fn label(&mut self, l: Label) -> Label {
+                if !self.relabel {
+                    return l;
+                }
+                let next = self.renaming.labels.len() as Label;
+                *self.renaming.labels.entry(l).or_insert(next)
+            }
+
+            fn go(&mut self, term: &Term) -> Term {
+                match term {
+                    Term::Var(x) => {
+                        let name = self.scope.iter().rev().find(|(old, _)| old == x);
+                        Term::Var(name.map_or(*x, |(_, new)| *new))
+                    }
+                    Term::Ref(name) => Term::Ref(*name),
+                    Term::Num(n) => Term::Num(*n),
+                    Term::Lam(x, e) => {
+                        let x = self.bind(*x);
+                        let e = self.go(e);
+                        self.scope.pop();
+                        Term::Lam(x, Box::new(e))
+                    }
+                    Term::App(e1, e2) => {
+                        let e1 = self.go(e1);
+                        let e2 = self.go(e2);
+                        Term::App(Box::new(e1), Box::new(e2))
+                    }
+                    Term::Sup(l, e1, e2) => {
+                        let l = self.label(*l);
+                        let e1 = self.go(e1);
+                        let e2 = self.go(e2);
+                        Term::Sup(l, Box::new(e1), Box::new(e2))
+                    }
+                    Term::Op2(op, e1, e2) => {
+                        let e1 = self.go(e1);
+                        let e2 = self.go(e2);
+                        Term::Op2(*op, Box::new(e1), Box::new(e2))
+                    }
+                    Term::Dup(l, a, b, e, cont) => {
+                        let l = self.label(*l);
+                        let e = self.go(e);
+                        let a = self.bind(*a);
+                        let b = self.bind(*b);
+                        let cont = self.go(cont);
+                        self.scope.truncate(self.scope.len() - 2);
+                        Term::Dup(l, a, b, Box::new(e), Box::new(cont))
+                    }
+                    Term::Let(x, e, cont) => {
+                        let e = self.go(e);
+                        let x = self.bind(*x);
+                        let cont = self.go(cont);
+                        self.scope.pop();
+                        Term::Let(x, Box::new(e), Box::new(cont))
+                    }
+                }
+            }
+        }
+
+        let mut canonicalizer = Canonicalizer {
+            relabel,
+            free: &self.free_vars() | &self.refs(),
+            next_binder: 0,
+            scope: vec![],
+            renaming: Renaming::default(),
+        };
+        let term = canonicalizer.go(self);
+        (term, canonicalizer.renaming)
+    }
 }
 
 #[cfg(test)]
@@ -68,9 +661,144 @@ mod test {
                 Term::Let(x, Box::new(Term::Var(expr)), Box::new(Term::Var(body))),
                 "(let x = expr; body)",
             ),
+            (Term::Num(42), "42"),
+            (
+                Term::Op2(Op::Lt, Box::new(Term::Var(x)), Box::new(Term::Num(1))),
+                "(< x 1)",
+            ),
         ];
         for (term, expected) in test_cases {
             assert_eq!(term.to_string(), *expected);
         }
     }
+
+    #[test]
+    fn test_op_apply() {
+        assert_eq!(Op::Add.apply(u64::MAX, 2), 1);
+        assert_eq!(Op::Sub.apply(0, 1), u64::MAX);
+        assert_eq!(Op::Mul.apply(6, 7), 42);
+        assert_eq!(Op::Div.apply(7, 2), 3);
+        assert_eq!(Op::Div.apply(7, 0), 0);
+        assert_eq!(Op::Eq.apply(3, 3), 1);
+        assert_eq!(Op::Lt.apply(3, 3), 0);
+    }
+
+    #[test]
+    fn test_display_colored() {
+        let term: Term = "λx dup #1{a b} = x; #2{a (let y = b; y)}".parse().unwrap();
+        let plain = term.to_string();
+        assert_eq!(term.colored(ColorChoice::Never).to_string(), plain);
+        let colored = term.colored(ColorChoice::Always).to_string();
+        assert!(colored.starts_with("\x1b[2m(\x1b[0m\x1b[35mλ\x1b[0m\x1b[36mx\x1b[0m "));
+        let mut stripped = String::new();
+        let mut rest = colored.as_str();
+        while let Some(start) = rest.find('\x1b') {
+            stripped += &rest[..start];
+            rest = &rest[start + rest[start..].find('m').unwrap() + 1..];
+        }
+        stripped += rest;
+        assert_eq!(stripped, plain);
+    }
+
+    #[test]
+    fn test_display_limited() {
+        let term: Term = "λx dup #1{a b} = x; #2{a (let y = b; y)}".parse().unwrap();
+        let limited = |depth, size| term.limited(DisplayLimits { depth, size }).to_string();
+        assert_eq!(limited(None, None), term.to_string());
+        assert_eq!(limited(Some(3), None), "(λx (dup #1{a b} = x; #2{…1 …3}))");
+        assert_eq!(limited(None, Some(5)), "(λx (dup #1{a b} = x; #2{a …3}))");
+        assert_eq!(limited(Some(1), Some(5)), "(λx …7)");
+        assert_eq!(limited(None, Some(0)), "…8");
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let parse = |src: &str| src.parse::<Term>().unwrap();
+        let (term, renaming) = parse("λx dup #3{a b} = x; #7{(a x0) b}").canonicalize();
+        assert_eq!(
+            term.to_string(),
+            "(λx1 (dup #0{x2 x3} = x1; #1{(x2 x0) x3}))"
+        );
+        assert_eq!(renaming.binders[&"x1".intern_static()], "x".intern_static());
+        assert_eq!(renaming.labels[&7], 1);
+
+        let canonical = |src: &str| parse(src).canonicalize().0;
+        assert_eq!(
+            canonical("λx dup #3{a b} = x; #7{a b}"),
+            canonical("λy dup #0{c d} = y; #1{c d}")
+        );
+        assert_ne!(
+            canonical("λx dup #0{a b} = x; #1{a b}"),
+            canonical("λx dup #0{a b} = x; #0{a b}")
+        );
+        assert_ne!(canonical("λx λy x"), canonical("λx λy y"));
+        assert_ne!(canonical("f"), canonical("g"));
+
+        let binders = |src: &str| parse(src).canonicalize_binders();
+        assert_eq!(
+            binders("λx dup #3{a b} = x; #7{a b}"),
+            binders("λy dup #3{c d} = y; #7{c d}")
+        );
+        assert_ne!(binders("#5{a b}"), binders("#7{a b}"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json() {
+        let term: Term = "λx (+ x 1)".parse().unwrap();
+        let json = r#"{"Lam":["x",{"Op2":["+",{"Var":"x"},{"Num":1}]}]}"#;
+        assert_eq!(serde_json::to_string(&term).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Term>(json).unwrap(), term);
+        let json = r#"{"Dup": [0, "a", "b", {"Var": "x"}, {"App": [{"Var": "a"}, {"Var": "b"}]}]}"#;
+        assert_eq!(
+            serde_json::from_str::<Term>(json).unwrap(),
+            "dup #0{a b} = x; (a b)".parse().unwrap()
+        );
+        for src in [
+            "λf dup #3{f1 f2} = f; λx (f1 (f2 x))",
+            "let x = #18446744073709551615{y (== 2 3)}; (x (< 1 (/ 4 (* 2 (- 5 3)))))",
+        ] {
+            let term: Term = src.parse().unwrap();
+            let json = serde_json::to_string(&term).unwrap();
+            assert_eq!(serde_json::from_str::<Term>(&json).unwrap(), term);
+        }
+        assert!(serde_json::from_str::<Term>(r#"{"Op2":["%",{"Num":1},{"Num":2}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_label_gen() {
+        let mut labels = LabelGen::new();
+        assert_eq!([labels.fresh(), labels.fresh()], [0, 1]);
+        let term: Term = "dup #7{a b} = #3{x y}; (a b)".parse().unwrap();
+        let mut labels = LabelGen::avoiding(&term);
+        assert_eq!(labels.fresh(), 8);
+        labels.reserve(4);
+        assert_eq!(labels.fresh(), 9);
+        labels.reserve(Label::MAX);
+        assert_eq!(labels.peek(), None);
+    }
+
+    #[test]
+    fn test_size_and_depth() {
+        let term: Term = "λx dup #0{a b} = x; (a #1{b y})".parse().unwrap();
+        assert_eq!(term.size(), 8);
+        assert_eq!(term.depth(), 5);
+    }
+
+    #[test]
+    fn test_deep_term() {
+        // Deep enough to overflow the stack if these recursed per node.
+        // Under Miri, which is far slower, only the memory accesses are checked.
+        let depth = if cfg!(miri) { 2_000 } else { 200_000 };
+        let mut term = Term::Var("x".into());
+        for _ in 0..depth {
+            term = Term::Lam("x".into(), Box::new(term));
+        }
+        assert_eq!(term.size(), depth + 1);
+        assert_eq!(term.depth(), depth + 1);
+        let src = term.to_string();
+        assert_eq!(src.chars().count(), depth * 5 + 1);
+        assert!(src.starts_with("(λx (λx "));
+        assert!(src.contains(" x))"));
+    }
 }
@@ -0,0 +1,639 @@
+//! Proptest strategies for generating terms, and a check that reductions in
+//! different orders agree, for property tests of this crate and of crates
+//! built on it.
+//!
+//! Enabled by the `testing` feature.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+
+use proptest::prelude::*;
+
+use crate::intern::{IStr, Intern};
+use crate::syntax::{Label, Op, Term};
+use crate::vm::{RandomSource, StrategyConfig, TermGraph};
+
+/// The shape of the terms generated by [`arb_term_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermConfig {
+    /// The maximum number of levels of nesting.
+    pub max_depth: u32,
+    /// The number of nodes to aim for. Terms are usually smaller.
+    pub size: u32,
+    /// Whether every variable must be bound.
+    pub closed: bool,
+    /// Whether each variable, bound or free, must be used at most once.
+    pub affine: bool,
+    /// The labels of superpositions and dups.
+    pub labels: RangeInclusive<Label>,
+}
+
+impl Default for TermConfig {
+    /// Any term up to 8 levels deep.
+    fn default() -> Self {
+        TermConfig {
+            max_depth: 8,
+            size: 256,
+            closed: false,
+            affine: false,
+            labels: 0..=Label::MAX,
+        }
+    }
+}
+
+/// Generates variable names, which may also be printed and parsed.
+pub fn arb_var_name() -> impl Strategy<Value = IStr> {
+    "[_a-z][_a-zA-Z0-9]*".prop_map(|s| s.into())
+}
+
+/// Generates any term, as [`arb_term_with`] with the default [`TermConfig`].
+pub fn arb_term() -> impl Strategy<Value = Term> {
+    arb_term_with(TermConfig::default())
+}
+
+/// Generates terms of the shape described by `config`.
+///
+/// A term is generated with arbitrary names first, and then, for a closed or
+/// affine term, each use of a variable that is not allowed is replaced: by
+/// another binder in scope that may still be used, if there is one, and
+/// otherwise by a number in a closed term, or by a free variable with a name
+/// of its own in an affine one. Shrinking works on the term before the
+/// replacement.
+///
+/// Such terms may still be rejected by [`TermGraph::from`], e.g. if both
+/// variables of a dup have the same name: see [`arb_graph_term`] for terms
+/// that can be reduced.
+///
+/// [`TermGraph::from`]: crate::vm::TermGraph
+pub fn arb_term_with(config: TermConfig) -> impl Strategy<Value = Term> {
+    let leaf = prop_oneof![
+        arb_var_name().prop_map(Term::Var),
+        prop::num::u64::ANY.prop_map(Term::Num),
+    ];
+    let labels = config.labels.clone();
+    let terms = leaf.prop_recursive(config.max_depth, config.size, 5, move |inner| {
+        let label = labels.clone();
+        prop_oneof![
+            (
+                prop::sample::select(&Op::ALL[..]),
+                inner.clone(),
+                inner.clone()
+            )
+                .prop_map(|(op, a, b)| Term::Op2(op, Box::new(a), Box::new(b))),
+            (arb_var_name(), inner.clone()).prop_map(|(v, t)| Term::Lam(v, Box::new(t))),
+            (inner.clone(), inner.clone())
+                .prop_map(|(f, arg)| Term::App(Box::new(f), Box::new(arg))),
+            (label.clone(), inner.clone(), inner.clone()).prop_map(|(l, a, b)| Term::Sup(
+                l,
+                Box::new(a),
+                Box::new(b)
+            )),
+            (
+                label,
+                arb_var_name(),
+                arb_var_name(),
+                inner.clone(),
+                inner.clone()
+            )
+                .prop_map(|(l, a, b, expr, cont)| Term::Dup(
+                    l,
+                    a,
+                    b,
+                    Box::new(expr),
+                    Box::new(cont)
+                )),
+            (arb_var_name(), inner.clone(), inner.clone()).prop_map(|(v, x, y)| Term::Let(
+                v,
+                Box::new(x),
+                Box::new(y)
+            )),
+        ]
+    });
+    terms.prop_map(move |term| match config.closed || config.affine {
+        true => Scope::new(&config).rescope(&term),
+        false => term,
+    })
+}
+
+/// Generates closed, affine terms that [`TermGraph::from`] accepts, up to
+/// `config.max_depth` levels deep (a little deeper when the variables in
+/// scope must all be used), with labels from `config.labels` and numbers
+/// below 100. The other fields of `config` are ignored.
+///
+/// Every variable of a dup is used exactly once, and never in the expression
+/// it copies, so no dup is erased as the graph is built. A lambda or `let`
+/// may leave its variable unused. Binders are named `x0`, `x1`, … by how many
+/// binders enclose them, so none shadows another.
+///
+/// [`TermGraph::from`]: crate::vm::TermGraph
+pub fn arb_graph_term(config: TermConfig) -> BoxedStrategy<Term> {
+    scoped_term(config.max_depth, config.labels, vec![], 0)
+}
+
+/// Generates a term that uses each of `vars` exactly once, and binds no
+/// other variable used in it, under `bound` binders.
+fn scoped_term(
+    depth: u32,
+    labels: RangeInclusive<Label>,
+    vars: Vec<IStr>,
+    bound: usize,
+) -> BoxedStrategy<Term> {
+    let name = |n: usize| format!("x{}", n).intern();
+    // Splits `vars` in two, each going to the first part or the second.
+    let split = |vars: Vec<IStr>| {
+        prop::collection::vec(any::<bool>(), vars.len()).prop_map(move |mask| {
+            let (a, b): (Vec<_>, Vec<_>) = vars.iter().zip(mask).partition(|(_, first)| *first);
+            let unzip = |part: Vec<(&IStr, bool)>| part.into_iter().map(|(x, _)| *x).collect();
+            (unzip(a), unzip(b))
+        })
+    };
+    let pair = {
+        let labels = labels.clone();
+        move |depth: u32, (a, b): (Vec<IStr>, Vec<IStr>)| {
+            (
+                scoped_term(depth, labels.clone(), a, bound),
+                scoped_term(depth, labels.clone(), b, bound),
+            )
+        }
+    };
+    if depth == 0 {
+        return match vars.len() {
+            0 => (0..100u64).prop_map(Term::Num).boxed(),
+            1 => Just(Term::Var(vars[0])).boxed(),
+            n => {
+                // Too deep: share the variables out as evenly as possible.
+                let (a, b) = vars.split_at(n / 2);
+                pair(0, (a.to_vec(), b.to_vec()))
+                    .prop_map(|(a, b)| Term::App(Box::new(a), Box::new(b)))
+                    .boxed()
+            }
+        };
+    }
+    let depth = depth - 1;
+    let lam = {
+        let (labels, vars) = (labels.clone(), vars.clone());
+        any::<bool>()
+            .prop_flat_map(move |used| {
+                let mut vars = vars.clone();
+                if used {
+                    vars.push(name(bound));
+                }
+                scoped_term(depth, labels.clone(), vars, bound + 1)
+            })
+            .prop_map(move |body| Term::Lam(name(bound), Box::new(body)))
+    };
+    let binary = {
+        let pair = pair.clone();
+        let ops = prop::option::of(prop::sample::select(&Op::ALL[..]));
+        (ops, split(vars.clone())).prop_flat_map(move |(op, parts)| {
+            pair(depth, parts).prop_map(move |(a, b)| match op {
+                Some(op) => Term::Op2(op, Box::new(a), Box::new(b)),
+                None => Term::App(Box::new(a), Box::new(b)),
+            })
+        })
+    };
+    let sup = {
+        let pair = pair.clone();
+        (labels.clone(), split(vars.clone())).prop_flat_map(move |(l, parts)| {
+            pair(depth, parts).prop_map(move |(a, b)| Term::Sup(l, Box::new(a), Box::new(b)))
+        })
+    };
+    let dup = {
+        let labels = labels.clone();
+        (labels.clone(), split(vars.clone())).prop_flat_map(move |(l, (expr, mut rest))| {
+            let (a, b) = (name(bound), name(bound + 1));
+            rest.extend([a, b]);
+            (
+                scoped_term(depth, labels.clone(), expr, bound),
+                scoped_term(depth, labels.clone(), rest, bound + 2),
+            )
+                .prop_map(move |(e, body)| Term::Dup(l, a, b, Box::new(e), Box::new(body)))
+        })
+    };
+    let let_ = {
+        let labels = labels.clone();
+        (any::<bool>(), split(vars.clone())).prop_flat_map(move |(used, (expr, mut rest))| {
+            let x = name(bound);
+            if used {
+                rest.push(x);
+            }
+            (
+                scoped_term(depth, labels.clone(), expr, bound),
+                scoped_term(depth, labels.clone(), rest, bound + 1),
+            )
+                .prop_map(move |(e, body)| Term::Let(x, Box::new(e), Box::new(body)))
+        })
+    };
+    let compound = prop_oneof![lam, binary, sup, dup, let_];
+    match vars.len() {
+        0 => prop_oneof![1 => (0..100u64).prop_map(Term::Num), 4 => compound].boxed(),
+        1 => prop_oneof![1 => Just(Term::Var(vars[0])), 4 => compound].boxed(),
+        _ => compound.boxed(),
+    }
+}
+
+/// Replaces the variables of a term that a [`TermConfig`] does not allow.
+struct Scope {
+    closed: bool,
+    affine: bool,
+    /// The binders in scope, innermost last, and whether each is used.
+    bound: Vec<(IStr, bool)>,
+    /// The free variables used so far.
+    free: HashSet<IStr>,
+}
+
+impl Scope {
+    fn new(config: &TermConfig) -> Self {
+        Scope {
+            closed: config.closed,
+            affine: config.affine,
+            bound: vec![],
+            free: HashSet::new(),
+        }
+    }
+
+    fn rescope(&mut self, term: &Term) -> Term {
+        match term {
+            Term::Var(x) => self.var(*x),
+            Term::Ref(name) => Term::Ref(*name),
+            Term::Num(n) => Term::Num(*n),
+            Term::Lam(x, body) => Term::Lam(*x, Box::new(self.bind(&[*x], body))),
+            Term::App(e1, e2) => {
+                let e1 = self.rescope(e1);
+                Term::App(Box::new(e1), Box::new(self.rescope(e2)))
+            }
+            Term::Sup(l, e1, e2) => {
+                let e1 = self.rescope(e1);
+                Term::Sup(*l, Box::new(e1), Box::new(self.rescope(e2)))
+            }
+            Term::Op2(op, e1, e2) => {
+                let e1 = self.rescope(e1);
+                Term::Op2(*op, Box::new(e1), Box::new(self.rescope(e2)))
+            }
+            Term::Dup(l, a, b, e, body) => {
+                let e = self.rescope(e);
+                let body = self.bind(&[*a, *b], body);
+                Term::Dup(*l, *a, *b, Box::new(e), Box::new(body))
+            }
+            Term::Let(x, e, body) => {
+                let e = self.rescope(e);
+                Term::Let(*x, Box::new(e), Box::new(self.bind(&[*x], body)))
+            }
+        }
+    }
+
+    fn bind(&mut self, names: &[IStr], body: &Term) -> Term {
+        let depth = self.bound.len();
+        self.bound.extend(names.iter().map(|name| (*name, false)));
+        let body = self.rescope(body);
+        self.bound.truncate(depth);
+        body
+    }
+
+    /// Returns whether the binder at `index` is the innermost one of its name.
+    fn visible(&self, index: usize) -> bool {
+        let name = self.bound[index].0;
+        self.bound[index + 1..]
+            .iter()
+            .all(|(other, _)| *other != name)
+    }
+
+    fn var(&mut self, x: IStr) -> Term {
+        let usable = |scope: &Scope, index: usize| {
+            scope.visible(index) && !(scope.affine && scope.bound[index].1)
+        };
+        let index = match self.bound.iter().rposition(|(name, _)| *name == x) {
+            Some(index) if usable(self, index) => Some(index),
+            // Another binder, picked by the name, so that the choice shrinks
+            // along with it.
+            _ => {
+                let candidates: Vec<usize> =
+                    (0..self.bound.len()).filter(|&i| usable(self, i)).collect();
+                let bound = self.bound.iter().any(|(name, _)| *name == x);
+                match (candidates.is_empty(), self.closed || bound) {
+                    (false, true) => Some(candidates[x.to_string().len() % candidates.len()]),
+                    _ => None,
+                }
+            }
+        };
+        if let Some(index) = index {
+            self.bound[index].1 = true;
+            return Term::Var(self.bound[index].0);
+        }
+        if self.closed {
+            return Term::Num(0);
+        }
+        // A free variable, renamed if it is taken. Generated names have no
+        // `$`, so the new one is not taken.
+        let taken = |scope: &Scope, name: IStr| {
+            scope.bound.iter().any(|(other, _)| *other == name)
+                || (scope.affine && scope.free.contains(&name))
+        };
+        let name = match taken(self, x) {
+            true => (0..)
+                .map(|n| format!("{}${}", x, n).intern())
+                .find(|name| !taken(self, *name))
+                .unwrap(),
+            false => x,
+        };
+        self.free.insert(name);
+        Term::Var(name)
+    }
+}
+
+/// The reductions tried by [`check_confluence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfluenceConfig {
+    /// The number of steps after which a reduction that has not reached a
+    /// normal form is given up on.
+    pub max_steps: usize,
+    /// The seeds of the reductions in a random order, one per seed.
+    pub seeds: Range<u64>,
+}
+
+impl Default for ConfluenceConfig {
+    /// Up to 10000 steps, with 16 random orders.
+    fn default() -> Self {
+        ConfluenceConfig {
+            max_steps: 10_000,
+            seeds: 0..16,
+        }
+    }
+}
+
+/// One way of reducing a graph, as tried by [`check_confluence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+    /// [`Strategy::Deterministic`] with the default [`StrategyConfig`].
+    ///
+    /// [`Strategy::Deterministic`]: crate::vm::Strategy::Deterministic
+    Deterministic,
+    /// [`Strategy::Deterministic`] with
+    /// [`StrategyConfig::prefer_annihilations`].
+    ///
+    /// [`Strategy::Deterministic`]: crate::vm::Strategy::Deterministic
+    PreferAnnihilations,
+    /// [`Strategy::NormalOrder`].
+    ///
+    /// [`Strategy::NormalOrder`]: crate::vm::Strategy::NormalOrder
+    NormalOrder,
+    /// A random order, with the default [`StrategyConfig`], drawn from a
+    /// generator seeded with this value.
+    Random(u64),
+}
+
+impl fmt::Display for Reduction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reduction::Deterministic => write!(f, "deterministic"),
+            Reduction::PreferAnnihilations => {
+                write!(f, "deterministic, preferring annihilations")
+            }
+            Reduction::NormalOrder => write!(f, "normal order"),
+            Reduction::Random(seed) => write!(f, "random order with seed {}", seed),
+        }
+    }
+}
+
+/// The reductions of a graph that agree, as returned by
+/// [`check_confluence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfluenceReport {
+    /// The normal form that the reductions reached, canonical (see
+    /// [`Term::canonicalize`]), or `None` if none reached one that could be
+    /// compared.
+    pub normal_form: Option<Term>,
+    /// Each reduction tried, in order, with the number of steps it took to
+    /// reach a normal form, or `None` if it did not within the bound.
+    pub runs: Vec<(Reduction, Option<usize>)>,
+}
+
+/// Two reductions of a graph that reached different normal forms, as
+/// returned by [`check_confluence`]. Both normal forms are canonical (see
+/// [`Term::canonicalize`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement {
+    /// The first reduction to reach a normal form, and that normal form.
+    pub first: (Reduction, Term),
+    /// The first reduction to reach another one, and the other normal form.
+    pub second: (Reduction, Term),
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "reducing in {} gives {}, but reducing in {} gives {}",
+            self.first.0, self.first.1, self.second.0, self.second.1
+        )
+    }
+}
+
+impl std::error::Error for Disagreement {}
+
+/// Reduces copies of `term_graph` in several orders, and checks that those
+/// that reach a normal form within `config.max_steps` steps all reach the
+/// same one, up to the names of binders and the values of labels.
+///
+/// Normal forms with a free variable that `term_graph` does not have are not
+/// compared. Such a variable is left where a lambda was erased while its
+/// variable was still used outside of its body, and erasure stops there, so
+/// whatever was built from the variable depends on when the lambda was
+/// erased.
+///
+/// The orders are those of [`Reduction`]: the three fixed strategies, then a
+/// random order for each of `config.seeds`. The random choices come from a
+/// generator of this module rather than from `rand`, so a seed replays the
+/// same reduction whatever the version of `rand`, or without it.
+///
+/// This is meant for property tests of new rules or strategies, e.g. with the
+/// terms of [`arb_graph_term`]. A reduction that runs out of steps is not a
+/// failure, since a term may diverge in one order and not in another.
+pub fn check_confluence(
+    term_graph: &TermGraph,
+    config: &ConfluenceConfig,
+) -> Result<ConfluenceReport, Box<Disagreement>> {
+    let fixed = [
+        Reduction::Deterministic,
+        Reduction::PreferAnnihilations,
+        Reduction::NormalOrder,
+    ];
+    let reductions = fixed
+        .into_iter()
+        .chain(config.seeds.clone().map(Reduction::Random));
+    let mut report = ConfluenceReport {
+        normal_form: None,
+        runs: vec![],
+    };
+    let free_vars = Term::from(term_graph).free_vars();
+    let mut first = None;
+    for reduction in reductions {
+        let mut term_graph = term_graph.clone();
+        let steps = reduce(&mut term_graph, reduction, config.max_steps);
+        report.runs.push((reduction, steps));
+        if steps.is_none() {
+            continue;
+        }
+        let normal_form = Term::from(&term_graph);
+        // A variable that lost its binder is read back with a made-up name.
+        if !normal_form.free_vars().is_subset(&free_vars) {
+            continue;
+        }
+        let normal_form = normal_form.canonicalize().0;
+        match &first {
+            None => first = Some((reduction, normal_form)),
+            Some((_, expected)) if *expected == normal_form => {}
+            Some(_) => {
+                return Err(Box::new(Disagreement {
+                    first: first.unwrap(),
+                    second: (reduction, normal_form),
+                }))
+            }
+        }
+    }
+    report.normal_form = first.map(|(_, normal_form)| normal_form);
+    Ok(report)
+}
+
+/// Reduces `term_graph` as `reduction` does, and returns the number of steps
+/// it took to reach a normal form, or `None` if it has not reached one after
+/// `max_steps` steps.
+fn reduce(term_graph: &mut TermGraph, reduction: Reduction, max_steps: usize) -> Option<usize> {
+    let default = StrategyConfig::default();
+    let annihilations = StrategyConfig::prefer_annihilations();
+    let mut rng = match reduction {
+        Reduction::Random(seed) => SplitMix(seed),
+        _ => SplitMix(0),
+    };
+    for steps in 0..=max_steps {
+        let rule = match reduction {
+            Reduction::Deterministic => term_graph.naive_reduce_step_with(&default),
+            Reduction::PreferAnnihilations => term_graph.naive_reduce_step_with(&annihilations),
+            Reduction::NormalOrder => term_graph.reduce_normal_order_step(),
+            Reduction::Random(_) => {
+                term_graph.naive_random_order_reduce_step_with_source(&default, &mut rng)
+            }
+        };
+        if rule.is_none() {
+            return Some(steps);
+        }
+    }
+    None
+}
+
+/// The SplitMix64 generator, which is small, fast and good enough to pick
+/// redexes.
+struct SplitMix(u64);
+
+impl RandomSource for SplitMix {
+    fn next_index(&mut self, len: usize) -> usize {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) % len as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::desugar::desugar;
+
+    proptest! {
+        #[test]
+        fn test_arb_term_closed(term in arb_term_with(TermConfig {
+            closed: true,
+            ..TermConfig::default()
+        })) {
+            prop_assert!(term.free_vars().is_empty(), "{}", term);
+        }
+
+        #[test]
+        fn test_arb_term_affine(term in arb_term_with(TermConfig {
+            affine: true,
+            labels: 0..=3,
+            ..TermConfig::default()
+        })) {
+            // Desugaring only changes variables that are used more than once.
+            prop_assert_eq!(&desugar(&term), &term);
+            let mut stack = vec![&term];
+            while let Some(term) = stack.pop() {
+                if let Term::Sup(l, ..) | Term::Dup(l, ..) = term {
+                    prop_assert!(*l <= 3);
+                }
+                stack.extend(term.children());
+            }
+        }
+
+        #[test]
+        fn test_arb_graph_term(term in arb_graph_term(TermConfig {
+            max_depth: 6,
+            labels: 0..=2,
+            ..TermConfig::default()
+        })) {
+            prop_assert!(term.free_vars().is_empty(), "{}", term);
+            prop_assert_eq!(&desugar(&term), &term);
+            let mut stack = vec![&term];
+            while let Some(term) = stack.pop() {
+                if let Term::Dup(_, a, b, e, body) = term {
+                    prop_assert!(!e.free_vars().contains(a) && !e.free_vars().contains(b));
+                    prop_assert!(body.free_vars().contains(a) && body.free_vars().contains(b));
+                }
+                stack.extend(term.children());
+            }
+            let mut term_graph = TermGraph::from(&term);
+            for _ in 0..100 {
+                let redexes = term_graph.redex_count();
+                prop_assert_eq!(redexes, term_graph.redex_sites().len());
+                prop_assert_eq!(term_graph.is_normal_form(), redexes == 0);
+                if term_graph.naive_reduce_step().is_none() {
+                    break;
+                }
+            }
+        }
+
+        #[test]
+        fn test_arb_graph_term_confluence(term in arb_graph_term(TermConfig {
+            max_depth: 6,
+            labels: 0..=2,
+            ..TermConfig::default()
+        })) {
+            let config = ConfluenceConfig {
+                max_steps: 1000,
+                seeds: 0..4,
+            };
+            let result = check_confluence(&TermGraph::from(&term), &config);
+            prop_assert!(result.is_ok(), "{}: {}", term, result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn test_check_confluence() {
+        let term: Term = "(λf λx dup #0{f1 f2} = f; (f1 (f2 x)) λy dup #1{y1 y2} = y; #2{y1 y2})"
+            .parse()
+            .unwrap();
+        let report =
+            check_confluence(&TermGraph::from(&term), &ConfluenceConfig::default()).unwrap();
+        // The labels are renumbered in the order they appear.
+        let expected = "λx dup #0{a b} = x; dup #0{c d} = a; dup #0{e f} = b; #1{#1{c e} #1{d f}}";
+        assert_eq!(
+            report.normal_form,
+            Some(expected.parse::<Term>().unwrap().canonicalize().0)
+        );
+        assert_eq!(report.runs.len(), 3 + 16);
+        assert_eq!(report.runs[3].0, Reduction::Random(0));
+        assert!(report.runs.iter().all(|(_, steps)| steps.is_some()));
+
+        // A term without a normal form.
+        let omega = desugar(&"(λx (x x) λx (x x))".parse().unwrap());
+        let config = ConfluenceConfig {
+            max_steps: 50,
+            seeds: 0..2,
+        };
+        let report = check_confluence(&TermGraph::from(&omega), &config).unwrap();
+        assert_eq!(report.normal_form, None);
+        assert_eq!(report.runs.len(), 5);
+    }
+}
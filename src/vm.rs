@@ -1,15 +1,99 @@
-use rand::seq::SliceRandom;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::mem::{align_of, size_of};
 use std::ptr::addr_of_mut;
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "profiling")]
+use std::time::Instant;
 use std::{fmt, ptr};
 
+use once_cell::sync::Lazy;
+
+use crate::book::{Book, Def};
+use crate::error::{Error, GraphBuildError};
 use crate::intern::{IStr, Intern, InternStatic};
-use crate::syntax::Term;
+use crate::syntax::{Label, Op, Term};
+
+mod arena;
+mod boehm;
+mod breakpoint;
+mod chrome_trace;
+mod clone;
+mod cost;
+mod cursor;
+mod cycle;
+mod dce;
+mod debug;
+mod derivation;
+mod diagnosis;
+mod dot;
+mod dump;
+mod eval;
+mod fallback;
+mod flatten;
+mod gc;
+mod hash;
+mod hnf;
+mod iso;
+mod metrics;
+mod outcomes;
+mod parallel;
+mod partial;
+mod pattern;
+mod pin;
+#[cfg(feature = "profiling")]
+mod profile;
+mod progress;
+mod random;
+mod replace;
+mod rewrite;
+mod roots;
+mod script;
+mod series;
+mod sharing;
+mod snapshot;
+mod spine;
+mod split;
+mod stats;
+mod strategy;
+mod stream;
+mod superstep;
+mod trace;
+mod validate;
+mod visit;
+
+use arena::Arena;
+pub use boehm::{boehm_compare, Comparison};
+pub use breakpoint::{Breakpoint, BreakpointHit};
+pub use cost::CostModel;
+pub use cursor::{Cursor, NodeKind};
+pub use cycle::CycleDetector;
+pub use derivation::Derivation;
+pub use diagnosis::Diagnosis;
+pub use dump::{ChildRecord, NodeRecord, RecordTag, UseRecord};
+pub use eval::eval_with_env;
+pub use fallback::{FallbackOutcome, FallbackPlan, Stage};
+pub use metrics::StepMetrics;
+pub use outcomes::Outcomes;
+pub use partial::specialize;
+pub use pattern::Pattern;
+pub use pin::NodeHandle;
+#[cfg(feature = "profiling")]
+pub use profile::{LatencyHistogram, LatencyStats};
+pub use progress::{Progress, ProgressThrottle};
+pub use random::{Counter, RandomSource};
+pub use rewrite::Match;
+pub use script::{Divergence, ReductionScript, ScriptStep};
+pub use series::{Sample, TimeSeries};
+pub use sharing::SharingReport;
+pub use stats::{LiveNodes, Stats};
+pub use strategy::{RedexSite, RuleKind, Strategy, StrategyConfig};
+pub use trace::TraceMode;
+pub(crate) use validate::validate;
+pub use visit::{GraphVisitor, Visit};
 
 /// A lambda node, e.g. `(λx e)`.
 #[derive(Debug, Clone, Copy)]
-#[repr(C, align(8))]
+#[repr(C)]
 struct Lam {
     x: Tagged,
     e: Tagged,
@@ -17,67 +101,280 @@ struct Lam {
 
 /// An application node, e.g. `(e1 e2)`.
 #[derive(Debug, Clone, Copy)]
-#[repr(C, align(8))]
+#[repr(C)]
 struct App {
     e1: Tagged,
     e2: Tagged,
 }
 
 /// A superposition node, e.g. `#l{e1 e2}`.
+///
+/// The label is packed into the header's word, so it is at most
+/// [`MAX_LABEL`].
 #[derive(Debug, Clone, Copy)]
-#[repr(C, align(8))]
+#[repr(C)]
 struct Sup {
-    l: u64,
+    header: Header,
+    l: u32,
     e1: Tagged,
     e2: Tagged,
 }
 
 /// A duplication node, e.g. `dup #l{a b} = e;`
+///
+/// As with [`Sup`], the label is packed into the header's word.
 #[derive(Debug, Clone, Copy)]
-#[repr(C, align(8))]
+#[repr(C)]
 struct Dup {
-    l: u64,
+    header: Header,
+    l: u32,
     a: Tagged,
     b: Tagged,
     e: Tagged,
 }
 
+/// A number node, e.g. `42`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Num {
+    n: u64,
+}
+
+/// A binary operation node, e.g. `(+ e1 e2)`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Op2 {
+    header: Header,
+    op: Op,
+    e1: Tagged,
+    e2: Tagged,
+}
+
+/// The largest label a superposition or dup of a [`TermGraph`] can hold.
+pub const MAX_LABEL: Label = u32::MAX as Label;
+
+/// Returns `l` as a node holds it.
+///
+/// Panics if `l` is larger than [`MAX_LABEL`].
+fn node_label(l: Label) -> u32 {
+    u32::try_from(l).expect("label larger than MAX_LABEL")
+}
+
+/// The first field of everything pointed to by a pointer with the primary tag
+/// [`Tag::EXT`], which holds the pointer's tag.
+///
+/// Every node of a type with a header is allocated with its header, which is
+/// never changed afterwards, even once the node is freed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub(crate) struct Header(Tag);
+
+impl Header {
+    const SUP: Header = Header(Tag::SupPtr);
+    const DUP: Header = Header(Tag::DupPtr);
+    const OP2: Header = Header(Tag::Op2Ptr);
+    const UNBOUND_VAR: Header = Header(Tag::UnboundVar);
+    /// The header of a [`Def`], which `Ref` pointers point to.
+    pub(crate) const REF: Header = Header(Tag::Ref);
+}
+
+/// The name of a free variable, which an `UnboundVar` pointer points to.
+#[repr(C)]
+struct FreeName {
+    header: Header,
+    /// The name, or `None` for a variable without a name, such as the
+    /// variable of an erased lambda that is still used.
+    name: Option<IStr>,
+}
+
+/// What the unbound variables without a name point to.
+static NAMELESS: FreeName = FreeName {
+    header: Header::UNBOUND_VAR,
+    name: None,
+};
+
+/// The name of each free variable of every graph built so far. Names are
+/// never freed, like the interned strings they hold.
+static FREE_NAMES: Lazy<RwLock<HashMap<IStr, &'static FreeName>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// The set of nodes allocated for a [`TermGraph`].
+///
+/// Every node allocated by graph construction or a rewrite rule lives in the
+/// heap's arena until it is deallocated, so that nodes which become
+/// unreachable can still be found and freed.
+#[derive(Default)]
+struct Heap {
+    /// Nodes pinned by a [`NodeHandle`], indexed by the handle.
+    /// Entries become `None` once the pinned node is freed.
+    pins: Vec<Option<Tagged>>,
+    /// The indices in `pins` of the handles pinning each pinned node, so that
+    /// freeing a node only looks up its own pins.
+    pinned: HashMap<Tagged, Vec<usize>>,
+    /// The named roots of a multi-root graph. The first of these is also the
+    /// graph's primary root.
+    roots: Vec<(IStr, *mut Tagged)>,
+    /// The number of nodes allocated, the number deallocated, the largest
+    /// number live at once, and the number of rewrites of each [`RuleKind`],
+    /// since the graph was built or [`TermGraph::reset_stats`] was called.
+    allocations: usize,
+    freed: usize,
+    peak_live: usize,
+    rewrites: [usize; 15],
+    /// The number of live nodes of each type, in the order of the free
+    /// lists.
+    live_by_type: [usize; 6],
+    /// Latency histograms of the rewrites and redex searches.
+    #[cfg(feature = "profiling")]
+    latency: LatencyStats,
+    /// With `Some`, this is the heap of a worker thread of
+    /// [`TermGraph::parallel_reduce`], and collects the nodes released that it
+    /// did not allocate, to be released from the graph's heap afterwards.
+    foreign: Option<Vec<Tagged>>,
+    /// The memory of the nodes, live or not. Deallocated nodes of each type
+    /// are kept for reuse by later allocations of the same type until the
+    /// graph is dropped.
+    arena: Arena,
+    /// Nodes connected to an eraser by a rewrite, waiting for
+    /// [`apply_erasures`] to erase them.
+    erasures: Vec<Tagged>,
+    /// The definitions that `Ref` pointers point to, kept alive as long as
+    /// the graph.
+    book: Arc<Book>,
+}
+
+impl Heap {
+    /// Records a newly allocated node.
+    fn track(&mut self, node: Tagged) {
+        self.allocations += 1;
+        self.live_by_type[unsafe { type_index(node.tag()) }] += 1;
+        self.peak_live = self.peak_live.max(self.live_count());
+    }
+
+    /// Forgets a node that is about to be deallocated.
+    fn release(&mut self, node: Tagged) {
+        if let Some(foreign) = &mut self.foreign {
+            if !self.arena.owns(node) {
+                foreign.push(node);
+                return;
+            }
+        }
+        self.freed += 1;
+        self.live_by_type[unsafe { type_index(node.tag()) }] -= 1;
+        if !self.pinned.is_empty() {
+            for index in self.pinned.remove(&node).into_iter().flatten() {
+                self.pins[index] = None;
+            }
+        }
+    }
+
+    /// Returns the number of live nodes.
+    fn live_count(&self) -> usize {
+        self.live_by_type.iter().sum()
+    }
+
+    fn live_nodes(&self) -> LiveNodes {
+        let [lams, apps, sups, dups, nums, ops] = self.live_by_type;
+        LiveNodes {
+            lams,
+            apps,
+            sups,
+            dups,
+            nums,
+            ops,
+        }
+    }
+
+    /// Returns the free list for nodes of the type pointed to by `tag`.
+    fn free_list(&mut self, tag: Tag) -> &mut Vec<*mut ()> {
+        &mut self.arena.slab(tag).free
+    }
+}
+
+/// Returns the index of the type of node pointed to by `tag` in the arrays of
+/// a [`Heap`] that hold something per type: `Lam`, `App`, `Sup`, `Dup`, `Num`,
+/// and `Op2`.
+fn type_index(tag: Tag) -> usize {
+    match tag {
+        Tag::LamPtr => 0,
+        Tag::AppPtr => 1,
+        Tag::SupPtr => 2,
+        Tag::DupPtr => 3,
+        Tag::NumPtr => 4,
+        Tag::Op2Ptr => 5,
+        _ => unreachable!("{:?} does not point to a node", tag),
+    }
+}
+
 enum NodeType {
     Lam,
     App,
     Sup,
     Dup,
+    Num,
+    Op2,
+}
+
+/// Allocates a node of type `T` and records it in `heap`, returning a pointer
+/// to it tagged with `tag`. The node is uninitialized but for its [`Header`],
+/// if its type has one.
+///
+/// Every node is allocated here, from the slab of the heap's arena for its
+/// type. A node of the same type from the slab's free list is reused if there
+/// is one. Otherwise, aborts if the allocator fails.
+#[inline(always)]
+unsafe fn alloc_node<T>(heap: &mut Heap, tag: Tag) -> Tagged {
+    unsafe {
+        debug_assert_eq!(size_of::<T>() % Tag::NODE_ALIGN, 0);
+        let ptr = heap.arena.slab(tag).alloc();
+        if tag.primary() == Tag::EXT {
+            (ptr as *mut Header).write(Header(tag));
+        }
+        let tagged = Tagged::new(ptr, tag);
+        heap.track(tagged);
+        tagged
+    }
 }
 
 impl Lam {
     #[inline(always)]
-    unsafe fn alloc() -> Tagged {
-        let ptr = std::alloc::alloc(std::alloc::Layout::new::<Self>()) as *mut ();
-        Tagged::new(ptr, Tag::LamPtr)
+    unsafe fn alloc(heap: &mut Heap) -> Tagged {
+        unsafe { alloc_node::<Self>(heap, Tag::LamPtr) }
     }
 }
 
 impl App {
     #[inline(always)]
-    unsafe fn alloc() -> Tagged {
-        let ptr = std::alloc::alloc(std::alloc::Layout::new::<Self>()) as *mut ();
-        Tagged::new(ptr, Tag::AppPtr)
+    unsafe fn alloc(heap: &mut Heap) -> Tagged {
+        unsafe { alloc_node::<Self>(heap, Tag::AppPtr) }
     }
 }
 
 impl Sup {
     #[inline(always)]
-    unsafe fn alloc() -> Tagged {
-        let ptr = std::alloc::alloc(std::alloc::Layout::new::<Self>()) as *mut ();
-        Tagged::new(ptr, Tag::SupPtr)
+    unsafe fn alloc(heap: &mut Heap) -> Tagged {
+        unsafe { alloc_node::<Self>(heap, Tag::SupPtr) }
     }
 }
 
 impl Dup {
     #[inline(always)]
-    unsafe fn alloc() -> Tagged {
-        let ptr = std::alloc::alloc(std::alloc::Layout::new::<Self>()) as *mut ();
-        Tagged::new(ptr, Tag::DupPtr)
+    unsafe fn alloc(heap: &mut Heap) -> Tagged {
+        unsafe { alloc_node::<Self>(heap, Tag::DupPtr) }
+    }
+}
+
+impl Num {
+    #[inline(always)]
+    unsafe fn alloc(heap: &mut Heap) -> Tagged {
+        unsafe { alloc_node::<Self>(heap, Tag::NumPtr) }
+    }
+}
+
+impl Op2 {
+    #[inline(always)]
+    unsafe fn alloc(heap: &mut Heap) -> Tagged {
+        unsafe { alloc_node::<Self>(heap, Tag::Op2Ptr) }
     }
 }
 
@@ -89,12 +386,12 @@ trait LamPtrExt {
 impl LamPtrExt for *mut Lam {
     #[inline(always)]
     unsafe fn x(self) -> *mut Tagged {
-        addr_of_mut!((*self).x)
+        unsafe { addr_of_mut!((*self).x) }
     }
 
     #[inline(always)]
     unsafe fn e(self) -> *mut Tagged {
-        addr_of_mut!((*self).e)
+        unsafe { addr_of_mut!((*self).e) }
     }
 }
 
@@ -106,40 +403,40 @@ trait AppPtrExt {
 impl AppPtrExt for *mut App {
     #[inline(always)]
     unsafe fn e1(self) -> *mut Tagged {
-        addr_of_mut!((*self).e1)
+        unsafe { addr_of_mut!((*self).e1) }
     }
 
     #[inline(always)]
     unsafe fn e2(self) -> *mut Tagged {
-        addr_of_mut!((*self).e2)
+        unsafe { addr_of_mut!((*self).e2) }
     }
 }
 
 trait SupPtrExt {
-    unsafe fn l(self) -> *mut u64;
+    unsafe fn l(self) -> *mut u32;
     unsafe fn e1(self) -> *mut Tagged;
     unsafe fn e2(self) -> *mut Tagged;
 }
 
 impl SupPtrExt for *mut Sup {
     #[inline(always)]
-    unsafe fn l(self) -> *mut u64 {
-        addr_of_mut!((*self).l)
+    unsafe fn l(self) -> *mut u32 {
+        unsafe { addr_of_mut!((*self).l) }
     }
 
     #[inline(always)]
     unsafe fn e1(self) -> *mut Tagged {
-        addr_of_mut!((*self).e1)
+        unsafe { addr_of_mut!((*self).e1) }
     }
 
     #[inline(always)]
     unsafe fn e2(self) -> *mut Tagged {
-        addr_of_mut!((*self).e2)
+        unsafe { addr_of_mut!((*self).e2) }
     }
 }
 
 trait DupPtrExt {
-    unsafe fn l(self) -> *mut u64;
+    unsafe fn l(self) -> *mut u32;
     unsafe fn a(self) -> *mut Tagged;
     unsafe fn b(self) -> *mut Tagged;
     unsafe fn e(self) -> *mut Tagged;
@@ -147,375 +444,599 @@ trait DupPtrExt {
 
 impl DupPtrExt for *mut Dup {
     #[inline(always)]
-    unsafe fn l(self) -> *mut u64 {
-        addr_of_mut!((*self).l)
+    unsafe fn l(self) -> *mut u32 {
+        unsafe { addr_of_mut!((*self).l) }
     }
 
     #[inline(always)]
     unsafe fn a(self) -> *mut Tagged {
-        addr_of_mut!((*self).a)
+        unsafe { addr_of_mut!((*self).a) }
     }
 
     #[inline(always)]
     unsafe fn b(self) -> *mut Tagged {
-        addr_of_mut!((*self).b)
+        unsafe { addr_of_mut!((*self).b) }
     }
 
     #[inline(always)]
     unsafe fn e(self) -> *mut Tagged {
-        addr_of_mut!((*self).e)
+        unsafe { addr_of_mut!((*self).e) }
+    }
+}
+
+trait Op2PtrExt {
+    unsafe fn op(self) -> *mut Op;
+    unsafe fn e1(self) -> *mut Tagged;
+    unsafe fn e2(self) -> *mut Tagged;
+}
+
+impl Op2PtrExt for *mut Op2 {
+    #[inline(always)]
+    unsafe fn op(self) -> *mut Op {
+        unsafe { addr_of_mut!((*self).op) }
+    }
+
+    #[inline(always)]
+    unsafe fn e1(self) -> *mut Tagged {
+        unsafe { addr_of_mut!((*self).e1) }
+    }
+
+    #[inline(always)]
+    unsafe fn e2(self) -> *mut Tagged {
+        unsafe { addr_of_mut!((*self).e2) }
     }
 }
 
 /// A tagged value or pointer.
+///
+/// The primary tag is stored in the low 3 bits of the pointer, which the
+/// 8-byte alignment of nodes and slots leaves free, so no assumption is made
+/// about the high bits of addresses. Three bits are too few for every tag, so
+/// the commonest tags each have a primary tag of their own, and the others
+/// share [`Tag::EXT`] and are told apart by the [`Header`] of what they point
+/// to. An unused variable is a `VarUsePtr` with a null pointer.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(C, align(8))]
 struct Tagged(*mut ());
 const _: () = assert!(size_of::<Tagged>() == 8);
-const _: () = assert!(align_of::<Tagged>() == 8);
+const _: () = assert!(align_of::<Tagged>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<Lam>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<App>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<Sup>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<Dup>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<Num>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<Op2>() == Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<Def>() >= Tag::NODE_ALIGN);
+const _: () = assert!(align_of::<FreeName>() >= Tag::NODE_ALIGN);
+const _: () = assert!(size_of::<Sup>() == 24);
+const _: () = assert!(size_of::<Dup>() == 32);
+const _: () = assert!(size_of::<Num>() == 8);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 enum Tag {
-    /// An unused variable.
-    /// This tag should only be used by `Lam::x`, `Dup::a`, or `Dup::b`.
-    UnusedVar = 1,
     /// A pointer to a field inside of a `Lam`, `App`, `Sup`, or `Dup` node,
     /// corresponding to a variable use.
     /// This tag should only be used by `Lam::x`, `Dup::a`, or `Dup::b`.
-    VarUsePtr = 2,
-    /// An unbound variable.
-    UnboundVar = 3,
+    VarUsePtr = 1,
     /// A variable bound by a `Lam::x`. Points to the binding `Lam` node.
-    LamBoundVar = 4,
+    LamBoundVar = 2,
     /// A variable bound by a `Dup::a`. Points to the binding `Dup` node.
-    DupABoundVar = 5,
+    DupABoundVar = 3,
     /// A variable bound by a `Dup::b`. Points to the binding `Dup` node.
-    DupBBoundVar = 6,
+    DupBBoundVar = 4,
     /// A pointer to a `Lam` node.
-    LamPtr = 7,
+    LamPtr = 5,
     /// A pointer to an `App` node.
-    AppPtr = 8,
+    AppPtr = 6,
+    /// A pointer to a `Num` node.
+    NumPtr = 7,
+    /// An unused variable, stored as a null `VarUsePtr`.
+    /// This tag should only be used by `Lam::x`, `Dup::a`, or `Dup::b`.
+    UnusedVar = 8,
+    /// An unbound variable. Points to its [`FreeName`].
+    UnboundVar = 9,
+    /// A reference to a definition of the graph's [`Book`]. Points to the
+    /// `Def`, which is not a node of the graph.
+    Ref = 10,
     /// A pointer to a `Sup` node.
-    SupPtr = 9,
+    SupPtr = 11,
     /// A pointer to a `Dup` node.
-    DupPtr = 10,
+    DupPtr = 12,
+    /// A pointer to an `Op2` node.
+    Op2Ptr = 13,
 }
 
 impl Tag {
-    // NOTE: We're storing the tag in the top 4 bits of the pointer.
-    const BIT_OFFSET: u64 = 60;
-    const MASK: u64 = 0xF000000000000000;
+    /// The alignment of every node and slot, which frees the bits of `MASK`.
+    const NODE_ALIGN: usize = 8;
+    /// The bits holding the primary tag.
+    const MASK: usize = Tag::NODE_ALIGN - 1;
+    /// The primary tag of the tags that are read from a [`Header`].
+    const EXT: usize = 0;
+
+    /// Returns the bits stored in a pointer with this tag.
+    #[inline(always)]
+    fn primary(self) -> usize {
+        match self {
+            Tag::UnusedVar => Tag::VarUsePtr as usize,
+            Tag::UnboundVar | Tag::Ref | Tag::SupPtr | Tag::DupPtr | Tag::Op2Ptr => Tag::EXT,
+            tag => tag as usize,
+        }
+    }
 }
 
 impl Tagged {
     #[inline(always)]
     unsafe fn new(ptr: *mut (), tag: Tag) -> Self {
-        const _: () = assert!(size_of::<*mut ()>() == size_of::<u64>());
-        let value = ((tag as u64) << Tag::BIT_OFFSET) | (ptr as u64);
-        let tagged = Tagged(value as *mut ());
-        debug_assert_eq!(tagged.ptr(), ptr);
-        debug_assert_eq!(tagged.tag(), tag);
-        tagged
+        unsafe {
+            debug_assert_eq!(ptr.addr() & Tag::MASK, 0);
+            debug_assert_eq!(ptr.is_null(), tag == Tag::UnusedVar);
+            let tagged = Tagged(ptr.map_addr(|addr| addr | tag.primary()));
+            debug_assert_eq!(tagged.ptr(), ptr);
+            debug_assert_eq!(tagged.tag(), tag);
+            tagged
+        }
     }
 
     #[inline(always)]
     fn ptr(self) -> *mut () {
-        // NOTE: We're assuming the top 4 pointer bits are always 0.
-        (self.0 as u64 & !Tag::MASK) as *mut ()
+        self.0.map_addr(|addr| addr & !Tag::MASK)
     }
 
+    /// Returns the tag of `self`, reading it from the header of what `self`
+    /// points to if its primary tag is [`Tag::EXT`].
     #[inline(always)]
     unsafe fn tag(self) -> Tag {
-        let value = (self.0 as u64 >> Tag::BIT_OFFSET) as u8;
-        debug_assert!(value >= Tag::UnusedVar as u8);
-        debug_assert!(value <= Tag::DupPtr as u8);
-        std::mem::transmute(value)
+        unsafe {
+            let addr = self.0.addr();
+            match addr & Tag::MASK {
+                Tag::EXT => {
+                    debug_assert_ne!(addr, 0, "invalid tag 0");
+                    (*(self.ptr() as *const Header)).0
+                }
+                _ if addr == Tag::VarUsePtr as usize => Tag::UnusedVar,
+                primary => std::mem::transmute::<u8, Tag>(primary as u8),
+            }
+        }
     }
 
-    unsafe fn node_type(self) -> NodeType {
-        match self.tag() {
-            Tag::LamPtr => NodeType::Lam,
-            Tag::AppPtr => NodeType::App,
-            Tag::SupPtr => NodeType::Sup,
-            Tag::DupPtr => NodeType::Dup,
-            _ => panic!(),
+    /// Returns the type of the node pointed to by `self`, or `None` if `self`
+    /// is a variable.
+    unsafe fn node_type(self) -> Option<NodeType> {
+        unsafe {
+            match self.tag() {
+                Tag::LamPtr => Some(NodeType::Lam),
+                Tag::AppPtr => Some(NodeType::App),
+                Tag::SupPtr => Some(NodeType::Sup),
+                Tag::DupPtr => Some(NodeType::Dup),
+                Tag::NumPtr => Some(NodeType::Num),
+                Tag::Op2Ptr => Some(NodeType::Op2),
+                _ => None,
+            }
         }
     }
 
     #[inline(always)]
     unsafe fn new_unused_var() -> Self {
-        Tagged::new(ptr::null_mut(), Tag::UnusedVar)
+        unsafe { Tagged::new(ptr::null_mut(), Tag::UnusedVar) }
     }
 
+    /// Returns an unbound variable without a name, such as the variable of
+    /// an erased lambda that is still used.
     #[inline(always)]
     unsafe fn new_unbound_var() -> Self {
-        Tagged::new(ptr::null_mut(), Tag::UnboundVar)
+        unsafe { Tagged::new(&NAMELESS as *const FreeName as *mut (), Tag::UnboundVar) }
+    }
+
+    /// Returns an unbound variable for the free variable `name` of a term.
+    unsafe fn new_free_var(name: IStr) -> Self {
+        let free_name = FREE_NAMES.read().unwrap().get(&name).copied();
+        let free_name = free_name.unwrap_or_else(|| {
+            *FREE_NAMES.write().unwrap().entry(name).or_insert_with(|| {
+                Box::leak(Box::new(FreeName {
+                    header: Header::UNBOUND_VAR,
+                    name: Some(name),
+                }))
+            })
+        });
+        unsafe { Tagged::new(free_name as *const FreeName as *mut (), Tag::UnboundVar) }
+    }
+
+    /// Returns the name of the unbound variable `self`, if it has one.
+    unsafe fn free_name(self) -> Option<IStr> {
+        unsafe {
+            if self.tag() != Tag::UnboundVar {
+                return None;
+            }
+            (*(self.ptr() as *const FreeName)).name
+        }
     }
 
     #[inline(always)]
     unsafe fn var_use(self) -> *mut Tagged {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert_eq!(self.tag(), Tag::VarUsePtr);
-        self.ptr() as *mut Tagged
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::VarUsePtr);
+            self.ptr() as *mut Tagged
+        }
     }
 
     #[inline(always)]
     unsafe fn lam(self) -> *mut Lam {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert!(self.tag() == Tag::LamPtr || self.tag() == Tag::LamBoundVar);
-        self.ptr() as *mut Lam
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert!(self.tag() == Tag::LamPtr || self.tag() == Tag::LamBoundVar);
+            self.ptr() as *mut Lam
+        }
     }
 
     #[inline(always)]
     unsafe fn app(self) -> *mut App {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert_eq!(self.tag(), Tag::AppPtr);
-        self.ptr() as *mut App
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::AppPtr);
+            self.ptr() as *mut App
+        }
     }
 
     #[inline(always)]
     unsafe fn sup(self) -> *mut Sup {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert_eq!(self.tag(), Tag::SupPtr);
-        self.ptr() as *mut Sup
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::SupPtr);
+            self.ptr() as *mut Sup
+        }
     }
 
     #[inline(always)]
     unsafe fn dup(self) -> *mut Dup {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert!(
-            self.tag() == Tag::DupPtr
-                || self.tag() == Tag::DupABoundVar
-                || self.tag() == Tag::DupBBoundVar
-        );
-        self.ptr() as *mut Dup
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert!(
+                self.tag() == Tag::DupPtr
+                    || self.tag() == Tag::DupABoundVar
+                    || self.tag() == Tag::DupBBoundVar
+            );
+            self.ptr() as *mut Dup
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn num(self) -> *mut Num {
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::NumPtr);
+            self.ptr() as *mut Num
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn op2(self) -> *mut Op2 {
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::Op2Ptr);
+            self.ptr() as *mut Op2
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn def<'a>(self) -> &'a Def {
+        unsafe {
+            debug_assert_eq!(self.tag(), Tag::Ref);
+            &*(self.ptr() as *const Def)
+        }
     }
 
     #[inline(always)]
     unsafe fn var_use_read(self) -> Tagged {
-        self.var_use().read()
+        unsafe { self.var_use().read() }
     }
 
     #[inline(always)]
     unsafe fn lam_read(self) -> Lam {
-        self.lam().read()
+        unsafe { self.lam().read() }
     }
 
     #[inline(always)]
     unsafe fn app_read(self) -> App {
-        self.app().read()
+        unsafe { self.app().read() }
     }
 
     #[inline(always)]
     unsafe fn sup_read(self) -> Sup {
-        self.sup().read()
+        unsafe { self.sup().read() }
     }
 
     #[inline(always)]
     unsafe fn dup_read(self) -> Dup {
-        self.dup().read()
+        unsafe { self.dup().read() }
     }
 
     #[inline(always)]
     unsafe fn lam_e_var_use_ptr(self) -> Tagged {
-        if self.tag() == Tag::UnboundVar {
-            debug_assert_eq!(self.ptr(), ptr::null_mut());
-            Tagged::new_unused_var()
-        } else {
-            Tagged::new(self.lam().e() as *mut (), Tag::VarUsePtr)
+        unsafe {
+            if self.tag() == Tag::UnboundVar {
+                debug_assert_eq!(self.free_name(), None);
+                Tagged::new_unused_var()
+            } else {
+                Tagged::new(self.lam().e() as *mut (), Tag::VarUsePtr)
+            }
         }
     }
 
     #[inline(always)]
     unsafe fn app_e1_var_use_ptr(self) -> Tagged {
-        Tagged::new(self.app().e1() as *mut (), Tag::VarUsePtr)
+        unsafe { Tagged::new(self.app().e1() as *mut (), Tag::VarUsePtr) }
     }
 
     #[inline(always)]
     unsafe fn app_e2_var_use_ptr(self) -> Tagged {
-        Tagged::new(self.app().e2() as *mut (), Tag::VarUsePtr)
+        unsafe { Tagged::new(self.app().e2() as *mut (), Tag::VarUsePtr) }
     }
 
     #[inline(always)]
     unsafe fn sup_e1_var_use_ptr(self) -> Tagged {
-        if self.tag() == Tag::UnboundVar {
-            debug_assert_eq!(self.ptr(), ptr::null_mut());
-            Tagged::new_unused_var()
-        } else {
-            Tagged::new(self.sup().e1() as *mut (), Tag::VarUsePtr)
+        unsafe {
+            if self.tag() == Tag::UnboundVar {
+                debug_assert_eq!(self.free_name(), None);
+                Tagged::new_unused_var()
+            } else {
+                Tagged::new(self.sup().e1() as *mut (), Tag::VarUsePtr)
+            }
         }
     }
 
     #[inline(always)]
     unsafe fn sup_e2_var_use_ptr(self) -> Tagged {
-        if self.tag() == Tag::UnboundVar {
-            debug_assert_eq!(self.ptr(), ptr::null_mut());
-            Tagged::new_unused_var()
-        } else {
-            Tagged::new(self.sup().e2() as *mut (), Tag::VarUsePtr)
+        unsafe {
+            if self.tag() == Tag::UnboundVar {
+                debug_assert_eq!(self.free_name(), None);
+                Tagged::new_unused_var()
+            } else {
+                Tagged::new(self.sup().e2() as *mut (), Tag::VarUsePtr)
+            }
         }
     }
 
+    #[inline(always)]
+    unsafe fn op2_e1_var_use_ptr(self) -> Tagged {
+        unsafe { Tagged::new(addr_of_mut!((*self.op2()).e1) as *mut (), Tag::VarUsePtr) }
+    }
+
+    #[inline(always)]
+    unsafe fn op2_e2_var_use_ptr(self) -> Tagged {
+        unsafe { Tagged::new(addr_of_mut!((*self.op2()).e2) as *mut (), Tag::VarUsePtr) }
+    }
+
     #[inline(always)]
     unsafe fn dup_e_var_use_ptr(self) -> Tagged {
-        Tagged::new(self.dup().e() as *mut (), Tag::VarUsePtr)
+        unsafe { Tagged::new(self.dup().e() as *mut (), Tag::VarUsePtr) }
     }
 
     #[inline(always)]
     unsafe fn if_bound_var_move_to(self, var_use_ptr: Tagged) {
-        match self.tag() {
-            Tag::LamBoundVar => self.lam().x().write(var_use_ptr),
-            Tag::DupABoundVar => self.dup().a().write(var_use_ptr),
-            Tag::DupBBoundVar => self.dup().b().write(var_use_ptr),
-            _ => {}
+        unsafe {
+            match self.tag() {
+                Tag::LamBoundVar => self.lam().x().write(var_use_ptr),
+                Tag::DupABoundVar => self.dup().a().write(var_use_ptr),
+                Tag::DupBBoundVar => self.dup().b().write(var_use_ptr),
+                _ => {}
+            }
         }
     }
 
     #[inline(always)]
     unsafe fn lam_bound_var(self) -> Tagged {
-        debug_assert!(self.tag() == Tag::LamPtr || self.tag() == Tag::LamBoundVar);
-        Tagged::new(self.ptr(), Tag::LamBoundVar)
+        unsafe {
+            debug_assert!(self.tag() == Tag::LamPtr || self.tag() == Tag::LamBoundVar);
+            Tagged::new(self.ptr(), Tag::LamBoundVar)
+        }
     }
 
     #[inline(always)]
     unsafe fn dup_a_bound_var(self) -> Tagged {
-        debug_assert!(
-            self.tag() == Tag::DupPtr
-                || self.tag() == Tag::DupABoundVar
-                || self.tag() == Tag::DupBBoundVar
-        );
-        Tagged::new(self.ptr(), Tag::DupABoundVar)
+        unsafe {
+            debug_assert!(
+                self.tag() == Tag::DupPtr
+                    || self.tag() == Tag::DupABoundVar
+                    || self.tag() == Tag::DupBBoundVar
+            );
+            Tagged::new(self.ptr(), Tag::DupABoundVar)
+        }
     }
 
     #[inline(always)]
     unsafe fn dup_b_bound_var(self) -> Tagged {
-        debug_assert!(
-            self.tag() == Tag::DupPtr
-                || self.tag() == Tag::DupABoundVar
-                || self.tag() == Tag::DupBBoundVar
-        );
-        Tagged::new(self.ptr(), Tag::DupBBoundVar)
+        unsafe {
+            debug_assert!(
+                self.tag() == Tag::DupPtr
+                    || self.tag() == Tag::DupABoundVar
+                    || self.tag() == Tag::DupBBoundVar
+            );
+            Tagged::new(self.ptr(), Tag::DupBBoundVar)
+        }
     }
 
-    unsafe fn garbage_collect(self) {
-        let mut queue = VecDeque::new();
-        queue.push_back(self);
-        while let Some(ptr) = queue.pop_front() {
-            match ptr.tag() {
-                Tag::UnboundVar => {}
-                Tag::LamBoundVar => ptr.lam().x().write(Tagged::new_unused_var()),
+    /// Connects the term pointed to by `self`, which is no longer used, to an
+    /// eraser.
+    ///
+    /// A variable meets the eraser at once: the binder of a bound variable
+    /// becomes unused, and a dup whose variables are now both unused is
+    /// erased itself. A node is queued in the heap, to be erased by
+    /// [`apply_erasures`] with the erase rule for its type.
+    unsafe fn erase(self, heap: &mut Heap) {
+        unsafe {
+            match self.tag() {
+                Tag::UnboundVar | Tag::Ref => {}
+                Tag::LamBoundVar => self.lam().x().write(Tagged::new_unused_var()),
                 Tag::DupABoundVar => {
-                    if ptr.dup().b().read().tag() == Tag::UnusedVar {
-                        queue.push_back(ptr.dup().e().read());
-                        ptr.dealloc_dup();
-                    } else {
-                        ptr.dup().a().write(Tagged::new_unused_var());
+                    self.dup().a().write(Tagged::new_unused_var());
+                    if self.dup().b().read().tag() == Tag::UnusedVar {
+                        heap.erasures.push(Tagged::new(self.ptr(), Tag::DupPtr));
                     }
                 }
                 Tag::DupBBoundVar => {
-                    if ptr.dup().a().read().tag() == Tag::UnusedVar {
-                        queue.push_back(ptr.dup().e().read());
-                        ptr.dealloc_dup();
-                    } else {
-                        ptr.dup().b().write(Tagged::new_unused_var());
+                    self.dup().b().write(Tagged::new_unused_var());
+                    if self.dup().a().read().tag() == Tag::UnusedVar {
+                        heap.erasures.push(Tagged::new(self.ptr(), Tag::DupPtr));
                     }
                 }
-                Tag::LamPtr => {
-                    queue.push_back(ptr.lam().e().read());
-                    ptr.dealloc_lam();
-                }
-                Tag::AppPtr => {
-                    queue.push_back(ptr.app().e1().read());
-                    queue.push_back(ptr.app().e2().read());
-                    ptr.dealloc_app();
-                }
-                Tag::SupPtr => {
-                    queue.push_back(ptr.sup().e1().read());
-                    queue.push_back(ptr.sup().e2().read());
-                    ptr.dealloc_sup();
+                Tag::LamPtr | Tag::AppPtr | Tag::SupPtr | Tag::NumPtr | Tag::Op2Ptr => {
+                    heap.erasures.push(self)
                 }
-                _ => unreachable!("{:?}", ptr.tag()),
+                _ => unreachable!("{:?}", self.tag()),
+            }
+        }
+    }
+
+    /// Returns the slots holding the children of the node pointed to by `self`
+    /// (none if `self` is a variable).
+    unsafe fn child_slots(self) -> Vec<*mut Tagged> {
+        unsafe {
+            match self.node_type() {
+                Some(NodeType::Lam) => vec![self.lam().e()],
+                Some(NodeType::App) => vec![self.app().e1(), self.app().e2()],
+                Some(NodeType::Sup) => vec![self.sup().e1(), self.sup().e2()],
+                Some(NodeType::Dup) => vec![self.dup().e()],
+                Some(NodeType::Num) => vec![],
+                Some(NodeType::Op2) => vec![self.op2().e1(), self.op2().e2()],
+                None => vec![],
             }
         }
     }
 
     #[inline(always)]
-    unsafe fn dealloc_lam(self) {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert!(self.tag() == Tag::LamPtr || self.tag() == Tag::LamBoundVar);
-        let ptr = self.ptr() as *mut u8;
+    unsafe fn dealloc_lam(self, heap: &mut Heap) {
         unsafe {
-            std::alloc::dealloc(ptr, std::alloc::Layout::new::<Lam>());
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert!(self.tag() == Tag::LamPtr || self.tag() == Tag::LamBoundVar);
+            heap.release(Tagged::new(self.ptr(), Tag::LamPtr));
+            heap.free_list(Tag::LamPtr).push(self.ptr());
         }
     }
 
     #[inline(always)]
-    unsafe fn dealloc_app(self) {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert_eq!(self.tag(), Tag::AppPtr);
-        let ptr = self.ptr() as *mut u8;
+    unsafe fn dealloc_app(self, heap: &mut Heap) {
         unsafe {
-            std::alloc::dealloc(ptr, std::alloc::Layout::new::<App>());
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::AppPtr);
+            heap.release(Tagged::new(self.ptr(), Tag::AppPtr));
+            heap.free_list(Tag::AppPtr).push(self.ptr());
         }
     }
 
     #[inline(always)]
-    unsafe fn dealloc_sup(self) {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert_eq!(self.tag(), Tag::SupPtr);
-        let ptr = self.ptr() as *mut u8;
+    unsafe fn dealloc_sup(self, heap: &mut Heap) {
         unsafe {
-            std::alloc::dealloc(ptr, std::alloc::Layout::new::<Sup>());
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::SupPtr);
+            heap.release(Tagged::new(self.ptr(), Tag::SupPtr));
+            heap.free_list(Tag::SupPtr).push(self.ptr());
         }
     }
 
     #[inline(always)]
-    unsafe fn dealloc_dup(self) {
-        debug_assert_ne!(self.ptr(), ptr::null_mut());
-        debug_assert!(
-            self.tag() == Tag::DupPtr
-                || self.tag() == Tag::DupABoundVar
-                || self.tag() == Tag::DupBBoundVar
-        );
-        let ptr = self.ptr() as *mut u8;
+    unsafe fn dealloc_dup(self, heap: &mut Heap) {
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert!(
+                self.tag() == Tag::DupPtr
+                    || self.tag() == Tag::DupABoundVar
+                    || self.tag() == Tag::DupBBoundVar
+            );
+            heap.release(Tagged::new(self.ptr(), Tag::DupPtr));
+            heap.free_list(Tag::DupPtr).push(self.ptr());
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn dealloc_num(self, heap: &mut Heap) {
+        unsafe {
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::NumPtr);
+            heap.release(Tagged::new(self.ptr(), Tag::NumPtr));
+            heap.free_list(Tag::NumPtr).push(self.ptr());
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn dealloc_op2(self, heap: &mut Heap) {
         unsafe {
-            std::alloc::dealloc(ptr, std::alloc::Layout::new::<Dup>());
+            debug_assert_ne!(self.ptr(), ptr::null_mut());
+            debug_assert_eq!(self.tag(), Tag::Op2Ptr);
+            heap.release(Tagged::new(self.ptr(), Tag::Op2Ptr));
+            heap.free_list(Tag::Op2Ptr).push(self.ptr());
         }
     }
 
     #[inline(always)]
-    unsafe fn dealloc_any_node(self) {
-        match self.tag() {
-            Tag::LamBoundVar | Tag::LamPtr => self.dealloc_lam(),
-            Tag::AppPtr => self.dealloc_app(),
-            Tag::SupPtr => self.dealloc_sup(),
-            Tag::DupABoundVar | Tag::DupBBoundVar | Tag::DupPtr => self.dealloc_dup(),
-            _ => panic!("dealloc_any_node called on non-node pointer"),
+    unsafe fn dealloc_any_node(self, heap: &mut Heap) {
+        unsafe {
+            match self.tag() {
+                Tag::LamBoundVar | Tag::LamPtr => self.dealloc_lam(heap),
+                Tag::AppPtr => self.dealloc_app(heap),
+                Tag::SupPtr => self.dealloc_sup(heap),
+                Tag::DupABoundVar | Tag::DupBBoundVar | Tag::DupPtr => self.dealloc_dup(heap),
+                Tag::NumPtr => self.dealloc_num(heap),
+                Tag::Op2Ptr => self.dealloc_op2(heap),
+                _ => panic!("dealloc_any_node called on non-node pointer"),
+            }
         }
     }
 }
 
-unsafe fn naive_random_order_reduce(root_ptr_ptr: *mut Tagged) {
-    loop {
-        let redexes = collect_redexes(root_ptr_ptr);
+unsafe fn naive_random_order_reduce(
+    heap: &mut Heap,
+    roots: &[*mut Tagged],
+    config: &StrategyConfig,
+    rng: &mut impl RandomSource,
+) {
+    unsafe { while naive_random_order_reduce_step(heap, roots, config, rng).is_some() {} }
+}
+
+unsafe fn naive_random_order_reduce_step(
+    heap: &mut Heap,
+    roots: &[*mut Tagged],
+    config: &StrategyConfig,
+    rng: &mut impl RandomSource,
+) -> Option<Rule> {
+    unsafe {
+        #[cfg(feature = "profiling")]
+        let start = Instant::now();
+        let redexes = config.prioritize(collect_redexes(roots));
+        #[cfg(feature = "profiling")]
+        heap.latency.redex_search.record(start.elapsed());
         if redexes.is_empty() {
-            return;
+            return None;
         }
         // select a random redex
-        let redex = redexes.choose(&mut rand::thread_rng()).copied().unwrap();
-        reduce_redex(redex);
+        let redex = redexes[rng.next_index(redexes.len())];
+        reduce_redex(heap, redex);
+        Some(redex.into())
     }
 }
 
-unsafe fn naive_reduce_step(root_ptr_ptr: *mut Tagged) -> Option<Rule> {
-    let redexes = collect_redexes(root_ptr_ptr);
-    if redexes.is_empty() {
-        return None;
+unsafe fn naive_reduce_step(
+    heap: &mut Heap,
+    roots: &[*mut Tagged],
+    config: &StrategyConfig,
+) -> Option<Rule> {
+    unsafe {
+        #[cfg(feature = "profiling")]
+        let start = Instant::now();
+        let redex = next_redex(roots, config);
+        #[cfg(feature = "profiling")]
+        heap.latency.redex_search.record(start.elapsed());
+        let redex = redex?;
+        reduce_redex(heap, redex);
+        Some(redex.into())
     }
-    let redex = redexes.first().copied().unwrap();
-    reduce_redex(redex);
-    Some(redex.into())
+}
+
+/// Returns the first of the redexes with the highest priority in `config`.
+unsafe fn next_redex(roots: &[*mut Tagged], config: &StrategyConfig) -> Option<Redex> {
+    unsafe { config.prioritize(collect_redexes(roots)).first().copied() }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -524,6 +1045,15 @@ pub enum Rule {
     AppSup,
     DupLam,
     DupSup,
+    Ref,
+    OpNum,
+    OpSup,
+    DupNum,
+    /// An eraser meeting a node that a rewrite discarded. Erasures are
+    /// applied along with the rewrite that triggers them, so this is never
+    /// the rule of a step, but it is what [`RuleKind::EraLam`] and the other
+    /// erase rules count as.
+    Era,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -546,6 +1076,28 @@ enum Redex {
         dup_ptr: Tagged,
         sup_ptr: Tagged,
     },
+    /// A reference in a root, the function of an application, the
+    /// expression of a dup, or an operand of an operation, stored in
+    /// `ptr_ptr`.
+    Ref {
+        ptr_ptr: *mut Tagged,
+    },
+    /// An operation on two numbers.
+    OpNum {
+        ptr_ptr: *mut Tagged,
+        op2_ptr: Tagged,
+    },
+    /// An operation whose first operand is a superposition, or whose first
+    /// operand is a number and second a superposition.
+    OpSup {
+        ptr_ptr: *mut Tagged,
+        op2_ptr: Tagged,
+        sup_ptr: Tagged,
+    },
+    DupNum {
+        dup_ptr: Tagged,
+        num_ptr: Tagged,
+    },
 }
 
 impl From<Redex> for Rule {
@@ -555,354 +1107,850 @@ impl From<Redex> for Rule {
             Redex::AppSup { .. } => Rule::AppSup,
             Redex::DupLam { .. } => Rule::DupLam,
             Redex::DupSup { .. } => Rule::DupSup,
+            Redex::Ref { .. } => Rule::Ref,
+            Redex::OpNum { .. } => Rule::OpNum,
+            Redex::OpSup { .. } => Rule::OpSup,
+            Redex::DupNum { .. } => Rule::DupNum,
         }
     }
 }
 
-unsafe fn collect_redexes(root_ptr_ptr: *mut Tagged) -> Vec<Redex> {
-    let mut visited = HashSet::new();
-    let mut redexes = Vec::new();
-    let mut stack = vec![root_ptr_ptr];
-    while let Some(ptr_ptr) = stack.pop() {
-        let ptr = ptr_ptr.read();
-        if visited.contains(&ptr.ptr()) {
-            continue;
-        }
-        visited.insert(ptr.ptr());
-        match ptr.tag() {
-            Tag::UnusedVar | Tag::VarUsePtr | Tag::UnboundVar | Tag::LamBoundVar => {}
-            Tag::LamPtr => {
-                stack.push(ptr.lam().e());
-            }
-            Tag::AppPtr => {
-                let e1 = ptr.app().e1().read();
-                match e1.tag() {
-                    Tag::LamPtr => redexes.push(Redex::AppLam {
-                        ptr_ptr,
-                        app_ptr: ptr,
-                        lam_ptr: e1,
-                    }),
-                    Tag::SupPtr => redexes.push(Redex::AppSup {
-                        ptr_ptr,
-                        app_ptr: ptr,
-                        sup_ptr: e1,
-                    }),
-                    _ => {}
+/// Returns the redexes reachable from `roots`, in the order documented on
+/// [`Strategy::Deterministic`].
+unsafe fn collect_redexes(roots: &[*mut Tagged]) -> Vec<Redex> {
+    unsafe {
+        collect_redex_sites(roots)
+            .into_iter()
+            .map(|(redex, _)| redex)
+            .collect()
+    }
+}
+
+/// Like [`collect_redexes`], but also describes where each redex is.
+///
+/// NOTE: The order only depends on the structure of the graph: `visited` is
+///       only used for membership tests, never iterated.
+unsafe fn collect_redex_sites(roots: &[*mut Tagged]) -> Vec<(Redex, RedexSite)> {
+    unsafe {
+        let mut visited = HashSet::new();
+        let mut redexes = Vec::new();
+        // Each slot is paired with its depth and the number of lambdas above it.
+        let mut stack = roots
+            .iter()
+            .rev()
+            .map(|root| (*root, 0, 0))
+            .collect::<Vec<_>>();
+        while let Some((ptr_ptr, depth, binders)) = stack.pop() {
+            let ptr = ptr_ptr.read();
+            let site = |kind, dup_label, sup_label| RedexSite {
+                kind,
+                depth,
+                binders,
+                dup_label,
+                sup_label,
+            };
+            // NOTE: References to the same definition share a pointer, so they
+            //       are never marked as visited.
+            if ptr.tag() == Tag::Ref {
+                if depth == 0 {
+                    redexes.push((Redex::Ref { ptr_ptr }, site(RuleKind::Ref, None, None)));
                 }
-                stack.push(ptr.app().e1());
-                stack.push(ptr.app().e2());
+                continue;
             }
-            Tag::SupPtr => {
-                stack.push(ptr.sup().e1());
-                stack.push(ptr.sup().e2());
+            // NOTE: A lambda's variable shares the lambda's pointer, and may be
+            //       reached before it after escaping its scope, so it is never
+            //       marked as visited either.
+            if ptr.tag() == Tag::LamBoundVar {
+                continue;
             }
-            Tag::DupABoundVar | Tag::DupBBoundVar | Tag::DupPtr => {
-                let e = ptr.dup().e().read();
-                match e.tag() {
-                    Tag::LamPtr => redexes.push(Redex::DupLam {
-                        dup_ptr: ptr,
-                        lam_ptr: e,
-                    }),
-                    Tag::SupPtr => redexes.push(Redex::DupSup {
-                        dup_ptr: ptr,
-                        sup_ptr: e,
-                    }),
-                    _ => {}
+            if visited.contains(&ptr.ptr()) {
+                continue;
+            }
+            visited.insert(ptr.ptr());
+            match ptr.tag() {
+                Tag::UnusedVar | Tag::VarUsePtr | Tag::UnboundVar | Tag::LamBoundVar | Tag::Ref => {
+                }
+                Tag::LamPtr => {
+                    stack.push((ptr.lam().e(), depth + 1, binders + 1));
+                }
+                Tag::AppPtr => {
+                    let e1 = ptr.app().e1().read();
+                    match e1.tag() {
+                        Tag::LamPtr => redexes.push((
+                            Redex::AppLam {
+                                ptr_ptr,
+                                app_ptr: ptr,
+                                lam_ptr: e1,
+                            },
+                            site(RuleKind::AppLam, None, None),
+                        )),
+                        Tag::SupPtr => redexes.push((
+                            Redex::AppSup {
+                                ptr_ptr,
+                                app_ptr: ptr,
+                                sup_ptr: e1,
+                            },
+                            site(RuleKind::AppSup, None, Some(e1.sup().l().read().into())),
+                        )),
+                        Tag::Ref => redexes.push((
+                            Redex::Ref {
+                                ptr_ptr: ptr.app().e1(),
+                            },
+                            site(RuleKind::Ref, None, None),
+                        )),
+                        _ => {}
+                    }
+                    stack.push((ptr.app().e1(), depth + 1, binders));
+                    stack.push((ptr.app().e2(), depth + 1, binders));
+                }
+                Tag::SupPtr => {
+                    stack.push((ptr.sup().e1(), depth + 1, binders));
+                    stack.push((ptr.sup().e2(), depth + 1, binders));
+                }
+                Tag::NumPtr => {}
+                Tag::Op2Ptr => {
+                    if let Some(redex) = op2_redex(ptr_ptr, ptr) {
+                        let sup_label = match redex {
+                            Redex::OpSup { sup_ptr, .. } => Some(sup_ptr.sup().l().read().into()),
+                            _ => None,
+                        };
+                        redexes.push((redex, site(redex.kind(), None, sup_label)));
+                    }
+                    stack.push((ptr.op2().e1(), depth + 1, binders));
+                    stack.push((ptr.op2().e2(), depth + 1, binders));
+                }
+                Tag::DupABoundVar | Tag::DupBBoundVar | Tag::DupPtr => {
+                    let e = ptr.dup().e().read();
+                    let label = Label::from(ptr.dup().l().read());
+                    match e.tag() {
+                        Tag::LamPtr => redexes.push((
+                            Redex::DupLam {
+                                dup_ptr: ptr,
+                                lam_ptr: e,
+                            },
+                            site(RuleKind::DupLam, Some(label), None),
+                        )),
+                        Tag::SupPtr => {
+                            let redex = Redex::DupSup {
+                                dup_ptr: ptr,
+                                sup_ptr: e,
+                            };
+                            let sup_label = Some(e.sup().l().read().into());
+                            redexes.push((redex, site(redex.kind(), Some(label), sup_label)));
+                        }
+                        Tag::NumPtr => redexes.push((
+                            Redex::DupNum {
+                                dup_ptr: ptr,
+                                num_ptr: e,
+                            },
+                            site(RuleKind::DupNum, Some(label), None),
+                        )),
+                        Tag::Ref => redexes.push((
+                            Redex::Ref {
+                                ptr_ptr: ptr.dup().e(),
+                            },
+                            site(RuleKind::Ref, Some(label), None),
+                        )),
+                        _ => {}
+                    }
+                    stack.push((ptr.dup().e(), depth + 1, binders));
                 }
-                stack.push(ptr.dup().e());
             }
         }
+        redexes
     }
-    redexes
-}
-
-unsafe fn reduce_redex(redex: Redex) {
-    match redex {
-        Redex::AppLam {
-            ptr_ptr,
-            app_ptr,
-            lam_ptr,
-        } => rule_app_lam(ptr_ptr, app_ptr, lam_ptr),
-        Redex::AppSup {
-            ptr_ptr,
-            app_ptr,
-            sup_ptr,
-        } => rule_app_sup(ptr_ptr, app_ptr, sup_ptr),
-        Redex::DupLam { dup_ptr, lam_ptr } => rule_dup_lam(dup_ptr, lam_ptr),
-        Redex::DupSup { dup_ptr, sup_ptr } => rule_dup_sup(dup_ptr, sup_ptr),
-    }
-}
-
-unsafe fn rule_app_lam(ptr_ptr: *mut Tagged, app_ptr: Tagged, lam_ptr: Tagged) {
-    // (λx e) e2
-    // ---------- AppLam
-    // x <- e2
-    // e
-
-    // x <- e2
-    let x_use_ptr = lam_ptr.lam().x().read();
-    let e2 = app_ptr.app().e2().read();
-    if x_use_ptr.tag() == Tag::UnusedVar {
-        e2.garbage_collect();
-    } else {
-        debug_assert_eq!(x_use_ptr.var_use_read(), lam_ptr.lam_bound_var());
-        x_use_ptr.var_use().write(e2);
-        e2.if_bound_var_move_to(x_use_ptr);
-    }
-
-    // e
-    let e_ptr = lam_ptr.lam().e().read();
-    ptr_ptr.write(e_ptr);
-    e_ptr.if_bound_var_move_to(Tagged::new(ptr_ptr as *mut _, Tag::VarUsePtr));
-
-    // deallocate unreachable nodes
-    app_ptr.dealloc_app();
-    lam_ptr.dealloc_lam();
-}
-
-unsafe fn rule_app_sup(ptr_ptr: *mut Tagged, app_ptr: Tagged, sup_ptr: Tagged) {
-    // #l{e1 e2} e3
-    // ----------------- AppSup
-    // dup #l{a b} = e3
-    // #l{(e1 a) (e2 b)}
-
-    let app_sup_e3_ptr = app_ptr;
-    let sup_e1_e2_ptr = sup_ptr;
-
-    let l = sup_e1_e2_ptr.sup().l().read();
-
-    let dup_a_b_ptr = Dup::alloc();
-    let app_e1_a_ptr = App::alloc();
-    let app_e2_b_ptr = App::alloc();
-    let sup_app_app_ptr = Sup::alloc();
-
-    // dup #l{a b} = e3
-    let a = app_e1_a_ptr.app_e2_var_use_ptr();
-    let b = app_e2_b_ptr.app_e2_var_use_ptr();
-    let e3 = app_sup_e3_ptr.app().e2().read();
-    dup_a_b_ptr.dup().write(Dup { l, a, b, e: e3 });
-    e3.if_bound_var_move_to(dup_a_b_ptr.dup_e_var_use_ptr());
-
-    // (e1 a)
-    let e1 = sup_e1_e2_ptr.sup().e1().read();
-    let a = dup_a_b_ptr.dup_a_bound_var();
-    app_e1_a_ptr.app().write(App { e1, e2: a });
-    e1.if_bound_var_move_to(app_e1_a_ptr.app_e1_var_use_ptr());
-
-    // (e2 b)
-    let e2 = sup_e1_e2_ptr.sup().e2().read();
-    let b = dup_a_b_ptr.dup_b_bound_var();
-    app_e2_b_ptr.app().write(App { e1: e2, e2: b });
-    e2.if_bound_var_move_to(app_e2_b_ptr.app_e1_var_use_ptr());
-
-    // #l{(e1 a) (e2 b)}
-    sup_app_app_ptr.sup().write(Sup {
-        l,
-        e1: app_e1_a_ptr,
-        e2: app_e2_b_ptr,
-    });
-    ptr_ptr.write(sup_app_app_ptr);
-
-    // deallocate unreachable nodes
-    app_sup_e3_ptr.dealloc_app();
-    sup_e1_e2_ptr.dealloc_sup();
-}
-
-unsafe fn rule_dup_lam(dup_ptr: Tagged, lam_ptr: Tagged) {
-    // dup #l{a b} = (λx e)
-    // -------------------- DupLam
-    // a <- (λx1 c)
-    // b <- (λx2 d)
-    // x <- #l{x1 x2}
-    // dup #l{c d} = e
-
-    let dup_a_b_ptr = dup_ptr;
-    let lam_x_e_ptr = lam_ptr;
-
-    let l = dup_a_b_ptr.dup().l().read();
-    let dup_a_b_a = dup_a_b_ptr.dup().a().read();
-    let dup_a_b_b = dup_a_b_ptr.dup().b().read();
-    let lam_x_e_x = lam_x_e_ptr.lam().x().read();
-
-    let lam_x1_c_ptr = if dup_a_b_a.tag() == Tag::UnusedVar {
-        Tagged::new_unbound_var()
-    } else {
-        Lam::alloc()
-    };
-    let lam_x2_d_ptr = if dup_a_b_b.tag() == Tag::UnusedVar {
-        Tagged::new_unbound_var()
-    } else {
-        Lam::alloc()
-    };
-    let sup_x1_x2_ptr = if lam_x_e_x.tag() == Tag::UnusedVar {
-        Tagged::new_unbound_var()
-    } else {
-        Sup::alloc()
-    };
-    debug_assert!(dup_a_b_a.tag() != Tag::UnusedVar || dup_a_b_b.tag() != Tag::UnusedVar);
-    let dup_c_d_ptr = Dup::alloc();
-
-    // a <- (λx1 c)
-    if dup_a_b_a.tag() != Tag::UnusedVar {
-        debug_assert_eq!(dup_a_b_a.var_use_read(), dup_a_b_ptr.dup_a_bound_var());
-        dup_a_b_a.var_use().write(lam_x1_c_ptr);
-        let x1 = sup_x1_x2_ptr.sup_e1_var_use_ptr();
-        let c = dup_c_d_ptr.dup_a_bound_var();
-        lam_x1_c_ptr.lam().write(Lam { x: x1, e: c });
-    }
-
-    // b <- (λx2 d)
-    if dup_a_b_b.tag() != Tag::UnusedVar {
-        debug_assert_eq!(dup_a_b_b.var_use_read(), dup_a_b_ptr.dup_b_bound_var());
-        dup_a_b_b.var_use().write(lam_x2_d_ptr);
-        let x2 = sup_x1_x2_ptr.sup_e2_var_use_ptr();
-        let d = dup_c_d_ptr.dup_b_bound_var();
-        lam_x2_d_ptr.lam().write(Lam { x: x2, e: d });
-    }
-
-    // x <- #l{x1,x2}
-    if lam_x_e_x.tag() != Tag::UnusedVar {
-        // TODO: do these actually need to be bound?
-        debug_assert_ne!(lam_x1_c_ptr.tag(), Tag::UnboundVar);
-        debug_assert_ne!(lam_x2_d_ptr.tag(), Tag::UnboundVar);
-
-        debug_assert_eq!(lam_x_e_x.var_use_read(), lam_x_e_ptr.lam_bound_var());
-        lam_x_e_x.var_use().write(sup_x1_x2_ptr);
-        let x1 = lam_x1_c_ptr.lam_bound_var();
-        let x2 = lam_x2_d_ptr.lam_bound_var();
-        sup_x1_x2_ptr.sup().write(Sup { l, e1: x1, e2: x2 });
-    }
-
-    // dup #l{c d} = e
-    let e = lam_x_e_ptr.lam().e().read();
-    let c = lam_x1_c_ptr.lam_e_var_use_ptr();
-    let d = lam_x2_d_ptr.lam_e_var_use_ptr();
-    dup_c_d_ptr.dup().write(Dup { l, a: c, b: d, e });
-    e.if_bound_var_move_to(dup_c_d_ptr.dup_e_var_use_ptr());
-
-    // deallocate unreachable nodes
-    dup_a_b_ptr.dealloc_dup();
-    lam_x_e_ptr.dealloc_lam();
-}
-
-unsafe fn rule_dup_sup(dup_ptr: Tagged, sup_ptr: Tagged) {
-    let dup_a_b_ptr = dup_ptr;
-    let sup_e1_e2_ptr = sup_ptr;
-
-    let l = dup_a_b_ptr.dup().l().read();
-    let m = sup_e1_e2_ptr.sup().l().read();
-    let dup_a_b_a = dup_a_b_ptr.dup().a().read();
-    let dup_a_b_b = dup_a_b_ptr.dup().b().read();
-    debug_assert!(dup_a_b_a.tag() != Tag::UnusedVar || dup_a_b_b.tag() != Tag::UnusedVar);
-
-    if l == m {
-        // dup #l{a b} = #l{e1 e2}
-        // ----------------------- DupSupSame
-        // a <- e1
-        // b <- e2
-
-        // a <- e1
-        let e1 = sup_e1_e2_ptr.sup().e1().read();
-        if dup_a_b_a.tag() == Tag::UnusedVar {
-            e1.garbage_collect();
-        } else {
-            debug_assert_eq!(dup_a_b_a.var_use_read(), dup_a_b_ptr.dup_a_bound_var());
-            dup_a_b_a.var_use().write(e1);
-            e1.if_bound_var_move_to(dup_a_b_a);
-        }
-
-        // b <- e2
-        let e2 = sup_e1_e2_ptr.sup().e2().read();
-        if dup_a_b_b.tag() == Tag::UnusedVar {
-            e2.garbage_collect();
-        } else {
-            debug_assert_eq!(dup_a_b_b.var_use_read(), dup_a_b_ptr.dup_b_bound_var());
-            dup_a_b_b.var_use().write(e2);
-            e2.if_bound_var_move_to(dup_a_b_b);
-        }
-    } else {
-        // dup #l{a b} = #m{e1 e2}
-        // ----------------------- DupSupDiff
-        // a <- #m{a1 a2}
-        // b <- #m{b1 b2}
-        // dup #l{a1 b1} = e1
-        // dup #l{a2 b2} = e2
-
-        let sup_a1_a2_ptr = if dup_a_b_a.tag() == Tag::UnusedVar {
-            Tagged::new_unbound_var()
-        } else {
-            Sup::alloc()
-        };
-        let sup_b1_b2_ptr = if dup_a_b_b.tag() == Tag::UnusedVar {
-            Tagged::new_unbound_var()
-        } else {
-            Sup::alloc()
-        };
-        let dup_a1_b1_ptr = Dup::alloc();
-        let dup_a2_b2_ptr = Dup::alloc();
-
-        // a <- #m{a1 a2}
-        if dup_a_b_a.tag() != Tag::UnusedVar {
-            debug_assert_eq!(dup_a_b_a.var_use_read(), dup_a_b_ptr.dup_a_bound_var());
-            dup_a_b_a.var_use().write(sup_a1_a2_ptr);
-            let a1 = dup_a1_b1_ptr.dup_a_bound_var();
-            let a2 = dup_a2_b2_ptr.dup_a_bound_var();
-            sup_a1_a2_ptr.sup().write(Sup {
-                l: m,
-                e1: a1,
-                e2: a2,
-            });
-        }
+}
 
-        // b <- #m{b1 b2}
-        if dup_a_b_b.tag() != Tag::UnusedVar {
-            debug_assert_eq!(dup_a_b_b.var_use_read(), dup_a_b_ptr.dup_b_bound_var());
-            dup_a_b_b.var_use().write(sup_b1_b2_ptr);
-            let b1 = dup_a1_b1_ptr.dup_b_bound_var();
-            let b2 = dup_a2_b2_ptr.dup_b_bound_var();
-            sup_b1_b2_ptr.sup().write(Sup {
-                l: m,
-                e1: b1,
-                e2: b2,
-            });
+/// Returns the redex of the operation `op2_ptr`, stored in `ptr_ptr`, if it
+/// has one: operands are needed from left to right, so the second is only
+/// looked at once the first is a number.
+unsafe fn op2_redex(ptr_ptr: *mut Tagged, op2_ptr: Tagged) -> Option<Redex> {
+    unsafe {
+        let e1 = op2_ptr.op2().e1().read();
+        let e2 = op2_ptr.op2().e2().read();
+        match (e1.tag(), e2.tag()) {
+            (Tag::NumPtr, Tag::NumPtr) => Some(Redex::OpNum { ptr_ptr, op2_ptr }),
+            (Tag::SupPtr, _) => Some(Redex::OpSup {
+                ptr_ptr,
+                op2_ptr,
+                sup_ptr: e1,
+            }),
+            (Tag::NumPtr, Tag::SupPtr) => Some(Redex::OpSup {
+                ptr_ptr,
+                op2_ptr,
+                sup_ptr: e2,
+            }),
+            (Tag::Ref, _) => Some(Redex::Ref {
+                ptr_ptr: op2_ptr.op2().e1(),
+            }),
+            (Tag::NumPtr, Tag::Ref) => Some(Redex::Ref {
+                ptr_ptr: op2_ptr.op2().e2(),
+            }),
+            _ => None,
         }
-
-        // dup #l{a1 b1} = e1
-        let e1 = sup_e1_e2_ptr.sup().e1().read();
-        dup_a1_b1_ptr.dup().write(Dup {
-            l,
-            a: sup_a1_a2_ptr.sup_e1_var_use_ptr(),
-            b: sup_b1_b2_ptr.sup_e1_var_use_ptr(),
-            e: e1,
-        });
-        e1.if_bound_var_move_to(dup_a1_b1_ptr.dup_e_var_use_ptr());
-
-        // dup #l{a2 b2} = e2
-        let e2 = sup_e1_e2_ptr.sup().e2().read();
-        dup_a2_b2_ptr.dup().write(Dup {
-            l,
-            a: sup_a1_a2_ptr.sup_e2_var_use_ptr(),
-            b: sup_b1_b2_ptr.sup_e2_var_use_ptr(),
-            e: e2,
-        });
-        e2.if_bound_var_move_to(dup_a2_b2_ptr.dup_e_var_use_ptr());
     }
-
-    // deallocate unreachable nodes
-    dup_a_b_ptr.dealloc_dup();
-    sup_e1_e2_ptr.dealloc_sup();
 }
 
-struct NodeIter {
-    visited: HashSet<Tagged>,
-    queue: VecDeque<Tagged>,
+unsafe fn reduce_redex(heap: &mut Heap, redex: Redex) {
+    unsafe {
+        let kind = redex.kind();
+        #[cfg(feature = "profiling")]
+        let start = Instant::now();
+        match redex {
+            Redex::AppLam {
+                ptr_ptr,
+                app_ptr,
+                lam_ptr,
+            } => rule_app_lam(heap, ptr_ptr, app_ptr, lam_ptr),
+            Redex::AppSup {
+                ptr_ptr,
+                app_ptr,
+                sup_ptr,
+            } => rule_app_sup(heap, ptr_ptr, app_ptr, sup_ptr),
+            Redex::DupLam { dup_ptr, lam_ptr } => rule_dup_lam(heap, dup_ptr, lam_ptr),
+            Redex::DupSup { dup_ptr, sup_ptr } => rule_dup_sup(heap, dup_ptr, sup_ptr),
+            Redex::Ref { ptr_ptr } => rule_ref(heap, ptr_ptr),
+            Redex::OpNum { ptr_ptr, op2_ptr } => rule_op_num(heap, ptr_ptr, op2_ptr),
+            Redex::OpSup {
+                ptr_ptr,
+                op2_ptr,
+                sup_ptr,
+            } => rule_op_sup(heap, ptr_ptr, op2_ptr, sup_ptr),
+            Redex::DupNum { dup_ptr, num_ptr } => rule_dup_num(heap, dup_ptr, num_ptr),
+        }
+        heap.rewrites[kind as usize] += 1;
+        #[cfg(feature = "profiling")]
+        heap.latency.record_rule(kind, start.elapsed());
+        apply_erasures(heap);
+    }
 }
 
-impl NodeIter {
-    fn new(ptr: Tagged) -> Self {
-        let visited = HashSet::new();
-        let mut queue = VecDeque::new();
-        queue.push_back(ptr);
+/// Applies the erase rule of every node queued by [`Tagged::erase`], until
+/// the queue is empty.
+///
+/// Erasure is eager: every rewrite erases what it discards before it returns,
+/// so that between rewrites, every live node is reachable from a root or a
+/// dup.
+///
+/// There is no eraser node. An eraser only exists from the moment a rewrite
+/// discards a node until the node is erased here, so it is represented by
+/// the node's entry in [`Heap::erasures`], and the erase rules (`rule_era_*`)
+/// are its interactions with each type of node. Erasing a node connects its
+/// children to erasers in turn, by queueing them too.
+unsafe fn apply_erasures(heap: &mut Heap) {
+    unsafe {
+        while let Some(ptr) = heap.erasures.pop() {
+            #[cfg(feature = "profiling")]
+            let start = Instant::now();
+            let kind = match ptr.tag() {
+                Tag::LamPtr => {
+                    rule_era_lam(heap, ptr);
+                    RuleKind::EraLam
+                }
+                Tag::AppPtr => {
+                    rule_era_app(heap, ptr);
+                    RuleKind::EraApp
+                }
+                Tag::SupPtr => {
+                    rule_era_sup(heap, ptr);
+                    RuleKind::EraSup
+                }
+                Tag::DupPtr => {
+                    rule_era_dup(heap, ptr);
+                    RuleKind::EraDup
+                }
+                Tag::NumPtr => {
+                    rule_era_num(heap, ptr);
+                    RuleKind::EraNum
+                }
+                Tag::Op2Ptr => {
+                    rule_era_op2(heap, ptr);
+                    RuleKind::EraOp2
+                }
+                _ => unreachable!("{:?}", ptr.tag()),
+            };
+            heap.rewrites[kind as usize] += 1;
+            #[cfg(feature = "profiling")]
+            heap.latency.record_rule(kind, start.elapsed());
+        }
+    }
+}
+
+unsafe fn rule_app_lam(heap: &mut Heap, ptr_ptr: *mut Tagged, app_ptr: Tagged, lam_ptr: Tagged) {
+    unsafe {
+        // (λx e) e2
+        // ---------- AppLam
+        // x <- e2
+        // e
+
+        // x <- e2
+        let x_use_ptr = lam_ptr.lam().x().read();
+        let e2 = app_ptr.app().e2().read();
+        if x_use_ptr.tag() == Tag::UnusedVar {
+            e2.erase(heap);
+        } else {
+            debug_assert_eq!(x_use_ptr.var_use_read(), lam_ptr.lam_bound_var());
+            x_use_ptr.var_use().write(e2);
+            e2.if_bound_var_move_to(x_use_ptr);
+        }
+
+        // e
+        let e_ptr = lam_ptr.lam().e().read();
+        ptr_ptr.write(e_ptr);
+        e_ptr.if_bound_var_move_to(Tagged::new(ptr_ptr as *mut _, Tag::VarUsePtr));
+
+        // deallocate unreachable nodes
+        app_ptr.dealloc_app(heap);
+        lam_ptr.dealloc_lam(heap);
+    }
+}
+
+unsafe fn rule_app_sup(heap: &mut Heap, ptr_ptr: *mut Tagged, app_ptr: Tagged, sup_ptr: Tagged) {
+    unsafe {
+        // #l{e1 e2} e3
+        // ----------------- AppSup
+        // dup #l{a b} = e3
+        // #l{(e1 a) (e2 b)}
+
+        let app_sup_e3_ptr = app_ptr;
+        let sup_e1_e2_ptr = sup_ptr;
+
+        let l = sup_e1_e2_ptr.sup().l().read();
+
+        let dup_a_b_ptr = Dup::alloc(heap);
+        let app_e1_a_ptr = App::alloc(heap);
+        let app_e2_b_ptr = App::alloc(heap);
+        let sup_app_app_ptr = Sup::alloc(heap);
+
+        // dup #l{a b} = e3
+        let a = app_e1_a_ptr.app_e2_var_use_ptr();
+        let b = app_e2_b_ptr.app_e2_var_use_ptr();
+        let e3 = app_sup_e3_ptr.app().e2().read();
+        dup_a_b_ptr.dup().write(Dup {
+            header: Header::DUP,
+            l,
+            a,
+            b,
+            e: e3,
+        });
+        e3.if_bound_var_move_to(dup_a_b_ptr.dup_e_var_use_ptr());
+
+        // (e1 a)
+        let e1 = sup_e1_e2_ptr.sup().e1().read();
+        let a = dup_a_b_ptr.dup_a_bound_var();
+        app_e1_a_ptr.app().write(App { e1, e2: a });
+        e1.if_bound_var_move_to(app_e1_a_ptr.app_e1_var_use_ptr());
+
+        // (e2 b)
+        let e2 = sup_e1_e2_ptr.sup().e2().read();
+        let b = dup_a_b_ptr.dup_b_bound_var();
+        app_e2_b_ptr.app().write(App { e1: e2, e2: b });
+        e2.if_bound_var_move_to(app_e2_b_ptr.app_e1_var_use_ptr());
+
+        // #l{(e1 a) (e2 b)}
+        sup_app_app_ptr.sup().write(Sup {
+            header: Header::SUP,
+            l,
+            e1: app_e1_a_ptr,
+            e2: app_e2_b_ptr,
+        });
+        ptr_ptr.write(sup_app_app_ptr);
+
+        // deallocate unreachable nodes
+        app_sup_e3_ptr.dealloc_app(heap);
+        sup_e1_e2_ptr.dealloc_sup(heap);
+    }
+}
+
+unsafe fn rule_dup_lam(heap: &mut Heap, dup_ptr: Tagged, lam_ptr: Tagged) {
+    unsafe {
+        // dup #l{a b} = (λx e)
+        // -------------------- DupLam
+        // a <- (λx1 c)
+        // b <- (λx2 d)
+        // x <- #l{x1 x2}
+        // dup #l{c d} = e
+
+        let dup_a_b_ptr = dup_ptr;
+        let lam_x_e_ptr = lam_ptr;
+
+        let l = dup_a_b_ptr.dup().l().read();
+        let dup_a_b_a = dup_a_b_ptr.dup().a().read();
+        let dup_a_b_b = dup_a_b_ptr.dup().b().read();
+        let lam_x_e_x = lam_x_e_ptr.lam().x().read();
+
+        let lam_x1_c_ptr = if dup_a_b_a.tag() == Tag::UnusedVar {
+            Tagged::new_unbound_var()
+        } else {
+            Lam::alloc(heap)
+        };
+        let lam_x2_d_ptr = if dup_a_b_b.tag() == Tag::UnusedVar {
+            Tagged::new_unbound_var()
+        } else {
+            Lam::alloc(heap)
+        };
+        let sup_x1_x2_ptr = if lam_x_e_x.tag() == Tag::UnusedVar {
+            Tagged::new_unbound_var()
+        } else {
+            Sup::alloc(heap)
+        };
+        debug_assert!(dup_a_b_a.tag() != Tag::UnusedVar || dup_a_b_b.tag() != Tag::UnusedVar);
+        let dup_c_d_ptr = Dup::alloc(heap);
+
+        // a <- (λx1 c)
+        if dup_a_b_a.tag() != Tag::UnusedVar {
+            debug_assert_eq!(dup_a_b_a.var_use_read(), dup_a_b_ptr.dup_a_bound_var());
+            dup_a_b_a.var_use().write(lam_x1_c_ptr);
+            let x1 = sup_x1_x2_ptr.sup_e1_var_use_ptr();
+            let c = dup_c_d_ptr.dup_a_bound_var();
+            lam_x1_c_ptr.lam().write(Lam { x: x1, e: c });
+        }
+
+        // b <- (λx2 d)
+        if dup_a_b_b.tag() != Tag::UnusedVar {
+            debug_assert_eq!(dup_a_b_b.var_use_read(), dup_a_b_ptr.dup_b_bound_var());
+            dup_a_b_b.var_use().write(lam_x2_d_ptr);
+            let x2 = sup_x1_x2_ptr.sup_e2_var_use_ptr();
+            let d = dup_c_d_ptr.dup_b_bound_var();
+            lam_x2_d_ptr.lam().write(Lam { x: x2, e: d });
+        }
+
+        // x <- #l{x1,x2}
+        if lam_x_e_x.tag() != Tag::UnusedVar {
+            // NOTE: If `a` (or `b`) is unused, then so is `c` (or `d`), so the
+            //       branch of the superposition for the erased copy is never
+            //       read, and is left as an unbound variable.
+            debug_assert_eq!(lam_x_e_x.var_use_read(), lam_x_e_ptr.lam_bound_var());
+            lam_x_e_x.var_use().write(sup_x1_x2_ptr);
+            let lam_bound_var_or_unbound = |lam_ptr: Tagged| {
+                if lam_ptr.tag() == Tag::UnboundVar {
+                    Tagged::new_unbound_var()
+                } else {
+                    lam_ptr.lam_bound_var()
+                }
+            };
+            let x1 = lam_bound_var_or_unbound(lam_x1_c_ptr);
+            let x2 = lam_bound_var_or_unbound(lam_x2_d_ptr);
+            sup_x1_x2_ptr.sup().write(Sup {
+                header: Header::SUP,
+                l,
+                e1: x1,
+                e2: x2,
+            });
+        }
+
+        // dup #l{c d} = e
+        let e = lam_x_e_ptr.lam().e().read();
+        let c = lam_x1_c_ptr.lam_e_var_use_ptr();
+        let d = lam_x2_d_ptr.lam_e_var_use_ptr();
+        dup_c_d_ptr.dup().write(Dup {
+            header: Header::DUP,
+            l,
+            a: c,
+            b: d,
+            e,
+        });
+        e.if_bound_var_move_to(dup_c_d_ptr.dup_e_var_use_ptr());
+
+        // deallocate unreachable nodes
+        dup_a_b_ptr.dealloc_dup(heap);
+        lam_x_e_ptr.dealloc_lam(heap);
+    }
+}
+
+unsafe fn rule_dup_sup(heap: &mut Heap, dup_ptr: Tagged, sup_ptr: Tagged) {
+    unsafe {
+        let dup_a_b_ptr = dup_ptr;
+        let sup_e1_e2_ptr = sup_ptr;
+
+        let l = dup_a_b_ptr.dup().l().read();
+        let m = sup_e1_e2_ptr.sup().l().read();
+        let dup_a_b_a = dup_a_b_ptr.dup().a().read();
+        let dup_a_b_b = dup_a_b_ptr.dup().b().read();
+        debug_assert!(dup_a_b_a.tag() != Tag::UnusedVar || dup_a_b_b.tag() != Tag::UnusedVar);
+
+        if l == m {
+            // dup #l{a b} = #l{e1 e2}
+            // ----------------------- DupSupSame
+            // a <- e1
+            // b <- e2
+
+            // a <- e1
+            let e1 = sup_e1_e2_ptr.sup().e1().read();
+            if e1 == dup_a_b_ptr.dup_b_bound_var() && dup_a_b_a.tag() == Tag::UnusedVar {
+                // dup #l{_ b} = #l{b e2}: `b` is only used by the erased `e1`.
+                dup_a_b_ptr.dup().b().write(Tagged::new_unused_var());
+            } else if dup_a_b_a.tag() == Tag::UnusedVar {
+                e1.erase(heap);
+            } else {
+                debug_assert_eq!(dup_a_b_a.var_use_read(), dup_a_b_ptr.dup_a_bound_var());
+                dup_a_b_a.var_use().write(e1);
+                e1.if_bound_var_move_to(dup_a_b_a);
+            }
+
+            // b <- e2
+            // NOTE: `b` is re-read, since `e1` may have been `b` itself, in which
+            //       case it has just moved to where `a` was used.
+            let dup_a_b_b = dup_a_b_ptr.dup().b().read();
+            let e2 = sup_e1_e2_ptr.sup().e2().read();
+            if dup_a_b_b.tag() == Tag::UnusedVar {
+                e2.erase(heap);
+            } else {
+                debug_assert_eq!(dup_a_b_b.var_use_read(), dup_a_b_ptr.dup_b_bound_var());
+                dup_a_b_b.var_use().write(e2);
+                e2.if_bound_var_move_to(dup_a_b_b);
+            }
+        } else {
+            // dup #l{a b} = #m{e1 e2}
+            // ----------------------- DupSupDiff
+            // a <- #m{a1 a2}
+            // b <- #m{b1 b2}
+            // dup #l{a1 b1} = e1
+            // dup #l{a2 b2} = e2
+
+            let sup_a1_a2_ptr = if dup_a_b_a.tag() == Tag::UnusedVar {
+                Tagged::new_unbound_var()
+            } else {
+                Sup::alloc(heap)
+            };
+            let sup_b1_b2_ptr = if dup_a_b_b.tag() == Tag::UnusedVar {
+                Tagged::new_unbound_var()
+            } else {
+                Sup::alloc(heap)
+            };
+            let dup_a1_b1_ptr = Dup::alloc(heap);
+            let dup_a2_b2_ptr = Dup::alloc(heap);
+
+            // a <- #m{a1 a2}
+            if dup_a_b_a.tag() != Tag::UnusedVar {
+                debug_assert_eq!(dup_a_b_a.var_use_read(), dup_a_b_ptr.dup_a_bound_var());
+                dup_a_b_a.var_use().write(sup_a1_a2_ptr);
+                let a1 = dup_a1_b1_ptr.dup_a_bound_var();
+                let a2 = dup_a2_b2_ptr.dup_a_bound_var();
+                sup_a1_a2_ptr.sup().write(Sup {
+                    header: Header::SUP,
+                    l: m,
+                    e1: a1,
+                    e2: a2,
+                });
+            }
+
+            // b <- #m{b1 b2}
+            if dup_a_b_b.tag() != Tag::UnusedVar {
+                debug_assert_eq!(dup_a_b_b.var_use_read(), dup_a_b_ptr.dup_b_bound_var());
+                dup_a_b_b.var_use().write(sup_b1_b2_ptr);
+                let b1 = dup_a1_b1_ptr.dup_b_bound_var();
+                let b2 = dup_a2_b2_ptr.dup_b_bound_var();
+                sup_b1_b2_ptr.sup().write(Sup {
+                    header: Header::SUP,
+                    l: m,
+                    e1: b1,
+                    e2: b2,
+                });
+            }
+
+            // dup #l{a1 b1} = e1
+            let e1 = sup_e1_e2_ptr.sup().e1().read();
+            dup_a1_b1_ptr.dup().write(Dup {
+                header: Header::DUP,
+                l,
+                a: sup_a1_a2_ptr.sup_e1_var_use_ptr(),
+                b: sup_b1_b2_ptr.sup_e1_var_use_ptr(),
+                e: e1,
+            });
+            e1.if_bound_var_move_to(dup_a1_b1_ptr.dup_e_var_use_ptr());
+
+            // dup #l{a2 b2} = e2
+            let e2 = sup_e1_e2_ptr.sup().e2().read();
+            dup_a2_b2_ptr.dup().write(Dup {
+                header: Header::DUP,
+                l,
+                a: sup_a1_a2_ptr.sup_e2_var_use_ptr(),
+                b: sup_b1_b2_ptr.sup_e2_var_use_ptr(),
+                e: e2,
+            });
+            e2.if_bound_var_move_to(dup_a2_b2_ptr.dup_e_var_use_ptr());
+        }
+
+        // deallocate unreachable nodes
+        dup_a_b_ptr.dealloc_dup(heap);
+        sup_e1_e2_ptr.dealloc_sup(heap);
+    }
+}
+
+unsafe fn rule_ref(heap: &mut Heap, ptr_ptr: *mut Tagged) {
+    unsafe {
+        // @f
+        // ---- Ref
+        // (the definition of f)
+
+        // NOTE: A definition is closed, apart from references to others, so
+        //       its graph is built on its own, like a fresh copy.
+        let def = ptr_ptr.read().def();
+        build_graph(heap, ptr_ptr, &def.term, &mut HashMap::new());
+    }
+}
+
+unsafe fn rule_op_num(heap: &mut Heap, ptr_ptr: *mut Tagged, op2_ptr: Tagged) {
+    unsafe {
+        // (op n m)
+        // --------- OpNum
+        // (n op m)
+
+        let op = op2_ptr.op2().op().read();
+        let num_n_ptr = op2_ptr.op2().e1().read();
+        let num_m_ptr = op2_ptr.op2().e2().read();
+
+        // NOTE: The node of `n` is reused for the result.
+        let n = num_n_ptr.num().read().n;
+        let m = num_m_ptr.num().read().n;
+        num_n_ptr.num().write(Num { n: op.apply(n, m) });
+        ptr_ptr.write(num_n_ptr);
+
+        // deallocate unreachable nodes
+        op2_ptr.dealloc_op2(heap);
+        num_m_ptr.dealloc_num(heap);
+    }
+}
+
+unsafe fn rule_op_sup(heap: &mut Heap, ptr_ptr: *mut Tagged, op2_ptr: Tagged, sup_ptr: Tagged) {
+    unsafe {
+        let op = op2_ptr.op2().op().read();
+        let l = sup_ptr.sup().l().read();
+        let e1 = sup_ptr.sup().e1().read();
+        let e2 = sup_ptr.sup().e2().read();
+
+        let op2_e1_ptr = Op2::alloc(heap);
+        let op2_e2_ptr = Op2::alloc(heap);
+        let sup_op2_op2_ptr = Sup::alloc(heap);
+
+        if op2_ptr.op2().e1().read().tag() == Tag::SupPtr {
+            // (op #l{e1 e2} e3)
+            // ----------------------- OpSup
+            // dup #l{a b} = e3
+            // #l{(op e1 a) (op e2 b)}
+
+            let dup_a_b_ptr = Dup::alloc(heap);
+
+            // dup #l{a b} = e3
+            let a = op2_e1_ptr.op2_e2_var_use_ptr();
+            let b = op2_e2_ptr.op2_e2_var_use_ptr();
+            let e3 = op2_ptr.op2().e2().read();
+            dup_a_b_ptr.dup().write(Dup {
+                header: Header::DUP,
+                l,
+                a,
+                b,
+                e: e3,
+            });
+            e3.if_bound_var_move_to(dup_a_b_ptr.dup_e_var_use_ptr());
+
+            // (op e1 a)
+            let a = dup_a_b_ptr.dup_a_bound_var();
+            op2_e1_ptr.op2().write(Op2 {
+                header: Header::OP2,
+                op,
+                e1,
+                e2: a,
+            });
+            e1.if_bound_var_move_to(op2_e1_ptr.op2_e1_var_use_ptr());
+
+            // (op e2 b)
+            let b = dup_a_b_ptr.dup_b_bound_var();
+            op2_e2_ptr.op2().write(Op2 {
+                header: Header::OP2,
+                op,
+                e1: e2,
+                e2: b,
+            });
+            e2.if_bound_var_move_to(op2_e2_ptr.op2_e1_var_use_ptr());
+        } else {
+            // (op n #l{e1 e2})
+            // ----------------------- OpSup
+            // #l{(op n e1) (op n e2)}
+
+            let num_n_ptr = op2_ptr.op2().e1().read();
+            let num_copy_ptr = Num::alloc(heap);
+            num_copy_ptr.num().write(num_n_ptr.num().read());
+
+            // (op n e1)
+            op2_e1_ptr.op2().write(Op2 {
+                header: Header::OP2,
+                op,
+                e1: num_n_ptr,
+                e2: e1,
+            });
+            e1.if_bound_var_move_to(op2_e1_ptr.op2_e2_var_use_ptr());
+
+            // (op n e2)
+            op2_e2_ptr.op2().write(Op2 {
+                header: Header::OP2,
+                op,
+                e1: num_copy_ptr,
+                e2,
+            });
+            e2.if_bound_var_move_to(op2_e2_ptr.op2_e2_var_use_ptr());
+        }
+
+        // #l{(op .. ..) (op .. ..)}
+        sup_op2_op2_ptr.sup().write(Sup {
+            header: Header::SUP,
+            l,
+            e1: op2_e1_ptr,
+            e2: op2_e2_ptr,
+        });
+        ptr_ptr.write(sup_op2_op2_ptr);
+
+        // deallocate unreachable nodes
+        op2_ptr.dealloc_op2(heap);
+        sup_ptr.dealloc_sup(heap);
+    }
+}
+
+unsafe fn rule_dup_num(heap: &mut Heap, dup_ptr: Tagged, num_ptr: Tagged) {
+    unsafe {
+        // dup #l{a b} = n
+        // --------------- DupNum
+        // a <- n
+        // b <- n
+
+        let dup_a_b_a = dup_ptr.dup().a().read();
+        let dup_a_b_b = dup_ptr.dup().b().read();
+        debug_assert!(dup_a_b_a.tag() != Tag::UnusedVar || dup_a_b_b.tag() != Tag::UnusedVar);
+
+        // a <- n
+        if dup_a_b_a.tag() != Tag::UnusedVar {
+            debug_assert_eq!(dup_a_b_a.var_use_read(), dup_ptr.dup_a_bound_var());
+            dup_a_b_a.var_use().write(num_ptr);
+        }
+
+        // b <- n
+        // NOTE: The node of `n` goes to `a` if it is used, and `b` gets a copy.
+        if dup_a_b_b.tag() != Tag::UnusedVar {
+            debug_assert_eq!(dup_a_b_b.var_use_read(), dup_ptr.dup_b_bound_var());
+            let num_b_ptr = if dup_a_b_a.tag() == Tag::UnusedVar {
+                num_ptr
+            } else {
+                let copy = Num::alloc(heap);
+                copy.num().write(num_ptr.num().read());
+                copy
+            };
+            dup_a_b_b.var_use().write(num_b_ptr);
+        }
+
+        // deallocate unreachable nodes
+        dup_ptr.dealloc_dup(heap);
+    }
+}
+
+unsafe fn rule_era_lam(heap: &mut Heap, lam_ptr: Tagged) {
+    unsafe {
+        // era (λx e)
+        // ---------- EraLam
+        // x <- era
+        // era e
+
+        // x <- era
+        // NOTE: A use of `x` that is still reachable, outside of `e`, becomes an
+        //       unbound variable. A use inside of `e` is erased along with it.
+        let x = lam_ptr.lam().x().read();
+        if x.tag() == Tag::VarUsePtr {
+            debug_assert_eq!(x.var_use_read(), lam_ptr.lam_bound_var());
+            x.var_use().write(Tagged::new_unbound_var());
+        }
+
+        // era e
+        lam_ptr.lam().e().read().erase(heap);
+
+        // deallocate unreachable nodes
+        lam_ptr.dealloc_lam(heap);
+    }
+}
+
+unsafe fn rule_era_app(heap: &mut Heap, app_ptr: Tagged) {
+    unsafe {
+        // era (e1 e2)
+        // ----------- EraApp
+        // era e1
+        // era e2
+
+        app_ptr.app().e1().read().erase(heap);
+        app_ptr.app().e2().read().erase(heap);
+
+        // deallocate unreachable nodes
+        app_ptr.dealloc_app(heap);
+    }
+}
+
+unsafe fn rule_era_sup(heap: &mut Heap, sup_ptr: Tagged) {
+    unsafe {
+        // era #l{e1 e2}
+        // ------------- EraSup
+        // era e1
+        // era e2
+
+        sup_ptr.sup().e1().read().erase(heap);
+        sup_ptr.sup().e2().read().erase(heap);
+
+        // deallocate unreachable nodes
+        sup_ptr.dealloc_sup(heap);
+    }
+}
+
+unsafe fn rule_era_dup(heap: &mut Heap, dup_ptr: Tagged) {
+    unsafe {
+        // dup #l{era era} = e
+        // ------------------- EraDup
+        // era e
+
+        debug_assert_eq!(dup_ptr.dup().a().read().tag(), Tag::UnusedVar);
+        debug_assert_eq!(dup_ptr.dup().b().read().tag(), Tag::UnusedVar);
+        dup_ptr.dup().e().read().erase(heap);
+
+        // deallocate unreachable nodes
+        dup_ptr.dealloc_dup(heap);
+    }
+}
+
+unsafe fn rule_era_num(heap: &mut Heap, num_ptr: Tagged) {
+    unsafe {
+        // era n
+        // ----- EraNum
+        // (nothing)
+
+        // deallocate unreachable nodes
+        num_ptr.dealloc_num(heap);
+    }
+}
+
+unsafe fn rule_era_op2(heap: &mut Heap, op2_ptr: Tagged) {
+    unsafe {
+        // era (op e1 e2)
+        // -------------- EraOp2
+        // era e1
+        // era e2
+
+        op2_ptr.op2().e1().read().erase(heap);
+        op2_ptr.op2().e2().read().erase(heap);
+
+        // deallocate unreachable nodes
+        op2_ptr.dealloc_op2(heap);
+    }
+}
+
+struct NodeIter {
+    visited: HashSet<Tagged>,
+    queue: VecDeque<Tagged>,
+}
+
+impl NodeIter {
+    fn new(ptr: Tagged) -> Self {
+        Self::from_roots([ptr])
+    }
+
+    fn from_roots(ptrs: impl IntoIterator<Item = Tagged>) -> Self {
+        let visited = HashSet::new();
+        let queue = ptrs.into_iter().collect();
         Self { visited, queue }
     }
 }
@@ -918,7 +1966,7 @@ impl Iterator for NodeIter {
                 self.visited.insert(ptr);
                 match ptr.tag() {
                     Tag::UnusedVar | Tag::VarUsePtr => unreachable!("{:?}", ptr.tag()),
-                    Tag::UnboundVar | Tag::LamBoundVar => {}
+                    Tag::UnboundVar | Tag::LamBoundVar | Tag::Ref => {}
                     Tag::DupABoundVar | Tag::DupBBoundVar => {
                         self.queue.push_front(Tagged::new(ptr.ptr(), Tag::DupPtr));
                     }
@@ -944,6 +1992,13 @@ impl Iterator for NodeIter {
                         self.queue.push_back(dup.e);
                         return Some(ptr);
                     }
+                    Tag::NumPtr => return Some(ptr),
+                    Tag::Op2Ptr => {
+                        let op2 = ptr.op2().read();
+                        self.queue.push_back(op2.e1);
+                        self.queue.push_back(op2.e2);
+                        return Some(ptr);
+                    }
                 }
             }
         }
@@ -971,12 +2026,17 @@ impl Iterator for TaggedIter {
         unsafe {
             while let Some(ptr) = self.queue.pop_front() {
                 let tag = ptr.tag();
-                if self.visited.contains(&ptr) && tag != Tag::UnusedVar && tag != Tag::UnboundVar {
+                let is_shared = matches!(tag, Tag::UnusedVar | Tag::UnboundVar | Tag::Ref);
+                if self.visited.contains(&ptr) && !is_shared {
                     continue;
                 }
                 self.visited.insert(ptr);
                 match tag {
-                    Tag::UnusedVar | Tag::VarUsePtr | Tag::UnboundVar | Tag::LamBoundVar => {
+                    Tag::UnusedVar
+                    | Tag::VarUsePtr
+                    | Tag::UnboundVar
+                    | Tag::LamBoundVar
+                    | Tag::Ref => {
                         return Some(ptr);
                     }
                     Tag::DupABoundVar | Tag::DupBBoundVar => {
@@ -998,6 +2058,12 @@ impl Iterator for TaggedIter {
                         self.queue.push_back(ptr.sup().e2().read());
                         return Some(ptr);
                     }
+                    Tag::NumPtr => return Some(ptr),
+                    Tag::Op2Ptr => {
+                        self.queue.push_back(ptr.op2().e1().read());
+                        self.queue.push_back(ptr.op2().e2().read());
+                        return Some(ptr);
+                    }
                     Tag::DupPtr => {
                         self.queue.push_back(ptr.dup().a().read());
                         self.queue.push_back(ptr.dup().b().read());
@@ -1014,213 +2080,386 @@ impl Iterator for TaggedIter {
 }
 
 /// An owned term graph.
-pub struct TermGraph(*mut Tagged);
+///
+/// The nodes live in an arena and are linked by tagged pointers, but none of
+/// this is exposed: every public operation is safe, and any input that would
+/// break the graph's invariants is rejected with an error or a documented
+/// panic.
+pub struct TermGraph(*mut Tagged, Heap);
+
+/// An opaque identifier for a node (e.g. a `Lam`, `App`, `Sup`, or `Dup`) in a
+/// [`TermGraph`].
+///
+/// Ids are only meaningful for the graph they were obtained from, and only
+/// until that graph is next mutated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    fn from_ptr(ptr: *mut ()) -> Self {
+        NodeId(ptr.addr())
+    }
+}
 
 impl TermGraph {
     // TODO: rename to `iter_nodes`
     fn node_iter(&self) -> NodeIter {
-        NodeIter::new(unsafe { self.0.read() })
+        let roots = self.root_slots();
+        NodeIter::from_roots(roots.into_iter().map(|root| unsafe { root.read() }))
     }
 
-    fn iter_taggeds(&self) -> TaggedIter {
-        TaggedIter::new(unsafe { self.0.read() })
+    /// Returns the slots of the primary root and any additional named roots.
+    fn root_slots(&self) -> Vec<*mut Tagged> {
+        let mut roots = vec![self.0];
+        roots.extend(
+            self.1
+                .roots
+                .iter()
+                .map(|(_, slot)| *slot)
+                .filter(|slot| *slot != self.0),
+        );
+        roots
     }
+}
+
+/// The names of the variables of a read-back term.
+///
+/// Bound variables are named `v1`, `v2`, and so on, numbered down from the
+/// number of variable uses, so that the last one visited is `v1`. Free
+/// variables keep their own names, if they have one, and a number that would
+/// name a bound variable like a free one gets underscores appended.
+struct VarNames {
+    remaining: usize,
+    free: HashSet<IStr>,
+}
 
-    fn count_vars(&self) -> usize {
-        let mut count = 0;
+impl VarNames {
+    unsafe fn new(root: Tagged) -> Self {
         unsafe {
-            for ptr in self.iter_taggeds() {
+            let mut remaining = 0;
+            let mut free = HashSet::new();
+            for ptr in TaggedIter::new(root) {
                 match ptr.tag() {
-                    Tag::UnboundVar | Tag::LamBoundVar | Tag::DupABoundVar | Tag::DupBBoundVar => {
-                        count += 1;
+                    Tag::UnboundVar => {
+                        remaining += 1;
+                        free.extend(ptr.free_name());
                     }
+                    Tag::LamBoundVar | Tag::DupABoundVar | Tag::DupBBoundVar => remaining += 1,
                     _ => {}
                 }
             }
+            VarNames { remaining, free }
         }
-        count
+    }
+
+    /// Returns the name of the next variable visited, `ptr`.
+    unsafe fn next(&mut self, ptr: Tagged) -> IStr {
+        let mut name = format!("v{}", self.remaining);
+        self.remaining -= 1;
+        if let Some(free_name) = unsafe { ptr.free_name() } {
+            return free_name;
+        }
+        while self.free.contains(&name.intern()) {
+            name.push('_');
+        }
+        name.intern()
     }
 }
 
+/// Frees the graph by dropping the arena of its heap rather than by
+/// traversing it, so dropping takes constant stack space however deep the
+/// graph is, and frees each node exactly once even in a partially reduced
+/// graph, including any regions that are no longer reachable.
 impl Drop for TermGraph {
     fn drop(&mut self) {
-        for node in self.node_iter() {
-            unsafe { node.dealloc_any_node() };
+        for slot in self.root_slots() {
+            unsafe { std::alloc::dealloc(slot as *mut u8, std::alloc::Layout::new::<Tagged>()) };
         }
-        unsafe { std::alloc::dealloc(self.0 as *mut u8, std::alloc::Layout::new::<Tagged>()) };
     }
 }
 
 impl fmt::Debug for Tagged {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?} {:?}", unsafe { self.tag() }, self.ptr())
+        unsafe {
+            match self.free_name() {
+                Some(name) => write!(f, "{:?} {}", self.tag(), name),
+                None => write!(f, "{:?} {:?}", self.tag(), self.ptr()),
+            }
+        }
     }
 }
 
-#[allow(dead_code)]
-unsafe fn print_graph(ptr: Tagged) {
-    for ptr in NodeIter::new(ptr) {
-        print!("{:?}", ptr.ptr());
-        match ptr.node_type() {
-            NodeType::Lam => println!(" {:?}", ptr.lam_read()),
-            NodeType::App => println!(" {:?}", ptr.app_read()),
-            NodeType::Sup => println!(" {:?}", ptr.sup_read()),
-            NodeType::Dup => println!(" {:?}", ptr.dup_read()),
+impl TermGraph {
+    /// Builds the graph for `term`, or returns an [`Error::Build`] if `term`
+    /// uses a bound variable more than once, binds a variable twice in a dup,
+    /// has a label larger than [`MAX_LABEL`] (see [`TermGraph::from`]), or
+    /// refers to a definition (see [`TermGraph::from_book`]). The
+    /// [`GraphBuildError`] names the variable or label at fault.
+    ///
+    /// This is the fallible constructor. `TryFrom<&Term>` cannot be
+    /// implemented alongside `From<&Term>`: the standard library derives an
+    /// infallible `TryFrom` from every `From`, so `TermGraph::try_from` panics
+    /// on the same terms as `TermGraph::from`.
+    pub fn try_from_term(term: &Term) -> Result<Self, Error> {
+        Self::from_book(Arc::default(), term)
+    }
+
+    /// Builds the graph for `term`, whose references are to the definitions
+    /// of `book`.
+    ///
+    /// References are leaves of the graph. The graph of a definition is only
+    /// built when a reference to it is in a root, the function of an
+    /// application, the expression of a dup, or an operand of an operation
+    /// that needs its value, by a rewrite of kind
+    /// [`RuleKind::Ref`], so a recursive definition is only unfolded as often
+    /// as the reduction needs. Other references are left as they are, and read
+    /// back as [`Term::Ref`].
+    ///
+    /// Returns an error if `term` or a definition is not well formed (see
+    /// [`TermGraph::try_from_term`]), or refers to a name that `book` does not
+    /// define.
+    pub fn from_book(book: Arc<Book>, term: &Term) -> Result<Self, Error> {
+        for name in book.names() {
+            let def = &book.def(name).unwrap().term;
+            validate(def)?;
+            check_refs(&book, def)?;
         }
+        validate(term)?;
+        check_refs(&book, term)?;
+        Ok(Self::with_book(book, term))
     }
-}
 
-impl fmt::Debug for TermGraph {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for ptr in self.node_iter() {
-            write!(f, "{:?}", ptr.ptr())?;
-            unsafe {
-                match ptr.node_type() {
-                    NodeType::Lam => writeln!(f, " {:?}", ptr.lam_read())?,
-                    NodeType::App => writeln!(f, " {:?}", ptr.app_read())?,
-                    NodeType::Sup => writeln!(f, " {:?}", ptr.sup_read())?,
-                    NodeType::Dup => writeln!(f, " {:?}", ptr.dup_read())?,
-                }
-            }
+    /// Builds the graph for `term`, whose references must be to definitions of
+    /// `book`.
+    fn with_book(book: Arc<Book>, term: &Term) -> Self {
+        unsafe {
+            let root_ptr = std::alloc::alloc(std::alloc::Layout::new::<Tagged>()) as *mut Tagged;
+            root_ptr.write(Tagged::new_unbound_var());
+            let mut heap = Heap {
+                book,
+                ..Heap::default()
+            };
+            build_graph(&mut heap, root_ptr, term, &mut HashMap::new());
+            TermGraph(root_ptr, heap)
         }
-        Ok(())
     }
 }
 
+/// Returns an error if `term` refers to a definition that `book` does not
+/// define.
+pub(crate) fn check_refs(book: &Book, term: &Term) -> Result<(), Error> {
+    match term
+        .refs()
+        .into_iter()
+        .find(|name| book.def(*name).is_none())
+    {
+        Some(name) => Err(GraphBuildError::UndefinedRef(name).into()),
+        None => Ok(()),
+    }
+}
+
+/// # Panics
+///
+/// Panics if `term` uses a bound variable more than once, binds the same
+/// variable twice in a dup, uses the variables of a dup in the expression
+/// they are bound to, has a label larger than [`MAX_LABEL`], or refers to a
+/// definition. Use
+/// [`TermGraph::try_from_term`] for terms that are not known to be well
+/// formed, and [`TermGraph::from_book`] for terms with references.
 impl From<&Term> for TermGraph {
     fn from(term: &Term) -> Self {
+        Self::with_book(Arc::default(), term)
+    }
+}
+
+/// Builds the graph for `term`, storing the resulting pointer into `storage_ptr`.
+/// Each free occurrence of a variable in `env` takes one of its (unused)
+/// binders; other free variables of `term` become unbound variables.
+unsafe fn build_graph(
+    heap: &mut Heap,
+    storage_ptr: *mut Tagged,
+    term: &Term,
+    env: &mut HashMap<IStr, Vec<Tagged>>,
+) {
+    unsafe {
         enum Task<'t> {
+            PushVarBinder(IStr, Tagged),
             PopVarBinder(IStr),
             Recurse(*mut Tagged, &'t Term),
         }
 
         let var_binders: &mut HashMap<IStr, Vec<Tagged>> = &mut HashMap::new();
         let dup_ptrs = &mut vec![];
-        unsafe {
-            let root_ptr = std::alloc::alloc(std::alloc::Layout::new::<Tagged>()) as *mut Tagged;
-            root_ptr.write(Tagged::new_unbound_var());
-            let stack = &mut vec![Task::Recurse(root_ptr, term)];
-            while let Some(task) = stack.pop() {
-                match task {
-                    Task::PopVarBinder(x) => {
-                        var_binders.entry(x).or_default().pop().unwrap();
-                    }
-                    Task::Recurse(storage_ptr, Term::Var(x)) => {
-                        if let Some(binders) = var_binders.get_mut(x) {
-                            let binder = binders.last().copied().unwrap();
-                            let binder_raw_ptr = match binder.tag() {
-                                Tag::LamBoundVar => binder.lam().x(),
-                                Tag::DupABoundVar => binder.dup().a(),
-                                Tag::DupBBoundVar => binder.dup().b(),
-                                _ => unreachable!("{:?}", binder.tag()),
-                            };
-                            assert_eq!(binder_raw_ptr.read(), Tagged::new_unused_var());
-                            binder_raw_ptr
-                                .write(Tagged::new(storage_ptr as *mut (), Tag::VarUsePtr));
-                            storage_ptr.write(binder);
-                        } else {
-                            storage_ptr.write(Tagged::new_unbound_var());
-                        }
-                    }
-                    Task::Recurse(storage_ptr, Term::Lam(x, e)) => {
-                        let lam_ptr = Lam::alloc();
-                        lam_ptr.lam().x().write(Tagged::new_unused_var());
-                        storage_ptr.write(lam_ptr);
-                        var_binders
-                            .entry(*x)
-                            .or_default()
-                            .push(lam_ptr.lam_bound_var());
-                        stack.push(Task::PopVarBinder(*x));
-                        stack.push(Task::Recurse(lam_ptr.lam().e(), e));
-                    }
-                    Task::Recurse(storage_ptr, Term::App(e1, e2)) => {
-                        let app_ptr = App::alloc();
-                        storage_ptr.write(app_ptr);
-                        stack.push(Task::Recurse(app_ptr.app().e2(), e2));
-                        stack.push(Task::Recurse(app_ptr.app().e1(), e1));
-                    }
-                    Task::Recurse(storage_ptr, Term::Sup(l, e1, e2)) => {
-                        let sup_ptr = Sup::alloc();
-                        storage_ptr.write(sup_ptr);
-                        sup_ptr.sup().l().write(*l);
-                        stack.push(Task::Recurse(sup_ptr.sup().e2(), e2));
-                        stack.push(Task::Recurse(sup_ptr.sup().e1(), e1));
-                    }
-                    Task::Recurse(storage_ptr, Term::Dup(l, a, b, e, cont)) => {
-                        let dup_ptr = Dup::alloc();
-                        dup_ptrs.push(dup_ptr);
-                        dup_ptr.dup().l().write(*l);
-                        assert_ne!(a, b);
-                        dup_ptr.dup().a().write(Tagged::new_unused_var());
-                        dup_ptr.dup().b().write(Tagged::new_unused_var());
-                        var_binders
-                            .entry(*a)
-                            .or_default()
-                            .push(dup_ptr.dup_a_bound_var());
-                        stack.push(Task::PopVarBinder(*a));
-                        var_binders
-                            .entry(*b)
-                            .or_default()
-                            .push(dup_ptr.dup_b_bound_var());
-                        stack.push(Task::PopVarBinder(*b));
-                        stack.push(Task::Recurse(storage_ptr, cont));
-                        stack.push(Task::Recurse(dup_ptr.dup().e(), e));
-                    }
-                    Task::Recurse(storage_ptr, Term::Let(x, e1, e2)) => {
-                        // let x = e1 in e2 => (λx e2) e1
-                        let app_ptr = App::alloc();
-                        storage_ptr.write(app_ptr);
-                        let lam_ptr = Lam::alloc();
-                        app_ptr.app().e1().write(lam_ptr);
-                        lam_ptr.lam().x().write(Tagged::new_unused_var());
-                        var_binders
-                            .entry(*x)
-                            .or_default()
-                            .push(lam_ptr.lam_bound_var());
-                        stack.push(Task::PopVarBinder(*x));
-                        stack.push(Task::Recurse(lam_ptr.lam().e(), e2));
-                        stack.push(Task::Recurse(app_ptr.app().e2(), e1));
+        let stack = &mut vec![Task::Recurse(storage_ptr, term)];
+        while let Some(task) = stack.pop() {
+            match task {
+                Task::PushVarBinder(x, binder) => {
+                    var_binders.entry(x).or_default().push(binder);
+                }
+                Task::PopVarBinder(x) => {
+                    var_binders.entry(x).or_default().pop().unwrap();
+                }
+                Task::Recurse(storage_ptr, Term::Var(x)) => {
+                    let binder = var_binders
+                        .get(x)
+                        .and_then(|binders| binders.last().copied())
+                        .or_else(|| env.get_mut(x).and_then(Vec::pop));
+                    if let Some(binder) = binder {
+                        let binder_raw_ptr = match binder.tag() {
+                            Tag::LamBoundVar => binder.lam().x(),
+                            Tag::DupABoundVar => binder.dup().a(),
+                            Tag::DupBBoundVar => binder.dup().b(),
+                            _ => unreachable!("{:?}", binder.tag()),
+                        };
+                        assert_eq!(binder_raw_ptr.read(), Tagged::new_unused_var());
+                        binder_raw_ptr.write(Tagged::new(storage_ptr as *mut (), Tag::VarUsePtr));
+                        storage_ptr.write(binder);
+                    } else {
+                        storage_ptr.write(Tagged::new_free_var(*x));
                     }
                 }
-            }
-            // garbage collect unreachable dup's
-            for dup_ptr in dup_ptrs.iter().copied() {
-                if dup_ptr.dup().a().read().tag() == Tag::UnusedVar
-                    && dup_ptr.dup().b().read().tag() == Tag::UnusedVar
-                {
-                    dup_ptr.dup().e().read().garbage_collect();
-                    dup_ptr.dealloc_dup();
+                Task::Recurse(storage_ptr, Term::Ref(name)) => {
+                    let def = heap
+                        .book
+                        .def(*name)
+                        .unwrap_or_else(|| panic!("reference to undefined definition {}", name));
+                    storage_ptr.write(Tagged::new(def as *const Def as *mut (), Tag::Ref));
+                }
+                Task::Recurse(storage_ptr, Term::Lam(x, e)) => {
+                    let lam_ptr = Lam::alloc(heap);
+                    lam_ptr.lam().x().write(Tagged::new_unused_var());
+                    storage_ptr.write(lam_ptr);
+                    var_binders
+                        .entry(*x)
+                        .or_default()
+                        .push(lam_ptr.lam_bound_var());
+                    stack.push(Task::PopVarBinder(*x));
+                    stack.push(Task::Recurse(lam_ptr.lam().e(), e));
+                }
+                Task::Recurse(storage_ptr, Term::App(e1, e2)) => {
+                    let app_ptr = App::alloc(heap);
+                    storage_ptr.write(app_ptr);
+                    stack.push(Task::Recurse(app_ptr.app().e2(), e2));
+                    stack.push(Task::Recurse(app_ptr.app().e1(), e1));
                 }
+                Task::Recurse(storage_ptr, Term::Sup(l, e1, e2)) => {
+                    let sup_ptr = Sup::alloc(heap);
+                    storage_ptr.write(sup_ptr);
+                    sup_ptr.sup().l().write(node_label(*l));
+                    stack.push(Task::Recurse(sup_ptr.sup().e2(), e2));
+                    stack.push(Task::Recurse(sup_ptr.sup().e1(), e1));
+                }
+                Task::Recurse(storage_ptr, Term::Num(n)) => {
+                    let num_ptr = Num::alloc(heap);
+                    num_ptr.num().write(Num { n: *n });
+                    storage_ptr.write(num_ptr);
+                }
+                Task::Recurse(storage_ptr, Term::Op2(op, e1, e2)) => {
+                    let op2_ptr = Op2::alloc(heap);
+                    storage_ptr.write(op2_ptr);
+                    op2_ptr.op2().op().write(*op);
+                    stack.push(Task::Recurse(op2_ptr.op2().e2(), e2));
+                    stack.push(Task::Recurse(op2_ptr.op2().e1(), e1));
+                }
+                Task::Recurse(storage_ptr, Term::Dup(l, a, b, e, cont)) => {
+                    let dup_ptr = Dup::alloc(heap);
+                    dup_ptrs.push(dup_ptr);
+                    dup_ptr.dup().l().write(node_label(*l));
+                    assert_ne!(a, b);
+                    dup_ptr.dup().a().write(Tagged::new_unused_var());
+                    dup_ptr.dup().b().write(Tagged::new_unused_var());
+                    var_binders
+                        .entry(*a)
+                        .or_default()
+                        .push(dup_ptr.dup_a_bound_var());
+                    stack.push(Task::PopVarBinder(*a));
+                    var_binders
+                        .entry(*b)
+                        .or_default()
+                        .push(dup_ptr.dup_b_bound_var());
+                    stack.push(Task::PopVarBinder(*b));
+                    stack.push(Task::Recurse(storage_ptr, cont));
+                    stack.push(Task::Recurse(dup_ptr.dup().e(), e));
+                }
+                Task::Recurse(storage_ptr, Term::Let(x, e1, e2)) => {
+                    // let x = e1 in e2 => (λx e2) e1
+                    let app_ptr = App::alloc(heap);
+                    storage_ptr.write(app_ptr);
+                    let lam_ptr = Lam::alloc(heap);
+                    app_ptr.app().e1().write(lam_ptr);
+                    lam_ptr.lam().x().write(Tagged::new_unused_var());
+                    // `x` is only bound in e2, so e1 is built first, and a use
+                    // of `x` in e1 refers to an outer `x`.
+                    stack.push(Task::PopVarBinder(*x));
+                    stack.push(Task::Recurse(lam_ptr.lam().e(), e2));
+                    stack.push(Task::PushVarBinder(*x, lam_ptr.lam_bound_var()));
+                    stack.push(Task::Recurse(app_ptr.app().e2(), e1));
+                }
+            }
+        }
+        // erase unreachable dup's
+        for dup_ptr in dup_ptrs.iter().copied() {
+            if dup_ptr.dup().a().read().tag() == Tag::UnusedVar
+                && dup_ptr.dup().b().read().tag() == Tag::UnusedVar
+            {
+                heap.erasures.push(dup_ptr);
             }
-            TermGraph(root_ptr)
         }
+        apply_erasures(heap);
     }
 }
 
 impl From<&TermGraph> for Term {
+    /// Reads back the term in the graph, which is never larger than the
+    /// graph. Use [`Term::limited`] to print only part of a large result.
     fn from(graph: &TermGraph) -> Self {
+        unsafe { read_back(graph.0) }
+    }
+}
+
+/// Reads back the term stored in `root_slot`.
+///
+/// Every node of the graph has a single parent, and subgraphs are only shared
+/// through dup nodes, so the term is never larger than the graph: each dup is
+/// read back once, as a `dup` binding placed at the innermost subterm
+/// containing both of its variables, rather than by copying the shared
+/// subterm into each use. Applied lambdas are read back as `let` bindings.
+///
+/// A dup variable whose sibling is only used outside of this term (e.g. under
+/// another root of a multi-root graph) is read back as a single-use dup.
+unsafe fn read_back(root_slot: *mut Tagged) -> Term {
+    unsafe {
         enum Task {
             Visit(Tagged),
             BuildVar(Tagged),
             BuildLam(Tagged),
             BuildApp,
-            BuildSup(u64),
-            BuildDup(u64, Tagged, Tagged),
+            BuildSup(u32),
+            BuildOp2(Op),
+            BuildDup(u32, Tagged, Tagged),
             BuildLet(Tagged),
         }
         let unused_var = "_".intern_static();
-        let mut vars_remaining = graph.count_vars();
-        let mut fresh_var = || {
-            let v = vars_remaining;
-            vars_remaining -= 1;
-            format!("v{}", v).intern()
+        let root = root_slot.read();
+        let local_slots: HashSet<*mut Tagged> = NodeIter::new(root)
+            .flat_map(|ptr| ptr.child_slots())
+            .chain([root_slot])
+            .collect();
+        let is_unused = |binder: *mut Tagged| {
+            let x = binder.read();
+            x.tag() != Tag::VarUsePtr || !local_slots.contains(&x.var_use())
         };
+        let mut var_names = VarNames::new(root);
         // vars maps from a (Lam|DupA|DupB)BoundVar to the variable's IStr:
         let mut vars: HashMap<Tagged, IStr> = HashMap::new();
         let mut terms: Vec<Term> = vec![];
         // used to idenity where in `terms`, the double-use Dup's vars are:
         let mut double_use_dups_var_tracker: Vec<HashMap<*mut Dup, usize>> = vec![];
         let mut single_use_dups: HashSet<*mut Dup> = HashSet::new();
+        // The order in which the first variable of each double-use dup was
+        // reached, so that dups that become ready together are placed in an
+        // order that does not depend on their addresses.
+        let mut dup_order: HashMap<*mut Dup, usize> = HashMap::new();
         fn merge_top_two(double_use_dups_var_tracker: &mut Vec<HashMap<*mut Dup, usize>>) {
             let tmp = double_use_dups_var_tracker.pop().unwrap();
             let map = double_use_dups_var_tracker.last_mut().unwrap();
@@ -1228,151 +2467,228 @@ impl From<&TermGraph> for Term {
                 *map.entry(dup).or_insert(0) += count;
             }
         }
-        unsafe {
-            let mut tasks = vec![Task::Visit(graph.0.read())];
-            while let Some(task) = tasks.pop() {
-                match task {
-                    Task::Visit(ptr) => {
-                        match ptr.tag() {
-                            Tag::UnboundVar | Tag::LamBoundVar => {
-                                tasks.push(Task::BuildVar(ptr));
-                            }
-                            Tag::DupABoundVar => {
-                                tasks.push(Task::BuildVar(ptr));
-                                if ptr.dup().b().read().tag() == Tag::UnusedVar {
-                                    single_use_dups.insert(ptr.dup());
-                                }
-                            }
-                            Tag::DupBBoundVar => {
-                                tasks.push(Task::BuildVar(ptr));
-                                if ptr.dup().a().read().tag() == Tag::UnusedVar {
-                                    single_use_dups.insert(ptr.dup());
-                                }
-                            }
-                            Tag::LamPtr => {
-                                tasks.push(Task::BuildLam(ptr.lam_bound_var()));
-                                tasks.push(Task::Visit(ptr.lam().e().read()));
+
+        let mut tasks = vec![Task::Visit(root)];
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Visit(ptr) => {
+                    match ptr.tag() {
+                        Tag::UnboundVar | Tag::LamBoundVar => {
+                            tasks.push(Task::BuildVar(ptr));
+                        }
+                        Tag::Ref => {
+                            terms.push(Term::Ref(ptr.def().name));
+                            double_use_dups_var_tracker.push(HashMap::new());
+                        }
+                        Tag::DupABoundVar => {
+                            tasks.push(Task::BuildVar(ptr));
+                            if is_unused(ptr.dup().b()) {
+                                single_use_dups.insert(ptr.dup());
                             }
-                            Tag::AppPtr => {
-                                let e1 = ptr.app().e1().read();
-                                if e1.tag() == Tag::LamPtr {
-                                    // If e1 is a LamPtr, then build a `Term::Let`.
-                                    // ((λx e1) e2) => (let x = e2; e1)
-                                    tasks.push(Task::BuildLet(e1.lam_bound_var()));
-                                    tasks.push(Task::Visit(ptr.app().e2().read()));
-                                    tasks.push(Task::Visit(e1.lam().e().read()));
-                                } else {
-                                    tasks.push(Task::BuildApp);
-                                    tasks.push(Task::Visit(e1));
-                                    tasks.push(Task::Visit(ptr.app().e2().read()));
-                                }
+                        }
+                        Tag::DupBBoundVar => {
+                            tasks.push(Task::BuildVar(ptr));
+                            if is_unused(ptr.dup().a()) {
+                                single_use_dups.insert(ptr.dup());
                             }
-                            Tag::SupPtr => {
-                                tasks.push(Task::BuildSup(ptr.sup().l().read()));
-                                tasks.push(Task::Visit(ptr.sup().e1().read()));
-                                tasks.push(Task::Visit(ptr.sup().e2().read()));
+                        }
+                        Tag::LamPtr => {
+                            tasks.push(Task::BuildLam(ptr.lam_bound_var()));
+                            tasks.push(Task::Visit(ptr.lam().e().read()));
+                        }
+                        Tag::AppPtr => {
+                            let e1 = ptr.app().e1().read();
+                            if e1.tag() == Tag::LamPtr {
+                                // If e1 is a LamPtr, then build a `Term::Let`.
+                                // ((λx e1) e2) => (let x = e2; e1)
+                                tasks.push(Task::BuildLet(e1.lam_bound_var()));
+                                tasks.push(Task::Visit(ptr.app().e2().read()));
+                                tasks.push(Task::Visit(e1.lam().e().read()));
+                            } else {
+                                tasks.push(Task::BuildApp);
+                                tasks.push(Task::Visit(e1));
+                                tasks.push(Task::Visit(ptr.app().e2().read()));
                             }
-                            _ => unreachable!("{:?}", ptr.tag()),
                         }
+                        Tag::SupPtr => {
+                            tasks.push(Task::BuildSup(ptr.sup().l().read()));
+                            tasks.push(Task::Visit(ptr.sup().e1().read()));
+                            tasks.push(Task::Visit(ptr.sup().e2().read()));
+                        }
+                        Tag::NumPtr => {
+                            terms.push(Term::Num(ptr.num().read().n));
+                            double_use_dups_var_tracker.push(HashMap::new());
+                        }
+                        Tag::Op2Ptr => {
+                            tasks.push(Task::BuildOp2(ptr.op2().op().read()));
+                            tasks.push(Task::Visit(ptr.op2().e1().read()));
+                            tasks.push(Task::Visit(ptr.op2().e2().read()));
+                        }
+                        _ => unreachable!("{:?}", ptr.tag()),
                     }
-                    Task::BuildVar(ptr) => {
-                        match ptr.tag() {
-                            Tag::UnboundVar | Tag::LamBoundVar => {
-                                let v = fresh_var();
-                                vars.insert(ptr, v);
-                                terms.push(Term::Var(v));
-                                double_use_dups_var_tracker.push(HashMap::new());
-                            }
-                            Tag::DupABoundVar | Tag::DupBBoundVar => {
-                                let v = fresh_var();
-                                vars.insert(ptr, v);
-                                terms.push(Term::Var(v));
-                                double_use_dups_var_tracker.push(HashMap::new());
-                                if single_use_dups.remove(&ptr.dup()) {
-                                    tasks.push(Task::BuildDup(
-                                        ptr.dup().l().read(),
-                                        ptr.dup_a_bound_var(),
-                                        ptr.dup_b_bound_var(),
-                                    ));
-                                } else {
-                                    double_use_dups_var_tracker
-                                        .last_mut()
-                                        .unwrap()
-                                        .insert(ptr.dup(), 1);
-                                }
+                }
+                Task::BuildVar(ptr) => {
+                    match ptr.tag() {
+                        Tag::UnboundVar | Tag::LamBoundVar => {
+                            let v = var_names.next(ptr);
+                            vars.insert(ptr, v);
+                            terms.push(Term::Var(v));
+                            double_use_dups_var_tracker.push(HashMap::new());
+                        }
+                        Tag::DupABoundVar | Tag::DupBBoundVar => {
+                            let v = var_names.next(ptr);
+                            vars.insert(ptr, v);
+                            terms.push(Term::Var(v));
+                            double_use_dups_var_tracker.push(HashMap::new());
+                            if single_use_dups.remove(&ptr.dup()) {
+                                tasks.push(Task::BuildDup(
+                                    ptr.dup().l().read(),
+                                    ptr.dup_a_bound_var(),
+                                    ptr.dup_b_bound_var(),
+                                ));
+                                tasks.push(Task::Visit(ptr.dup().e().read()));
+                            } else {
+                                let next = dup_order.len();
+                                dup_order.entry(ptr.dup()).or_insert(next);
+                                double_use_dups_var_tracker
+                                    .last_mut()
+                                    .unwrap()
+                                    .insert(ptr.dup(), 1);
                             }
-                            _ => unreachable!("{:?}", ptr.tag()),
-                        };
-                    }
-                    Task::BuildLam(lam_bound_var) => {
-                        let x = vars.remove(&lam_bound_var).unwrap_or(unused_var);
-                        let e = terms.pop().unwrap();
-                        terms.push(Term::Lam(x, Box::new(e)));
-                        // NOTE: double_use_dups_var_tracker is unaffected
-                    }
-                    Task::BuildApp => {
-                        let e1 = terms.pop().unwrap();
-                        let e2 = terms.pop().unwrap();
-                        terms.push(Term::App(Box::new(e1), Box::new(e2)));
-                        merge_top_two(&mut double_use_dups_var_tracker);
-                    }
-                    Task::BuildSup(l) => {
-                        let e1 = terms.pop().unwrap();
-                        let e2 = terms.pop().unwrap();
-                        terms.push(Term::Sup(l, Box::new(e1), Box::new(e2)));
-                        merge_top_two(&mut double_use_dups_var_tracker);
-                    }
-                    Task::BuildDup(l, dup_a_bound_var, dup_b_bound_var) => {
-                        let a = vars.remove(&dup_a_bound_var).unwrap_or(unused_var);
-                        let b = vars.remove(&dup_b_bound_var).unwrap_or(unused_var);
-                        let e = terms.pop().unwrap();
-                        let cont = terms.pop().unwrap();
-                        terms.push(Term::Dup(l, a, b, Box::new(e), Box::new(cont)));
-                        merge_top_two(&mut double_use_dups_var_tracker);
-                    }
-                    Task::BuildLet(lam_bound_var) => {
-                        // ((λx e1) e2) => (let x = e2; e1)
-                        let x = vars.remove(&lam_bound_var).unwrap_or(unused_var);
-                        let e2 = terms.pop().unwrap();
-                        let e1 = terms.pop().unwrap();
-                        terms.push(Term::Let(x, Box::new(e2), Box::new(e1)));
-                        merge_top_two(&mut double_use_dups_var_tracker);
-                    }
+                        }
+                        _ => unreachable!("{:?}", ptr.tag()),
+                    };
                 }
-                debug_assert_eq!(terms.len(), double_use_dups_var_tracker.len());
-                // check if any double-use dups are ready to be built
-                if let Some(top) = double_use_dups_var_tracker.last_mut() {
-                    let dups_to_build: Vec<*mut Dup> = top
-                        .iter()
-                        .filter_map(|(dup, count)| if *count == 2 { Some(*dup) } else { None })
-                        .collect();
-                    for dup in dups_to_build {
-                        top.remove(&dup);
-                        tasks.push(Task::BuildDup(
-                            dup.l().read(),
-                            Tagged::new(dup as *mut (), Tag::DupABoundVar),
-                            Tagged::new(dup as *mut (), Tag::DupBBoundVar),
-                        ));
-                        tasks.push(Task::Visit(dup.e().read()));
-                        // Note: the top of `terms` already contains the continuation
-                    }
+                Task::BuildLam(lam_bound_var) => {
+                    let x = vars.remove(&lam_bound_var).unwrap_or(unused_var);
+                    let e = terms.pop().unwrap();
+                    terms.push(Term::Lam(x, Box::new(e)));
+                    // NOTE: double_use_dups_var_tracker is unaffected
+                }
+                Task::BuildApp => {
+                    let e1 = terms.pop().unwrap();
+                    let e2 = terms.pop().unwrap();
+                    terms.push(Term::App(Box::new(e1), Box::new(e2)));
+                    merge_top_two(&mut double_use_dups_var_tracker);
+                }
+                Task::BuildSup(l) => {
+                    let e1 = terms.pop().unwrap();
+                    let e2 = terms.pop().unwrap();
+                    terms.push(Term::Sup(l.into(), Box::new(e1), Box::new(e2)));
+                    merge_top_two(&mut double_use_dups_var_tracker);
+                }
+                Task::BuildOp2(op) => {
+                    let e1 = terms.pop().unwrap();
+                    let e2 = terms.pop().unwrap();
+                    terms.push(Term::Op2(op, Box::new(e1), Box::new(e2)));
+                    merge_top_two(&mut double_use_dups_var_tracker);
+                }
+                Task::BuildDup(l, dup_a_bound_var, dup_b_bound_var) => {
+                    let a = vars.remove(&dup_a_bound_var).unwrap_or(unused_var);
+                    let b = vars.remove(&dup_b_bound_var).unwrap_or(unused_var);
+                    let e = terms.pop().unwrap();
+                    let cont = terms.pop().unwrap();
+                    terms.push(Term::Dup(l.into(), a, b, Box::new(e), Box::new(cont)));
+                    merge_top_two(&mut double_use_dups_var_tracker);
+                }
+                Task::BuildLet(lam_bound_var) => {
+                    // ((λx e1) e2) => (let x = e2; e1)
+                    let x = vars.remove(&lam_bound_var).unwrap_or(unused_var);
+                    let e2 = terms.pop().unwrap();
+                    let e1 = terms.pop().unwrap();
+                    terms.push(Term::Let(x, Box::new(e2), Box::new(e1)));
+                    merge_top_two(&mut double_use_dups_var_tracker);
+                }
+            }
+            debug_assert_eq!(terms.len(), double_use_dups_var_tracker.len());
+            // check if any double-use dups are ready to be built
+            if let Some(top) = double_use_dups_var_tracker.last_mut() {
+                let mut dups_to_build: Vec<*mut Dup> = top
+                    .iter()
+                    .filter_map(|(dup, count)| if *count == 2 { Some(*dup) } else { None })
+                    .collect();
+                // Variables are reached right to left, and the first dup in
+                // the list is placed outermost.
+                dups_to_build.sort_by_key(|dup| std::cmp::Reverse(dup_order[dup]));
+                for dup in dups_to_build {
+                    top.remove(&dup);
+                    tasks.push(Task::BuildDup(
+                        dup.l().read(),
+                        Tagged::new(dup as *mut (), Tag::DupABoundVar),
+                        Tagged::new(dup as *mut (), Tag::DupBBoundVar),
+                    ));
+                    tasks.push(Task::Visit(dup.e().read()));
+                    // Note: the top of `terms` already contains the continuation
                 }
             }
         }
+
         assert_eq!(terms.len(), 1);
         terms.pop().unwrap()
     }
 }
 
 impl TermGraph {
+    #[cfg(feature = "rand")]
     pub fn naive_random_order_reduce(&mut self) {
+        self.naive_random_order_reduce_with(&StrategyConfig::default());
+    }
+
+    /// Reduces the first redex of the graph, as chosen by
+    /// [`Strategy::Deterministic`], if there is one.
+    pub fn naive_reduce_step(&mut self) -> Option<Rule> {
+        self.naive_reduce_step_with(&StrategyConfig::default())
+    }
+
+    /// Like [`TermGraph::naive_random_order_reduce`], but only picks among the
+    /// redexes with the highest priority in `config`.
+    #[cfg(feature = "rand")]
+    pub fn naive_random_order_reduce_with(&mut self, config: &StrategyConfig) {
+        self.naive_random_order_reduce_with_source(config, &mut rand::thread_rng());
+    }
+
+    /// Like [`TermGraph::naive_random_order_reduce`], but draws the random
+    /// choices from a generator seeded with `seed`, so that a failing
+    /// reduction can be replayed by passing the same seed again.
+    ///
+    /// The choices made for a seed only stay the same for a given version of
+    /// `rand`.
+    #[cfg(feature = "rand")]
+    pub fn naive_random_order_reduce_with_seed(&mut self, seed: u64) {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(seed);
+        self.naive_random_order_reduce_with_source(&StrategyConfig::default(), &mut rng);
+    }
+
+    /// Like [`TermGraph::naive_random_order_reduce_with`], but draws the
+    /// random choices from `rng`.
+    pub fn naive_random_order_reduce_with_source(
+        &mut self,
+        config: &StrategyConfig,
+        rng: &mut impl RandomSource,
+    ) {
+        let roots = self.root_slots();
         unsafe {
-            naive_random_order_reduce(addr_of_mut!(*self.0));
+            naive_random_order_reduce(&mut self.1, &roots, config, rng);
         }
     }
 
-    pub fn naive_reduce_step(&mut self) -> Option<Rule> {
-        unsafe { naive_reduce_step(addr_of_mut!(*self.0)) }
+    /// Reduces a redex chosen by `rng` among those with the highest priority
+    /// in `config`, if there is one.
+    pub fn naive_random_order_reduce_step_with_source(
+        &mut self,
+        config: &StrategyConfig,
+        rng: &mut impl RandomSource,
+    ) -> Option<Rule> {
+        let roots = self.root_slots();
+        unsafe { naive_random_order_reduce_step(&mut self.1, &roots, config, rng) }
+    }
+
+    /// Like [`TermGraph::naive_reduce_step`], but reduces the first of the
+    /// redexes with the highest priority in `config`, in the order of
+    /// [`Strategy::Deterministic`].
+    pub fn naive_reduce_step_with(&mut self, config: &StrategyConfig) -> Option<Rule> {
+        let roots = self.root_slots();
+        unsafe { naive_reduce_step(&mut self.1, &roots, config) }
     }
 }
 
@@ -1407,11 +2723,240 @@ mod test {
             Box::new(Term::Var("y".into())),
         );
         let term_graph = TermGraph::from(&term);
+        assert!(!format!("{:?}", term_graph).is_empty());
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        let mut heap = Heap::default();
         unsafe {
-            print_graph(*term_graph.0);
+            let app = App::alloc(&mut heap);
+            let primary_tags = [
+                Tag::LamBoundVar,
+                Tag::DupABoundVar,
+                Tag::DupBBoundVar,
+                Tag::LamPtr,
+                Tag::AppPtr,
+                Tag::NumPtr,
+            ];
+            for tag in primary_tags {
+                let tagged = Tagged::new(app.ptr(), tag);
+                assert_eq!((tagged.ptr(), tagged.tag()), (app.ptr(), tag));
+            }
+            let slot = app.app().e2() as *mut ();
+            let tagged = Tagged::new(slot, Tag::VarUsePtr);
+            assert_eq!((tagged.ptr(), tagged.tag()), (slot, Tag::VarUsePtr));
+            let unused = Tagged::new_unused_var();
+            assert_eq!(
+                (unused.ptr(), unused.tag()),
+                (ptr::null_mut(), Tag::UnusedVar)
+            );
+
+            // The other tags are read from the header of what they point to.
+            let sup = Sup::alloc(&mut heap);
+            let dup = Dup::alloc(&mut heap);
+            let op2 = Op2::alloc(&mut heap);
+            for (node, tag) in [(sup, Tag::SupPtr), (dup, Tag::DupPtr), (op2, Tag::Op2Ptr)] {
+                assert_eq!(node.0.addr() & Tag::MASK, Tag::EXT);
+                assert_eq!(node.tag(), tag);
+            }
+            assert_eq!(
+                Tagged::new(dup.ptr(), Tag::DupABoundVar).tag(),
+                Tag::DupABoundVar
+            );
+            let x = Tagged::new_free_var("x".intern());
+            assert_eq!(
+                (x.tag(), x.free_name()),
+                (Tag::UnboundVar, Some("x".intern()))
+            );
+            let nameless = Tagged::new_unbound_var();
+            assert_eq!(
+                (nameless.tag(), nameless.free_name()),
+                (Tag::UnboundVar, None)
+            );
+            app.dealloc_app(&mut heap);
+            sup.dealloc_sup(&mut heap);
+            dup.dealloc_dup(&mut heap);
+            op2.dealloc_op2(&mut heap);
         }
     }
 
+    #[test]
+    fn test_drop_partially_reduced() {
+        let term: Term = "((λx x) λy (y z))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        term_graph.naive_reduce_step();
+        // Strand the pinned `λy` by pointing the root directly at its body,
+        // which still uses its variable.
+        unsafe {
+            let lam_ptr = term_graph.0.read();
+            term_graph.pin(NodeId::from_ptr(lam_ptr.ptr())).unwrap();
+            term_graph.0.write(lam_ptr.lam().e().read());
+        }
+        assert_eq!(term_graph.1.arena.live().count(), 2);
+        assert_eq!(term_graph.node_iter().count(), 1);
+        drop(term_graph);
+    }
+
+    #[test]
+    fn test_free_list_reuse() {
+        let mut heap = Heap::default();
+        unsafe {
+            let lam = Lam::alloc(&mut heap);
+            lam.dealloc_lam(&mut heap);
+            // A node of another type does not take the freed lambda's memory.
+            let app = App::alloc(&mut heap);
+            assert_ne!(app.ptr(), lam.ptr());
+            let reused = Lam::alloc(&mut heap);
+            assert_eq!(reused.ptr(), lam.ptr());
+            assert_eq!(heap.allocations, 3);
+            assert_eq!(heap.freed, 1);
+            reused.dealloc_lam(&mut heap);
+            app.dealloc_app(&mut heap);
+        }
+        // The freed nodes stay in the arena until the heap is dropped.
+        assert_eq!(heap.free_list(Tag::LamPtr).len(), 1);
+        assert_eq!(heap.free_list(Tag::AppPtr).len(), 1);
+    }
+
+    #[test]
+    fn test_ref_unfolds_lazily() {
+        let mut book = Book::new();
+        let main = book
+            .load("def step = λf (f step); def loop = λx (loop x);\n((step λg λx λy y) loop)")
+            .unwrap()
+            .unwrap();
+        let mut term_graph = TermGraph::from_book(Arc::new(book), &main).unwrap();
+        let mut rules = vec![];
+        while let Some(rule) = term_graph.naive_reduce_step() {
+            rules.push(rule);
+        }
+        // `loop` is erased without ever being unfolded.
+        assert_eq!(rules, [Rule::Ref, Rule::AppLam, Rule::AppLam, Rule::AppLam]);
+        assert_eq!(term_graph.stats().rewrites(RuleKind::Ref), 1);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+        assert_eq!(term_graph.gc(), 0);
+    }
+
+    #[test]
+    fn test_ref_read_back() {
+        let mut book = Book::new();
+        let main = book.load("def id = λx x;\nλy (y id)").unwrap().unwrap();
+        let term_graph = TermGraph::from_book(Arc::new(book), &main).unwrap();
+        // A reference in argument position is not unfolded.
+        assert!(term_graph.clone().reduce_in_supersteps().is_empty());
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 (v1 id))");
+    }
+
+    #[test]
+    fn test_dup_ref() {
+        let mut book = Book::new();
+        let main = book
+            .load("def id = λx x;\ndup #0{a b} = id; (a (b z))")
+            .unwrap()
+            .unwrap();
+        let book = Arc::new(book);
+        let mut naive = TermGraph::from_book(book.clone(), &main).unwrap();
+        while naive.naive_reduce_step().is_some() {}
+        let mut supersteps = TermGraph::from_book(book, &main).unwrap();
+        supersteps.reduce_in_supersteps();
+        assert_eq!(format!("{}", Term::from(&naive)), "z");
+        assert_eq!(Term::from(&supersteps), Term::from(&naive));
+    }
+
+    #[test]
+    fn test_ref_errors() {
+        let term = Term::Ref("f".into());
+        assert!(matches!(
+            TermGraph::try_from_term(&term),
+            Err(Error::Build(GraphBuildError::UndefinedRef(_)))
+        ));
+        let book: Book = "def f = λx (g x); def g = λy h;".parse().unwrap();
+        assert!(TermGraph::from_book(Arc::new(book), &term).is_ok());
+        let term = Term::Ref("h".into());
+        let book: Book = "def f = λx x;".parse().unwrap();
+        assert!(matches!(
+            TermGraph::from_book(Arc::new(book), &term),
+            Err(Error::Build(GraphBuildError::UndefinedRef(_)))
+        ));
+    }
+
+    #[test]
+    fn test_op_reduce() {
+        let cases = [
+            ("(+ 2 3)", "5", vec![Rule::OpNum]),
+            ("(- 2 3)", "18446744073709551615", vec![Rule::OpNum]),
+            ("(/ 7 0)", "0", vec![Rule::OpNum]),
+            ("(< 1 (* 2 3))", "1", vec![Rule::OpNum, Rule::OpNum]),
+            (
+                "(* #0{2 3} 4)",
+                "#0{8 12}",
+                vec![Rule::OpSup, Rule::DupNum, Rule::OpNum, Rule::OpNum],
+            ),
+            (
+                "(- 10 #0{1 2})",
+                "#0{9 8}",
+                vec![Rule::OpSup, Rule::OpNum, Rule::OpNum],
+            ),
+            (
+                "dup #0{a b} = 7; (+ a b)",
+                "14",
+                vec![Rule::DupNum, Rule::OpNum],
+            ),
+            ("((λx (+ x 1)) 41)", "42", vec![Rule::AppLam, Rule::OpNum]),
+        ];
+        for (src, expected, expected_rules) in cases {
+            let term: Term = src.parse().unwrap();
+            let mut term_graph = TermGraph::from(&term);
+            let mut rules = vec![];
+            while let Some(rule) = term_graph.naive_reduce_step() {
+                rules.push(rule);
+            }
+            assert_eq!(format!("{}", Term::from(&term_graph)), expected, "{}", src);
+            assert_eq!(rules, expected_rules, "{}", src);
+            assert_eq!(term_graph.gc(), 0, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_op_stuck() {
+        // An operation on a variable is stuck, but its operands still reduce.
+        let term: Term = "λx (+ x ((λy y) 1))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 (+ v1 1))");
+    }
+
+    #[test]
+    fn test_erase_rules() {
+        let term: Term = "((λx y) dup #0{a b} = λz z; #1{a b})".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
+        assert_eq!(term_graph.naive_reduce_step(), None);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "y");
+        let stats = term_graph.stats();
+        for kind in [RuleKind::EraSup, RuleKind::EraDup, RuleKind::EraLam] {
+            assert_eq!(stats.rewrites(kind), 1, "{:?}", kind);
+        }
+        assert_eq!(stats.total_rewrites(), 4);
+        assert_eq!(stats.allocated, stats.freed);
+    }
+
+    #[test]
+    fn test_erase_copy() {
+        // The first copy of `λx x` is erased once the dup has made it.
+        let term: Term = "dup #0{f g} = λx x; #1{((λh w) f) g}".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut rules = vec![];
+        while let Some(rule) = term_graph.naive_reduce_step() {
+            rules.push(rule);
+        }
+        assert_eq!(rules, [Rule::DupLam, Rule::DupSup, Rule::AppLam]);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "#1{w (λv2 v2)}");
+        assert_eq!(term_graph.stats().rewrites(RuleKind::EraLam), 1);
+        assert_eq!(term_graph.gc(), 0);
+    }
+
     #[test]
     fn test_round_trip() {
         let cases = [
@@ -1427,6 +2972,118 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_deep_term_to_graph() {
+        // Deep enough to overflow the stack if construction recursed per node.
+        // Under Miri, which is far slower, only the memory accesses are checked.
+        let depth = if cfg!(miri) { 2_000 } else { 200_000 };
+        let mut term = Term::Var("x".into());
+        for i in 0..depth {
+            term = match i % 3 {
+                0 => Term::Lam("x".into(), Box::new(term)),
+                1 => Term::App(Box::new(term), Box::new(Term::Var("y".into()))),
+                _ => Term::Sup(0, Box::new(Term::Var("z".into())), Box::new(term)),
+            };
+        }
+        let term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.1.arena.live().count(), depth);
+    }
+
+    #[test]
+    fn test_read_back_dup_order() {
+        // Both dups are placed at the same application, in an order that
+        // only depends on the structure of the graph.
+        let term: Term = "λx λy dup #0{a b} = x; dup #1{c d} = y; ((a c) (b d))"
+            .parse()
+            .unwrap();
+        let term_graph = TermGraph::from(&term);
+        let expected = "(λv1 (λv2 (dup #0{v3 v5} = v1; (dup #1{v4 v6} = v2; ((v3 v4) (v5 v6))))))";
+        for _ in 0..8 {
+            assert_eq!(format!("{}", Term::from(&term_graph.clone())), expected);
+        }
+    }
+
+    #[test]
+    fn test_read_back_preserves_sharing() {
+        // (λy dup #k{ak bk} = (... dup #0{a0 b0} = y; (a0 b0) ...); (ak bk)) w
+        // is already normal after one step, but copying each dup's expression
+        // into both of its uses would give a tree with 2^k leaves.
+        let k = 24;
+        let mut body = Term::Var("y".into());
+        for i in 0..k {
+            let a: IStr = format!("a{}", i).into();
+            let b: IStr = format!("b{}", i).into();
+            let pair = Term::App(Box::new(Term::Var(a)), Box::new(Term::Var(b)));
+            body = Term::Dup(i, a, b, Box::new(body), Box::new(pair));
+        }
+        let term = Term::App(
+            Box::new(Term::Lam("y".into(), Box::new(body))),
+            Box::new(Term::Var("w".into())),
+        );
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
+        assert_eq!(term_graph.naive_reduce_step(), None);
+        let read_back = Term::from(&term_graph);
+        assert_eq!(read_back.size(), 4 * k as usize + 1);
+    }
+
+    #[test]
+    fn test_round_trip_single_use_dup() {
+        let term: Term = "dup #0{a b} = (f x); a".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(dup #0{v3 _} = (f x); v3)"
+        );
+    }
+
+    #[test]
+    fn test_free_var_names() {
+        let term: Term = "((λx λy (x (f y))) g)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv3 (g (f v3)))");
+        // Bound variables are renamed away from the free variables.
+        let term: Term = "λx (x v2 v1)".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1_ ((v1_ v2) v1))"
+        );
+        let term: Term = "λx λy (x y v2)".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (λv2_ ((v1 v2_) v2)))"
+        );
+    }
+
+    #[test]
+    fn test_dup_sup_same_self_reference() {
+        // dup #0{a b} = #0{b λy y}; a
+        // ---------------------------- DupSupSame
+        // a <- b
+        // b <- λy y
+        let term: Term = "dup #0{a b} = #0{b λy y}; a".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.naive_reduce_step(), Some(Rule::DupSup));
+        assert_eq!(term_graph.naive_reduce_step(), None);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_single_use_dup_lam_with_used_var() {
+        // dup #0{a b} = λx x; (a λy y)
+        // ----------------------------- DupLam
+        // dup #0{c _} = #0{x1 _}; ((λx1 c) λy y)
+        let term: Term = "dup #0{a b} = λx x; (a λy y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.naive_reduce_step(), Some(Rule::DupLam));
+        term_graph.naive_random_order_reduce();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
     #[test]
     fn test_app_lam_from_term_to_term() {
         // ((λx. x) y)
@@ -1452,7 +3109,7 @@ mod test {
         }
 
         // ((λx. x) y) => (let v2 = v1 in v2)
-        assert_eq!(format!("{}", Term::from(&term_graph)), "(let v2 = v1; v2)");
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(let v2 = y; v2)");
     }
 
     #[test]
@@ -1485,7 +3142,7 @@ mod test {
         }
 
         // (#0{x0 x1} y) => (#0{v1 v2} v3)
-        assert_eq!(format!("{}", Term::from(&term_graph)), "(#0{v1 v2} v3)");
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(#0{x0 x1} y)");
     }
 
     #[test]
@@ -1527,7 +3184,7 @@ mod test {
             assert_eq!(dup.l, 0);
             assert_eq!(dup.a, sup_ptr.sup_e1_var_use_ptr());
             assert_eq!(dup.b, sup_ptr.sup_e2_var_use_ptr());
-            assert_eq!(dup.e, Tagged::new_unbound_var());
+            assert_eq!(dup.e, Tagged::new_free_var("v1".into()));
         }
 
         assert_eq!(
@@ -1635,7 +3292,7 @@ mod test {
             assert_eq!(dup_v3_v4.l, 1);
             assert_eq!(dup_v3_v4.a, sup_v3_v4_ptr.sup_e1_var_use_ptr());
             assert_eq!(dup_v3_v4.b, sup_v3_v4_ptr.sup_e2_var_use_ptr());
-            assert_eq!(dup_v3_v4.e, Tagged::new_unbound_var());
+            assert_eq!(dup_v3_v4.e, Tagged::new_free_var("v1".into()));
         }
 
         assert_eq!(
@@ -1732,12 +3389,12 @@ mod test {
             let app = app_ptr.app_read();
             let lam = lam_ptr.lam_read();
             assert_eq!(app.e1, lam_ptr);
-            assert_eq!(app.e2, Tagged::new_unbound_var());
+            assert_eq!(app.e2, Tagged::new_free_var("y".into()));
             assert_eq!(lam.x, lam_ptr.lam_e_var_use_ptr());
             assert_eq!(lam.e, lam_ptr.lam_bound_var());
         }
 
-        assert_eq!(format!("{}", Term::from(&term_graph)), "(let v2 = v1; v2)");
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(let v2 = y; v2)");
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
         println!("After:\n{:?}", term_graph);
@@ -1749,9 +3406,10 @@ mod test {
             assert_eq!(nodes.len(), 0);
         }
 
-        assert_eq!(format!("{}", Term::from(&term_graph)), "v1");
+        assert_eq!(format!("{}", Term::from(&term_graph)), "y");
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_app_lam_var_reduce() {
         // ((λx. x) y)
@@ -1763,7 +3421,7 @@ mod test {
         );
         let mut term_graph = TermGraph::from(&term);
 
-        assert_eq!(format!("{}", Term::from(&term_graph)), "(let v2 = v1; v2)");
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(let v2 = y; v2)");
 
         println!("Before:\n{:?}", term_graph);
         term_graph.naive_random_order_reduce();
@@ -1775,9 +3433,10 @@ mod test {
             assert_eq!(nodes.len(), 0);
         }
 
-        assert_eq!(format!("{}", Term::from(&term_graph)), "v1");
+        assert_eq!(format!("{}", Term::from(&term_graph)), "y");
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_lam_app_lam_reduce() {
         // λy ((λx x) y)
@@ -1817,6 +3476,7 @@ mod test {
         assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_app_sup_reduce() {
         // #0{x0 x1} y
@@ -1856,17 +3516,18 @@ mod test {
             assert_eq!(sup.l, 0);
             assert_eq!(sup.e1, app_x0_y0_ptr);
             assert_eq!(sup.e2, app_x1_y1_ptr);
-            assert_eq!(app_x0_y0.e1, Tagged::new_unbound_var());
+            assert_eq!(app_x0_y0.e1, Tagged::new_free_var("x0".into()));
             assert_eq!(app_x0_y0.e2, dup_ptr.dup_a_bound_var());
-            assert_eq!(app_x1_y1.e1, Tagged::new_unbound_var());
+            assert_eq!(app_x1_y1.e1, Tagged::new_free_var("x1".into()));
             assert_eq!(app_x1_y1.e2, dup_ptr.dup_b_bound_var());
             assert_eq!(dup.l, 0);
             assert_eq!(dup.a, app_x0_y0_ptr.app_e2_var_use_ptr());
             assert_eq!(dup.b, app_x1_y1_ptr.app_e2_var_use_ptr());
-            assert_eq!(dup.e, Tagged::new_unbound_var());
+            assert_eq!(dup.e, Tagged::new_free_var("y".into()));
         }
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_lam_lam_lam_app_sup_reduce() {
         // λx λy λz #0{x y} z
@@ -1940,6 +3601,7 @@ mod test {
         }
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_app_dup_app_sup_reduce() {
         // dup #0{v1 v2} = v0
@@ -1997,14 +3659,15 @@ mod test {
             assert_eq!(dup_v1_v2.l, 0);
             assert_eq!(dup_v1_v2.a, app_v1_v4_ptr.app_e1_var_use_ptr());
             assert_eq!(dup_v1_v2.b, app_v2_v5_ptr.app_e1_var_use_ptr());
-            assert_eq!(dup_v1_v2.e, Tagged::new_unbound_var());
+            assert_eq!(dup_v1_v2.e, Tagged::new_free_var("v0".into()));
             assert_eq!(dup_v4_v5.l, 0);
             assert_eq!(dup_v4_v5.a, app_v1_v4_ptr.app_e2_var_use_ptr());
             assert_eq!(dup_v4_v5.b, app_v2_v5_ptr.app_e2_var_use_ptr());
-            assert_eq!(dup_v4_v5.e, Tagged::new_unbound_var());
+            assert_eq!(dup_v4_v5.e, Tagged::new_free_var("v3".into()));
         }
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_dup_lam_lam_sup() {
         // dup #0{a b} = (λx y)
@@ -2210,6 +3873,7 @@ mod test {
         }
     }
 
+    #[cfg(feature = "rand")]
     #[test]
     fn test_lam_lam_dup_sup_same() {
         // dup #0{a b} = #0{x y}
@@ -2582,11 +4246,11 @@ mod test {
             let lam = lam_ptr.lam_read();
             let app_b_c = app_b_c_ptr.app_read();
             assert_eq!(app_lam_d.e1, lam_ptr);
-            assert_eq!(app_lam_d.e2, Tagged::new_unbound_var());
+            assert_eq!(app_lam_d.e2, Tagged::new_free_var("d".into()));
             assert_eq!(lam.x, Tagged::new_unused_var());
             assert_eq!(lam.e, app_b_c_ptr);
-            assert_eq!(app_b_c.e1, Tagged::new_unbound_var());
-            assert_eq!(app_b_c.e2, Tagged::new_unbound_var());
+            assert_eq!(app_b_c.e1, Tagged::new_free_var("b".into()));
+            assert_eq!(app_b_c.e2, Tagged::new_free_var("c".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
@@ -2600,8 +4264,8 @@ mod test {
             assert_eq!(nodes[0].tag(), Tag::AppPtr);
             let app_ptr = nodes[0];
             let app = app_ptr.app_read();
-            assert_eq!(app.e1, Tagged::new_unbound_var());
-            assert_eq!(app.e2, Tagged::new_unbound_var());
+            assert_eq!(app.e1, Tagged::new_free_var("b".into()));
+            assert_eq!(app.e2, Tagged::new_free_var("c".into()));
         }
     }
 
@@ -2650,7 +4314,7 @@ mod test {
             assert_eq!(lam_y.x, lam_x_ptr.lam_e_var_use_ptr());
             assert_eq!(lam_y.e, sup_ptr);
             assert_eq!(sup.l, 0);
-            assert_eq!(sup.e1, Tagged::new_unbound_var());
+            assert_eq!(sup.e1, Tagged::new_free_var("z".into()));
             assert_eq!(sup.e2, dup_ptr.dup_b_bound_var());
             assert_eq!(dup.l, 0);
             assert_eq!(dup.a, Tagged::new_unused_var());
@@ -2684,7 +4348,7 @@ mod test {
             assert_eq!(lam_y.x, dup_ptr.dup_e_var_use_ptr());
             assert_eq!(lam_y.e, sup_ptr);
             assert_eq!(sup.l, 0);
-            assert_eq!(sup.e1, Tagged::new_unbound_var());
+            assert_eq!(sup.e1, Tagged::new_free_var("z".into()));
             assert_eq!(sup.e2, lam_x2_ptr);
             assert_eq!(lam_x2.x, Tagged::new_unused_var());
             assert_eq!(lam_x2.e, dup_ptr.dup_b_bound_var());
@@ -2741,7 +4405,7 @@ mod test {
             assert_eq!(lam_y.e, sup_ptr);
             assert_eq!(sup.l, 0);
             assert_eq!(sup.e1, dup_ptr.dup_a_bound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e2, Tagged::new_free_var("z".into()));
             assert_eq!(dup.l, 0);
             assert_eq!(dup.a, sup_ptr.sup_e1_var_use_ptr());
             assert_eq!(dup.b, Tagged::new_unused_var());
@@ -2775,7 +4439,7 @@ mod test {
             assert_eq!(lam_y.e, sup_ptr);
             assert_eq!(sup.l, 0);
             assert_eq!(sup.e1, lam_x1_ptr);
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e2, Tagged::new_free_var("z".into()));
             assert_eq!(lam_x1.x, Tagged::new_unused_var());
             assert_eq!(lam_x1.e, dup_ptr.dup_a_bound_var());
             assert_eq!(dup.l, 0);
@@ -2831,7 +4495,7 @@ mod test {
             assert_eq!(dup.e, sup_ptr);
             assert_eq!(sup.l, 0);
             assert_eq!(sup.e1, lam_x_ptr.lam_bound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e2, Tagged::new_free_var("y".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::DupSup));
@@ -2846,7 +4510,7 @@ mod test {
             let lam_x_ptr = nodes[0];
             let lam_x = lam_x_ptr.lam_read();
             assert_eq!(lam_x.x, Tagged::new_unused_var());
-            assert_eq!(lam_x.e, Tagged::new_unbound_var());
+            assert_eq!(lam_x.e, Tagged::new_free_var("y".into()));
         }
     }
 
@@ -2896,7 +4560,7 @@ mod test {
             assert_eq!(dup.e, sup_ptr);
             assert_eq!(sup.l, 0);
             assert_eq!(sup.e1, lam_x_ptr.lam_bound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e2, Tagged::new_free_var("y".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::DupSup));
@@ -2963,7 +4627,7 @@ mod test {
             assert_eq!(dup.e, sup_ptr);
             assert_eq!(sup.l, 1);
             assert_eq!(sup.e1, lam_x_ptr.lam_bound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e2, Tagged::new_free_var("y".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::DupSup));
@@ -3000,7 +4664,7 @@ mod test {
             assert_eq!(dup_ay_by.l, 0);
             assert_eq!(dup_ay_by.a, Tagged::new_unused_var());
             assert_eq!(dup_ay_by.b, sup_ptr.sup_e2_var_use_ptr());
-            assert_eq!(dup_ay_by.e, Tagged::new_unbound_var());
+            assert_eq!(dup_ay_by.e, Tagged::new_free_var("y".into()));
         }
     }
 
@@ -3052,7 +4716,7 @@ mod test {
             assert_eq!(dup.e, sup_ptr);
             assert_eq!(sup.l, 1);
             assert_eq!(sup.e1, lam_x_ptr.lam_bound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e2, Tagged::new_free_var("y".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::DupSup));
@@ -3089,12 +4753,12 @@ mod test {
             assert_eq!(dup_ay_by.l, 0);
             assert_eq!(dup_ay_by.a, sup_ptr.sup_e2_var_use_ptr());
             assert_eq!(dup_ay_by.b, Tagged::new_unused_var());
-            assert_eq!(dup_ay_by.e, Tagged::new_unbound_var());
+            assert_eq!(dup_ay_by.e, Tagged::new_free_var("y".into()));
         }
     }
 
     #[test]
-    fn test_garbage_collect_dup_a_bound_var() {
+    fn test_erase_dup_a_bound_var() {
         // dup #0{a b} = z
         // (λx y) a
         // --------------- AppLam
@@ -3130,11 +4794,11 @@ mod test {
             assert_eq!(app.e1, lam_ptr);
             assert_eq!(app.e2, dup_ptr.dup_a_bound_var());
             assert_eq!(lam.x, Tagged::new_unused_var());
-            assert_eq!(lam.e, Tagged::new_unbound_var());
+            assert_eq!(lam.e, Tagged::new_free_var("y".into()));
             assert_eq!(dup.l, 0);
             assert_eq!(dup.a, app_ptr.app_e2_var_use_ptr());
             assert_eq!(dup.b, Tagged::new_unused_var());
-            assert_eq!(dup.e, Tagged::new_unbound_var());
+            assert_eq!(dup.e, Tagged::new_free_var("z".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
@@ -3149,7 +4813,7 @@ mod test {
     }
 
     #[test]
-    fn test_garbage_collect_dup_b_bound_var() {
+    fn test_erase_dup_b_bound_var() {
         // dup #0{a b} = z
         // (λx y) b
         // --------------- AppLam
@@ -3185,11 +4849,11 @@ mod test {
             assert_eq!(app.e1, lam_ptr);
             assert_eq!(app.e2, dup_ptr.dup_b_bound_var());
             assert_eq!(lam.x, Tagged::new_unused_var());
-            assert_eq!(lam.e, Tagged::new_unbound_var());
+            assert_eq!(lam.e, Tagged::new_free_var("y".into()));
             assert_eq!(dup.l, 0);
             assert_eq!(dup.a, Tagged::new_unused_var());
             assert_eq!(dup.b, app_ptr.app_e2_var_use_ptr());
-            assert_eq!(dup.e, Tagged::new_unbound_var());
+            assert_eq!(dup.e, Tagged::new_free_var("z".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
@@ -3204,7 +4868,7 @@ mod test {
     }
 
     #[test]
-    fn test_garbage_collect() {
+    fn test_erase() {
         // dup #0{a b} = (λz #0{c d})
         // (λx y) (a b)
         // -------------------------- AppLam
@@ -3260,7 +4924,7 @@ mod test {
             assert_eq!(app.e1, lam_x_ptr);
             assert_eq!(app.e2, app_a_b_ptr);
             assert_eq!(lam_x.x, Tagged::new_unused_var());
-            assert_eq!(lam_x.e, Tagged::new_unbound_var());
+            assert_eq!(lam_x.e, Tagged::new_free_var("y".into()));
             assert_eq!(app_a_b.e1, dup_ptr.dup_a_bound_var());
             assert_eq!(app_a_b.e2, dup_ptr.dup_b_bound_var());
             assert_eq!(dup.l, 0);
@@ -3270,8 +4934,8 @@ mod test {
             assert_eq!(lam_z.x, Tagged::new_unused_var());
             assert_eq!(lam_z.e, sup_ptr);
             assert_eq!(sup.l, 0);
-            assert_eq!(sup.e1, Tagged::new_unbound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e1, Tagged::new_free_var("c".into()));
+            assert_eq!(sup.e2, Tagged::new_free_var("d".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
@@ -3286,7 +4950,7 @@ mod test {
     }
 
     #[test]
-    fn test_garbage_collect2() {
+    fn test_erase2() {
         // dup #0{a b} = (λz #0{c d})
         // (λx y) (b a)
         // -------------------------- AppLam
@@ -3342,7 +5006,7 @@ mod test {
             assert_eq!(app.e1, lam_x_ptr);
             assert_eq!(app.e2, app_a_b_ptr);
             assert_eq!(lam_x.x, Tagged::new_unused_var());
-            assert_eq!(lam_x.e, Tagged::new_unbound_var());
+            assert_eq!(lam_x.e, Tagged::new_free_var("y".into()));
             assert_eq!(app_a_b.e1, dup_ptr.dup_b_bound_var());
             assert_eq!(app_a_b.e2, dup_ptr.dup_a_bound_var());
             assert_eq!(dup.l, 0);
@@ -3352,8 +5016,8 @@ mod test {
             assert_eq!(lam_z.x, Tagged::new_unused_var());
             assert_eq!(lam_z.e, sup_ptr);
             assert_eq!(sup.l, 0);
-            assert_eq!(sup.e1, Tagged::new_unbound_var());
-            assert_eq!(sup.e2, Tagged::new_unbound_var());
+            assert_eq!(sup.e1, Tagged::new_free_var("c".into()));
+            assert_eq!(sup.e2, Tagged::new_free_var("d".into()));
         }
 
         assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
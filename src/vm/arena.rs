@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::mem::{size_of, MaybeUninit};
+
+use super::{type_index, App, Dup, Lam, Num, Op2, Sup, Tag, Tagged};
+
+/// Memory as large and as aligned as the smallest node. Every node takes a
+/// whole number of blocks, so a chunk of blocks keeps every node aligned.
+#[derive(Clone, Copy)]
+#[repr(C, align(8))]
+struct Block([u8; Tag::NODE_ALIGN]);
+const _: () = assert!(size_of::<Block>() == Tag::NODE_ALIGN);
+
+/// The number of nodes in the first chunk of a slab. Each later chunk has
+/// room for twice as many as the one before, up to `MAX_CHUNK_NODES`.
+const MIN_CHUNK_NODES: usize = 32;
+const MAX_CHUNK_NODES: usize = 1 << 16;
+
+/// A chunk of a [`Slab`].
+struct Chunk {
+    /// Owns the memory of the chunk. Its length stays 0, so it never grows,
+    /// and the nodes never move.
+    _memory: Vec<MaybeUninit<Block>>,
+    /// The start of `memory`, which every pointer into the chunk is derived
+    /// from.
+    start: *mut Block,
+    /// The number of nodes the chunk has room for.
+    capacity: usize,
+    /// The number of nodes handed out from the start of the chunk.
+    used: usize,
+}
+
+/// The memory of the nodes of one type.
+///
+/// Nodes are handed out in order from `Vec`-backed chunks, which are only
+/// freed with the slab, so a node never moves, and dropping the slab frees
+/// every node at once. A freed node is kept for reuse by a later allocation.
+pub(super) struct Slab {
+    /// The number of blocks each node takes.
+    blocks: usize,
+    chunks: Vec<Chunk>,
+    /// The nodes freed since they were handed out, most recently freed last.
+    pub(super) free: Vec<*mut ()>,
+}
+
+impl Slab {
+    fn new(node_size: usize) -> Self {
+        debug_assert_eq!(node_size % size_of::<Block>(), 0);
+        Slab {
+            blocks: node_size / size_of::<Block>(),
+            chunks: vec![],
+            free: vec![],
+        }
+    }
+
+    /// Returns an uninitialized node, reusing the most recently freed node if
+    /// there is one.
+    #[inline(always)]
+    pub(super) fn alloc(&mut self) -> *mut () {
+        if let Some(ptr) = self.free.pop() {
+            return ptr;
+        }
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.used < chunk.capacity => {
+                let ptr = unsafe { chunk.start.add(chunk.used * self.blocks) };
+                chunk.used += 1;
+                ptr as *mut ()
+            }
+            _ => self.alloc_in_new_chunk(),
+        }
+    }
+
+    #[cold]
+    fn alloc_in_new_chunk(&mut self) -> *mut () {
+        let capacity = match self.chunks.last() {
+            Some(chunk) => (chunk.capacity * 2).min(MAX_CHUNK_NODES),
+            None => MIN_CHUNK_NODES,
+        };
+        let mut memory = Vec::with_capacity(capacity * self.blocks);
+        let start = memory.as_mut_ptr() as *mut Block;
+        self.chunks.push(Chunk {
+            _memory: memory,
+            start,
+            capacity,
+            used: 1,
+        });
+        start as *mut ()
+    }
+
+    /// Returns every node handed out, whether or not it has been freed since.
+    fn handed_out(&self) -> impl Iterator<Item = *mut ()> + '_ {
+        self.chunks.iter().flat_map(move |chunk| {
+            (0..chunk.used).map(move |i| unsafe { chunk.start.add(i * self.blocks) } as *mut ())
+        })
+    }
+
+    /// Returns every live node: those handed out and not freed since.
+    fn live(&self) -> impl Iterator<Item = *mut ()> + '_ {
+        let free: HashSet<*mut ()> = self.free.iter().copied().collect();
+        self.handed_out().filter(move |ptr| !free.contains(ptr))
+    }
+
+    /// Returns the node handed out at address `addr`, if there is one.
+    ///
+    /// This takes time linear in the number of chunks, which only grows
+    /// logarithmically with the number of nodes.
+    fn find(&self, addr: usize) -> Option<*mut ()> {
+        let node_size = self.blocks * size_of::<Block>();
+        self.chunks.iter().find_map(|chunk| {
+            let offset = addr.checked_sub(chunk.start.addr())?;
+            (offset < chunk.used * node_size && offset % node_size == 0)
+                .then(|| chunk.start.with_addr(addr) as *mut ())
+        })
+    }
+
+    /// Takes over the chunks and freed nodes of `other`.
+    ///
+    /// The room left in the chunks of `other` is added to the freed nodes,
+    /// since nodes are only handed out in order from the last chunk.
+    fn absorb(&mut self, mut other: Slab) {
+        debug_assert_eq!(self.blocks, other.blocks);
+        self.free.append(&mut other.free);
+        for chunk in other.chunks.iter_mut() {
+            let room = (chunk.used..chunk.capacity)
+                .map(|i| unsafe { chunk.start.add(i * self.blocks) } as *mut ());
+            self.free.extend(room);
+            chunk.used = chunk.capacity;
+        }
+        let last = self.chunks.len().saturating_sub(1);
+        self.chunks.splice(last..last, other.chunks);
+    }
+}
+
+/// The tags of pointers to the nodes of each slab of an [`Arena`].
+const NODE_TAGS: [Tag; 6] = [
+    Tag::LamPtr,
+    Tag::AppPtr,
+    Tag::SupPtr,
+    Tag::DupPtr,
+    Tag::NumPtr,
+    Tag::Op2Ptr,
+];
+
+/// The memory of the nodes of a [`Heap`](super::Heap): a [`Slab`] for each
+/// type of node.
+///
+/// A node is live from when it is handed out until it is freed, which is
+/// all a slab needs to know, so allocating and freeing a node does no
+/// bookkeeping beyond the free list.
+///
+/// The arena only owns the memory: nodes and slots are still addressed by
+/// [`Tagged`] pointers, not by indices into the slabs, so a node is as large
+/// as its fields of 8-byte pointers.
+pub(super) struct Arena {
+    /// The slabs, in the order of [`type_index`].
+    slabs: [Slab; 6],
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            slabs: [
+                Slab::new(size_of::<Lam>()),
+                Slab::new(size_of::<App>()),
+                Slab::new(size_of::<Sup>()),
+                Slab::new(size_of::<Dup>()),
+                Slab::new(size_of::<Num>()),
+                Slab::new(size_of::<Op2>()),
+            ],
+        }
+    }
+}
+
+impl Arena {
+    /// Returns the slab for nodes of the type pointed to by `tag`.
+    #[inline(always)]
+    pub(super) fn slab(&mut self, tag: Tag) -> &mut Slab {
+        &mut self.slabs[type_index(tag)]
+    }
+
+    /// Returns whether the node pointed to by `node` was handed out by this
+    /// arena, whether or not it has been freed since.
+    pub(super) fn owns(&self, node: Tagged) -> bool {
+        let tag = unsafe { node.tag() };
+        self.slabs[type_index(tag)]
+            .find(node.ptr().addr())
+            .is_some()
+    }
+
+    /// Returns whether the node pointed to by `node` is live.
+    ///
+    /// This takes time linear in the number of freed nodes of its type, so it
+    /// is meant for occasional checks rather than for rewrites.
+    pub(super) fn is_live(&self, node: Tagged) -> bool {
+        let slab = &self.slabs[type_index(unsafe { node.tag() })];
+        slab.find(node.ptr().addr()).is_some() && !slab.free.contains(&node.ptr())
+    }
+
+    /// Returns a pointer to the live node at address `addr` of the type
+    /// pointed to by `tag`, if there is one.
+    pub(super) fn find_live(&self, tag: Tag, addr: usize) -> Option<Tagged> {
+        let ptr = self.slabs[type_index(tag)].find(addr)?;
+        let node = unsafe { Tagged::new(ptr, tag) };
+        self.is_live(node).then_some(node)
+    }
+
+    /// Returns a pointer to every live node.
+    pub(super) fn live(&self) -> impl Iterator<Item = Tagged> + '_ {
+        self.slabs
+            .iter()
+            .zip(NODE_TAGS)
+            .flat_map(|(slab, tag)| slab.live().map(move |ptr| unsafe { Tagged::new(ptr, tag) }))
+    }
+
+    /// Takes over the nodes of `other`, such as the arena of a worker thread.
+    pub(super) fn absorb(&mut self, other: Arena) {
+        for (slab, other_slab) in self.slabs.iter_mut().zip(other.slabs) {
+            slab.absorb(other_slab);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slab_chunks() {
+        let mut slab = Slab::new(size_of::<Dup>());
+        let nodes: Vec<*mut ()> = (0..MIN_CHUNK_NODES * 3 + 1).map(|_| slab.alloc()).collect();
+        // Chunks of 32, 64, and 128 nodes, each used in order.
+        let capacities: Vec<usize> = slab.chunks.iter().map(|chunk| chunk.capacity).collect();
+        assert_eq!(capacities, [32, 64, 128]);
+        for (i, node) in nodes.iter().enumerate().take(MIN_CHUNK_NODES) {
+            assert_eq!(node.addr(), nodes[0].addr() + i * size_of::<Dup>());
+        }
+        assert!(nodes.iter().all(|node| node.addr() % Tag::NODE_ALIGN == 0));
+
+        slab.free.push(nodes[1]);
+        assert_eq!(slab.alloc(), nodes[1]);
+        assert_eq!(slab.chunks[2].used, 1);
+    }
+
+    #[test]
+    fn test_arena_live() {
+        let mut arena = Arena::default();
+        unsafe {
+            let lam = Tagged::new(arena.slab(Tag::LamPtr).alloc(), Tag::LamPtr);
+            let app = Tagged::new(arena.slab(Tag::AppPtr).alloc(), Tag::AppPtr);
+            assert!(arena.is_live(lam) && arena.is_live(app));
+            assert_eq!(arena.find_live(Tag::LamPtr, lam.ptr().addr()), Some(lam));
+            assert_eq!(arena.find_live(Tag::AppPtr, lam.ptr().addr()), None);
+
+            arena.slab(Tag::LamPtr).free.push(lam.ptr());
+            assert!(arena.owns(lam) && !arena.is_live(lam));
+            assert_eq!(arena.find_live(Tag::LamPtr, lam.ptr().addr()), None);
+            assert_eq!(arena.live().collect::<Vec<_>>(), [app]);
+        }
+    }
+
+    #[test]
+    fn test_slab_absorb() {
+        let mut slab = Slab::new(size_of::<Lam>());
+        let mut other = Slab::new(size_of::<Lam>());
+        let first = slab.alloc();
+        for _ in 0..3 {
+            other.alloc();
+        }
+        slab.absorb(other);
+        // The room left in the absorbed chunk is free, and the next fresh node
+        // still comes from the slab's own chunk.
+        assert_eq!(slab.chunks.len(), 2);
+        assert_eq!(slab.free.len(), MIN_CHUNK_NODES - 3);
+        slab.free.clear();
+        assert_eq!(slab.alloc().addr(), first.addr() + size_of::<Lam>());
+    }
+}
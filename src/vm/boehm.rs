@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use super::hnf::head_redex;
+use super::{
+    reduce_redex, AppPtrExt, DupPtrExt, LamPtrExt, Op2PtrExt, SupPtrExt, Tag, Tagged, TermGraph,
+};
+use crate::error::Error;
+use crate::syntax::{Label, Op, Term};
+
+/// The number of rewrites after which the search for the head normal form of
+/// a subterm is abandoned.
+const HEAD_STEP_LIMIT: usize = 100_000;
+
+/// The result of [`boehm_compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    /// The Böhm trees of the terms agree up to the given depth.
+    Equal,
+    /// The Böhm trees of the terms differ within the given depth, so the terms
+    /// are not equivalent.
+    Different,
+    /// No difference was found, but the head normal form of some subterm
+    /// could not be reached, so the trees could not be compared everywhere.
+    Undecided,
+}
+
+/// Compares the Böhm trees of two closed terms up to `depth` levels.
+///
+/// Rather than normalizing the terms, which may never finish, this reduces
+/// both to head normal form, compares their heads (the number of leading
+/// lambdas, the variable or superposition at the head, and the number of
+/// arguments), and then does the same for each pair of arguments, one level
+/// deeper. So terms that only differ inside a diverging argument compare as
+/// equal, and terms that differ at their heads compare as different even if
+/// both diverge further down.
+///
+/// The search for a head normal form is abandoned after a fixed number of
+/// rewrites, which makes that subterm undecided. Returns an error if a term
+/// is not well formed (see [`TermGraph::try_from_term`]) or has free
+/// variables, which the graph does not tell apart.
+pub fn boehm_compare(a: &Term, b: &Term, depth: usize) -> Result<Comparison, Error> {
+    for term in [a, b] {
+        if let Some(x) = term.free_vars().into_iter().next() {
+            return Err(Error::Graph(format!(
+                "cannot compare terms with a free variable {}",
+                x
+            )));
+        }
+    }
+    let mut a = TermGraph::try_from_term(a)?;
+    let mut b = TermGraph::try_from_term(b)?;
+    let mut result = Comparison::Equal;
+    // Each pair of positions carries the lambdas enclosing it on its side,
+    // outermost first, to number the variables they bind.
+    let mut stack = vec![(a.0, vec![], b.0, vec![], depth)];
+    while let Some((slot_a, mut lams_a, slot_b, mut lams_b, depth)) = stack.pop() {
+        if depth == 0 {
+            continue;
+        }
+        let (Some(head_a), Some(head_b)) =
+            (unsafe { a.head_normal_form(slot_a, &mut lams_a) }, unsafe {
+                b.head_normal_form(slot_b, &mut lams_b)
+            })
+        else {
+            result = Comparison::Undecided;
+            continue;
+        };
+        if head_a.lams != head_b.lams
+            || head_a.head != head_b.head
+            || head_a.args.len() != head_b.args.len()
+        {
+            return Ok(Comparison::Different);
+        }
+        for (arg_a, arg_b) in head_a.args.into_iter().zip(head_b.args).rev() {
+            stack.push((arg_a, lams_a.clone(), arg_b, lams_b.clone(), depth - 1));
+        }
+    }
+    Ok(result)
+}
+
+/// The head of a term in head normal form.
+#[derive(Debug, PartialEq, Eq)]
+enum Head {
+    /// The variable of the lambda with this level, counting the enclosing
+    /// lambdas from the outermost.
+    Var(usize),
+    /// A superposition, whose branches are the arguments.
+    Sup(Label),
+    /// A number, with no arguments.
+    Num(u64),
+    /// An operation that cannot be reduced, whose operands are the
+    /// arguments.
+    Op2(Op),
+    /// A variable whose binder is not in scope, which only appears in
+    /// malformed graphs.
+    Unknown,
+}
+
+/// The shape of a term in head normal form.
+struct HeadNormalForm {
+    /// The number of leading lambdas.
+    lams: usize,
+    head: Head,
+    /// The slots of the arguments of the head, in order.
+    args: Vec<*mut Tagged>,
+}
+
+impl TermGraph {
+    /// Reduces the term in `slot` to head normal form and returns its shape,
+    /// or `None` if that takes too long or the head is a dup variable whose
+    /// expression depends on itself. The leading lambdas are pushed onto
+    /// `lams`.
+    unsafe fn head_normal_form(
+        &mut self,
+        slot: *mut Tagged,
+        lams: &mut Vec<Tagged>,
+    ) -> Option<HeadNormalForm> {
+        unsafe {
+            let mut steps = 0;
+            while let Some(redex) = head_redex(slot, true) {
+                if steps == HEAD_STEP_LIMIT {
+                    return None;
+                }
+                reduce_redex(&mut self.1, redex);
+                steps += 1;
+            }
+            let mut slot = slot;
+            let mut leading = 0;
+            while slot.read().tag() == Tag::LamPtr {
+                lams.push(slot.read());
+                leading += 1;
+                slot = slot.read().lam().e();
+            }
+            // Collects the arguments innermost first, following shared
+            // expressions from dup variables, whose head is the same.
+            let mut args = vec![];
+            let mut dups = HashSet::new();
+            let head = loop {
+                let ptr = slot.read();
+                match ptr.tag() {
+                    Tag::AppPtr => {
+                        args.push(ptr.app().e2());
+                        slot = ptr.app().e1();
+                    }
+                    Tag::DupABoundVar | Tag::DupBBoundVar => {
+                        if !dups.insert(ptr.ptr()) {
+                            return None;
+                        }
+                        slot = ptr.dup().e();
+                    }
+                    Tag::SupPtr => {
+                        args.extend([ptr.sup().e2(), ptr.sup().e1()]);
+                        break Head::Sup(ptr.sup().l().read().into());
+                    }
+                    Tag::NumPtr => break Head::Num(ptr.num().read().n),
+                    Tag::Op2Ptr => {
+                        args.extend([ptr.op2().e2(), ptr.op2().e1()]);
+                        break Head::Op2(ptr.op2().op().read());
+                    }
+                    Tag::LamBoundVar => {
+                        break match lams.iter().rposition(|lam| lam.ptr() == ptr.ptr()) {
+                            Some(level) => Head::Var(level),
+                            None => Head::Unknown,
+                        }
+                    }
+                    _ => break Head::Unknown,
+                }
+            };
+            args.reverse();
+            Some(HeadNormalForm {
+                lams: leading,
+                head,
+                args,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compare(a: &str, b: &str, depth: usize) -> Comparison {
+        boehm_compare(&a.parse().unwrap(), &b.parse().unwrap(), depth).unwrap()
+    }
+
+    /// `Ω = (δ δ)`, with `δ = λx dup #9{a b} = x; (a b)`, which reduces to a
+    /// dup of its own variable, `dup #9{a b} = b; a`, with no head.
+    const OMEGA: &str = "(λd dup #9{a b} = d; (a b) λd dup #9{a b} = d; (a b))";
+
+    #[test]
+    fn test_boehm_compare() {
+        assert_eq!(compare("((λx x) λy y)", "λz z", 3), Comparison::Equal);
+        assert_eq!(compare("λx λy x", "λx λy y", 3), Comparison::Different);
+        assert_eq!(
+            compare("λx λy λz (x y)", "λx λy λz ((x y) z)", 1),
+            Comparison::Different
+        );
+        let church_2 = "λf dup #0{f1 f2} = f; λx (f1 (f2 x))";
+        let church_2_reduced = "λf dup #1{g h} = f; λy (g (h y))";
+        assert_eq!(compare(church_2, church_2_reduced, 5), Comparison::Equal);
+    }
+
+    #[test]
+    fn test_boehm_compare_diverging() {
+        // The heads differ, even though the arguments diverge.
+        let a = format!("λx (x {})", OMEGA);
+        let b = format!("λx λy (x {})", OMEGA);
+        assert_eq!(compare(&a, &b, 1), Comparison::Different);
+        // The diverging arguments are below the compared depth.
+        assert_eq!(compare(&a, &a, 1), Comparison::Equal);
+        assert_eq!(compare(&a, &a, 2), Comparison::Undecided);
+    }
+
+    #[test]
+    fn test_boehm_compare_open_terms() {
+        let x: Term = "x".parse().unwrap();
+        assert!(boehm_compare(&x, &x, 1).is_err());
+    }
+}
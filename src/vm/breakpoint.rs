@@ -0,0 +1,138 @@
+use super::{RedexSite, RuleKind, StrategyConfig, TermGraph};
+use crate::syntax::Label;
+
+/// A condition on the next redex to reduce, at which a reduction pauses.
+///
+/// A redex matches if its dup or its superposition has one of `labels`, and
+/// its kind is one of `kinds` (or `kinds` is empty). For example, a
+/// breakpoint on label 3 with kinds `[DupSupDiff]` pauses at every
+/// commutation of a dup and a superposition where either one has label 3,
+/// which is where a mislabeled dup usually shows itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub labels: Vec<Label>,
+    pub kinds: Vec<RuleKind>,
+}
+
+impl Breakpoint {
+    /// Returns a breakpoint on every redex that involves `label`.
+    pub fn on_label(label: Label) -> Self {
+        Breakpoint {
+            labels: vec![label],
+            kinds: vec![],
+        }
+    }
+
+    /// Returns whether the redex described by `site` matches.
+    ///
+    /// This can also be used with [`TermGraph::reduce_step_chosen_by`], to
+    /// pause a custom strategy.
+    pub fn matches(&self, site: &RedexSite) -> bool {
+        let label_matches = [site.dup_label, site.sup_label]
+            .into_iter()
+            .flatten()
+            .any(|label| self.labels.contains(&label));
+        label_matches && (self.kinds.is_empty() || self.kinds.contains(&site.kind))
+    }
+}
+
+/// The redex at which [`TermGraph::naive_reduce_to_breakpoint`] paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BreakpointHit {
+    /// The number of steps taken before pausing.
+    pub steps: usize,
+    /// The redex that is about to be reduced.
+    pub site: RedexSite,
+}
+
+impl TermGraph {
+    /// Reduces the graph like [`TermGraph::naive_reduce_step_with`] until the
+    /// next redex to reduce matches `breakpoint`, without reducing it, or
+    /// until no redexes remain.
+    ///
+    /// Returns the matching redex, or `None` if the graph reached its normal
+    /// form. The graph can be inspected while paused; to resume, take one
+    /// step with [`TermGraph::naive_reduce_step_with`] (which reduces the
+    /// matching redex) and call this again.
+    pub fn naive_reduce_to_breakpoint(
+        &mut self,
+        config: &StrategyConfig,
+        breakpoint: &Breakpoint,
+    ) -> Option<BreakpointHit> {
+        let mut steps = 0;
+        loop {
+            let mut hit = None;
+            let rule = self.reduce_step_chosen_by(|sites| {
+                let index = config.choose(sites)?;
+                if breakpoint.matches(&sites[index]) {
+                    hit = Some(BreakpointHit {
+                        steps,
+                        site: sites[index],
+                    });
+                    return None;
+                }
+                Some(index)
+            });
+            if rule.is_none() {
+                return hit;
+            }
+            steps += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_break_on_label() {
+        // The dup with label 1 commutes with the superposition of label 0
+        // once, in the first step. Later redexes involve label 0 too, but
+        // none of them is a commutation of a dup and a superposition.
+        let term: Term = "dup #1{a b} = #0{λx x λy y}; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let config = StrategyConfig::default();
+        let breakpoint = Breakpoint {
+            labels: vec![0],
+            kinds: vec![RuleKind::DupSupDiff],
+        };
+        let hit = term_graph
+            .naive_reduce_to_breakpoint(&config, &breakpoint)
+            .unwrap();
+        assert_eq!(hit.steps, 0);
+        assert_eq!(hit.site.kind, RuleKind::DupSupDiff);
+        assert_eq!((hit.site.dup_label, hit.site.sup_label), (Some(1), Some(0)));
+        // Pausing does not reduce the matching redex.
+        assert_eq!(term_graph.redex_sites()[0], hit.site);
+
+        term_graph.naive_reduce_step_with(&config);
+        assert_eq!(
+            term_graph.naive_reduce_to_breakpoint(&config, &breakpoint),
+            None
+        );
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "#0{(λv1 v1) (λv2 v2)}"
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let site = RedexSite {
+            kind: RuleKind::AppSup,
+            depth: 0,
+            binders: 0,
+            dup_label: None,
+            sup_label: Some(3),
+        };
+        assert!(Breakpoint::on_label(3).matches(&site));
+        assert!(!Breakpoint::on_label(4).matches(&site));
+        let breakpoint = Breakpoint {
+            labels: vec![3],
+            kinds: vec![RuleKind::DupSupSame],
+        };
+        assert!(!breakpoint.matches(&site));
+    }
+}
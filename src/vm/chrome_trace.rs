@@ -0,0 +1,105 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::{StepMetrics, StrategyConfig, TermGraph};
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// writing a trace of the reduction to `out` in the Chrome `trace_event`
+    /// JSON format.
+    ///
+    /// Every step becomes a slice named after its rule, and the live node
+    /// counts (see [`StepMetrics`]) become a `nodes` counter, so the trace can
+    /// be opened in `chrome://tracing` or Perfetto. Returns the number of
+    /// steps taken.
+    pub fn naive_reduce_chrome_trace(
+        &mut self,
+        config: &StrategyConfig,
+        out: &mut impl io::Write,
+    ) -> io::Result<usize> {
+        writeln!(out, "{{\"traceEvents\":[")?;
+        write_counter(
+            out,
+            &self.metrics(0, None, self.1.allocations),
+            Duration::ZERO,
+        )?;
+        let start = Instant::now();
+        let mut allocations = self.1.allocations;
+        let mut steps = 0;
+        loop {
+            let step_start = start.elapsed();
+            let rule = match self.naive_reduce_step_with(config) {
+                Some(rule) => rule,
+                None => break,
+            };
+            let step_end = start.elapsed();
+            steps += 1;
+            let metrics = self.metrics(steps, Some(rule), self.1.allocations - allocations);
+            allocations = self.1.allocations;
+            writeln!(out, ",")?;
+            write!(
+                out,
+                "{{\"name\":\"{:?}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1,\
+                 \"args\":{{\"step\":{},\"allocations\":{}}}}}",
+                rule,
+                micros(step_start),
+                micros(step_end - step_start),
+                metrics.step,
+                metrics.allocations,
+            )?;
+            writeln!(out, ",")?;
+            write_counter(out, &metrics, step_end)?;
+        }
+        writeln!(out, "\n]}}")?;
+        Ok(steps)
+    }
+}
+
+fn write_counter(out: &mut impl io::Write, metrics: &StepMetrics, ts: Duration) -> io::Result<()> {
+    write!(
+        out,
+        "{{\"name\":\"nodes\",\"ph\":\"C\",\"ts\":{:.3},\"pid\":1,\"tid\":1,\
+         \"args\":{{\"lams\":{},\"apps\":{},\"sups\":{},\"dups\":{}}}}}",
+        micros(ts),
+        metrics.lams,
+        metrics.apps,
+        metrics.sups,
+        metrics.dups,
+    )
+}
+
+/// Trace event timestamps are in microseconds.
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e6
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_chrome_trace() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut out = vec![];
+        let steps = term_graph
+            .naive_reduce_chrome_trace(&StrategyConfig::default(), &mut out)
+            .unwrap();
+        assert_eq!(steps, 3);
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "{\"traceEvents\":[");
+        assert_eq!(
+            lines[1],
+            "{\"name\":\"nodes\",\"ph\":\"C\",\"ts\":0.000,\"pid\":1,\"tid\":1,\
+             \"args\":{\"lams\":1,\"apps\":1,\"sups\":0,\"dups\":1}},"
+        );
+        assert!(lines[2].starts_with("{\"name\":\"DupLam\",\"ph\":\"X\",\"ts\":"));
+        assert!(lines[2].ends_with("\"args\":{\"step\":1,\"allocations\":4}},"));
+        assert!(lines[7].ends_with("\"args\":{\"lams\":1,\"apps\":0,\"sups\":0,\"dups\":0}}"));
+        assert_eq!(lines[8], "]}");
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use super::{
+    App, Dup, DupPtrExt, Heap, Lam, LamPtrExt, NodeType, Num, Op2, Sup, Tag, Tagged, TermGraph,
+};
+
+impl Tagged {
+    /// Returns the slots holding the variables bound by the node pointed to by
+    /// `self`: the variable of a `Lam`, or the two variables of a `Dup`.
+    unsafe fn binder_slots(self) -> Vec<*mut Tagged> {
+        unsafe {
+            match self.node_type() {
+                Some(NodeType::Lam) => vec![self.lam().x()],
+                Some(NodeType::Dup) => vec![self.dup().a(), self.dup().b()],
+                _ => vec![],
+            }
+        }
+    }
+}
+
+/// Copies every live node of a graph, including any that are no longer
+/// reachable, along with its roots, pins, and counters.
+///
+/// The copy of each node starts as a bitwise copy, so labels carry over, and
+/// then every slot is redirected: pointers to nodes and bound variables point
+/// to the copies of their nodes, and the var-use pointers of binders point to
+/// the copies of their slots.
+impl Clone for TermGraph {
+    fn clone(&self) -> Self {
+        unsafe {
+            let mut heap = Heap::default();
+            let mut nodes: HashMap<*mut (), Tagged> = HashMap::new();
+            let mut slots: HashMap<*mut Tagged, *mut Tagged> = HashMap::new();
+            for node in self.1.arena.live() {
+                let copy = match node.node_type() {
+                    Some(NodeType::Lam) => {
+                        let copy = Lam::alloc(&mut heap);
+                        copy.lam().write(node.lam().read());
+                        copy
+                    }
+                    Some(NodeType::App) => {
+                        let copy = App::alloc(&mut heap);
+                        copy.app().write(node.app().read());
+                        copy
+                    }
+                    Some(NodeType::Sup) => {
+                        let copy = Sup::alloc(&mut heap);
+                        copy.sup().write(node.sup().read());
+                        copy
+                    }
+                    Some(NodeType::Dup) => {
+                        let copy = Dup::alloc(&mut heap);
+                        copy.dup().write(node.dup().read());
+                        copy
+                    }
+                    Some(NodeType::Num) => {
+                        let copy = Num::alloc(&mut heap);
+                        copy.num().write(node.num().read());
+                        copy
+                    }
+                    Some(NodeType::Op2) => {
+                        let copy = Op2::alloc(&mut heap);
+                        copy.op2().write(node.op2().read());
+                        copy
+                    }
+                    None => unreachable!("{:?}", node),
+                };
+                nodes.insert(node.ptr(), copy);
+                let old_slots = node.child_slots().into_iter().chain(node.binder_slots());
+                let new_slots = copy.child_slots().into_iter().chain(copy.binder_slots());
+                slots.extend(old_slots.zip(new_slots));
+            }
+            let mut roots: HashMap<*mut Tagged, *mut Tagged> = HashMap::new();
+            for slot in self.root_slots() {
+                let copy = std::alloc::alloc(std::alloc::Layout::new::<Tagged>()) as *mut Tagged;
+                copy.write(slot.read());
+                roots.insert(slot, copy);
+                slots.insert(slot, copy);
+            }
+
+            let redirect = |ptr: Tagged| match ptr.tag() {
+                Tag::UnusedVar | Tag::UnboundVar | Tag::Ref => ptr,
+                Tag::VarUsePtr => Tagged::new(slots[&ptr.var_use()] as *mut (), Tag::VarUsePtr),
+                tag => Tagged::new(nodes[&ptr.ptr()].ptr(), tag),
+            };
+            for &slot in slots.values() {
+                slot.write(redirect(slot.read()));
+            }
+
+            heap.roots = self
+                .1
+                .roots
+                .iter()
+                .map(|(name, slot)| (*name, roots[slot]))
+                .collect();
+            heap.pins = self
+                .1
+                .pins
+                .iter()
+                .map(|pin| pin.map(|node| nodes[&node.ptr()]))
+                .collect();
+            for (index, pin) in heap.pins.iter().enumerate() {
+                if let Some(node) = pin {
+                    heap.pinned.entry(*node).or_default().push(index);
+                }
+            }
+            heap.allocations = self.1.allocations;
+            heap.freed = self.1.freed;
+            heap.peak_live = self.1.peak_live;
+            heap.rewrites = self.1.rewrites;
+            heap.book = self.1.book.clone();
+            #[cfg(feature = "profiling")]
+            {
+                heap.latency = self.1.latency.clone();
+            }
+            TermGraph(roots[&self.0], heap)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_clone() {
+        let term: Term = "(λf λx dup #0{f1 f2} = f; (f1 (f2 x)) λy dup #1{y1 y2} = y; #2{y1 y2})"
+            .parse()
+            .unwrap();
+        let mut original = TermGraph::from(&term);
+        original.naive_reduce_step();
+        let mut copy = original.clone();
+        assert_eq!(copy.structural_hash(), original.structural_hash());
+        assert_eq!(copy.stats(), original.stats());
+
+        // Reducing the copy leaves the original as it was.
+        let before = original.structural_hash();
+        while copy.naive_reduce_step().is_some() {}
+        assert_eq!(original.structural_hash(), before);
+        while original.naive_reduce_step().is_some() {}
+        assert_eq!(copy.structural_hash(), original.structural_hash());
+    }
+
+    #[test]
+    fn test_clone_roots() {
+        let defs = [("id".into(), "λx x".parse().unwrap())];
+        let roots = [
+            ("a".into(), "(id y)".parse().unwrap()),
+            ("b".into(), "(id z)".parse().unwrap()),
+        ];
+        let mut original = TermGraph::from_roots(&defs, &roots).unwrap();
+        let copy = original.clone();
+        while original.naive_reduce_step().is_some() {}
+        assert_eq!(copy.root_names(), original.root_names());
+        let fresh = TermGraph::from_roots(&defs, &roots).unwrap();
+        assert_eq!(copy.structural_hash(), fresh.structural_hash());
+        assert_ne!(copy.structural_hash(), original.structural_hash());
+    }
+}
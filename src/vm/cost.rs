@@ -0,0 +1,137 @@
+use crate::error::Error;
+
+use super::{next_redex, reduce_redex, RuleKind, StrategyConfig, TermGraph};
+
+/// Per-rule weights used to meter a reduction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostModel {
+    pub app_lam: u64,
+    pub app_sup: u64,
+    pub dup_lam: u64,
+    pub dup_sup_same: u64,
+    pub dup_sup_diff: u64,
+    /// The weight of unfolding a reference to a definition.
+    pub ref_unfold: u64,
+    pub op_num: u64,
+    pub op_sup: u64,
+    pub dup_num: u64,
+    /// The maximum total cost a reduction may accumulate, if any.
+    pub limit: Option<u64>,
+}
+
+impl Default for CostModel {
+    /// Every rule costs 1, with no limit.
+    fn default() -> Self {
+        CostModel {
+            app_lam: 1,
+            app_sup: 1,
+            dup_lam: 1,
+            dup_sup_same: 1,
+            dup_sup_diff: 1,
+            ref_unfold: 1,
+            op_num: 1,
+            op_sup: 1,
+            dup_num: 1,
+            limit: None,
+        }
+    }
+}
+
+impl CostModel {
+    /// Returns the weight of a rewrite of kind `kind`.
+    pub fn weight(&self, kind: RuleKind) -> u64 {
+        match kind {
+            RuleKind::AppLam => self.app_lam,
+            RuleKind::AppSup => self.app_sup,
+            RuleKind::DupLam => self.dup_lam,
+            RuleKind::DupSupSame => self.dup_sup_same,
+            RuleKind::DupSupDiff => self.dup_sup_diff,
+            RuleKind::Ref => self.ref_unfold,
+            RuleKind::OpNum => self.op_num,
+            RuleKind::OpSup => self.op_sup,
+            RuleKind::DupNum => self.dup_num,
+            // Erasures are applied along with the rewrite that discards the
+            // erased term, which is the one metered.
+            RuleKind::EraLam
+            | RuleKind::EraApp
+            | RuleKind::EraSup
+            | RuleKind::EraDup
+            | RuleKind::EraNum
+            | RuleKind::EraOp2 => 0,
+        }
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// and returns the total cost of the rewrites performed.
+    ///
+    /// If the next rewrite would take the total cost above `cost.limit`, it is
+    /// not performed and an error is returned instead. The graph is left in a
+    /// consistent state, so reduction can be resumed later.
+    pub fn naive_reduce_metered(
+        &mut self,
+        config: &StrategyConfig,
+        cost: &CostModel,
+    ) -> Result<u64, Error> {
+        let roots = self.root_slots();
+        let mut total: u64 = 0;
+        unsafe {
+            while let Some(redex) = next_redex(&roots, config) {
+                let next_total = total.saturating_add(cost.weight(redex.kind()));
+                if let Some(limit) = cost.limit {
+                    if next_total > limit {
+                        return Err(Error::CostLimit {
+                            limit,
+                            spent: total,
+                        });
+                    }
+                }
+                reduce_redex(&mut self.1, redex);
+                total = next_total;
+            }
+        }
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_weighted_cost() {
+        // AppLam, then DupLam and DupSupSame.
+        let term: Term = "dup #0{a b} = ((λx x) λy y); #1{a b}".parse().unwrap();
+        let config = StrategyConfig {
+            priorities: vec![RuleKind::AppLam],
+        };
+        let cost = CostModel {
+            app_lam: 10,
+            dup_lam: 3,
+            ..CostModel::default()
+        };
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.naive_reduce_metered(&config, &cost).unwrap(), 14);
+    }
+
+    #[test]
+    fn test_cost_limit() {
+        let term: Term = "((λx x) ((λy y) λz z))".parse().unwrap();
+        let cost = CostModel {
+            limit: Some(1),
+            ..CostModel::default()
+        };
+        let mut term_graph = TermGraph::from(&term);
+        let config = StrategyConfig::default();
+        assert!(matches!(
+            term_graph.naive_reduce_metered(&config, &cost),
+            Err(Error::CostLimit { limit: 1, spent: 1 })
+        ));
+        // Resuming with a fresh budget finishes the reduction.
+        assert_eq!(term_graph.naive_reduce_metered(&config, &cost).unwrap(), 1);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+}
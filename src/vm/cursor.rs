@@ -0,0 +1,306 @@
+use std::marker::PhantomData;
+
+use super::{
+    AppPtrExt, DupPtrExt, LamPtrExt, NodeId, Op2PtrExt, SupPtrExt, Tag, Tagged, TermGraph,
+};
+use crate::intern::IStr;
+use crate::syntax::{Label, Op};
+
+/// The kind of term found at a [`Cursor`] position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// A lambda, e.g. `(λx e)`.
+    Lam,
+    /// An application, e.g. `(e1 e2)`.
+    App,
+    /// A superposition, e.g. `#l{e1 e2}`.
+    Sup,
+    /// A variable bound by a lambda.
+    LamVar,
+    /// The first variable bound by a dup, e.g. `a` in `dup #l{a b} = e;`.
+    DupAVar,
+    /// The second variable bound by a dup, e.g. `b` in `dup #l{a b} = e;`.
+    DupBVar,
+    /// A free (unbound) variable.
+    FreeVar,
+    /// A reference to a definition that has not been unfolded.
+    Ref,
+    /// A number, e.g. `42`.
+    Num,
+    /// A binary operation, e.g. `(+ e1 e2)`.
+    Op2,
+}
+
+/// A read-only cursor over a [`TermGraph`].
+///
+/// A cursor points at a position in the graph (the root, a lambda body, an
+/// application function or argument, a superposition branch, an operand, or
+/// the expression of a dup) and can be moved into the children of the term found
+/// there. Cursors borrow the graph, so it cannot be reduced while they exist.
+#[derive(Clone, Copy)]
+pub struct Cursor<'g> {
+    slot: *mut Tagged,
+    graph: PhantomData<&'g TermGraph>,
+}
+
+impl TermGraph {
+    /// Returns a cursor pointing at the root of the graph.
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor::new(self.0)
+    }
+
+    /// Returns a cursor pointing at the root named `name`, if there is one.
+    pub fn root_cursor(&self, name: IStr) -> Option<Cursor<'_>> {
+        self.root_slot(name).map(Cursor::new)
+    }
+}
+
+impl<'g> Cursor<'g> {
+    pub(super) fn new(slot: *mut Tagged) -> Self {
+        Cursor {
+            slot,
+            graph: PhantomData,
+        }
+    }
+
+    pub(super) fn ptr(&self) -> Tagged {
+        unsafe { self.slot.read() }
+    }
+
+    /// Returns the slot this cursor points at.
+    pub(super) fn slot(&self) -> *mut Tagged {
+        self.slot
+    }
+
+    /// Returns the kind of term at this position.
+    pub fn kind(&self) -> NodeKind {
+        unsafe {
+            match self.ptr().tag() {
+                Tag::LamPtr => NodeKind::Lam,
+                Tag::AppPtr => NodeKind::App,
+                Tag::SupPtr => NodeKind::Sup,
+                Tag::LamBoundVar => NodeKind::LamVar,
+                Tag::DupABoundVar => NodeKind::DupAVar,
+                Tag::DupBBoundVar => NodeKind::DupBVar,
+                Tag::UnboundVar => NodeKind::FreeVar,
+                Tag::Ref => NodeKind::Ref,
+                Tag::NumPtr => NodeKind::Num,
+                Tag::Op2Ptr => NodeKind::Op2,
+                tag => unreachable!("{:?}", tag),
+            }
+        }
+    }
+
+    /// Returns the label of the superposition or dup at this position, if any.
+    pub fn label(&self) -> Option<Label> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::SupPtr => Some(ptr.sup().l().read().into()),
+                Tag::DupABoundVar | Tag::DupBBoundVar => Some(ptr.dup().l().read().into()),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the id of the node at this position, if any.
+    ///
+    /// Variables bound by a dup report the id of the binding dup node.
+    /// Other variables have no id.
+    pub fn node_id(&self) -> Option<NodeId> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::LamPtr
+                | Tag::AppPtr
+                | Tag::SupPtr
+                | Tag::NumPtr
+                | Tag::Op2Ptr
+                | Tag::DupABoundVar
+                | Tag::DupBBoundVar => Some(NodeId::from_ptr(ptr.ptr())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the name of the definition referred to at this position, if
+    /// any.
+    pub fn ref_name(&self) -> Option<IStr> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::Ref => Some(ptr.def().name),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the name of the free variable at this position, if it has one.
+    ///
+    /// Free variables of the term a graph was built from keep their names, but
+    /// those left behind by erasing a lambda whose variable was still used
+    /// have none.
+    pub fn free_name(&self) -> Option<IStr> {
+        unsafe { self.ptr().free_name() }
+    }
+
+    /// Returns the number at this position, if any.
+    pub fn num(&self) -> Option<u64> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::NumPtr => Some(ptr.num().read().n),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the operator of the operation at this position, if any.
+    pub fn op(&self) -> Option<Op> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::Op2Ptr => Some(ptr.op2().op().read()),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the body of the lambda at this position.
+    pub fn lam_body(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::LamPtr => Some(Cursor::new(ptr.lam().e())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the function of the application at this position.
+    pub fn app_fun(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::AppPtr => Some(Cursor::new(ptr.app().e1())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the argument of the application at this position.
+    pub fn app_arg(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::AppPtr => Some(Cursor::new(ptr.app().e2())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the first branch of the superposition at this position.
+    pub fn sup_left(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::SupPtr => Some(Cursor::new(ptr.sup().e1())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the second branch of the superposition at this position.
+    pub fn sup_right(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::SupPtr => Some(Cursor::new(ptr.sup().e2())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the first operand of the operation at this position.
+    pub fn op_left(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::Op2Ptr => Some(Cursor::new(ptr.op2().e1())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves into the second operand of the operation at this position.
+    pub fn op_right(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::Op2Ptr => Some(Cursor::new(ptr.op2().e2())),
+                _ => None,
+            }
+        }
+    }
+
+    /// Moves from a dup-bound variable into the expression being duplicated.
+    pub fn dup_expr(&self) -> Option<Cursor<'g>> {
+        let ptr = self.ptr();
+        unsafe {
+            match ptr.tag() {
+                Tag::DupABoundVar | Tag::DupBBoundVar => Some(Cursor::new(ptr.dup().e())),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_cursor_navigation() {
+        let term: Term = "λx dup #3{a b} = x; (#1{a b} λy y)".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let root = term_graph.cursor();
+        assert_eq!(root.kind(), NodeKind::Lam);
+        assert_eq!(root.label(), None);
+        assert!(root.app_fun().is_none());
+
+        let app = root.lam_body().unwrap();
+        assert_eq!(app.kind(), NodeKind::App);
+
+        let sup = app.app_fun().unwrap();
+        assert_eq!(sup.kind(), NodeKind::Sup);
+        assert_eq!(sup.label(), Some(1));
+
+        let a = sup.sup_left().unwrap();
+        let b = sup.sup_right().unwrap();
+        assert_eq!(a.kind(), NodeKind::DupAVar);
+        assert_eq!(b.kind(), NodeKind::DupBVar);
+        assert_eq!(a.label(), Some(3));
+        assert_eq!(a.node_id(), b.node_id());
+
+        let x = a.dup_expr().unwrap();
+        assert_eq!(x.kind(), NodeKind::LamVar);
+        assert_eq!(x.node_id(), None);
+
+        let lam_y = app.app_arg().unwrap();
+        assert_eq!(lam_y.kind(), NodeKind::Lam);
+        assert_eq!(lam_y.lam_body().unwrap().kind(), NodeKind::LamVar);
+    }
+
+    #[test]
+    fn test_cursor_free_var() {
+        let term: Term = "(f x)".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let root = term_graph.cursor();
+        assert_eq!(root.app_fun().unwrap().kind(), NodeKind::FreeVar);
+        assert_eq!(root.app_arg().unwrap().kind(), NodeKind::FreeVar);
+        assert!(root.app_arg().unwrap().lam_body().is_none());
+        assert_eq!(root.app_fun().unwrap().free_name(), Some("f".into()));
+        assert_eq!(root.free_name(), None);
+    }
+}
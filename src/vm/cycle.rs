@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use super::{StrategyConfig, TermGraph};
+use crate::error::Error;
+
+/// Detects a reduction that comes back to a graph it has already reached, by
+/// recording the [`TermGraph::structural_hash`] of the graph every `every`
+/// steps.
+///
+/// Graphs with the same structural hash are equal up to node addresses and
+/// the values of labels (with overwhelming probability), and so reduce the
+/// same way, which makes a repeat a proof that the reduction never ends.
+#[derive(Debug, Clone)]
+pub struct CycleDetector {
+    every: usize,
+    seen: HashMap<u64, usize>,
+}
+
+impl CycleDetector {
+    /// Returns a detector that fingerprints the graph every `every` steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is 0.
+    pub fn new(every: usize) -> Self {
+        assert!(every > 0, "fingerprint interval must be positive");
+        CycleDetector {
+            every,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Records `graph` as it is after `step` steps, if `step` is a multiple of
+    /// the interval, and returns the earlier step at which the graph was the
+    /// same, if any.
+    pub fn observe(&mut self, graph: &TermGraph, step: usize) -> Option<usize> {
+        if !step.is_multiple_of(self.every) {
+            return None;
+        }
+        let fingerprint = graph.structural_hash();
+        match self.seen.get(&fingerprint) {
+            Some(&earlier) => Some(earlier),
+            None => {
+                self.seen.insert(fingerprint, step);
+                None
+            }
+        }
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// watching for cycles with a [`CycleDetector`] that fingerprints the graph
+    /// every `every` steps. Returns the number of steps taken.
+    ///
+    /// If the graph repeats, returns [`Error::Cycle`] with the exact period,
+    /// found by fingerprinting every step after the repeat until the graph
+    /// comes back once more. The graph is left in a consistent state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is 0.
+    pub fn naive_reduce_detecting_cycles(
+        &mut self,
+        config: &StrategyConfig,
+        every: usize,
+    ) -> Result<usize, Error> {
+        let mut detector = CycleDetector::new(every);
+        let mut steps = 0;
+        loop {
+            if let Some(earlier) = detector.observe(self, steps) {
+                let fingerprint = self.structural_hash();
+                let mut period = steps - earlier;
+                for offset in 1..period {
+                    self.naive_reduce_step_with(config);
+                    if self.structural_hash() == fingerprint {
+                        period = offset;
+                        break;
+                    }
+                }
+                return Err(Error::Cycle {
+                    step: steps,
+                    period,
+                });
+            }
+            if self.naive_reduce_step_with(config).is_none() {
+                return Ok(steps);
+            }
+            steps += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_cycle_detector() {
+        let term: Term = "((λx x) ((λy y) λz z))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut detector = CycleDetector::new(2);
+        assert_eq!(detector.observe(&term_graph, 0), None);
+        term_graph.naive_reduce_step();
+        assert_eq!(detector.observe(&term_graph, 1), None);
+        assert_eq!(detector.observe(&term_graph, 2), None);
+        // Only every second step is fingerprinted.
+        assert_eq!(detector.observe(&term_graph, 3), None);
+        assert_eq!(detector.observe(&term_graph, 4), Some(2));
+    }
+
+    #[test]
+    fn test_reduce_detecting_cycles() {
+        let term: Term = "dup #0{a b} = λx x; ((a b) λy y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let steps = term_graph
+            .naive_reduce_detecting_cycles(&StrategyConfig::default(), 1)
+            .unwrap();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+        assert!(steps > 0);
+    }
+}
@@ -0,0 +1,78 @@
+use super::{rule_app_lam, AppPtrExt, LamPtrExt, Tag, Tagged, TermGraph};
+
+impl TermGraph {
+    /// Erases the parts of the graph that cannot contribute to its result,
+    /// returning the number of nodes removed.
+    ///
+    /// An application of a lambda whose variable is unused, `((λ_ e) arg)`, is
+    /// replaced by `e` and `arg` is erased, along with any dup that loses both
+    /// of its variables this way. Finally, [`TermGraph::gc`] frees whatever is
+    /// no longer reachable. Running this before reduction keeps the strategies
+    /// from spending rewrites inside arguments that would be discarded anyway.
+    pub fn eliminate_dead_code(&mut self) -> usize {
+        let live = self.1.live_count();
+        unsafe {
+            while let Some((slot, app_ptr, lam_ptr)) = self.erasing_app() {
+                rule_app_lam(&mut self.1, slot, app_ptr, lam_ptr);
+            }
+        }
+        self.gc();
+        live - self.1.live_count()
+    }
+
+    /// Finds an application of a lambda whose variable is unused, returning
+    /// the slot holding it, the `App` node, and the `Lam` node.
+    unsafe fn erasing_app(&self) -> Option<(*mut Tagged, Tagged, Tagged)> {
+        unsafe {
+            let slots = self
+                .node_iter()
+                .flat_map(|node| node.child_slots())
+                .chain(self.root_slots());
+            for slot in slots {
+                let ptr = slot.read();
+                if ptr.tag() != Tag::AppPtr {
+                    continue;
+                }
+                let e1 = ptr.app().e1().read();
+                if e1.tag() == Tag::LamPtr && e1.lam().x().read().tag() == Tag::UnusedVar {
+                    return Some((slot, ptr, e1));
+                }
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_eliminate_dead_code() {
+        // The discarded argument Ω is erased without being reduced.
+        let omega = "(λd dup #0{a b} = d; (a b) λd dup #0{a b} = d; (a b))";
+        let term: Term = format!("λx ((λy λz (z x)) {})", omega).parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        // The outer App and Lam, plus the 3 Apps, 2 Lams, and 2 Dups of Ω.
+        assert_eq!(term_graph.eliminate_dead_code(), 9);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv2 (λv1 (v1 v2)))"
+        );
+        assert_eq!(term_graph.naive_reduce_step(), None);
+    }
+
+    #[test]
+    fn test_eliminate_dead_code_dup_half() {
+        // Erasing `b` leaves the dup with a single use.
+        let term: Term = "λx dup #0{a b} = x; ((λy a) b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.eliminate_dead_code(), 2);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (dup #0{v2 _} = v1; v2))"
+        );
+        assert_eq!(term_graph.eliminate_dead_code(), 0);
+    }
+}
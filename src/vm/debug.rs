@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{NodeType, Tag, Tagged, TermGraph};
+
+/// The names of the nodes of a graph and of the slots they hold, for printing
+/// the graph without the addresses of its nodes.
+struct Names {
+    nodes: HashMap<*mut (), usize>,
+    slots: HashMap<*mut Tagged, String>,
+}
+
+impl Names {
+    /// Renders the contents of a slot: its tag, followed by the node or slot
+    /// it points to, if any.
+    unsafe fn slot(&self, ptr: Tagged) -> String {
+        unsafe {
+            let tag = ptr.tag();
+            match tag {
+                Tag::UnusedVar => format!("{:?}", tag),
+                Tag::UnboundVar => match ptr.free_name() {
+                    Some(name) => format!("{:?} {}", tag, name),
+                    None => format!("{:?}", tag),
+                },
+                Tag::Ref => format!("{:?} {}", tag, ptr.def().name),
+                Tag::VarUsePtr => match self.slots.get(&ptr.var_use()) {
+                    Some(slot) => format!("{:?} {}", tag, slot),
+                    None => format!("{:?} (unreachable)", tag),
+                },
+                _ => match self.nodes.get(&ptr.ptr()) {
+                    Some(id) => format!("{:?} n{}", tag, id),
+                    None => format!("{:?} (unreachable)", tag),
+                },
+            }
+        }
+    }
+}
+
+/// Prints the roots, then every node reachable from them, one per line in
+/// the order of a breadth-first traversal. Nodes are named `n0`, `n1`, and so
+/// on in that order, and a variable's use by the node and field that hold it,
+/// as in `n1.e2`, so the output only depends on the structure of the graph.
+impl fmt::Debug for TermGraph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nodes: Vec<Tagged> = self.node_iter().collect();
+        let mut names = Names {
+            nodes: HashMap::new(),
+            slots: HashMap::new(),
+        };
+        let roots: Vec<(String, *mut Tagged)> = match self.1.roots.as_slice() {
+            [] => vec![("root".to_string(), self.0)],
+            roots => roots
+                .iter()
+                .map(|(name, slot)| (format!("root {}", name), *slot))
+                .collect(),
+        };
+        for (name, slot) in &roots {
+            names.slots.insert(*slot, name.clone());
+        }
+        unsafe {
+            for (id, node) in nodes.iter().enumerate() {
+                names.nodes.insert(node.ptr(), id);
+                let fields: &[&str] = match node.node_type() {
+                    Some(NodeType::Lam) | Some(NodeType::Dup) => &["e"],
+                    _ => &["e1", "e2"],
+                };
+                for (slot, field) in node.child_slots().into_iter().zip(fields) {
+                    names.slots.insert(slot, format!("n{}.{}", id, field));
+                }
+            }
+            for (name, slot) in &roots {
+                writeln!(f, "{}: {}", name, names.slot(slot.read()))?;
+            }
+            for (id, node) in nodes.iter().enumerate() {
+                write!(f, "n{} ", id)?;
+                match node.node_type() {
+                    Some(NodeType::Lam) => {
+                        let lam = node.lam_read();
+                        writeln!(
+                            f,
+                            "Lam {{ x: {}, e: {} }}",
+                            names.slot(lam.x),
+                            names.slot(lam.e)
+                        )?
+                    }
+                    Some(NodeType::App) => {
+                        let app = node.app_read();
+                        writeln!(
+                            f,
+                            "App {{ e1: {}, e2: {} }}",
+                            names.slot(app.e1),
+                            names.slot(app.e2)
+                        )?
+                    }
+                    Some(NodeType::Sup) => {
+                        let sup = node.sup_read();
+                        writeln!(
+                            f,
+                            "Sup {{ l: {}, e1: {}, e2: {} }}",
+                            sup.l,
+                            names.slot(sup.e1),
+                            names.slot(sup.e2)
+                        )?
+                    }
+                    Some(NodeType::Dup) => {
+                        let dup = node.dup_read();
+                        writeln!(
+                            f,
+                            "Dup {{ l: {}, a: {}, b: {}, e: {} }}",
+                            dup.l,
+                            names.slot(dup.a),
+                            names.slot(dup.b),
+                            names.slot(dup.e)
+                        )?
+                    }
+                    Some(NodeType::Num) => writeln!(f, "{:?}", node.num().read())?,
+                    Some(NodeType::Op2) => {
+                        let op2 = node.op2().read();
+                        writeln!(
+                            f,
+                            "Op2 {{ op: {:?}, e1: {}, e2: {} }}",
+                            op2.op,
+                            names.slot(op2.e1),
+                            names.slot(op2.e2)
+                        )?
+                    }
+                    None => unreachable!(),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_debug() {
+        let term: Term = "λf dup #3{a b} = f; (a (+ b 1))".parse().unwrap();
+        let expected = "\
+            root: LamPtr n0\n\
+            n0 Lam { x: VarUsePtr n2.e, e: AppPtr n1 }\n\
+            n1 App { e1: DupABoundVar n2, e2: Op2Ptr n3 }\n\
+            n2 Dup { l: 3, a: VarUsePtr n1.e1, b: VarUsePtr n3.e1, e: LamBoundVar n0 }\n\
+            n3 Op2 { op: Add, e1: DupBBoundVar n2, e2: NumPtr n4 }\n\
+            n4 Num { n: 1 }\n";
+        assert_eq!(format!("{:?}", TermGraph::from(&term)), expected);
+        assert_eq!(format!("{:?}", TermGraph::from(&term).clone()), expected);
+
+        let term: Term = "λx y".parse().unwrap();
+        assert_eq!(
+            format!("{:?}", TermGraph::from(&term)),
+            "root: LamPtr n0\nn0 Lam { x: UnusedVar, e: UnboundVar y }\n"
+        );
+    }
+}
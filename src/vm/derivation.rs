@@ -0,0 +1,98 @@
+use std::fmt;
+
+use super::{Rule, StrategyConfig, TermGraph};
+use crate::syntax::Term;
+
+/// A recorded reduction: the starting term and the term after each step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivation {
+    pub start: Term,
+    pub steps: Vec<(Rule, Term)>,
+}
+
+impl Derivation {
+    /// Returns the final term of the derivation.
+    pub fn end(&self) -> &Term {
+        self.steps.last().map_or(&self.start, |(_, term)| term)
+    }
+
+    /// Renders the derivation as premise/conclusion blocks, in the notation
+    /// used by the comments in the vm tests:
+    ///
+    /// ```text
+    /// ((λv1 v1) v2)
+    /// ------------- AppLam
+    /// v2
+    /// ```
+    pub fn to_blocks(&self) -> String {
+        let mut out = format!("{}\n", self.start);
+        let mut premise = self.start.to_string();
+        for (rule, term) in &self.steps {
+            let conclusion = term.to_string();
+            let width = premise.chars().count().max(conclusion.chars().count());
+            out += &format!("{} {:?}\n{}\n", "-".repeat(width), rule, conclusion);
+            premise = conclusion;
+        }
+        out
+    }
+}
+
+impl fmt::Display for Derivation {
+    /// Writes one `term --Rule--> term` line per step.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut premise = &self.start;
+        for (rule, term) in &self.steps {
+            writeln!(f, "{} --{:?}--> {}", premise, rule, term)?;
+            premise = term;
+        }
+        Ok(())
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// recording the term read back after every step.
+    pub fn naive_derivation(&mut self, config: &StrategyConfig) -> Derivation {
+        let start = Term::from(&*self);
+        let mut steps = vec![];
+        while let Some(rule) = self.naive_reduce_step_with(config) {
+            steps.push((rule, Term::from(&*self)));
+        }
+        Derivation { start, steps }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_derivation() {
+        let term: Term = "dup #0{a b} = #0{x y}; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let derivation = term_graph.naive_derivation(&StrategyConfig::default());
+        assert_eq!(derivation.steps.len(), 1);
+        assert_eq!(format!("{}", derivation.end()), "(x y)");
+        assert_eq!(
+            format!("{}", derivation),
+            "(dup #0{v3 v4} = #0{x y}; (v3 v4)) --DupSup--> (x y)\n"
+        );
+        assert_eq!(
+            derivation.to_blocks(),
+            "(dup #0{v3 v4} = #0{x y}; (v3 v4))\n\
+             ---------------------------------- DupSup\n\
+             (x y)\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_derivation() {
+        let term: Term = "λx x".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let derivation = term_graph.naive_derivation(&StrategyConfig::default());
+        assert_eq!(derivation.end(), &derivation.start);
+        assert_eq!(format!("{}", derivation), "");
+        assert_eq!(derivation.to_blocks(), "(λv1 v1)\n");
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+use super::{RuleKind, Strategy, StrategyConfig, TermGraph, TimeSeries};
+
+/// The number of samples [`TermGraph::reduce_diagnosed`] aims to take over
+/// its step budget.
+const SAMPLES: usize = 64;
+
+/// What a reduction that ran out of steps did, as returned by
+/// [`TermGraph::reduce_diagnosed`], to tell a reduction that is merely long
+/// from one that diverges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    /// The number of steps taken.
+    pub steps: usize,
+    /// The size of the graph over the reduction, from the initial graph to
+    /// the graph after the last step.
+    pub series: TimeSeries,
+    /// The number of rewrites of each kind of rule that was used, most
+    /// frequent first.
+    pub rules: Vec<(RuleKind, usize)>,
+}
+
+impl Diagnosis {
+    /// Returns whether the graph never shrank between two samples and ended
+    /// up larger than it started, as when a dup keeps copying a term that
+    /// holds a dup with the same label.
+    pub fn grew_monotonically(&self) -> bool {
+        let samples = self.series.samples();
+        match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) => {
+                last.nodes > first.nodes
+                    && samples
+                        .windows(2)
+                        .all(|pair| pair[0].nodes <= pair[1].nodes)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the most frequent kind of rewrite, if any step was taken.
+    pub fn dominant_rule(&self) -> Option<RuleKind> {
+        self.rules.first().map(|(kind, _)| *kind)
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not in normal form after {} steps", self.steps)?;
+        let samples = self.series.samples();
+        if let (Some(first), Some(last)) = (samples.first(), samples.last()) {
+            let peak = samples.iter().map(|sample| sample.nodes).max().unwrap();
+            write!(
+                f,
+                "\n  nodes: {} at step {}, {} at step {} (peak {})",
+                first.nodes, first.step, last.nodes, last.step, peak
+            )?;
+        }
+        if !self.rules.is_empty() {
+            let total: usize = self.rules.iter().map(|(_, count)| count).sum();
+            let rules: Vec<String> = self
+                .rules
+                .iter()
+                .map(|(kind, count)| {
+                    format!("{:?} {:.1}%", kind, 100.0 * *count as f64 / total as f64)
+                })
+                .collect();
+            write!(f, "\n  rules: {}", rules.join(", "))?;
+        }
+        if self.grew_monotonically() {
+            write!(
+                f,
+                "\n  the graph grew monotonically, so the reduction probably diverges"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph with `strategy`, like [`TermGraph::reduce_step_by`]
+    /// repeated until no redexes remain, for at most `max_steps` steps.
+    /// Returns the number of steps taken, or a [`Diagnosis`] of the
+    /// reduction if the graph is not in normal form after `max_steps` steps.
+    ///
+    /// The size of the graph is sampled about 64 times over the budget, so
+    /// the overhead does not depend on `max_steps`.
+    pub fn reduce_diagnosed(
+        &mut self,
+        strategy: Strategy,
+        config: &StrategyConfig,
+        max_steps: usize,
+    ) -> Result<usize, Box<Diagnosis>> {
+        let before = self.stats();
+        let mut series = TimeSeries::new(max_steps.div_ceil(SAMPLES).max(1));
+        let mut steps = 0;
+        let mut sampled = series.observe(self, steps);
+        while steps < max_steps {
+            if self.reduce_step_by(strategy, config).is_none() {
+                return Ok(steps);
+            }
+            steps += 1;
+            sampled = series.observe(self, steps);
+        }
+        if self.is_normal_form() {
+            return Ok(steps);
+        }
+        if !sampled {
+            series.record(self, steps);
+        }
+        let after = self.stats();
+        let mut rules: Vec<(RuleKind, usize)> = RuleKind::ALL
+            .iter()
+            .map(|kind| (*kind, after.rewrites(*kind) - before.rewrites(*kind)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        rules.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        Err(Box::new(Diagnosis {
+            steps,
+            series,
+            rules,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::book::Book;
+
+    fn diagnose(src: &str, max_steps: usize) -> Result<usize, Box<Diagnosis>> {
+        let mut book = Book::new();
+        let main = book.load(src).unwrap().unwrap();
+        TermGraph::from_book(Arc::new(book), &main)
+            .unwrap()
+            .reduce_diagnosed(
+                Strategy::Deterministic,
+                &StrategyConfig::default(),
+                max_steps,
+            )
+    }
+
+    #[test]
+    fn test_diagnosis_normalizes() {
+        assert_eq!(diagnose("dup #0{a b} = λx x; (a b)", 3), Ok(3));
+        assert_eq!(diagnose("dup #0{a b} = λx x; (a b)", 100), Ok(3));
+    }
+
+    #[test]
+    fn test_diagnosis_loop() {
+        let diagnosis = diagnose("def loop = λx (loop x);\n(loop 0)", 200).unwrap_err();
+        assert_eq!(diagnosis.steps, 200);
+        // Sampled every 4 steps, since 200 steps do not fit in 64 samples of
+        // 3 steps.
+        assert_eq!(diagnosis.series.interval(), 4);
+        assert_eq!(diagnosis.series.samples().len(), 51);
+        assert!(!diagnosis.grew_monotonically());
+        assert_eq!(
+            diagnosis.rules,
+            [(RuleKind::AppLam, 100), (RuleKind::Ref, 100)]
+        );
+        assert_eq!(diagnosis.dominant_rule(), Some(RuleKind::AppLam));
+        assert_eq!(
+            diagnosis.to_string(),
+            "not in normal form after 200 steps\n\
+             \x20 nodes: 2 at step 0, 2 at step 200 (peak 2)\n\
+             \x20 rules: AppLam 50.0%, Ref 50.0%"
+        );
+    }
+
+    #[test]
+    fn test_diagnosis_growth() {
+        let diagnosis = diagnose("def grow = λx (grow (x 1));\n(grow 0)", 99).unwrap_err();
+        assert!(diagnosis.grew_monotonically());
+        let last = diagnosis.series.samples().last().unwrap();
+        assert_eq!(last.step, 99);
+        assert!(diagnosis
+            .to_string()
+            .ends_with("the graph grew monotonically, so the reduction probably diverges"));
+    }
+}
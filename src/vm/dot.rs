@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::{ChildRecord, RecordTag, Tag, TermGraph, UseRecord};
+
+impl TermGraph {
+    /// Returns the graph in the DOT language of Graphviz, for debugging.
+    ///
+    /// Nodes are numbered as in [`TermGraph::dump`] and labeled `λ`, `@`,
+    /// `#l{}`, `dup #l`, their number, or their operator. Solid edges go from each node to its children,
+    /// labeled with the name of the slot. Dashed edges are the back-edges:
+    /// from a slot holding a bound variable to its binder, labeled with the
+    /// variable (`x` for a lambda's, `a` or `b` for a dup's), and in gray from
+    /// each binder to the node that uses its variable. A graph whose sharing
+    /// is broken shows up as a dashed edge without its partner.
+    pub fn to_dot(&self) -> String {
+        let records = self.dump();
+        let roots = self.root_entries();
+        let mut out = String::new();
+        out.push_str("digraph {\n");
+        out.push_str("  node [fontname=\"monospace\"];\n");
+        for (index, (name, child)) in roots.iter().enumerate() {
+            writeln!(out, "  r{} [shape=plaintext, label=\"{}\"];", index, name).unwrap();
+            write_child(&mut out, &format!("r{}", index), "", *child);
+        }
+        for record in &records {
+            let label = match (record.tag, record.label) {
+                (RecordTag::Lam, _) => "λ".to_string(),
+                (RecordTag::App, _) => "@".to_string(),
+                (RecordTag::Sup, Some(l)) => format!("#{}{{}}", l),
+                (RecordTag::Dup, Some(l)) => format!("dup #{}", l),
+                (RecordTag::Num(n), _) => n.to_string(),
+                (RecordTag::Op2(op), _) => op.to_string(),
+                _ => unreachable!(),
+            };
+            writeln!(out, "  n{} [label=\"{}\"];", record.id, label).unwrap();
+            let slot_names: &[&str] = match record.tag {
+                RecordTag::Lam | RecordTag::Dup => &["e"],
+                RecordTag::App | RecordTag::Sup | RecordTag::Op2(_) => &["e1", "e2"],
+                RecordTag::Num(_) => &[],
+            };
+            let from = format!("n{}", record.id);
+            for (slot, child) in slot_names.iter().zip(&record.children) {
+                write_child(&mut out, &from, slot, *child);
+            }
+            let var_names: &[&str] = match record.tag {
+                RecordTag::Lam => &["x"],
+                _ => &["a", "b"],
+            };
+            for (var, var_use) in var_names.iter().zip(&record.uses) {
+                let to = match var_use {
+                    UseRecord::Unused => continue,
+                    UseRecord::Node(id) => format!("n{}", id),
+                    UseRecord::Root => {
+                        let index = roots
+                            .iter()
+                            .position(|(_, child)| is_var_of(*child, record.id, var))
+                            .unwrap_or(0);
+                        format!("r{}", index)
+                    }
+                };
+                writeln!(
+                    out,
+                    "  {} -> {} [style=dashed, color=gray, label=\"{}\", constraint=false];",
+                    from, to, var
+                )
+                .unwrap();
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Returns the name and contents of each root, in the order of
+    /// `root_slots`. A graph without named roots has one root, `root`.
+    pub(crate) fn root_entries(&self) -> Vec<(String, ChildRecord)> {
+        let ids: HashMap<*mut (), usize> = self
+            .node_iter()
+            .enumerate()
+            .map(|(id, node)| (node.ptr(), id))
+            .collect();
+        let names: HashMap<_, _> = self
+            .1
+            .roots
+            .iter()
+            .map(|(name, slot)| (*slot, name.to_string()))
+            .collect();
+        self.root_slots()
+            .into_iter()
+            .map(|slot| {
+                let name = names.get(&slot).cloned().unwrap_or("root".to_string());
+                let child = unsafe { slot.read() };
+                let child = match unsafe { child.tag() } {
+                    Tag::LamPtr | Tag::AppPtr | Tag::SupPtr | Tag::NumPtr | Tag::Op2Ptr => {
+                        ChildRecord::Node(ids[&child.ptr()])
+                    }
+                    Tag::LamBoundVar => ChildRecord::LamVar(ids[&child.ptr()]),
+                    Tag::DupABoundVar => ChildRecord::DupAVar(ids[&child.ptr()]),
+                    Tag::DupBBoundVar => ChildRecord::DupBVar(ids[&child.ptr()]),
+                    Tag::Ref => ChildRecord::Ref(unsafe { child.def() }.name),
+                    _ => ChildRecord::FreeVar(unsafe { child.free_name() }),
+                };
+                (name, child)
+            })
+            .collect()
+    }
+}
+
+/// Returns whether `child` is the variable `var` of the node `id`.
+fn is_var_of(child: ChildRecord, id: usize, var: &str) -> bool {
+    matches!(
+        (child, var),
+        (ChildRecord::LamVar(binder), "x")
+            | (ChildRecord::DupAVar(binder), "a")
+            | (ChildRecord::DupBVar(binder), "b")
+            if binder == id
+    )
+}
+
+/// Writes the edge from the slot `slot` of `from` (empty for a root) to
+/// `child`. A free variable or a reference gets a node of its own, labeled
+/// with its name.
+fn write_child(out: &mut String, from: &str, slot: &str, child: ChildRecord) {
+    let label = if slot.is_empty() {
+        String::new()
+    } else {
+        format!(" [label=\"{}\"]", slot)
+    };
+    let (to, var) = match child {
+        ChildRecord::Node(id) => {
+            writeln!(out, "  {} -> n{}{};", from, id, label).unwrap();
+            return;
+        }
+        ChildRecord::LamVar(id) => (id, "x"),
+        ChildRecord::DupAVar(id) => (id, "a"),
+        ChildRecord::DupBVar(id) => (id, "b"),
+        ChildRecord::FreeVar(name) => {
+            let free = format!("{}{}_free", from, slot);
+            let name = name.map_or("free".to_string(), |name| name.to_string());
+            writeln!(out, "  {} [shape=plaintext, label=\"{}\"];", free, name).unwrap();
+            writeln!(out, "  {} -> {}{};", from, free, label).unwrap();
+            return;
+        }
+        ChildRecord::Ref(name) => {
+            let reference = format!("{}{}_ref", from, slot);
+            writeln!(
+                out,
+                "  {} [shape=plaintext, label=\"{}\"];",
+                reference, name
+            )
+            .unwrap();
+            writeln!(out, "  {} -> {}{};", from, reference, label).unwrap();
+            return;
+        }
+    };
+    writeln!(
+        out,
+        "  {} -> n{} [style=dashed, label=\"{}\", constraint=false];",
+        from, to, var
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_to_dot() {
+        let term: Term = "λx dup #3{a b} = x; (a (b y))".parse().unwrap();
+        let dot = TermGraph::from(&term).to_dot();
+        assert_eq!(
+            dot,
+            "digraph {\n  \
+             node [fontname=\"monospace\"];\n  \
+             r0 [shape=plaintext, label=\"root\"];\n  \
+             r0 -> n0;\n  \
+             n0 [label=\"λ\"];\n  \
+             n0 -> n1 [label=\"e\"];\n  \
+             n0 -> n2 [style=dashed, color=gray, label=\"x\", constraint=false];\n  \
+             n1 [label=\"@\"];\n  \
+             n1 -> n2 [style=dashed, label=\"a\", constraint=false];\n  \
+             n1 -> n3 [label=\"e2\"];\n  \
+             n2 [label=\"dup #3\"];\n  \
+             n2 -> n0 [style=dashed, label=\"x\", constraint=false];\n  \
+             n2 -> n1 [style=dashed, color=gray, label=\"a\", constraint=false];\n  \
+             n2 -> n3 [style=dashed, color=gray, label=\"b\", constraint=false];\n  \
+             n3 [label=\"@\"];\n  \
+             n3 -> n2 [style=dashed, label=\"b\", constraint=false];\n  \
+             n3e2_free [shape=plaintext, label=\"y\"];\n  \
+             n3 -> n3e2_free [label=\"e2\"];\n\
+             }\n"
+        );
+    }
+}
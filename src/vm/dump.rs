@@ -0,0 +1,470 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    node_label, App, Dup, DupPtrExt, Heap, Lam, LamPtrExt, Num, Op2, Op2PtrExt, Sup, SupPtrExt,
+    Tag, Tagged, TermGraph, MAX_LABEL,
+};
+use crate::error::Error;
+use crate::intern::IStr;
+use crate::syntax::{Label, Op};
+
+/// A plain-data snapshot of one node of a [`TermGraph`], as returned by
+/// [`TermGraph::dump`].
+///
+/// Unlike `NodeId`s, the ids in a dump do not depend on where the nodes are
+/// allocated: they number the nodes from 0, in the order they are reached
+/// from the roots, so dumping the same graph twice gives the same records.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeRecord {
+    pub id: usize,
+    pub tag: RecordTag,
+    /// The label of a `Sup` or `Dup` node.
+    pub label: Option<Label>,
+    /// What the child slots of the node hold: the body of a `Lam`, the
+    /// function and argument of an `App`, the two branches of a `Sup`, the
+    /// two operands of an `Op2`, or the expression of a `Dup`. Empty for
+    /// `Num`.
+    pub children: Vec<ChildRecord>,
+    /// Where the variables bound by the node are used: the variable of a
+    /// `Lam`, or the two variables of a `Dup`. Empty for other nodes.
+    pub uses: Vec<UseRecord>,
+}
+
+/// The type of a [`NodeRecord`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordTag {
+    Lam,
+    App,
+    Sup,
+    Dup,
+    /// A number, with its value.
+    Num(u64),
+    /// A binary operation, with its operator.
+    Op2(Op),
+}
+
+/// The contents of a child slot in a [`NodeRecord`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildRecord {
+    /// The node with this id, which is not a `Dup`.
+    Node(usize),
+    /// The variable of the `Lam` node with this id.
+    LamVar(usize),
+    /// The first variable of the `Dup` node with this id.
+    DupAVar(usize),
+    /// The second variable of the `Dup` node with this id.
+    DupBVar(usize),
+    /// A free variable, with its name if it has one.
+    FreeVar(Option<IStr>),
+    /// A reference to the definition with this name.
+    Ref(IStr),
+}
+
+/// Where a variable bound by a [`NodeRecord`] is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UseRecord {
+    /// The variable is not used.
+    Unused,
+    /// The variable is held by a child slot of the node with this id.
+    Node(usize),
+    /// The variable is held directly by a root of the graph.
+    Root,
+}
+
+impl TermGraph {
+    /// Returns a record of every node reachable from the roots, in id order.
+    pub fn dump(&self) -> Vec<NodeRecord> {
+        let nodes: Vec<Tagged> = self.node_iter().collect();
+        let ids: HashMap<*mut (), usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| (node.ptr(), id))
+            .collect();
+        unsafe {
+            let owners: HashMap<*mut Tagged, usize> = nodes
+                .iter()
+                .flat_map(|node| {
+                    let id = ids[&node.ptr()];
+                    node.child_slots().into_iter().map(move |slot| (slot, id))
+                })
+                .collect();
+            let var_use = |binder: *mut Tagged| {
+                let x = binder.read();
+                match x.tag() {
+                    Tag::VarUsePtr => match owners.get(&x.var_use()) {
+                        Some(id) => UseRecord::Node(*id),
+                        None => UseRecord::Root,
+                    },
+                    _ => UseRecord::Unused,
+                }
+            };
+            nodes
+                .iter()
+                .enumerate()
+                .map(|(id, node)| {
+                    let children = node
+                        .child_slots()
+                        .into_iter()
+                        .map(|slot| {
+                            let child = slot.read();
+                            match child.tag() {
+                                Tag::LamPtr
+                                | Tag::AppPtr
+                                | Tag::SupPtr
+                                | Tag::NumPtr
+                                | Tag::Op2Ptr => ChildRecord::Node(ids[&child.ptr()]),
+                                Tag::LamBoundVar => ChildRecord::LamVar(ids[&child.ptr()]),
+                                Tag::DupABoundVar => ChildRecord::DupAVar(ids[&child.ptr()]),
+                                Tag::DupBBoundVar => ChildRecord::DupBVar(ids[&child.ptr()]),
+                                Tag::Ref => ChildRecord::Ref(child.def().name),
+                                _ => ChildRecord::FreeVar(child.free_name()),
+                            }
+                        })
+                        .collect();
+                    let (tag, label, uses) = match node.tag() {
+                        Tag::LamPtr => (RecordTag::Lam, None, vec![var_use(node.lam().x())]),
+                        Tag::AppPtr => (RecordTag::App, None, vec![]),
+                        Tag::SupPtr => (RecordTag::Sup, Some(node.sup().l().read().into()), vec![]),
+                        Tag::NumPtr => (RecordTag::Num(node.num().read().n), None, vec![]),
+                        Tag::Op2Ptr => (RecordTag::Op2(node.op2().op().read()), None, vec![]),
+                        _ => (
+                            RecordTag::Dup,
+                            Some(node.dup().l().read().into()),
+                            vec![var_use(node.dup().a()), var_use(node.dup().b())],
+                        ),
+                    };
+                    NodeRecord {
+                        id,
+                        tag,
+                        label,
+                        children,
+                        uses,
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// Rebuilds a graph from records like those returned by
+    /// [`TermGraph::dump`], which need not come from a dump: tests and fuzzers
+    /// can describe arbitrary graphs this way.
+    ///
+    /// The records are checked before anything is built. Each must have the
+    /// id of its position and the children, uses, and label of its tag; each
+    /// child must refer to a node or variable of the right kind, whose binder
+    /// records that child's node as its use; every node other than a dup must
+    /// have exactly one parent; and every node must be reachable from the
+    /// root. The root is the variable recorded as used by the root, if any,
+    /// and otherwise node 0, or an anonymous free variable if there are no
+    /// records.
+    /// Returns [`Error::Graph`] describing the first problem found.
+    pub fn from_dump(records: &[NodeRecord]) -> Result<TermGraph, Error> {
+        let root = check_dump(records)?;
+        unsafe {
+            let mut heap = Heap::default();
+            let nodes: Vec<Tagged> = records
+                .iter()
+                .map(|record| match record.tag {
+                    RecordTag::Lam => Lam::alloc(&mut heap),
+                    RecordTag::App => App::alloc(&mut heap),
+                    RecordTag::Sup => Sup::alloc(&mut heap),
+                    RecordTag::Dup => Dup::alloc(&mut heap),
+                    RecordTag::Num(_) => Num::alloc(&mut heap),
+                    RecordTag::Op2(_) => Op2::alloc(&mut heap),
+                })
+                .collect();
+            for (record, node) in records.iter().zip(&nodes) {
+                match record.tag {
+                    RecordTag::Lam => node.lam().x().write(Tagged::new_unused_var()),
+                    RecordTag::App => {}
+                    RecordTag::Sup => node.sup().l().write(node_label(record.label.unwrap())),
+                    RecordTag::Dup => {
+                        node.dup().l().write(node_label(record.label.unwrap()));
+                        node.dup().a().write(Tagged::new_unused_var());
+                        node.dup().b().write(Tagged::new_unused_var());
+                    }
+                    RecordTag::Num(n) => node.num().write(Num { n }),
+                    RecordTag::Op2(op) => node.op2().op().write(op),
+                }
+            }
+            let fill = |slot: *mut Tagged, child: ChildRecord| {
+                let ptr = match child {
+                    ChildRecord::Node(id) => nodes[id],
+                    ChildRecord::LamVar(id) => nodes[id].lam_bound_var(),
+                    ChildRecord::DupAVar(id) => nodes[id].dup_a_bound_var(),
+                    ChildRecord::DupBVar(id) => nodes[id].dup_b_bound_var(),
+                    ChildRecord::FreeVar(None) => Tagged::new_unbound_var(),
+                    ChildRecord::FreeVar(Some(name)) => Tagged::new_free_var(name),
+                    ChildRecord::Ref(_) => unreachable!("{:?}", child),
+                };
+                slot.write(ptr);
+                ptr.if_bound_var_move_to(Tagged::new(slot as *mut (), Tag::VarUsePtr));
+            };
+            for (record, node) in records.iter().zip(&nodes) {
+                for (slot, child) in node.child_slots().into_iter().zip(&record.children) {
+                    fill(slot, *child);
+                }
+            }
+            let root_ptr = std::alloc::alloc(std::alloc::Layout::new::<Tagged>()) as *mut Tagged;
+            fill(root_ptr, root);
+            Ok(TermGraph(root_ptr, heap))
+        }
+    }
+}
+
+/// Checks that `records` describe a graph, as documented on
+/// [`TermGraph::from_dump`], and returns what its root holds.
+fn check_dump(records: &[NodeRecord]) -> Result<ChildRecord, Error> {
+    let invalid = |message: String| Err(Error::Graph(format!("invalid dump: {}", message)));
+    // The node a child refers to, and where the variable it refers to is
+    // recorded among the uses of that node.
+    let target = |child: ChildRecord| match child {
+        ChildRecord::Node(id) => Some((id, None)),
+        ChildRecord::LamVar(id) | ChildRecord::DupAVar(id) => Some((id, Some(0))),
+        ChildRecord::DupBVar(id) => Some((id, Some(1))),
+        ChildRecord::FreeVar(_) | ChildRecord::Ref(_) => None,
+    };
+    let mut root = None;
+    for (id, record) in records.iter().enumerate() {
+        if record.id != id {
+            return invalid(format!("record {} has id {}", id, record.id));
+        }
+        let (children, uses, labelled) = match record.tag {
+            RecordTag::Lam => (1, 1, false),
+            RecordTag::App => (2, 0, false),
+            RecordTag::Sup => (2, 0, true),
+            RecordTag::Dup => (1, 2, true),
+            RecordTag::Num(_) => (0, 0, false),
+            RecordTag::Op2(_) => (2, 0, false),
+        };
+        if record.children.len() != children
+            || record.uses.len() != uses
+            || record.label.is_some() != labelled
+        {
+            return invalid(format!(
+                "node {} has the wrong number of children, uses, or labels for its tag {:?}",
+                id, record.tag
+            ));
+        }
+        if let Some(label) = record.label.filter(|&l| l > MAX_LABEL) {
+            return invalid(format!(
+                "node {} has label {}, which is larger than {}",
+                id, label, MAX_LABEL
+            ));
+        }
+        for (index, use_) in record.uses.iter().enumerate() {
+            if *use_ == UseRecord::Root && root.replace(bound_var(record, index)).is_some() {
+                return invalid("more than one variable is used by the root".to_string());
+            }
+        }
+    }
+    let root = root.unwrap_or(match records.is_empty() {
+        true => ChildRecord::FreeVar(None),
+        false => ChildRecord::Node(0),
+    });
+    if root == ChildRecord::Node(0) && records[0].tag == RecordTag::Dup {
+        return invalid("the root is node 0, which is a Dup".to_string());
+    }
+
+    let mut parents: HashMap<ChildRecord, usize> = HashMap::new();
+    for record in records {
+        for &child in &record.children {
+            if let ChildRecord::Ref(name) = child {
+                return invalid(format!(
+                    "node {} refers to definition {}, but a dump has no definitions",
+                    record.id, name
+                ));
+            }
+            let Some((id, use_index)) = target(child) else {
+                continue;
+            };
+            let Some(target) = records.get(id) else {
+                return invalid(format!("node {} refers to missing node {}", record.id, id));
+            };
+            let expected = match child {
+                ChildRecord::Node(_) => target.tag != RecordTag::Dup,
+                ChildRecord::LamVar(_) => target.tag == RecordTag::Lam,
+                _ => target.tag == RecordTag::Dup,
+            };
+            if !expected {
+                return invalid(format!(
+                    "node {} refers to {:?}, but node {} is a {:?}",
+                    record.id, child, id, target.tag
+                ));
+            }
+            if child == root || parents.insert(child, record.id).is_some() {
+                return invalid(format!("{:?} has more than one parent", child));
+            }
+            if let Some(index) = use_index {
+                if target.uses[index] != UseRecord::Node(record.id) {
+                    return invalid(format!(
+                        "node {} uses {:?}, whose use is recorded as {:?}",
+                        record.id, child, target.uses[index]
+                    ));
+                }
+            }
+        }
+    }
+    for record in records {
+        if record.tag != RecordTag::Dup
+            && root != ChildRecord::Node(record.id)
+            && !parents.contains_key(&ChildRecord::Node(record.id))
+        {
+            return invalid(format!("node {} has no parent", record.id));
+        }
+        for (index, use_) in record.uses.iter().enumerate() {
+            if let UseRecord::Node(owner) = use_ {
+                if parents.get(&bound_var(record, index)) != Some(owner) {
+                    return invalid(format!(
+                        "a variable of node {} is recorded as used by node {}, which does not use it",
+                        record.id, owner
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut reached = HashSet::new();
+    let mut stack: Vec<usize> = target(root).map(|(id, _)| id).into_iter().collect();
+    while let Some(id) = stack.pop() {
+        if reached.insert(id) {
+            stack.extend(records[id].children.iter().filter_map(|child| match child {
+                ChildRecord::LamVar(_) => None,
+                _ => target(*child).map(|(id, _)| id),
+            }));
+        }
+    }
+    if let Some(record) = records.iter().find(|record| !reached.contains(&record.id)) {
+        return invalid(format!("node {} is not reachable from the root", record.id));
+    }
+    Ok(root)
+}
+
+/// Returns the child that refers to the variable recorded in the `index`th use
+/// of `record`.
+fn bound_var(record: &NodeRecord, index: usize) -> ChildRecord {
+    match (record.tag, index) {
+        (RecordTag::Lam, _) => ChildRecord::LamVar(record.id),
+        (_, 0) => ChildRecord::DupAVar(record.id),
+        _ => ChildRecord::DupBVar(record.id),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_dump() {
+        let term: Term = "λx dup #3{a b} = x; #5{a (b y)}".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let records = term_graph.dump();
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[0],
+            NodeRecord {
+                id: 0,
+                tag: RecordTag::Lam,
+                label: None,
+                children: vec![ChildRecord::Node(1)],
+                uses: vec![UseRecord::Node(2)],
+            }
+        );
+        assert_eq!(records[1].tag, RecordTag::Sup);
+        assert_eq!(records[1].label, Some(5));
+        assert_eq!(
+            records[1].children,
+            vec![ChildRecord::DupAVar(2), ChildRecord::Node(3)]
+        );
+        // A dup is numbered as soon as one of its variables is reached.
+        assert_eq!(records[2].tag, RecordTag::Dup);
+        assert_eq!(records[2].label, Some(3));
+        assert_eq!(records[2].children, vec![ChildRecord::LamVar(0)]);
+        assert_eq!(
+            records[2].uses,
+            vec![UseRecord::Node(1), UseRecord::Node(3)]
+        );
+        assert_eq!(
+            records[3].children,
+            vec![
+                ChildRecord::DupBVar(2),
+                ChildRecord::FreeVar(Some("y".into()))
+            ]
+        );
+        assert_eq!(TermGraph::from(&term).dump(), records);
+    }
+
+    #[test]
+    fn test_from_dump() {
+        for src in [
+            "λx dup #3{a b} = x; #5{a (b y)}",
+            "dup #0{a _} = λx x; a",
+            "((λx x) λy y)",
+        ] {
+            let term: Term = src.parse().unwrap();
+            let records = TermGraph::from(&term).dump();
+            let term_graph = TermGraph::from_dump(&records).unwrap();
+            assert_eq!(term_graph.dump(), records);
+            assert_eq!(Term::from(&term_graph), Term::from(&TermGraph::from(&term)));
+        }
+        // A lone free variable dumps to no records, and so loses its name.
+        let term_graph = TermGraph::from_dump(&TermGraph::from(&"y".parse().unwrap()).dump());
+        assert_eq!(format!("{}", Term::from(&term_graph.unwrap())), "v1");
+        let term: Term = "((λx x) λy y)".parse().unwrap();
+        let mut term_graph = TermGraph::from_dump(&TermGraph::from(&term).dump()).unwrap();
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_from_dump_invalid() {
+        let lam = |child, use_| NodeRecord {
+            id: 0,
+            tag: RecordTag::Lam,
+            label: None,
+            children: vec![child],
+            uses: vec![use_],
+        };
+        let error = |records: &[NodeRecord]| match TermGraph::from_dump(records) {
+            Err(Error::Graph(message)) => message,
+            other => panic!("expected an error, got {:?}", other.map(|g| g.dump())),
+        };
+        assert!(TermGraph::from_dump(&[lam(ChildRecord::LamVar(0), UseRecord::Node(0))]).is_ok());
+        assert_eq!(
+            error(&[lam(ChildRecord::LamVar(0), UseRecord::Unused)]),
+            "invalid dump: node 0 uses LamVar(0), whose use is recorded as Unused"
+        );
+        assert_eq!(
+            error(&[lam(ChildRecord::FreeVar(None), UseRecord::Node(0))]),
+            "invalid dump: a variable of node 0 is recorded as used by node 0, \
+             which does not use it"
+        );
+        assert_eq!(
+            error(&[lam(ChildRecord::Node(1), UseRecord::Unused)]),
+            "invalid dump: node 0 refers to missing node 1"
+        );
+        assert_eq!(
+            error(&[lam(ChildRecord::Node(0), UseRecord::Unused)]),
+            "invalid dump: Node(0) has more than one parent"
+        );
+        let mut records = vec![lam(ChildRecord::FreeVar(None), UseRecord::Unused)];
+        records.push(NodeRecord {
+            id: 1,
+            ..lam(ChildRecord::Node(2), UseRecord::Unused)
+        });
+        records.push(NodeRecord {
+            id: 2,
+            ..lam(ChildRecord::Node(1), UseRecord::Unused)
+        });
+        assert_eq!(
+            error(&records),
+            "invalid dump: node 1 is not reachable from the root"
+        );
+        records[2].tag = RecordTag::App;
+        assert_eq!(
+            error(&records),
+            "invalid dump: node 2 has the wrong number of children, uses, or labels for its tag App"
+        );
+    }
+}
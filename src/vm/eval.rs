@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use super::TermGraph;
+use crate::error::Error;
+use crate::intern::{IStr, InternStatic};
+use crate::syntax::Term;
+
+/// Reduces `term` to normal form, resolving its free variables from `env`.
+///
+/// Each definition in `env` that `term` uses is built once and shared between
+/// all of its uses, so it is never reduced more than once. Free variables of
+/// the definitions themselves are not resolved, and free variables of `term`
+/// that are not in `env` stay free.
+///
+/// Returns an error if `term` or one of the definitions is not well formed
+/// (see [`TermGraph::try_from_term`]).
+pub fn eval_with_env(term: &Term, env: &HashMap<IStr, Term>) -> Result<Term, Error> {
+    let mut term_graph = graph_with_env(term, env)?;
+    while term_graph.naive_reduce_step().is_some() {}
+    Ok(Term::from(&term_graph))
+}
+
+/// Builds the graph for `term`, sharing the definitions in `env` that it uses.
+fn graph_with_env(term: &Term, env: &HashMap<IStr, Term>) -> Result<TermGraph, Error> {
+    let defs: Vec<(IStr, Term)> = env.iter().map(|(name, def)| (*name, def.clone())).collect();
+    let roots = [("main".intern_static(), term.clone())];
+    TermGraph::from_roots(&defs, &roots)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eval_with_env() {
+        let mut env = HashMap::new();
+        env.insert("id".intern_static(), "λx x".parse().unwrap());
+        env.insert(
+            "two".intern_static(),
+            "λf dup #0{f1 f2} = f; λx (f1 (f2 x))".parse().unwrap(),
+        );
+        let term: Term = "((two id) (id λy y))".parse().unwrap();
+        assert_eq!(
+            format!("{}", eval_with_env(&term, &env).unwrap()),
+            "(λv1 v1)"
+        );
+    }
+
+    #[test]
+    fn test_eval_with_env_free_vars() {
+        let mut env = HashMap::new();
+        env.insert("id".intern_static(), "λx x".parse().unwrap());
+        let term: Term = "(id (id y))".parse().unwrap();
+        assert_eq!(format!("{}", eval_with_env(&term, &env).unwrap()), "y");
+    }
+
+    #[test]
+    fn test_eval_with_env_malformed() {
+        let mut env = HashMap::new();
+        env.insert("twice".intern_static(), "λx (x x)".parse().unwrap());
+        let term: Term = "(twice λy y)".parse().unwrap();
+        assert!(matches!(eval_with_env(&term, &env), Err(Error::Build(_))));
+    }
+}
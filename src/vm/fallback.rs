@@ -0,0 +1,122 @@
+use super::{collect_redexes, RandomSource, Strategy, StrategyConfig, TermGraph};
+
+/// A way of choosing redexes, tried by
+/// [`TermGraph::normalize_with_fallback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Reduce the redex chosen by a built-in strategy.
+    Strategy(Strategy),
+    /// Reduce a random redex among those with the highest priority, as
+    /// [`TermGraph::naive_random_order_reduce_step_with_source`] does.
+    Random,
+}
+
+/// The stages tried by [`TermGraph::normalize_with_fallback`], in order, each
+/// with the number of steps it may take.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FallbackPlan {
+    pub stages: Vec<(Stage, usize)>,
+}
+
+impl Default for FallbackPlan {
+    /// Normal order for 10,000 steps, then random order for 10,000,000.
+    fn default() -> Self {
+        FallbackPlan {
+            stages: vec![
+                (Stage::Strategy(Strategy::NormalOrder), 10_000),
+                (Stage::Random, 10_000_000),
+            ],
+        }
+    }
+}
+
+/// The result of [`TermGraph::normalize_with_fallback`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FallbackOutcome {
+    /// The number of steps taken by each stage that was tried.
+    pub steps: Vec<usize>,
+    /// The stage that reached normal form, or `None` if every stage ran out
+    /// of steps first.
+    pub finished_by: Option<Stage>,
+}
+
+impl FallbackOutcome {
+    /// Returns the total number of steps taken.
+    pub fn total_steps(&self) -> usize {
+        self.steps.iter().sum()
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form with each stage of `plan` in turn,
+    /// moving on to the next stage when one runs out of steps.
+    ///
+    /// Different families of terms normalize fastest under different orders,
+    /// and some only normalize under some orders, so a cheap, predictable
+    /// strategy can be tried first and a more robust one kept in reserve. A
+    /// stage picks up the graph where the previous one left it, so no work is
+    /// lost. The priorities of `config` apply to the stages that use them,
+    /// and the random choices are drawn from `rng`.
+    pub fn normalize_with_fallback(
+        &mut self,
+        plan: &FallbackPlan,
+        config: &StrategyConfig,
+        rng: &mut impl RandomSource,
+    ) -> FallbackOutcome {
+        let mut outcome = FallbackOutcome {
+            steps: vec![],
+            finished_by: None,
+        };
+        for &(stage, budget) in &plan.stages {
+            let mut steps = 0;
+            while steps < budget {
+                let rule = match stage {
+                    Stage::Strategy(strategy) => self.reduce_step_by(strategy, config),
+                    Stage::Random => self.naive_random_order_reduce_step_with_source(config, rng),
+                };
+                if rule.is_none() {
+                    break;
+                }
+                steps += 1;
+            }
+            outcome.steps.push(steps);
+            if unsafe { collect_redexes(&self.root_slots()) }.is_empty() {
+                outcome.finished_by = Some(stage);
+                break;
+            }
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+    use crate::vm::Counter;
+
+    #[test]
+    fn test_normalize_with_fallback() {
+        let term: Term = "((λa a) ((λb b) ((λc c) λd d)))".parse().unwrap();
+        let normalize = |stages| {
+            let mut term_graph = TermGraph::from(&term);
+            let outcome = term_graph.normalize_with_fallback(
+                &FallbackPlan { stages },
+                &StrategyConfig::default(),
+                &mut Counter(0),
+            );
+            (outcome, Term::from(&term_graph).to_string())
+        };
+        let normal_order = Stage::Strategy(Strategy::NormalOrder);
+        let (outcome, result) = normalize(vec![(normal_order, 1), (Stage::Random, 10)]);
+        assert_eq!(outcome.steps, [1, 2]);
+        assert_eq!(outcome.finished_by, Some(Stage::Random));
+        assert_eq!(result, "(λv1 v1)");
+        let (outcome, _) = normalize(vec![(normal_order, 3), (Stage::Random, 10)]);
+        assert_eq!(outcome.steps, [3]);
+        assert_eq!(outcome.finished_by, Some(normal_order));
+        let (outcome, _) = normalize(vec![(normal_order, 1), (Stage::Random, 1)]);
+        assert_eq!(outcome.total_steps(), 2);
+        assert_eq!(outcome.finished_by, None);
+    }
+}
@@ -0,0 +1,102 @@
+use super::{DupPtrExt, NodeIter, SupPtrExt, Tag, Tagged, TermGraph};
+
+impl TermGraph {
+    /// Removes the dups that only pass their expression on, returning the
+    /// number of dups removed.
+    ///
+    /// Commutations leave behind chains of dups with the same label, such as
+    /// `dup #0{a _} = e; dup #0{c d} = a; ...`, where a dup with an unused
+    /// variable feeds another. A dup `dup #l{a _} = e` with an unused variable
+    /// only projects the superpositions labelled `l` in `e`, so if `e` has no
+    /// node labelled `l`, its other variable is `e` itself and the dup can be
+    /// removed, merging the chain into `dup #0{c d} = e; ...`. Dups whose
+    /// expression has a node with their label are left alone.
+    pub fn flatten_dup_chains(&mut self) -> usize {
+        let mut removed = 0;
+        loop {
+            let dups: Vec<Tagged> = self
+                .node_iter()
+                .filter(|node| unsafe { node.tag() } == Tag::DupPtr)
+                .collect();
+            let before = removed;
+            for dup in dups {
+                // An earlier removal may have freed this dup.
+                if self.1.arena.is_live(dup) && unsafe { self.forward_dup(dup) } {
+                    removed += 1;
+                }
+            }
+            if removed == before {
+                return removed;
+            }
+        }
+    }
+
+    /// Removes `dup` if it has exactly one used variable and no node in its
+    /// expression has its label, moving the expression to that variable.
+    unsafe fn forward_dup(&mut self, dup: Tagged) -> bool {
+        unsafe {
+            let (a, b) = (dup.dup().a().read(), dup.dup().b().read());
+            let var_use = match (a.tag(), b.tag()) {
+                (Tag::VarUsePtr, Tag::UnusedVar) => a,
+                (Tag::UnusedVar, Tag::VarUsePtr) => b,
+                _ => return false,
+            };
+            let label = dup.dup().l().read();
+            let e = dup.dup().e().read();
+            let clashes = NodeIter::new(e).any(|node| match node.tag() {
+                Tag::SupPtr => node.sup().l().read() == label,
+                Tag::DupPtr => node.dup().l().read() == label,
+                _ => false,
+            });
+            if clashes {
+                return false;
+            }
+            var_use.var_use().write(e);
+            e.if_bound_var_move_to(var_use);
+            dup.dealloc_dup(&mut self.1);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_flatten_dup_chains() {
+        let src = "λx dup #0{a _} = x; dup #0{b _} = a; dup #0{c d} = b; (c d)";
+        let term: Term = src.parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.flatten_dup_chains(), 2);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (dup #0{v2 v3} = v1; (v2 v3)))"
+        );
+
+        // Reduces to the same normal form as the original.
+        let src = format!("({} λy y)", src);
+        let term: Term = src.parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.flatten_dup_chains(), 2);
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_flatten_dup_chains_label_clash() {
+        // Projecting `#0{x y}` keeps only `x`, so the dup must stay.
+        let term: Term = "λx λy dup #0{a _} = #0{x y}; a".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.flatten_dup_chains(), 0);
+        // A different label does not clash.
+        let term: Term = "λx λy dup #1{a _} = #0{x y}; a".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.flatten_dup_chains(), 1);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (λv2 #0{v1 v2}))"
+        );
+    }
+}
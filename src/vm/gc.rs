@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+
+use super::{DupPtrExt, LamPtrExt, NodeIter, Tag, Tagged, TermGraph};
+
+impl TermGraph {
+    /// Frees every node that is no longer reachable from the root, returning
+    /// the number of nodes freed.
+    ///
+    /// Rewrite rules erase what they discard eagerly, but a dup is only erased
+    /// once neither of its variables is used, so dups whose variables are used
+    /// by each other's expressions are left behind. This can be run between
+    /// reduction phases to reclaim them. Nodes pinned by a [`NodeHandle`](super::NodeHandle) are kept, along
+    /// with everything reachable from them. Binders that stay reachable lose
+    /// their uses inside the freed region, and variables bound inside the freed
+    /// region become unbound.
+    pub fn gc(&mut self) -> usize {
+        unsafe {
+            let mut reachable: HashSet<Tagged> = self.node_iter().collect();
+            for pinned in self.pinned_nodes().collect::<Vec<_>>() {
+                reachable.extend(NodeIter::new(pinned));
+            }
+            let garbage: Vec<Tagged> = self
+                .1
+                .arena
+                .live()
+                .filter(|node| !reachable.contains(node))
+                .collect();
+            let garbage_slots: HashSet<*mut Tagged> =
+                garbage.iter().flat_map(|node| node.child_slots()).collect();
+
+            let unbind_escaped = |binder: *mut Tagged, unused: Tagged| {
+                let x = binder.read();
+                if x.tag() == Tag::VarUsePtr && garbage_slots.contains(&x.var_use()) {
+                    binder.write(unused);
+                }
+            };
+            for node in reachable.iter().copied() {
+                match node.tag() {
+                    Tag::LamPtr => unbind_escaped(node.lam().x(), Tagged::new_unused_var()),
+                    Tag::DupPtr => {
+                        unbind_escaped(node.dup().a(), Tagged::new_unused_var());
+                        unbind_escaped(node.dup().b(), Tagged::new_unused_var());
+                    }
+                    _ => {}
+                }
+            }
+            for node in garbage.iter().copied() {
+                if node.tag() == Tag::LamPtr {
+                    let x = node.lam().x().read();
+                    if x.tag() == Tag::VarUsePtr && !garbage_slots.contains(&x.var_use()) {
+                        x.var_use().write(Tagged::new_unbound_var());
+                    }
+                }
+            }
+
+            for node in garbage.iter().copied() {
+                node.dealloc_any_node(&mut self.1);
+            }
+            garbage.len()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+    use crate::vm::App;
+
+    #[test]
+    fn test_gc_nothing_to_free() {
+        let term: Term = "λx (x y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.gc(), 0);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 (v1 y))");
+    }
+
+    #[test]
+    fn test_gc_unreachable_node() {
+        let term: Term = "λx x".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        unsafe {
+            let app_ptr = App::alloc(&mut term_graph.1);
+            app_ptr.app().write(App {
+                e1: Tagged::new_unbound_var(),
+                e2: Tagged::new_unbound_var(),
+            });
+        }
+        assert_eq!(term_graph.gc(), 1);
+        assert_eq!(term_graph.gc(), 0);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_gc_unbinds_escaped_var() {
+        // Strand `λx` by pointing the root directly at its body.
+        let term: Term = "λx (x y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        unsafe {
+            let lam_ptr = term_graph.0.read();
+            let app_ptr = lam_ptr.lam().e().read();
+            term_graph.0.write(app_ptr);
+        }
+        assert_eq!(term_graph.gc(), 1);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(v1 y)");
+    }
+
+    #[test]
+    fn test_gc_releases_reachable_binder() {
+        // Strand `(x y)` by replacing the body of `λx` with a free variable.
+        let term: Term = "λx (x y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        unsafe {
+            let lam_ptr = term_graph.0.read();
+            lam_ptr.lam().e().write(Tagged::new_unbound_var());
+        }
+        assert_eq!(term_graph.gc(), 1);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λ_ v1)");
+    }
+}
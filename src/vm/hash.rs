@@ -0,0 +1,131 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::{AppPtrExt, DupPtrExt, LamPtrExt, Op2PtrExt, SupPtrExt, Tag, Tagged, TermGraph};
+
+/// Numbers binders and labels in the order a traversal first reaches them.
+#[derive(Default)]
+struct Numbering {
+    binders: HashMap<*mut (), usize>,
+    labels: HashMap<u32, usize>,
+}
+
+impl Numbering {
+    /// Returns the number of the binder node `node`, and whether this is the
+    /// first time it was reached.
+    fn binder(&mut self, node: *mut ()) -> (usize, bool) {
+        let next = self.binders.len();
+        let mut first = false;
+        let number = *self.binders.entry(node).or_insert_with(|| {
+            first = true;
+            next
+        });
+        (number, first)
+    }
+
+    fn label(&mut self, l: u32) -> usize {
+        let next = self.labels.len();
+        *self.labels.entry(l).or_insert(next)
+    }
+}
+
+impl TermGraph {
+    /// Returns a hash of the structure of the graph.
+    ///
+    /// The hash is computed from a depth-first traversal of the roots, in which
+    /// binders and labels are numbered in the order they are first reached, so
+    /// it does not depend on node addresses, binder names, the names of free
+    /// variables, or the absolute values of labels. Graphs that are equal up to those have the same hash,
+    /// which makes it usable as a cache key, or as a cheap way to rule out
+    /// graphs that cannot be isomorphic (see [`TermGraph::isomorphic`]).
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let mut numbering = Numbering::default();
+        let mut stack: Vec<*mut Tagged> = self.root_slots().into_iter().rev().collect();
+        unsafe {
+            while let Some(slot) = stack.pop() {
+                let ptr = slot.read();
+                (ptr.tag() as u8).hash(&mut hasher);
+                match ptr.tag() {
+                    Tag::LamPtr => {
+                        let (number, _) = numbering.binder(ptr.ptr());
+                        number.hash(&mut hasher);
+                        (ptr.lam().x().read().tag() as u8).hash(&mut hasher);
+                        stack.push(ptr.lam().e());
+                    }
+                    Tag::AppPtr => {
+                        stack.push(ptr.app().e2());
+                        stack.push(ptr.app().e1());
+                    }
+                    Tag::SupPtr => {
+                        numbering.label(*ptr.sup().l()).hash(&mut hasher);
+                        stack.push(ptr.sup().e2());
+                        stack.push(ptr.sup().e1());
+                    }
+                    Tag::NumPtr => ptr.num().read().n.hash(&mut hasher),
+                    Tag::Op2Ptr => {
+                        ptr.op2().op().read().hash(&mut hasher);
+                        stack.push(ptr.op2().e2());
+                        stack.push(ptr.op2().e1());
+                    }
+                    Tag::LamBoundVar => numbering.binder(ptr.ptr()).0.hash(&mut hasher),
+                    Tag::Ref => ptr.def().name.hash(&mut hasher),
+                    Tag::DupABoundVar | Tag::DupBBoundVar => {
+                        let (number, first) = numbering.binder(ptr.ptr());
+                        number.hash(&mut hasher);
+                        if first {
+                            let dup = ptr.dup();
+                            numbering.label(*dup.l()).hash(&mut hasher);
+                            (dup.a().read().tag() as u8).hash(&mut hasher);
+                            (dup.b().read().tag() as u8).hash(&mut hasher);
+                            stack.push(dup.e());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    fn hash(src: &str) -> u64 {
+        let term: Term = src.parse().unwrap();
+        TermGraph::from(&term).structural_hash()
+    }
+
+    #[test]
+    fn test_structural_hash() {
+        assert_eq!(
+            hash("λx dup #3{a b} = x; #7{a b}"),
+            hash("λy dup #0{c d} = y; #1{c d}")
+        );
+        assert_ne!(
+            hash("λx dup #0{a b} = x; #1{a b}"),
+            hash("λx dup #0{a b} = x; #0{a b}")
+        );
+        assert_ne!(
+            hash("λx dup #0{a b} = x; #1{a b}"),
+            hash("λx dup #0{a b} = x; #1{b a}")
+        );
+        assert_ne!(hash("λx λy x"), hash("λx λy y"));
+        assert_ne!(hash("λx (x y)"), hash("λx (y x)"));
+        assert_ne!(hash("(+ x 1)"), hash("(+ x 2)"));
+        assert_ne!(hash("(+ x 1)"), hash("(* x 1)"));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_structural_hash_after_reduction() {
+        let term: Term = "((λx λy (y x)) z)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        term_graph.naive_random_order_reduce();
+        assert_eq!(term_graph.structural_hash(), hash("λw (w v)"));
+    }
+}
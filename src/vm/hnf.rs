@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use super::spine::{root_ref, walk_spine, Spine};
+use super::{reduce_redex, Redex, Rule, Tagged, TermGraph};
+
+/// Finds the redex at the head of the term in the root slot `slot`, if there
+/// is one. With `under_lambdas` false, a lambda at the head is in (weak) head
+/// normal form.
+pub(super) unsafe fn head_redex(slot: *mut Tagged, under_lambdas: bool) -> Option<Redex> {
+    unsafe {
+        if let Some(redex) = root_ref(slot) {
+            return Some(redex);
+        }
+        match walk_spine(slot, &mut HashSet::new(), under_lambdas) {
+            Spine::Redex(redex) => Some(redex),
+            Spine::Stuck(_) => None,
+        }
+    }
+}
+
+impl TermGraph {
+    /// Reduces the redex at the head of the graph, if there is one.
+    pub fn reduce_hnf_step(&mut self) -> Option<Rule> {
+        unsafe {
+            let redex = head_redex(self.0, true)?;
+            reduce_redex(&mut self.1, redex);
+            Some(redex.into())
+        }
+    }
+
+    /// Reduces the graph to head normal form, returning the number of steps
+    /// taken.
+    ///
+    /// Only the head spine is reduced: the body of leading lambdas, the
+    /// function of applications, and the expressions of the dups that the
+    /// head depends on. Arguments and the branches of a superposition at the
+    /// head are left alone, so no work is spent on terms like `(x Ω)` beyond
+    /// their head.
+    pub fn reduce_hnf(&mut self) -> usize {
+        let mut steps = 0;
+        while self.reduce_hnf_step().is_some() {
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Reduces the redex at the head of the graph, if there is one and the
+    /// graph is not a lambda.
+    pub fn reduce_whnf_step(&mut self) -> Option<Rule> {
+        unsafe {
+            let redex = head_redex(self.0, false)?;
+            reduce_redex(&mut self.1, redex);
+            Some(redex.into())
+        }
+    }
+
+    /// Reduces the graph to weak head normal form, returning the number of
+    /// steps taken.
+    ///
+    /// Like [`TermGraph::reduce_hnf`], but stops as soon as the graph is a
+    /// lambda or a superposition, without reducing the body of the lambda.
+    /// This is what a lazy host needs to take apart one constructor at a time.
+    pub fn reduce_whnf(&mut self) -> usize {
+        let mut steps = 0;
+        while self.reduce_whnf_step().is_some() {
+            steps += 1;
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_reduce_hnf() {
+        // The argument `((λy y) z)` is not reduced.
+        let term: Term = "λx ((λf f) (x ((λy y) z)))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_hnf(), 1);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (v1 (let v3 = z; v3)))"
+        );
+    }
+
+    #[test]
+    fn test_reduce_hnf_ignores_argument() {
+        // x applied to Ω, with Ω = (δ δ) and δ = λx dup #0{a b} = x; (a b).
+        let omega = "(λd dup #0{a b} = d; (a b) λd dup #0{a b} = d; (a b))";
+        let term: Term = format!("λx (x {})", omega).parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_hnf(), 0);
+        assert_eq!(term_graph.reduce_hnf_step(), None);
+    }
+
+    #[test]
+    fn test_reduce_hnf_through_dup() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_hnf_step(), Some(Rule::DupLam));
+        term_graph.reduce_hnf();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_reduce_whnf() {
+        // The head redex is reduced, but not the one under the lambda.
+        let term: Term = "((λf f) λx ((λy y) x))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_whnf(), 1);
+        assert_eq!(term_graph.reduce_whnf_step(), None);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (let v2 = v1; v2))"
+        );
+
+        // A superposition is in weak head normal form too.
+        let term: Term = "((λf #0{f λz z}) λx ((λy y) x))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_whnf(), 1);
+        assert_eq!(term_graph.reduce_hnf(), 0);
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use super::{AppPtrExt, DupPtrExt, LamPtrExt, Op2PtrExt, SupPtrExt, Tag, Tagged, TermGraph};
+
+/// A one-to-one correspondence between the nodes of two graphs, built up as
+/// they are traversed side by side.
+#[derive(Default)]
+struct Bijection {
+    forward: HashMap<*mut (), *mut ()>,
+    backward: HashMap<*mut (), *mut ()>,
+}
+
+impl Bijection {
+    /// Pairs node `a` of the first graph with node `b` of the second. Returns
+    /// whether they were not paired yet, or `None` if either is already
+    /// paired with another node.
+    fn pair(&mut self, a: *mut (), b: *mut ()) -> Option<bool> {
+        match (self.forward.get(&a), self.backward.get(&b)) {
+            (None, None) => {
+                self.forward.insert(a, b);
+                self.backward.insert(b, a);
+                Some(true)
+            }
+            (Some(b2), Some(_)) if *b2 == b => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl TermGraph {
+    /// Returns whether the two graphs have the same structure, whatever the
+    /// addresses of their nodes.
+    ///
+    /// The nodes reachable from the roots of the graphs must correspond one
+    /// to one: corresponding nodes have the same kind, label, number or
+    /// operation, their children correspond, and their variables are either
+    /// both unused or used by corresponding nodes. Free variables and
+    /// references must have the same names, and the graphs the same roots, in
+    /// the same order.
+    ///
+    /// Unlike [`TermGraph::structural_hash`], labels and the names of free
+    /// variables are compared as they are, but isomorphic graphs always have
+    /// the same hash.
+    pub fn isomorphic(&self, other: &TermGraph) -> bool {
+        if self.root_names() != other.root_names() {
+            return false;
+        }
+        let mut nodes = Bijection::default();
+        let mut stack: Vec<(*mut Tagged, *mut Tagged)> = self
+            .root_slots()
+            .into_iter()
+            .zip(other.root_slots())
+            .rev()
+            .collect();
+        unsafe {
+            while let Some((slot_a, slot_b)) = stack.pop() {
+                let (a, b) = (slot_a.read(), slot_b.read());
+                if a.tag() != b.tag() {
+                    return false;
+                }
+                let agree = match a.tag() {
+                    // Each lambda has a single parent, so its body is only
+                    // pushed once, whether or not its variable came first.
+                    Tag::LamPtr => {
+                        stack.push((a.lam().e(), b.lam().e()));
+                        nodes.pair(a.ptr(), b.ptr()).is_some()
+                            && a.lam().x().read().tag() == b.lam().x().read().tag()
+                    }
+                    Tag::LamBoundVar => nodes.pair(a.ptr(), b.ptr()).is_some(),
+                    Tag::AppPtr => {
+                        stack.push((a.app().e2(), b.app().e2()));
+                        stack.push((a.app().e1(), b.app().e1()));
+                        true
+                    }
+                    Tag::SupPtr => {
+                        stack.push((a.sup().e2(), b.sup().e2()));
+                        stack.push((a.sup().e1(), b.sup().e1()));
+                        *a.sup().l() == *b.sup().l()
+                    }
+                    Tag::NumPtr => a.num().read().n == b.num().read().n,
+                    Tag::Op2Ptr => {
+                        stack.push((a.op2().e2(), b.op2().e2()));
+                        stack.push((a.op2().e1(), b.op2().e1()));
+                        a.op2().op().read() == b.op2().op().read()
+                    }
+                    Tag::DupABoundVar | Tag::DupBBoundVar => {
+                        let (dup_a, dup_b) = (a.dup(), b.dup());
+                        match nodes.pair(a.ptr(), b.ptr()) {
+                            None => false,
+                            Some(false) => true,
+                            Some(true) => {
+                                stack.push((dup_a.e(), dup_b.e()));
+                                *dup_a.l() == *dup_b.l()
+                                    && dup_a.a().read().tag() == dup_b.a().read().tag()
+                                    && dup_a.b().read().tag() == dup_b.b().read().tag()
+                            }
+                        }
+                    }
+                    Tag::UnboundVar => a.free_name() == b.free_name(),
+                    Tag::Ref => a.def().name == b.def().name,
+                    _ => true,
+                };
+                if !agree {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::intern::Intern;
+    use crate::syntax::Term;
+
+    fn graph(src: &str) -> TermGraph {
+        TermGraph::from(&src.parse::<Term>().unwrap())
+    }
+
+    #[test]
+    fn test_isomorphic() {
+        let same = [
+            ("λx dup #3{a b} = x; #7{a b}", "λy dup #3{c d} = y; #7{c d}"),
+            ("λx λy (x (f y))", "λa λb (a (f b))"),
+            ("(+ 1 (λx x 2))", "(+ 1 (λy y 2))"),
+        ];
+        for (a, b) in same {
+            assert!(graph(a).isomorphic(&graph(b)), "{} {}", a, b);
+            assert_eq!(graph(a).structural_hash(), graph(b).structural_hash());
+        }
+        let different = [
+            // Labels and free variables are compared as they are.
+            ("λx dup #0{a b} = x; #1{a b}", "λx dup #2{a b} = x; #1{a b}"),
+            ("λx (x y)", "λx (x z)"),
+            // Swapped dup variables.
+            ("λx dup #0{a b} = x; #1{a b}", "λx dup #0{a b} = x; #1{b a}"),
+            ("λx λy x", "λx λy y"),
+            ("λx λy x", "λx λy λz x"),
+            ("(+ x 1)", "(* x 1)"),
+            ("(+ x 1)", "(+ x 2)"),
+            ("λx 1", "λx (x 1)"),
+        ];
+        for (a, b) in different {
+            assert!(!graph(a).isomorphic(&graph(b)), "{} {}", a, b);
+            assert!(!graph(b).isomorphic(&graph(a)), "{} {}", b, a);
+        }
+    }
+
+    #[test]
+    fn test_isomorphic_after_reduction() {
+        let mut term_graph = graph("((λf λx dup #0{f1 f2} = f; (f1 (f2 x))) λy (+ y 1))");
+        assert!(term_graph.isomorphic(&term_graph.clone()));
+        while term_graph.naive_reduce_step().is_some() {}
+        let expected = graph("λx (+ (+ x 1) 1)");
+        assert!(term_graph.isomorphic(&expected));
+        assert!(expected.isomorphic(&term_graph));
+    }
+
+    #[test]
+    fn test_isomorphic_roots() {
+        let y = "y".into();
+        let roots = |names: [&str; 2]| {
+            let roots: Vec<_> = names
+                .iter()
+                .map(|name| (name.intern(), Term::Var(y)))
+                .collect();
+            TermGraph::from_roots(&[], &roots).unwrap()
+        };
+        assert!(roots(["a", "b"]).isomorphic(&roots(["a", "b"])));
+        assert!(!roots(["a", "b"]).isomorphic(&roots(["b", "a"])));
+    }
+}
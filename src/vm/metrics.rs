@@ -0,0 +1,136 @@
+use std::io;
+
+use super::{Rule, StrategyConfig, TermGraph};
+
+/// Measurements of a graph taken after a reduction step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StepMetrics {
+    /// The number of the step, where step 0 is the initial graph.
+    pub step: usize,
+    /// The rule applied in this step, or `None` for step 0.
+    pub rule: Option<Rule>,
+    /// The number of live `Lam` nodes.
+    pub lams: usize,
+    /// The number of live `App` nodes.
+    pub apps: usize,
+    /// The number of live `Sup` nodes.
+    pub sups: usize,
+    /// The number of live `Dup` nodes.
+    pub dups: usize,
+    /// The number of live `Num` nodes.
+    pub nums: usize,
+    /// The number of live `Op2` nodes.
+    pub ops: usize,
+    /// The number of nodes allocated in this step. For step 0, this is the
+    /// number of nodes the graph was built with.
+    pub allocations: usize,
+}
+
+impl StepMetrics {
+    /// The header row of [`TermGraph::naive_reduce_csv`].
+    pub const CSV_HEADER: &'static str = "step,rule,lams,apps,sups,dups,nums,ops,allocations";
+
+    /// Formats the metrics as a CSV row matching [`StepMetrics::CSV_HEADER`].
+    pub fn to_csv_row(&self) -> String {
+        let rule = self
+            .rule
+            .map(|rule| format!("{:?}", rule))
+            .unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.step,
+            rule,
+            self.lams,
+            self.apps,
+            self.sups,
+            self.dups,
+            self.nums,
+            self.ops,
+            self.allocations
+        )
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// passing the metrics of the initial graph and of the graph after every
+    /// step to `on_step`.
+    ///
+    /// Stops at the first error returned by `on_step`. Otherwise, returns the
+    /// number of steps taken.
+    pub fn naive_reduce_with_metrics<E>(
+        &mut self,
+        config: &StrategyConfig,
+        mut on_step: impl FnMut(&StepMetrics) -> Result<(), E>,
+    ) -> Result<usize, E> {
+        let mut allocations = self.1.allocations;
+        on_step(&self.metrics(0, None, allocations))?;
+        let mut steps = 0;
+        while let Some(rule) = self.naive_reduce_step_with(config) {
+            steps += 1;
+            let allocated = self.1.allocations - allocations;
+            allocations = self.1.allocations;
+            on_step(&self.metrics(steps, Some(rule), allocated))?;
+        }
+        Ok(steps)
+    }
+
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_with_metrics`], writing the metrics to `out`
+    /// as CSV: a header row, then one row per step. Returns the number of
+    /// steps taken.
+    pub fn naive_reduce_csv(
+        &mut self,
+        config: &StrategyConfig,
+        out: &mut impl io::Write,
+    ) -> io::Result<usize> {
+        writeln!(out, "{}", StepMetrics::CSV_HEADER)?;
+        self.naive_reduce_with_metrics(config, |metrics| writeln!(out, "{}", metrics.to_csv_row()))
+    }
+
+    pub(super) fn metrics(
+        &self,
+        step: usize,
+        rule: Option<Rule>,
+        allocations: usize,
+    ) -> StepMetrics {
+        let live = self.1.live_nodes();
+        StepMetrics {
+            step,
+            rule,
+            lams: live.lams,
+            apps: live.apps,
+            sups: live.sups,
+            dups: live.dups,
+            nums: live.nums,
+            ops: live.ops,
+            allocations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_csv() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut out = vec![];
+        let steps = term_graph
+            .naive_reduce_csv(&StrategyConfig::default(), &mut out)
+            .unwrap();
+        assert_eq!(steps, 3);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "step,rule,lams,apps,sups,dups,nums,ops,allocations\n\
+             0,,1,1,0,1,0,0,3\n\
+             1,DupLam,2,1,1,1,0,0,4\n\
+             2,AppLam,1,0,1,1,0,0,0\n\
+             3,DupSup,1,0,0,0,0,0,0\n"
+        );
+    }
+}
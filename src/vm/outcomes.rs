@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::TermGraph;
+use crate::book::Book;
+use crate::intern::IStr;
+use crate::syntax::{Label, Term};
+
+/// An iterator over the outcomes of a graph, created by
+/// [`TermGraph::outcomes`].
+pub struct Outcomes {
+    pending: Vec<Term>,
+    /// The definitions that the pending terms refer to.
+    book: Arc<Book>,
+}
+
+impl TermGraph {
+    /// Returns an iterator over the outcomes of the graph: its normal form with
+    /// every superposition resolved to one of its branches.
+    ///
+    /// All superpositions with the same label are resolved to the same side,
+    /// so a graph with `n` distinct labels has up to `2^n` outcomes. They are
+    /// produced one at a time, first branches first, each as its own graph in
+    /// normal form. A superposition inside the expression of a dup with the
+    /// same label belongs to that dup and is left alone.
+    pub fn outcomes(&self) -> Outcomes {
+        Outcomes {
+            pending: vec![Term::from(self)],
+            book: self.1.book.clone(),
+        }
+    }
+
+    /// Returns an iterator over the outcomes of the graph as plain lambda
+    /// terms, with neither superpositions nor dups.
+    ///
+    /// Each outcome of [`TermGraph::outcomes`] is collapsed by substituting
+    /// the expression of each of its remaining dups for the dup's variables,
+    /// where the first variable takes the first branch of the superpositions
+    /// with the dup's label, and the second takes the second. A term may then
+    /// use a variable more than once, so it is not necessarily one that a
+    /// graph can be built from.
+    pub fn collapse(&self) -> impl Iterator<Item = Term> {
+        self.outcomes()
+            .map(|term_graph| expand_dups(&Term::from(&term_graph), &mut HashMap::new()))
+    }
+}
+
+impl Iterator for Outcomes {
+    type Item = TermGraph;
+
+    fn next(&mut self) -> Option<TermGraph> {
+        loop {
+            let term = self.pending.pop()?;
+            let mut term_graph = TermGraph::with_book(self.book.clone(), &term);
+            term_graph.reduce_normal_order();
+            let term = Term::from(&term_graph);
+            match sup_label(&term, &mut vec![]) {
+                Some(l) => {
+                    self.pending.push(project(&term, l, false));
+                    self.pending.push(project(&term, l, true));
+                }
+                None => return Some(term_graph),
+            }
+        }
+    }
+}
+
+/// Returns the label of the first superposition in `term` that is not inside
+/// the expression of a dup with the same label.
+fn sup_label(term: &Term, dup_labels: &mut Vec<Label>) -> Option<Label> {
+    match term {
+        Term::Var(_) | Term::Ref(_) | Term::Num(_) => None,
+        Term::Lam(_, e) => sup_label(e, dup_labels),
+        Term::App(e1, e2) | Term::Op2(_, e1, e2) | Term::Let(_, e1, e2) => {
+            sup_label(e1, dup_labels).or_else(|| sup_label(e2, dup_labels))
+        }
+        Term::Sup(l, e1, e2) => {
+            if !dup_labels.contains(l) {
+                return Some(*l);
+            }
+            sup_label(e1, dup_labels).or_else(|| sup_label(e2, dup_labels))
+        }
+        Term::Dup(l, _, _, e, cont) => {
+            dup_labels.push(*l);
+            let label = sup_label(e, dup_labels);
+            dup_labels.pop();
+            label.or_else(|| sup_label(cont, dup_labels))
+        }
+    }
+}
+
+/// Replaces each superposition labeled `l` in `term` (outside the expressions
+/// of dups labeled `l`) by its first branch, or by its second if `first` is
+/// false.
+fn project(term: &Term, l: Label, first: bool) -> Term {
+    let recurse = |e: &Term| Box::new(project(e, l, first));
+    match term {
+        Term::Var(x) => Term::Var(*x),
+        Term::Ref(name) => Term::Ref(*name),
+        Term::Num(n) => Term::Num(*n),
+        Term::Lam(x, e) => Term::Lam(*x, recurse(e)),
+        Term::App(e1, e2) => Term::App(recurse(e1), recurse(e2)),
+        Term::Op2(op, e1, e2) => Term::Op2(*op, recurse(e1), recurse(e2)),
+        Term::Sup(m, e1, e2) if *m == l => {
+            if first {
+                project(e1, l, first)
+            } else {
+                project(e2, l, first)
+            }
+        }
+        Term::Sup(m, e1, e2) => Term::Sup(*m, recurse(e1), recurse(e2)),
+        Term::Dup(m, a, b, e, cont) if *m == l => Term::Dup(*m, *a, *b, e.clone(), recurse(cont)),
+        Term::Dup(m, a, b, e, cont) => Term::Dup(*m, *a, *b, recurse(e), recurse(cont)),
+        Term::Let(x, e, cont) => Term::Let(*x, recurse(e), recurse(cont)),
+    }
+}
+
+/// Returns `term` with the variables of each dup replaced by their copies of
+/// its expression, and the variables in `env` by their terms.
+fn expand_dups(term: &Term, env: &mut HashMap<IStr, Term>) -> Term {
+    let recurse = |e: &Term, env: &mut HashMap<IStr, Term>| Box::new(expand_dups(e, env));
+    match term {
+        Term::Var(x) => env.get(x).cloned().unwrap_or(Term::Var(*x)),
+        Term::Ref(_) | Term::Num(_) => term.clone(),
+        Term::Lam(x, e) => Term::Lam(*x, recurse(e, env)),
+        Term::App(e1, e2) => Term::App(recurse(e1, env), recurse(e2, env)),
+        Term::Sup(l, e1, e2) => Term::Sup(*l, recurse(e1, env), recurse(e2, env)),
+        Term::Op2(op, e1, e2) => Term::Op2(*op, recurse(e1, env), recurse(e2, env)),
+        Term::Dup(l, a, b, e, cont) => {
+            let a_copy = expand_dups(&project(e, *l, true), env);
+            let b_copy = expand_dups(&project(e, *l, false), env);
+            env.insert(*a, a_copy);
+            env.insert(*b, b_copy);
+            expand_dups(cont, env)
+        }
+        Term::Let(x, e, cont) => Term::Let(*x, recurse(e, env), recurse(cont, env)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn outcomes(src: &str) -> Vec<String> {
+        let term: Term = src.parse().unwrap();
+        TermGraph::from(&term)
+            .outcomes()
+            .map(|term_graph| format!("{}", Term::from(&term_graph)))
+            .collect()
+    }
+
+    #[test]
+    fn test_outcomes() {
+        assert_eq!(outcomes("λx x"), vec!["(λv1 v1)"]);
+        assert_eq!(
+            outcomes("λa λb λc #0{a #1{b c}}"),
+            vec![
+                "(λv1 (λ_ (λ_ v1)))",
+                "(λ_ (λv1 (λ_ v1)))",
+                "(λ_ (λ_ (λv1 v1)))"
+            ]
+        );
+        assert_eq!(outcomes("λx #0{x λy y}"), vec!["(λv1 v1)", "(λ_ (λv1 v1))"]);
+    }
+
+    #[test]
+    fn test_outcomes_same_label() {
+        // Both superpositions resolve to the same side.
+        assert_eq!(
+            outcomes("λx λy dup #1{x1 x2} = x; dup #2{y1 y2} = y; (#0{x1 y1} #0{y2 x2})"),
+            vec![
+                "(λv1 (λv3 ((dup #1{v2 _} = v1; v2) (dup #2{_ v4} = v3; v4))))",
+                "(λv3 (λv1 ((dup #2{v2 _} = v1; v2) (dup #1{_ v4} = v3; v4))))"
+            ]
+        );
+    }
+
+    fn collapsed(src: &str) -> Vec<String> {
+        let term: Term = src.parse().unwrap();
+        TermGraph::from(&term)
+            .collapse()
+            .map(|term| format!("{}", term))
+            .collect()
+    }
+
+    #[test]
+    fn test_collapse() {
+        assert_eq!(
+            collapsed("λx #0{x λy y}"),
+            vec!["(λv1 v1)", "(λ_ (λv1 v1))"]
+        );
+        assert_eq!(
+            collapsed("λx λy dup #1{x1 x2} = x; dup #2{y1 y2} = y; (#0{x1 y1} #0{y2 x2})"),
+            vec!["(λv1 (λv3 (v1 v3)))", "(λv3 (λv1 (v1 v3)))"]
+        );
+        // A dup of a variable copies it.
+        assert_eq!(
+            collapsed("λf dup #0{f1 f2} = f; λx (f1 (f2 x))"),
+            vec!["(λv1 (λv4 (v1 (v1 v4))))"]
+        );
+        // Each copy of a stuck expression takes its own side of the
+        // superpositions with the dup's label.
+        assert_eq!(
+            collapsed("λf dup #0{a b} = (f #0{1 2}); #1{a b}"),
+            vec!["(λv1 (v1 1))", "(λv1 (v1 2))"]
+        );
+    }
+
+    #[test]
+    fn test_outcomes_after_reduction() {
+        // dup #0{a b} = #0{λx x λy λz y}; #1{a b}
+        assert_eq!(
+            outcomes("dup #0{a b} = #0{λx x λy λz y}; #1{a b}"),
+            vec!["(λv1 v1)", "(λv1 (λ_ v1))"]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::thread;
+
+use super::{reduce_redex, Heap, Redex, TermGraph};
+
+/// Supersteps with fewer redexes than this per thread are reduced on the
+/// calling thread, since spawning the workers would cost more than it saves.
+const MIN_REDEXES_PER_THREAD: usize = 32;
+
+/// A value holding pointers into a graph, moved to or from a worker thread.
+struct Disjoint<T>(T);
+
+// SAFETY: The redexes of a superstep are independent: no rewrite frees a node
+// that another frees or writes to, and no two rewrites write the same slot.
+// Each worker reduces its own redexes into its own `Heap`, so the workers
+// never access the same memory, except to read nodes that none of them
+// writes.
+unsafe impl<T> Send for Disjoint<T> {}
+
+impl<T> Disjoint<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl Heap {
+    /// Takes over the nodes allocated and released by a worker's heap.
+    fn absorb(&mut self, worker: Heap) {
+        self.allocations += worker.allocations;
+        self.freed += worker.freed;
+        for (rewrites, worker_rewrites) in self.rewrites.iter_mut().zip(worker.rewrites) {
+            *rewrites += worker_rewrites;
+        }
+        // A worker may have reused the memory of a node it released for a
+        // new node, and released that too, so the new nodes are counted
+        // before the released ones are forgotten. The released nodes are in
+        // the worker's free lists, so its arena is only taken over after.
+        for (count, worker_count) in self.live_by_type.iter_mut().zip(worker.live_by_type) {
+            *count += worker_count;
+        }
+        for node in worker.foreign.unwrap_or_default() {
+            self.release(node);
+        }
+        self.arena.absorb(worker.arena);
+        // The peaks of the workers need not have happened at the same time,
+        // so the peak is only sampled between supersteps.
+        self.peak_live = self.peak_live.max(self.live_count());
+        #[cfg(feature = "profiling")]
+        self.latency.merge(&worker.latency);
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form in supersteps, like
+    /// [`TermGraph::reduce_in_supersteps`], splitting the redexes of each
+    /// superstep between up to `num_threads` threads. Returns the number of
+    /// rewrites.
+    ///
+    /// Since the redexes of a superstep are independent, the normal form is
+    /// the same as with one thread. Finding the redexes of each superstep
+    /// still takes a pass over the whole graph on the calling thread, so this
+    /// only pays off for graphs with many redexes at once.
+    ///
+    /// Panics if `num_threads` is 0.
+    pub fn parallel_reduce(&mut self, num_threads: usize) -> usize {
+        assert!(num_threads > 0, "parallel_reduce needs at least one thread");
+        let mut rewrites = 0;
+        loop {
+            let redexes = unsafe { self.independent_redexes() };
+            if redexes.is_empty() {
+                return rewrites;
+            }
+            rewrites += redexes.len();
+            let threads = num_threads.min(redexes.len() / MIN_REDEXES_PER_THREAD);
+            if threads <= 1 {
+                for redex in redexes {
+                    unsafe { reduce_redex(&mut self.1, redex) };
+                }
+                continue;
+            }
+            let chunk_size = redexes.len().div_ceil(threads);
+            let workers: Vec<Heap> = thread::scope(|scope| {
+                let handles: Vec<_> = redexes
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        let chunk = Disjoint(chunk.to_vec());
+                        // Unfolding a reference looks up the definitions it
+                        // refers to in the book.
+                        let book = self.1.book.clone();
+                        scope.spawn(move || {
+                            let redexes: Vec<Redex> = chunk.into_inner();
+                            let mut heap = Heap {
+                                foreign: Some(vec![]),
+                                book,
+                                ..Heap::default()
+                            };
+                            for redex in redexes {
+                                unsafe { reduce_redex(&mut heap, redex) };
+                            }
+                            Disjoint(heap)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap().into_inner())
+                    .collect()
+            });
+            for worker in workers {
+                self.1.absorb(worker);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::examples;
+    use crate::syntax::Term;
+
+    /// Returns a tree of superpositions with `leaves` applications of an
+    /// identity at the leaves.
+    fn wide_term(leaves: usize) -> Term {
+        let mut terms: Vec<String> = (0..leaves).map(|i| format!("((λx x) y{})", i)).collect();
+        while terms.len() > 1 {
+            terms = terms
+                .chunks(2)
+                .map(|pair| format!("#0{{{} {}}}", pair[0], pair[1]))
+                .collect();
+        }
+        terms[0].parse().unwrap()
+    }
+
+    #[test]
+    fn test_parallel_reduce() {
+        // Miri is slow enough that a term for two threads has to do.
+        let term = wide_term(if cfg!(miri) { 64 } else { 256 });
+        let mut sequential = TermGraph::from(&term);
+        let mut steps = 0;
+        while sequential.naive_reduce_step().is_some() {
+            steps += 1;
+        }
+        let mut parallel = TermGraph::from(&term);
+        assert_eq!(parallel.parallel_reduce(4), steps);
+        assert_eq!(parallel.structural_hash(), sequential.structural_hash());
+        assert_eq!(
+            parallel.1.arena.live().count(),
+            sequential.1.arena.live().count()
+        );
+        assert_eq!(parallel.gc(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_parallel_reduce_examples() {
+        for name in examples::names() {
+            let term = examples::load(name).unwrap();
+            let mut sequential = TermGraph::from(&term);
+            sequential.reduce_in_supersteps();
+            let mut parallel = TermGraph::from(&term);
+            parallel.parallel_reduce(2);
+            assert_eq!(Term::from(&parallel), Term::from(&sequential), "{}", name);
+        }
+    }
+}
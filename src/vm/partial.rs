@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+
+use super::spine::root_ref;
+use super::{
+    op2_redex, reduce_redex, AppPtrExt, DupPtrExt, LamPtrExt, Op2PtrExt, Redex, SupPtrExt, Tag,
+    Tagged, TermGraph,
+};
+use crate::error::Error;
+use crate::syntax::Term;
+
+/// Finds the first redex, in the same order as the naive strategies, that
+/// lies under at most `max_depth` lambdas.
+///
+/// The depth of a dup's redexes is the depth at which one of its variables is
+/// first reached.
+unsafe fn shallow_redex(roots: &[*mut Tagged], max_depth: usize) -> Option<Redex> {
+    unsafe {
+        if let Some(redex) = roots.iter().find_map(|root| root_ref(*root)) {
+            return Some(redex);
+        }
+        let mut visited = HashSet::new();
+        let mut stack: Vec<(*mut Tagged, usize)> = roots.iter().rev().map(|r| (*r, 0)).collect();
+        while let Some((ptr_ptr, depth)) = stack.pop() {
+            let ptr = ptr_ptr.read();
+            // A lambda's variable shares the lambda's pointer, and may be
+            // reached before it.
+            if ptr.tag() == Tag::LamBoundVar || !visited.insert(ptr.ptr()) {
+                continue;
+            }
+            match ptr.tag() {
+                Tag::LamPtr if depth < max_depth => stack.push((ptr.lam().e(), depth + 1)),
+                Tag::AppPtr => {
+                    let e1 = ptr.app().e1().read();
+                    match e1.tag() {
+                        Tag::LamPtr => {
+                            return Some(Redex::AppLam {
+                                ptr_ptr,
+                                app_ptr: ptr,
+                                lam_ptr: e1,
+                            })
+                        }
+                        Tag::SupPtr => {
+                            return Some(Redex::AppSup {
+                                ptr_ptr,
+                                app_ptr: ptr,
+                                sup_ptr: e1,
+                            })
+                        }
+                        Tag::Ref => {
+                            return Some(Redex::Ref {
+                                ptr_ptr: ptr.app().e1(),
+                            })
+                        }
+                        _ => {}
+                    }
+                    stack.push((ptr.app().e2(), depth));
+                    stack.push((ptr.app().e1(), depth));
+                }
+                Tag::SupPtr => {
+                    stack.push((ptr.sup().e2(), depth));
+                    stack.push((ptr.sup().e1(), depth));
+                }
+                Tag::Op2Ptr => {
+                    if let Some(redex) = op2_redex(ptr_ptr, ptr) {
+                        return Some(redex);
+                    }
+                    stack.push((ptr.op2().e2(), depth));
+                    stack.push((ptr.op2().e1(), depth));
+                }
+                Tag::DupABoundVar | Tag::DupBBoundVar => {
+                    let e = ptr.dup().e().read();
+                    match e.tag() {
+                        Tag::LamPtr => {
+                            return Some(Redex::DupLam {
+                                dup_ptr: ptr,
+                                lam_ptr: e,
+                            })
+                        }
+                        Tag::SupPtr => {
+                            return Some(Redex::DupSup {
+                                dup_ptr: ptr,
+                                sup_ptr: e,
+                            })
+                        }
+                        Tag::NumPtr => {
+                            return Some(Redex::DupNum {
+                                dup_ptr: ptr,
+                                num_ptr: e,
+                            })
+                        }
+                        Tag::Ref => {
+                            return Some(Redex::Ref {
+                                ptr_ptr: ptr.dup().e(),
+                            })
+                        }
+                        _ => {}
+                    }
+                    stack.push((ptr.dup().e(), depth));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph until no redexes remain under at most `max_depth`
+    /// lambdas, returning the number of steps taken.
+    ///
+    /// With a `max_depth` of 0, only redexes outside of every lambda are
+    /// reduced, as in a whole-program evaluator. Larger depths also normalize
+    /// the open subterms in the bodies of the outermost lambdas, which
+    /// specializes a function to the arguments it was applied to.
+    pub fn reduce_under_lambdas(&mut self, max_depth: usize) -> usize {
+        let roots = self.root_slots();
+        let mut steps = 0;
+        unsafe {
+            while let Some(redex) = shallow_redex(&roots, max_depth) {
+                reduce_redex(&mut self.1, redex);
+                steps += 1;
+            }
+        }
+        steps
+    }
+}
+
+/// Applies `function` to `args`, reduces the result under at most `max_depth`
+/// lambdas (see [`TermGraph::reduce_under_lambdas`]), and reads back the
+/// specialized function.
+///
+/// Returns an error if `function` or one of `args` is not well formed (see
+/// [`TermGraph::try_from_term`]).
+pub fn specialize(function: &Term, args: &[Term], max_depth: usize) -> Result<Term, Error> {
+    let applied = args.iter().fold(function.clone(), |f, arg| {
+        Term::App(Box::new(f), Box::new(arg.clone()))
+    });
+    let mut term_graph = TermGraph::try_from_term(&applied)?;
+    term_graph.reduce_under_lambdas(max_depth);
+    Ok(Term::from(&term_graph))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(src: &str) -> Term {
+        src.parse().unwrap()
+    }
+
+    #[test]
+    fn test_reduce_under_lambdas() {
+        let term = parse("((λx λy ((λz z) (x y))) λw w)");
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_under_lambdas(0), 1);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (let v3 = (let v2 = v1; v2); v3))"
+        );
+        assert_eq!(term_graph.reduce_under_lambdas(1), 2);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+        assert_eq!(term_graph.naive_reduce_step(), None);
+    }
+
+    #[test]
+    fn test_specialize() {
+        // Specializing `λb λx λy ((b x) y)` to `true` gives `λx λy x`.
+        let select = parse("λb λx λy ((b x) y)");
+        let specialized = specialize(&select, &[parse("λt λf t")], 2).unwrap();
+        assert_eq!(format!("{}", specialized), "(λv1 (λ_ v1))");
+        // At depth 0, the body is left alone.
+        let specialized = specialize(&select, &[parse("λt λf t")], 0).unwrap();
+        assert_eq!(
+            format!("{}", specialized),
+            "(λv1 (λv3 ((let v2 = v1; (λ_ v2)) v3)))"
+        );
+    }
+}
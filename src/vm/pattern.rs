@@ -0,0 +1,410 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use super::{Cursor, GraphVisitor, LamPtrExt, NodeId, NodeKind, Tag, TermGraph, Visit};
+use crate::error::Error;
+use crate::intern::{IStr, Intern, InternStatic};
+use crate::syntax::{Label, Op, Term};
+
+/// The shape of a subgraph, to be found with [`TermGraph::find`].
+///
+/// Patterns can be built with the constructor functions, e.g.
+/// `Pattern::app(Pattern::lam(Pattern::Any).unused(), Pattern::Any)` for an
+/// application of a lambda whose variable is unused, or parsed from the term
+/// syntax, where the same pattern is `((λ_ body) arg)`:
+///
+/// - a free variable matches anything, and captures it under its name (see
+///   [`TermGraph::rewrite`]), except for `_`, which only matches;
+/// - a lambda bound to `_` only matches lambdas whose variable is unused;
+/// - a variable bound by a lambda matches the variable of the lambda matched
+///   by that lambda's pattern;
+/// - a variable bound by a dup matches a variable of a dup with the same label
+///   whose expression matches the dup's expression;
+/// - superpositions match superpositions with the same label;
+/// - numbers and operations match the same number or operator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// Matches anything.
+    Any,
+    /// Matches anything, capturing it under a name.
+    Capture(IStr),
+    /// Matches any variable.
+    Var,
+    /// Matches the variable of the lambda matched by the enclosing lambda
+    /// pattern at this level, counting from the outermost one (0).
+    LamVar(usize),
+    /// Matches a free variable.
+    FreeVar,
+    /// Matches a reference to the definition with the given name.
+    Ref(IStr),
+    /// Matches the given number.
+    Num(u64),
+    Lam {
+        /// If set, whether the variable of the lambda must be used.
+        var_used: Option<bool>,
+        body: Box<Pattern>,
+    },
+    App(Box<Pattern>, Box<Pattern>),
+    Op2(Op, Box<Pattern>, Box<Pattern>),
+    Sup {
+        label: Option<Label>,
+        left: Box<Pattern>,
+        right: Box<Pattern>,
+    },
+    /// Matches a variable bound by a dup.
+    Dup {
+        label: Option<Label>,
+        expr: Box<Pattern>,
+    },
+}
+
+impl Pattern {
+    pub fn capture(name: &str) -> Self {
+        Pattern::Capture(name.intern())
+    }
+
+    pub fn lam(body: Pattern) -> Self {
+        Pattern::Lam {
+            var_used: None,
+            body: Box::new(body),
+        }
+    }
+
+    pub fn app(fun: Pattern, arg: Pattern) -> Self {
+        Pattern::App(Box::new(fun), Box::new(arg))
+    }
+
+    pub fn sup(left: Pattern, right: Pattern) -> Self {
+        Pattern::Sup {
+            label: None,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn dup(expr: Pattern) -> Self {
+        Pattern::Dup {
+            label: None,
+            expr: Box::new(expr),
+        }
+    }
+
+    /// Restricts a lambda pattern to lambdas whose variable is unused.
+    pub fn unused(self) -> Self {
+        self.with_var_used(false)
+    }
+
+    /// Restricts a lambda pattern to lambdas whose variable is used.
+    pub fn used(self) -> Self {
+        self.with_var_used(true)
+    }
+
+    fn with_var_used(self, used: bool) -> Self {
+        match self {
+            Pattern::Lam { body, .. } => Pattern::Lam {
+                var_used: Some(used),
+                body,
+            },
+            pattern => pattern,
+        }
+    }
+
+    /// Restricts a superposition or dup pattern to the label `l`.
+    pub fn with_label(self, l: Label) -> Self {
+        match self {
+            Pattern::Sup { left, right, .. } => Pattern::Sup {
+                label: Some(l),
+                left,
+                right,
+            },
+            Pattern::Dup { expr, .. } => Pattern::Dup {
+                label: Some(l),
+                expr,
+            },
+            pattern => pattern,
+        }
+    }
+
+    /// Converts a term to the pattern it denotes (see [`Pattern`]).
+    pub fn from_term(term: &Term) -> Self {
+        from_term(term, 0, &mut HashMap::new())
+    }
+
+    /// Returns whether the position of `cursor` matches this pattern.
+    pub fn matches(&self, cursor: Cursor<'_>) -> bool {
+        self.match_at(cursor, &mut vec![], &mut None)
+    }
+
+    /// Like [`Pattern::matches`], but also returns the captured positions.
+    pub(super) fn captures<'g>(&self, cursor: Cursor<'g>) -> Option<Vec<(IStr, Cursor<'g>)>> {
+        let mut captures = vec![];
+        self.match_at(cursor, &mut vec![], &mut Some(&mut captures))
+            .then_some(captures)
+    }
+
+    /// Matches `cursor` against this pattern, where `lams` holds the lambdas
+    /// matched by the enclosing lambda patterns. Captured positions are added
+    /// to `captures`, if given, except inside the expression of a dup.
+    fn match_at<'g>(
+        &self,
+        cursor: Cursor<'g>,
+        lams: &mut Vec<*mut ()>,
+        captures: &mut Option<&mut Vec<(IStr, Cursor<'g>)>>,
+    ) -> bool {
+        let kind = cursor.kind();
+        match self {
+            Pattern::Any => true,
+            Pattern::Capture(x) => {
+                if let Some(captures) = captures {
+                    captures.push((*x, cursor));
+                }
+                true
+            }
+            Pattern::Var => matches!(
+                kind,
+                NodeKind::LamVar | NodeKind::DupAVar | NodeKind::DupBVar | NodeKind::FreeVar
+            ),
+            Pattern::LamVar(level) => {
+                kind == NodeKind::LamVar && lams.get(*level) == Some(&cursor.ptr().ptr())
+            }
+            Pattern::FreeVar => kind == NodeKind::FreeVar,
+            Pattern::Ref(name) => cursor.ref_name() == Some(*name),
+            Pattern::Num(n) => cursor.num() == Some(*n),
+            Pattern::Op2(op, e1, e2) => {
+                cursor.op() == Some(*op)
+                    && cursor
+                        .op_left()
+                        .is_some_and(|e| e1.match_at(e, lams, captures))
+                    && cursor
+                        .op_right()
+                        .is_some_and(|e| e2.match_at(e, lams, captures))
+            }
+            Pattern::Lam { var_used, body } => {
+                if kind != NodeKind::Lam
+                    || var_used.is_some_and(|used| lam_var_used(cursor) != used)
+                {
+                    return false;
+                }
+                lams.push(cursor.ptr().ptr());
+                let matched = cursor
+                    .lam_body()
+                    .is_some_and(|e| body.match_at(e, lams, captures));
+                lams.pop();
+                matched
+            }
+            Pattern::App(fun, arg) => {
+                kind == NodeKind::App
+                    && cursor
+                        .app_fun()
+                        .is_some_and(|e| fun.match_at(e, lams, captures))
+                    && cursor
+                        .app_arg()
+                        .is_some_and(|e| arg.match_at(e, lams, captures))
+            }
+            Pattern::Sup { label, left, right } => {
+                kind == NodeKind::Sup
+                    && label.is_none_or(|l| cursor.label() == Some(l))
+                    && cursor
+                        .sup_left()
+                        .is_some_and(|e| left.match_at(e, lams, captures))
+                    && cursor
+                        .sup_right()
+                        .is_some_and(|e| right.match_at(e, lams, captures))
+            }
+            Pattern::Dup { label, expr } => {
+                matches!(kind, NodeKind::DupAVar | NodeKind::DupBVar)
+                    && label.is_none_or(|l| cursor.label() == Some(l))
+                    && cursor
+                        .dup_expr()
+                        .is_some_and(|e| expr.match_at(e, lams, &mut None))
+            }
+        }
+    }
+
+    /// Returns whether a capture appears inside the expression of a dup
+    /// pattern, where it cannot be moved by [`TermGraph::rewrite`].
+    pub(super) fn captures_under_dup(&self) -> bool {
+        match self {
+            Pattern::Any | Pattern::Capture(_) | Pattern::Var | Pattern::LamVar(_) => false,
+            Pattern::FreeVar | Pattern::Ref(_) | Pattern::Num(_) => false,
+            Pattern::Lam { body, .. } => body.captures_under_dup(),
+            Pattern::App(e1, e2) | Pattern::Op2(_, e1, e2) => {
+                e1.captures_under_dup() || e2.captures_under_dup()
+            }
+            Pattern::Sup { left, right, .. } => {
+                left.captures_under_dup() || right.captures_under_dup()
+            }
+            Pattern::Dup { expr, .. } => expr.has_capture(),
+        }
+    }
+
+    fn has_capture(&self) -> bool {
+        match self {
+            Pattern::Capture(_) => true,
+            Pattern::Any
+            | Pattern::Var
+            | Pattern::LamVar(_)
+            | Pattern::FreeVar
+            | Pattern::Ref(_)
+            | Pattern::Num(_) => false,
+            Pattern::Lam { body, .. } => body.has_capture(),
+            Pattern::App(e1, e2) | Pattern::Op2(_, e1, e2) => e1.has_capture() || e2.has_capture(),
+            Pattern::Sup { left, right, .. } => left.has_capture() || right.has_capture(),
+            Pattern::Dup { expr, .. } => expr.has_capture(),
+        }
+    }
+}
+
+fn lam_var_used(lam: Cursor<'_>) -> bool {
+    unsafe { lam.ptr().lam().x().read().tag() != Tag::UnusedVar }
+}
+
+/// Converts `term`, under `level` lambdas, to a pattern, where `bound` holds
+/// the patterns of the variables bound by the enclosing lambdas and dups.
+fn from_term(term: &Term, level: usize, bound: &mut HashMap<IStr, Vec<Pattern>>) -> Pattern {
+    let bind = |x: IStr, pattern: Pattern, e: &Term, level, bound: &mut HashMap<_, Vec<_>>| {
+        bound.entry(x).or_default().push(pattern);
+        let e = from_term(e, level, bound);
+        bound.get_mut(&x).and_then(Vec::pop);
+        e
+    };
+    match term {
+        Term::Var(x) => match bound.get(x).and_then(|patterns| patterns.last()) {
+            Some(pattern) => pattern.clone(),
+            None if *x == "_".intern_static() => Pattern::Any,
+            None => Pattern::Capture(*x),
+        },
+        Term::Ref(name) => Pattern::Ref(*name),
+        Term::Num(n) => Pattern::Num(*n),
+        Term::Lam(x, e) => Pattern::Lam {
+            var_used: (*x == "_".intern_static()).then_some(false),
+            body: Box::new(bind(*x, Pattern::LamVar(level), e, level + 1, bound)),
+        },
+        Term::App(e1, e2) => Pattern::app(from_term(e1, level, bound), from_term(e2, level, bound)),
+        Term::Op2(op, e1, e2) => Pattern::Op2(
+            *op,
+            Box::new(from_term(e1, level, bound)),
+            Box::new(from_term(e2, level, bound)),
+        ),
+        Term::Sup(l, e1, e2) => {
+            Pattern::sup(from_term(e1, level, bound), from_term(e2, level, bound)).with_label(*l)
+        }
+        Term::Dup(l, a, b, e, cont) => {
+            let dup = Pattern::dup(from_term(e, level, bound)).with_label(*l);
+            bound.entry(*a).or_default().push(dup.clone());
+            let cont = bind(*b, dup, cont, level, bound);
+            bound.get_mut(a).and_then(Vec::pop);
+            cont
+        }
+        Term::Let(x, e, cont) => {
+            let e = from_term(e, level, bound);
+            let lam = Term::Lam(*x, cont.clone());
+            Pattern::app(from_term(&lam, level, bound), e)
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Pattern::from_term(&s.parse()?))
+    }
+}
+
+/// Collects the ids of the positions that match a pattern.
+struct Finder<'p> {
+    pattern: &'p Pattern,
+    found: Vec<NodeId>,
+    seen: HashSet<NodeId>,
+}
+
+impl Finder<'_> {
+    fn check(&mut self, cursor: Cursor<'_>) -> Visit {
+        if let Some(id) = cursor.node_id() {
+            if self.pattern.matches(cursor) && self.seen.insert(id) {
+                self.found.push(id);
+            }
+        }
+        Visit::Continue
+    }
+}
+
+impl GraphVisitor for Finder<'_> {
+    fn visit_lam(&mut self, lam: Cursor<'_>, _: usize) -> Visit {
+        self.check(lam)
+    }
+
+    fn visit_app(&mut self, app: Cursor<'_>, _: usize) -> Visit {
+        self.check(app)
+    }
+
+    fn visit_sup(&mut self, sup: Cursor<'_>, _: usize) -> Visit {
+        self.check(sup)
+    }
+
+    fn visit_var(&mut self, var: Cursor<'_>, _: usize) -> Visit {
+        self.check(var)
+    }
+}
+
+impl TermGraph {
+    /// Returns the ids of the nodes reachable from the primary root that match
+    /// `pattern`, in the order [`TermGraph::visit`] reaches them.
+    ///
+    /// A dup matches through its variables, and is reported (once) by the id
+    /// of the dup node. Variables bound by lambdas and free variables have no
+    /// id, so they are never reported on their own.
+    pub fn find(&self, pattern: &Pattern) -> Vec<NodeId> {
+        let mut finder = Finder {
+            pattern,
+            found: vec![],
+            seen: HashSet::new(),
+        };
+        self.visit(&mut finder);
+        finder.found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let term: Term = "λx ((λy x) ((λz z) #1{w (f w2)}))".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let erasing_app = Pattern::app(Pattern::lam(Pattern::Any).unused(), Pattern::Any);
+        let found = term_graph.find(&erasing_app);
+        assert_eq!(
+            found,
+            vec![term_graph.cursor().lam_body().unwrap().node_id().unwrap()]
+        );
+        assert_eq!(found, term_graph.find(&"((λ_ body) arg)".parse().unwrap()));
+
+        let beta = "((λx body) arg)".parse().unwrap();
+        assert_eq!(term_graph.find(&beta).len(), 2);
+        // `λy x` does not match, since `x` is not bound by `λy`.
+        let identity = "λx x".parse().unwrap();
+        assert_eq!(term_graph.find(&identity).len(), 1);
+        assert_eq!(term_graph.find(&"#1{a b}".parse().unwrap()).len(), 1);
+        assert_eq!(term_graph.find(&"#2{a b}".parse().unwrap()).len(), 0);
+        let app_free = Pattern::app(Pattern::FreeVar, Pattern::FreeVar);
+        assert_eq!(term_graph.find(&app_free).len(), 1);
+    }
+
+    #[test]
+    fn test_find_dup() {
+        let term: Term = "dup #0{a b} = λx x; (a (b c))".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let pattern = "dup #0{p q} = λy y; p".parse().unwrap();
+        // The dup is reported once, though both of its variables match.
+        let found = term_graph.find(&pattern);
+        let a = term_graph.cursor().app_fun().unwrap();
+        assert_eq!(found, vec![a.node_id().unwrap()]);
+        assert_eq!(
+            term_graph.find(&"dup #1{p q} = λy y; p".parse().unwrap()),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use super::{NodeId, Tag, Tagged, TermGraph};
+
+/// A handle that pins a node of a [`TermGraph`].
+///
+/// Pinned nodes are treated as roots by [`TermGraph::gc`], and the handle keeps
+/// tracking its node for as long as the node is alive. If a rewrite rule (or
+/// [`TermGraph::replace_at`]) frees the node, the handle resolves to `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeHandle(usize);
+
+impl TermGraph {
+    /// Pins the live node `node`, returning a handle to it.
+    ///
+    /// Returns `None` if `node` is not a live node of this graph.
+    pub fn pin(&mut self, node: NodeId) -> Option<NodeHandle> {
+        let node = self.live_node(node)?;
+        let pins = &mut self.1.pins;
+        let index = match pins.iter().position(|pin| pin.is_none()) {
+            Some(index) => {
+                pins[index] = Some(node);
+                index
+            }
+            None => {
+                pins.push(Some(node));
+                pins.len() - 1
+            }
+        };
+        self.1.pinned.entry(node).or_default().push(index);
+        Some(NodeHandle(index))
+    }
+
+    /// Releases a handle returned by [`TermGraph::pin`].
+    ///
+    /// The handle (and any copies of it) must not be used afterwards, since its
+    /// slot may be reused by a later pin.
+    pub fn unpin(&mut self, handle: NodeHandle) {
+        if let Some(node) = self.1.pins.get_mut(handle.0).and_then(Option::take) {
+            let indices = self.1.pinned.get_mut(&node).unwrap();
+            indices.retain(|&index| index != handle.0);
+            if indices.is_empty() {
+                self.1.pinned.remove(&node);
+            }
+        }
+    }
+
+    /// Returns the current id of the node pinned by `handle`, or `None` if the
+    /// node has been freed.
+    pub fn pinned(&self, handle: NodeHandle) -> Option<NodeId> {
+        let node = self.1.pins.get(handle.0).copied().flatten()?;
+        Some(NodeId::from_ptr(node.ptr()))
+    }
+
+    /// Returns the pointers to all currently pinned nodes.
+    pub(super) fn pinned_nodes(&self) -> impl Iterator<Item = Tagged> + '_ {
+        self.1.pins.iter().copied().flatten()
+    }
+
+    /// Looks up the live node with id `node`.
+    fn live_node(&self, node: NodeId) -> Option<Tagged> {
+        [
+            Tag::LamPtr,
+            Tag::AppPtr,
+            Tag::SupPtr,
+            Tag::DupPtr,
+            Tag::NumPtr,
+            Tag::Op2Ptr,
+        ]
+        .into_iter()
+        .find_map(|tag| self.1.arena.find_live(tag, node.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+    use crate::vm::LamPtrExt;
+
+    #[test]
+    fn test_pin_survives_gc() {
+        let term: Term = "λx (x y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let app = term_graph.cursor().lam_body().unwrap().node_id().unwrap();
+        let handle = term_graph.pin(app).unwrap();
+        unsafe {
+            // Strand `(x y)` by replacing the body of `λx` with a free variable.
+            let lam_ptr = term_graph.0.read();
+            lam_ptr.lam().e().write(Tagged::new_unbound_var());
+        }
+        assert_eq!(term_graph.gc(), 0);
+        assert_eq!(term_graph.pinned(handle), Some(app));
+
+        term_graph.unpin(handle);
+        assert_eq!(term_graph.pinned(handle), None);
+        assert_eq!(term_graph.gc(), 1);
+    }
+
+    #[test]
+    fn test_pin_cleared_when_freed() {
+        let term: Term = "((λx x) y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let lam = term_graph.cursor().app_fun().unwrap().node_id().unwrap();
+        let handle = term_graph.pin(lam).unwrap();
+        assert_eq!(term_graph.pinned(handle), Some(lam));
+        term_graph.naive_reduce_step();
+        assert_eq!(term_graph.pinned(handle), None);
+        assert!(term_graph.pin(lam).is_none());
+        assert!(term_graph.1.pinned.is_empty());
+    }
+
+    #[test]
+    fn test_pin_twice() {
+        let term: Term = "((λx x) y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let lam = term_graph.cursor().app_fun().unwrap().node_id().unwrap();
+        let first = term_graph.pin(lam).unwrap();
+        let second = term_graph.pin(lam).unwrap();
+        term_graph.unpin(first);
+        assert_eq!(term_graph.pinned(second), Some(lam));
+        term_graph.naive_reduce_step();
+        assert_eq!(term_graph.pinned(second), None);
+        assert!(term_graph.1.pinned.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use super::{RuleKind, TermGraph};
+
+/// The number of buckets per power of two. Recorded values are kept to within
+/// 1/16 of their size.
+const SUB_BUCKETS: u64 = 16;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+const BUCKETS: usize = ((64 - SUB_BUCKET_BITS as usize) + 1) * SUB_BUCKETS as usize;
+
+/// A histogram of durations with log-linear buckets, in the style of HDR
+/// histograms.
+///
+/// Durations are recorded in nanoseconds. Each power of two is split into 16
+/// buckets, so reported values are within about 6% of the recorded ones, while
+/// the histogram takes a fixed amount of memory however long the tail gets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    count: u64,
+    total_nanos: u128,
+    min_nanos: u64,
+    max_nanos: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            counts: vec![0; BUCKETS],
+            count: 0,
+            total_nanos: 0,
+            min_nanos: u64::MAX,
+            max_nanos: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[bucket(nanos)] += 1;
+        self.count += 1;
+        self.total_nanos += nanos as u128;
+        self.min_nanos = self.min_nanos.min(nanos);
+        self.max_nanos = self.max_nanos.max(nanos);
+    }
+
+    /// Adds the durations recorded by `other` to this histogram.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.count += other.count;
+        self.total_nanos += other.total_nanos;
+        self.min_nanos = self.min_nanos.min(other.min_nanos);
+        self.max_nanos = self.max_nanos.max(other.max_nanos);
+    }
+
+    /// The number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_nanos(self.min_nanos))
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_nanos(self.max_nanos))
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0)
+            .then(|| Duration::from_nanos((self.total_nanos / self.count as u128) as u64))
+    }
+
+    /// Returns the smallest duration that at least `percentile` percent of the
+    /// recorded durations do not exceed, rounded down to its bucket.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let nanos = bucket_start(index).clamp(self.min_nanos, self.max_nanos);
+                return Some(Duration::from_nanos(nanos));
+            }
+        }
+        self.max()
+    }
+}
+
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS {
+        return nanos as usize;
+    }
+    let exponent = 63 - nanos.leading_zeros();
+    let sub = (nanos >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    ((exponent - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+fn bucket_start(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let exponent = (index / SUB_BUCKETS) as u32 + SUB_BUCKET_BITS - 1;
+    let sub = index % SUB_BUCKETS;
+    (SUB_BUCKETS + sub) << (exponent - SUB_BUCKET_BITS)
+}
+
+/// Latency histograms collected while reducing a [`TermGraph`], with the
+/// `profiling` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// The time taken to find each redex reduced by the naive strategies.
+    pub redex_search: LatencyHistogram,
+    rules: [LatencyHistogram; 15],
+}
+
+impl LatencyStats {
+    /// The time taken by each rewrite of kind `kind`.
+    pub fn rule(&self, kind: RuleKind) -> &LatencyHistogram {
+        &self.rules[kind as usize]
+    }
+
+    pub(super) fn record_rule(&mut self, kind: RuleKind, duration: Duration) {
+        self.rules[kind as usize].record(duration);
+    }
+
+    pub(super) fn merge(&mut self, other: &LatencyStats) {
+        self.redex_search.merge(&other.redex_search);
+        for (rule, other_rule) in self.rules.iter_mut().zip(&other.rules) {
+            rule.merge(other_rule);
+        }
+    }
+}
+
+impl TermGraph {
+    /// Returns the latency histograms collected so far.
+    pub fn latency_stats(&self) -> &LatencyStats {
+        &self.1.latency
+    }
+
+    /// Clears the latency histograms.
+    pub fn reset_latency_stats(&mut self) {
+        self.1.latency = LatencyStats::default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_buckets() {
+        for nanos in [0, 1, 15, 16, 17, 31, 32, 1000, 123_456_789, u64::MAX] {
+            let start = bucket_start(bucket(nanos));
+            assert!(start <= nanos);
+            assert!(nanos - start <= nanos / SUB_BUCKETS);
+        }
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(50.0), None);
+        for micros in 1..=100 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.min(), Some(Duration::from_micros(1)));
+        assert_eq!(histogram.max(), Some(Duration::from_micros(100)));
+        let p50 = histogram.percentile(50.0).unwrap().as_nanos() as f64;
+        assert!((47_000.0..=50_000.0).contains(&p50), "{}", p50);
+        assert_eq!(histogram.percentile(100.0), histogram.percentile(99.9));
+        let mut merged = LatencyHistogram::default();
+        merged.merge(&histogram);
+        assert_eq!(merged, histogram);
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        while term_graph.naive_reduce_step().is_some() {}
+        let stats = term_graph.latency_stats();
+        // The last search finds no redex.
+        assert_eq!(stats.redex_search.count(), 4);
+        assert_eq!(stats.rule(RuleKind::DupLam).count(), 1);
+        assert_eq!(stats.rule(RuleKind::AppLam).count(), 1);
+        assert_eq!(stats.rule(RuleKind::DupSupSame).count(), 1);
+        term_graph.reset_latency_stats();
+        assert_eq!(term_graph.latency_stats().redex_search.count(), 0);
+    }
+}
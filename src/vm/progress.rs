@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use super::{StrategyConfig, TermGraph};
+
+/// How often [`TermGraph::naive_reduce_with_progress`] reports progress.
+///
+/// A report is made as soon as either limit is reached since the previous
+/// one, so a limit of `None` only disables that half of the throttle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressThrottle {
+    /// Report after this many steps.
+    pub steps: Option<usize>,
+    /// Report after this much time.
+    pub interval: Option<Duration>,
+}
+
+impl Default for ProgressThrottle {
+    /// Every 100,000 steps or 100 milliseconds.
+    fn default() -> Self {
+        ProgressThrottle {
+            steps: Some(100_000),
+            interval: Some(Duration::from_millis(100)),
+        }
+    }
+}
+
+/// A progress report of a reduction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The number of steps taken so far.
+    pub steps: usize,
+    /// The number of live nodes in the graph.
+    pub nodes: usize,
+    /// The time since the reduction started.
+    pub elapsed: Duration,
+    /// The average number of rewrites per second since the previous report.
+    pub rewrites_per_sec: f64,
+    /// Whether the graph is in normal form. The last report always is.
+    pub done: bool,
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// passing a progress report to `on_progress` whenever `throttle` allows,
+    /// and once more at the end. Returns the number of steps taken.
+    pub fn naive_reduce_with_progress(
+        &mut self,
+        config: &StrategyConfig,
+        throttle: &ProgressThrottle,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> usize {
+        let start = Instant::now();
+        let mut last = (start, 0);
+        let mut steps = 0;
+        loop {
+            let done = self.naive_reduce_step_with(config).is_none();
+            if !done {
+                steps += 1;
+            }
+            let due_by_steps = throttle.steps.is_some_and(|n| steps - last.1 >= n);
+            if !done && !due_by_steps && throttle.interval.is_none() {
+                continue;
+            }
+            let now = Instant::now();
+            let due_by_time = throttle.interval.is_some_and(|t| now - last.0 >= t);
+            if done || due_by_steps || due_by_time {
+                let secs = (now - last.0).as_secs_f64();
+                on_progress(&Progress {
+                    steps,
+                    nodes: self.1.live_count(),
+                    elapsed: now - start,
+                    rewrites_per_sec: if secs > 0.0 {
+                        (steps - last.1) as f64 / secs
+                    } else {
+                        0.0
+                    },
+                    done,
+                });
+                last = (now, steps);
+            }
+            if done {
+                return steps;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_progress_every_n_steps() {
+        // Four AppLam steps.
+        let term: Term = "((λa a) ((λb b) ((λc c) ((λd d) λe e))))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let throttle = ProgressThrottle {
+            steps: Some(3),
+            interval: None,
+        };
+        let mut reports = vec![];
+        let steps = term_graph.naive_reduce_with_progress(
+            &StrategyConfig::default(),
+            &throttle,
+            |progress| reports.push((progress.steps, progress.nodes, progress.done)),
+        );
+        assert_eq!(steps, 4);
+        assert_eq!(reports, [(3, 3, false), (4, 1, true)]);
+    }
+}
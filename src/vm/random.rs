@@ -0,0 +1,79 @@
+/// A source of randomness for the random-order strategies.
+///
+/// With the `rand` feature (enabled by default), every [`rand::RngCore`] is a
+/// `RandomSource`. Embedders without `rand`, or that want reproducible runs,
+/// can implement it themselves or use a [`Counter`].
+pub trait RandomSource {
+    /// Returns an index in `0..len`. `len` is never 0.
+    fn next_index(&mut self, len: usize) -> usize;
+}
+
+#[cfg(feature = "rand")]
+impl<R: rand::RngCore> RandomSource for R {
+    fn next_index(&mut self, len: usize) -> usize {
+        rand::Rng::gen_range(self, 0..len)
+    }
+}
+
+/// A deterministic [`RandomSource`] that counts up from its value, wrapping
+/// each index into range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counter(pub usize);
+
+impl RandomSource for Counter {
+    fn next_index(&mut self, len: usize) -> usize {
+        let index = self.0 % len;
+        self.0 = self.0.wrapping_add(1);
+        index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+    use crate::vm::{StrategyConfig, TermGraph};
+
+    #[test]
+    fn test_counter() {
+        let mut counter = Counter(1);
+        assert_eq!(counter.next_index(3), 1);
+        assert_eq!(counter.next_index(3), 2);
+        assert_eq!(counter.next_index(3), 0);
+        assert_eq!(counter.next_index(1), 0);
+    }
+
+    #[test]
+    fn test_reduce_with_counter() {
+        let term: Term = "λx λy dup #0{a b} = #1{x y}; (a b)".parse().unwrap();
+        for start in 0..4 {
+            let mut term_graph = TermGraph::from(&term);
+            term_graph.naive_random_order_reduce_with_source(
+                &StrategyConfig::prefer_annihilations(),
+                &mut Counter(start),
+            );
+            assert_eq!(
+                format!("{}", Term::from(&term_graph)),
+                "(λv1 (λv4 #1{(dup #0{v2 v3} = v1; (v2 v3)) (dup #0{v5 v6} = v4; (v5 v6))}))"
+            );
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_reduce_with_seed() {
+        let term: Term = "(λf λx dup #0{f1 f2} = f; (f1 (f2 x)) λy dup #1{y1 y2} = y; #2{y1 y2})"
+            .parse()
+            .unwrap();
+        for seed in 0..8 {
+            let runs: Vec<(usize, u64)> = (0..2)
+                .map(|_| {
+                    let mut term_graph = TermGraph::from(&term);
+                    term_graph.naive_random_order_reduce_with_seed(seed);
+                    (term_graph.1.allocations, term_graph.structural_hash())
+                })
+                .collect();
+            assert_eq!(runs[0], runs[1], "seed {}", seed);
+        }
+    }
+}
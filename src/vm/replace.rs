@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use super::{apply_erasures, build_graph, check_refs, validate, NodeId, Tag, Tagged, TermGraph};
+use crate::error::Error;
+use crate::syntax::Term;
+
+impl TermGraph {
+    /// Replaces the subterm rooted at `node` with a freshly built graph for `term`.
+    ///
+    /// The old subterm is erased: binders outside of it whose variables were
+    /// used inside of it become unused, and variables bound inside of it but
+    /// used elsewhere become unbound. Free variables of `term`
+    /// become unbound variables.
+    ///
+    /// Returns an error if `node` is not a node of this graph other than a
+    /// `Dup`, if `term` is not well formed (see [`TermGraph::try_from_term`]),
+    /// or if it refers to a definition missing from the graph's book.
+    pub fn replace_at(&mut self, node: NodeId, term: &Term) -> Result<(), Error> {
+        validate(term)?;
+        check_refs(&self.1.book, term)?;
+        unsafe {
+            let slot = self.find_slot(node).ok_or_else(|| {
+                Error::Graph(format!("no node other than a Dup with id {:?}", node))
+            })?;
+            let old = slot.read();
+            slot.write(Tagged::new_unbound_var());
+            old.erase(&mut self.1);
+            apply_erasures(&mut self.1);
+            build_graph(&mut self.1, slot, term, &mut HashMap::new());
+        }
+        Ok(())
+    }
+
+    /// Finds the slot that points to the node `node`, which is not a `Dup`.
+    unsafe fn find_slot(&self, node: NodeId) -> Option<*mut Tagged> {
+        unsafe {
+            let is_node = |slot: *mut Tagged| {
+                let ptr = slot.read();
+                matches!(
+                    ptr.tag(),
+                    Tag::LamPtr | Tag::AppPtr | Tag::SupPtr | Tag::NumPtr | Tag::Op2Ptr
+                ) && NodeId::from_ptr(ptr.ptr()) == node
+            };
+            if let Some(root) = self.root_slots().into_iter().find(|slot| is_node(*slot)) {
+                return Some(root);
+            }
+            self.node_iter()
+                .flat_map(|ptr| ptr.child_slots())
+                .find(|slot| is_node(*slot))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_replace_at() {
+        let term: Term = "λx ((λy y) x)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let lam_y = term_graph.cursor().lam_body().unwrap().app_fun().unwrap();
+        let lam_y = lam_y.node_id().unwrap();
+        term_graph
+            .replace_at(lam_y, &"#0{a b}".parse().unwrap())
+            .unwrap();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv3 (#0{a b} v3))");
+    }
+
+    #[test]
+    fn test_replace_at_releases_outer_binders() {
+        let term: Term = "λx λy (x y)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let app = term_graph.cursor().lam_body().unwrap().lam_body().unwrap();
+        let app = app.node_id().unwrap();
+        term_graph
+            .replace_at(app, &"λz z".parse().unwrap())
+            .unwrap();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λ_ (λ_ (λv1 v1)))");
+    }
+
+    #[test]
+    fn test_replace_at_unbinds_escaped_vars() {
+        // dup #0{a b} = λx x; #0{a b}
+        // --------------------------- DupLam
+        // dup #0{c d} = #0{x1 x2}; #0{(λx1 c) (λx2 d)}
+        let term: Term = "dup #0{a b} = λx x; #0{a b}".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        term_graph.naive_reduce_step();
+        let lam_x1 = term_graph.cursor().sup_left().unwrap();
+        let lam_x1 = lam_x1.node_id().unwrap();
+        term_graph
+            .replace_at(lam_x1, &"y".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "#0{y (λv3 (dup #0{_ v4} = #0{v2 v3}; v4))}"
+        );
+    }
+
+    #[test]
+    fn test_replace_at_root() {
+        let term: Term = "(f x)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let root = term_graph.cursor().node_id().unwrap();
+        term_graph.replace_at(root, &"g".parse().unwrap()).unwrap();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "g");
+        assert!(term_graph.replace_at(root, &"h".parse().unwrap()).is_err());
+    }
+}
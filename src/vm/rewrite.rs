@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+
+use super::roots::count_free_uses;
+use super::{
+    apply_erasures, build_graph, check_refs, validate, Cursor, DupPtrExt, GraphVisitor, Lam,
+    LamPtrExt, Pattern, Tag, Tagged, TermGraph, Visit,
+};
+use crate::error::Error;
+use crate::intern::{IStr, Intern};
+use crate::syntax::Term;
+
+/// A position matched by a pattern, as passed to the builder of
+/// [`TermGraph::rewrite`].
+pub struct Match<'g> {
+    root: Cursor<'g>,
+    captures: Vec<(IStr, Cursor<'g>)>,
+}
+
+impl<'g> Match<'g> {
+    /// Returns a cursor at the matched position.
+    pub fn root(&self) -> Cursor<'g> {
+        self.root
+    }
+
+    /// Returns a cursor at the position captured as `name`, if any.
+    pub fn capture(&self, name: &str) -> Option<Cursor<'g>> {
+        let name = name.intern();
+        self.captures
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, cursor)| *cursor)
+    }
+}
+
+/// Collects the slots of the positions that match a pattern.
+struct Sites<'p> {
+    pattern: &'p Pattern,
+    slots: Vec<*mut Tagged>,
+}
+
+impl Sites<'_> {
+    fn check(&mut self, cursor: Cursor<'_>) -> Visit {
+        if self.pattern.matches(cursor) {
+            self.slots.push(cursor.slot());
+        }
+        Visit::Continue
+    }
+}
+
+impl GraphVisitor for Sites<'_> {
+    fn visit_lam(&mut self, lam: Cursor<'_>, _: usize) -> Visit {
+        self.check(lam)
+    }
+
+    fn visit_app(&mut self, app: Cursor<'_>, _: usize) -> Visit {
+        self.check(app)
+    }
+
+    fn visit_sup(&mut self, sup: Cursor<'_>, _: usize) -> Visit {
+        self.check(sup)
+    }
+
+    fn visit_var(&mut self, var: Cursor<'_>, _: usize) -> Visit {
+        self.check(var)
+    }
+}
+
+impl TermGraph {
+    /// Rewrites the positions reachable from the primary root that match
+    /// `pattern`, returning the number of rewrites performed.
+    ///
+    /// The matches are found first, in the order [`TermGraph::visit`] reaches
+    /// them. For each match that is still in the graph and still matches,
+    /// `build` is called and may return the term to put in its place, in which
+    /// the free variables named after a capture of `pattern` stand for the
+    /// captured subgraphs. These are moved, not copied, so each may be used
+    /// at most once; captures that are not used are erased, along with the
+    /// rest of the matched subgraph. Other free variables become unbound.
+    ///
+    /// Returns an error, leaving the rewrites performed so far in place, if
+    /// `pattern` captures inside the expression of a dup (which is shared),
+    /// has two captures with the same name, or if `build` returns a term that
+    /// is not well formed (see [`TermGraph::try_from_term`]) or uses a capture
+    /// more than once.
+    pub fn rewrite(
+        &mut self,
+        pattern: &Pattern,
+        mut build: impl FnMut(&Match<'_>) -> Option<Term>,
+    ) -> Result<usize, Error> {
+        if pattern.captures_under_dup() {
+            return Err(Error::Graph(
+                "a pattern cannot capture inside the expression of a dup".to_string(),
+            ));
+        }
+        let mut sites = Sites {
+            pattern,
+            slots: vec![],
+        };
+        self.visit(&mut sites);
+        let owners: HashMap<*mut Tagged, Tagged> = unsafe {
+            self.node_iter()
+                .flat_map(|node| node.child_slots().into_iter().map(move |slot| (slot, node)))
+                .collect()
+        };
+        let mut count = 0;
+        for slot in sites.slots {
+            // Skip the sites freed by an earlier rewrite.
+            if owners
+                .get(&slot)
+                .is_some_and(|owner| !self.1.arena.is_live(*owner))
+            {
+                continue;
+            }
+            let root = Cursor::new(slot);
+            let Some(captures) = pattern.captures(root) else {
+                continue;
+            };
+            let site = Match { root, captures };
+            let Some(term) = build(&site) else {
+                continue;
+            };
+            let captures: Vec<(IStr, *mut Tagged)> = site
+                .captures
+                .iter()
+                .map(|(x, cursor)| (*x, cursor.slot()))
+                .collect();
+            check_replacement(&term, &captures)?;
+            check_refs(&self.1.book, &term)?;
+            unsafe {
+                self.replace_match(slot, &captures, &term);
+            }
+            count += 1;
+        }
+        #[cfg(debug_assertions)]
+        unsafe {
+            self.assert_var_uses();
+        }
+        Ok(count)
+    }
+
+    /// Replaces the subgraph in `slot` by the graph for `term`, moving each of
+    /// `captures` to the free occurrence of its name in `term`.
+    unsafe fn replace_match(
+        &mut self,
+        slot: *mut Tagged,
+        captures: &[(IStr, *mut Tagged)],
+        term: &Term,
+    ) {
+        unsafe {
+            let heap = &mut self.1;
+            // Move the captured subgraphs out of the way, so that erasing the
+            // match leaves them alone.
+            let held: Vec<*mut Tagged> = captures
+                .iter()
+                .map(|(_, capture)| {
+                    let temp =
+                        std::alloc::alloc(std::alloc::Layout::new::<Tagged>()) as *mut Tagged;
+                    move_slot(*capture, temp);
+                    capture.write(Tagged::new_unbound_var());
+                    temp
+                })
+                .collect();
+            let old = slot.read();
+            slot.write(Tagged::new_unbound_var());
+            old.erase(heap);
+            apply_erasures(heap);
+
+            // Build `term` with each capture bound by a placeholder lambda, to
+            // find the slot that uses it.
+            let mut env = HashMap::new();
+            let placeholders: Vec<Tagged> = captures
+                .iter()
+                .map(|(x, _)| {
+                    let lam = Lam::alloc(heap);
+                    lam.lam().x().write(Tagged::new_unused_var());
+                    lam.lam().e().write(Tagged::new_unbound_var());
+                    env.insert(*x, vec![lam.lam_bound_var()]);
+                    lam
+                })
+                .collect();
+            build_graph(heap, slot, term, &mut env);
+            for (lam, temp) in placeholders.into_iter().zip(held) {
+                let x = lam.lam().x().read();
+                if x.tag() == Tag::VarUsePtr {
+                    move_slot(temp, x.var_use());
+                } else {
+                    temp.read().erase(heap);
+                }
+                lam.dealloc_lam(heap);
+                std::alloc::dealloc(temp as *mut u8, std::alloc::Layout::new::<Tagged>());
+            }
+            apply_erasures(heap);
+        }
+    }
+
+    /// Asserts that every variable in the graph and its binder point at each
+    /// other.
+    #[cfg(debug_assertions)]
+    unsafe fn assert_var_uses(&self) {
+        unsafe {
+            let slots = self
+                .node_iter()
+                .flat_map(|node| node.child_slots())
+                .chain(self.root_slots());
+            for slot in slots {
+                let binder = match slot.read().tag() {
+                    Tag::LamBoundVar => slot.read().lam().x(),
+                    Tag::DupABoundVar => slot.read().dup().a(),
+                    Tag::DupBBoundVar => slot.read().dup().b(),
+                    _ => continue,
+                };
+                assert_eq!(
+                    binder.read(),
+                    Tagged::new(slot as *mut (), Tag::VarUsePtr),
+                    "variable and binder disagree"
+                );
+            }
+            for node in self.node_iter() {
+                let binders = match node.tag() {
+                    Tag::LamPtr => vec![(node.lam().x(), node.lam_bound_var())],
+                    Tag::DupPtr => vec![
+                        (node.dup().a(), node.dup_a_bound_var()),
+                        (node.dup().b(), node.dup_b_bound_var()),
+                    ],
+                    _ => continue,
+                };
+                for (binder, var) in binders {
+                    let x = binder.read();
+                    if x.tag() == Tag::VarUsePtr {
+                        assert_eq!(x.var_use_read(), var, "binder and variable disagree");
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Moves the contents of `from` to `to`, updating the binder of a variable.
+unsafe fn move_slot(from: *mut Tagged, to: *mut Tagged) {
+    unsafe {
+        let ptr = from.read();
+        to.write(ptr);
+        ptr.if_bound_var_move_to(Tagged::new(to as *mut (), Tag::VarUsePtr));
+    }
+}
+
+/// Checks that `term` can replace a match with `captures`.
+fn check_replacement(term: &Term, captures: &[(IStr, *mut Tagged)]) -> Result<(), Error> {
+    validate(term)?;
+    let mut names = HashSet::new();
+    for (x, _) in captures {
+        if !names.insert(*x) {
+            return Err(Error::Graph(format!("{} is captured twice", x)));
+        }
+    }
+    let mut uses = HashMap::new();
+    count_free_uses(term, &names, &mut uses);
+    match uses.into_iter().find(|(_, n)| *n > 1) {
+        Some((x, _)) => Err(Error::Graph(format!(
+            "capture {} is used more than once",
+            x
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rewrite_all(src: &str, pattern: &str, replacement: &str) -> Result<(usize, String), Error> {
+        let term: Term = src.parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let replacement: Term = replacement.parse().unwrap();
+        let count = term_graph.rewrite(&pattern.parse().unwrap(), |_| Some(replacement.clone()))?;
+        Ok((count, format!("{}", Term::from(&term_graph))))
+    }
+
+    #[test]
+    fn test_rewrite_eta() {
+        assert_eq!(
+            rewrite_all("λg λh (λx (g x) λy (h y))", "λx (f x)", "f").unwrap(),
+            (2, "(λv1 (λv2 (v1 v2)))".to_string())
+        );
+        // `λx (x x2)` does not match, since `x2` is not bound by `λx`.
+        assert_eq!(
+            rewrite_all("λy λx (x y)", "λx (f x)", "f").unwrap(),
+            (0, "(λv2 (λv1 (v1 v2)))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rewrite_erases_unused_captures() {
+        assert_eq!(
+            rewrite_all("λy λz ((λ_ y) (z w))", "((λ_ body) arg)", "body").unwrap(),
+            (1, "(λv1 (λ_ v1))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rewrite_builder() {
+        // Swap the branches of the superpositions labelled 1.
+        let term: Term = "λx λy #1{x #2{y #1{a b}}}".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let pattern = Pattern::sup(Pattern::capture("l"), Pattern::capture("r"));
+        let swapped: Term = "#1{r l}".parse().unwrap();
+        let count = term_graph
+            .rewrite(&pattern, |site| {
+                (site.root().label() == Some(1)).then(|| swapped.clone())
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv4 (λv1 #1{#2{v1 #1{b a}} v4}))"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_errors() {
+        assert!(rewrite_all("λx (f x)", "λx (f x)", "(f f)").is_err());
+        assert!(rewrite_all("(a b)", "(f f)", "f").is_err());
+        assert!(rewrite_all("dup #0{a b} = c; (a b)", "dup #0{p q} = e; p", "e").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    build_graph, check_refs, node_label, read_back, validate, Dup, DupPtrExt, Heap, Tag, Tagged,
+    TermGraph, MAX_LABEL,
+};
+use crate::book::Book;
+use crate::error::Error;
+use crate::intern::IStr;
+use crate::syntax::{LabelGen, Term};
+
+impl TermGraph {
+    /// Builds a graph with several named roots that share the definitions in
+    /// `defs`.
+    ///
+    /// Free occurrences of a definition's name in any of the roots refer to
+    /// that definition. Each definition is built once and shared between all of
+    /// its occurrences through a chain of dups with fresh labels, so reducing
+    /// the graph normalizes every root without re-evaluating the definitions.
+    /// Free variables of the definitions themselves are unbound.
+    ///
+    /// The first root is the graph's primary root, as used by
+    /// [`TermGraph::cursor`] and `Term::from`. Returns an error if there are no
+    /// roots, if two roots (or two definitions) have the same name, if one of
+    /// the terms is not well formed (see [`TermGraph::try_from_term`]), or if
+    /// there are not enough labels above those of the terms for the dups.
+    pub fn from_roots(defs: &[(IStr, Term)], roots: &[(IStr, Term)]) -> Result<Self, Error> {
+        if roots.is_empty() {
+            return Err(Error::Graph("a graph needs at least one root".to_string()));
+        }
+        let mut names = HashSet::new();
+        for (name, _) in roots {
+            if !names.insert(*name) {
+                return Err(Error::Graph(format!("duplicate root {}", name)));
+            }
+        }
+        let mut def_names = HashSet::new();
+        for (name, _) in defs {
+            if !def_names.insert(*name) {
+                return Err(Error::Graph(format!("duplicate definition {}", name)));
+            }
+        }
+        for (_, term) in defs.iter().chain(roots) {
+            validate(term)?;
+            check_refs(&Book::default(), term)?;
+        }
+
+        let mut uses = HashMap::new();
+        for (_, term) in roots {
+            count_free_uses(term, &def_names, &mut uses);
+        }
+        let mut labels = LabelGen::new();
+        for (_, term) in defs.iter().chain(roots) {
+            labels.avoid(term);
+        }
+        // Sharing a definition with `n` uses takes a chain of `max(n - 1, 1)`
+        // dups, each with a fresh label.
+        let dups = defs
+            .iter()
+            .filter_map(|(name, _)| uses.get(name))
+            .map(|&n| n.saturating_sub(1).max(1) as u64)
+            .sum::<u64>();
+        let last_label = labels
+            .peek()
+            .and_then(|l| l.checked_add(dups.saturating_sub(1)));
+        if dups > 0 && last_label.is_none_or(|l| l > MAX_LABEL) {
+            return Err(Error::Graph(
+                "not enough labels left to share the definitions".to_string(),
+            ));
+        }
+
+        unsafe {
+            let mut heap = Heap::default();
+            let mut env: HashMap<IStr, Vec<Tagged>> = HashMap::new();
+            for (name, term) in defs {
+                let uses = uses.get(name).copied().unwrap_or(0);
+                if uses == 0 {
+                    continue;
+                }
+                let binders = share(&mut heap, term, uses, &mut labels);
+                env.insert(*name, binders);
+            }
+            for (name, _) in roots {
+                let slot = std::alloc::alloc(std::alloc::Layout::new::<Tagged>()) as *mut Tagged;
+                slot.write(Tagged::new_unbound_var());
+                heap.roots.push((*name, slot));
+            }
+            for ((_, slot), (_, term)) in heap.roots.clone().into_iter().zip(roots) {
+                build_graph(&mut heap, slot, term, &mut env);
+            }
+            let primary = heap.roots[0].1;
+            Ok(TermGraph(primary, heap))
+        }
+    }
+
+    /// Returns the names of the graph's roots, in order.
+    ///
+    /// A graph that was not built with [`TermGraph::from_roots`] has no named
+    /// roots.
+    pub fn root_names(&self) -> Vec<IStr> {
+        self.1.roots.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Reads back the term at the root named `name`, if there is one.
+    pub fn root_term(&self, name: IStr) -> Option<Term> {
+        let slot = self.root_slot(name)?;
+        Some(unsafe { read_back(slot) })
+    }
+
+    /// Returns the slot of the root named `name`.
+    pub(super) fn root_slot(&self, name: IStr) -> Option<*mut Tagged> {
+        self.1
+            .roots
+            .iter()
+            .find(|(root_name, _)| *root_name == name)
+            .map(|(_, slot)| *slot)
+    }
+}
+
+/// Builds `term` once and returns `uses` unused binders that all share it.
+unsafe fn share(heap: &mut Heap, term: &Term, uses: usize, labels: &mut LabelGen) -> Vec<Tagged> {
+    unsafe {
+        // A chain of dups, each duplicating the second variable of the previous:
+        // dup #l0{a0 b0} = term; dup #l1{a1 b1} = b0; ...
+        let mut binders = Vec::with_capacity(uses);
+        let mut dup_ptr = fresh_dup(heap, labels);
+        build_graph(heap, dup_ptr.dup().e(), term, &mut HashMap::new());
+        for _ in 2..uses {
+            binders.push(dup_ptr.dup_a_bound_var());
+            let next_dup_ptr = fresh_dup(heap, labels);
+            next_dup_ptr.dup().e().write(dup_ptr.dup_b_bound_var());
+            dup_ptr.dup().b().write(Tagged::new(
+                next_dup_ptr.dup().e() as *mut (),
+                Tag::VarUsePtr,
+            ));
+            dup_ptr = next_dup_ptr;
+        }
+        binders.push(dup_ptr.dup_a_bound_var());
+        if uses >= 2 {
+            binders.push(dup_ptr.dup_b_bound_var());
+        }
+        binders
+    }
+}
+
+unsafe fn fresh_dup(heap: &mut Heap, labels: &mut LabelGen) -> Tagged {
+    unsafe {
+        let dup_ptr = Dup::alloc(heap);
+        dup_ptr.dup().l().write(node_label(labels.fresh()));
+        dup_ptr.dup().a().write(Tagged::new_unused_var());
+        dup_ptr.dup().b().write(Tagged::new_unused_var());
+        dup_ptr
+    }
+}
+
+/// Counts the free occurrences in `term` of each of `names`.
+pub(super) fn count_free_uses(term: &Term, names: &HashSet<IStr>, uses: &mut HashMap<IStr, usize>) {
+    enum Task<'t> {
+        Visit(&'t Term),
+        Bind(IStr),
+        Unbind(usize),
+    }
+
+    let mut bound = vec![];
+    let mut stack = vec![Task::Visit(term)];
+    while let Some(task) = stack.pop() {
+        match task {
+            Task::Visit(Term::Var(x)) => {
+                if names.contains(x) && !bound.contains(x) {
+                    *uses.entry(*x).or_insert(0) += 1;
+                }
+            }
+            Task::Visit(Term::Ref(_) | Term::Num(_)) => {}
+            Task::Visit(Term::Lam(x, e)) => {
+                stack.push(Task::Unbind(1));
+                stack.push(Task::Visit(e));
+                stack.push(Task::Bind(*x));
+            }
+            Task::Visit(Term::App(e1, e2) | Term::Sup(_, e1, e2) | Term::Op2(_, e1, e2)) => {
+                stack.push(Task::Visit(e2));
+                stack.push(Task::Visit(e1));
+            }
+            Task::Visit(Term::Dup(_, a, b, e, cont)) => {
+                stack.push(Task::Unbind(2));
+                stack.push(Task::Visit(cont));
+                stack.push(Task::Bind(*b));
+                stack.push(Task::Bind(*a));
+                stack.push(Task::Visit(e));
+            }
+            Task::Visit(Term::Let(x, e, cont)) => {
+                stack.push(Task::Unbind(1));
+                stack.push(Task::Visit(cont));
+                stack.push(Task::Bind(*x));
+                stack.push(Task::Visit(e));
+            }
+            Task::Bind(x) => bound.push(x),
+            Task::Unbind(n) => bound.truncate(bound.len() - n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::intern::InternStatic;
+
+    fn parse(src: &str) -> Term {
+        src.parse().unwrap()
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_shared_definition() {
+        let id = "id".intern_static();
+        let a = "a".intern_static();
+        let b = "b".intern_static();
+        let c = "c".intern_static();
+        let defs = [(id, parse("λx x"))];
+        let roots = [
+            (a, parse("(id λy y)")),
+            (b, parse("(id (id λz z))")),
+            (c, parse("λw w")),
+        ];
+        let mut term_graph = TermGraph::from_roots(&defs, &roots).unwrap();
+        assert_eq!(term_graph.root_names(), vec![a, b, c]);
+        term_graph.naive_random_order_reduce();
+        for name in [a, b, c] {
+            let term = term_graph.root_term(name).unwrap();
+            assert_eq!(format!("{}", term), "(λv1 v1)");
+        }
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+        assert!(term_graph.root_term("d".intern_static()).is_none());
+    }
+
+    #[test]
+    fn test_shared_before_reduction() {
+        let f = "f".intern_static();
+        let a = "a".intern_static();
+        let b = "b".intern_static();
+        let defs = [(f, parse("λx x")), ("g".intern_static(), parse("λx x"))];
+        let roots = [(a, parse("f")), (b, parse("λf f"))];
+        let term_graph = TermGraph::from_roots(&defs, &roots).unwrap();
+        assert_eq!(
+            format!("{}", term_graph.root_term(a).unwrap()),
+            "(dup #0{v2 _} = (λv1 v1); v2)"
+        );
+        assert_eq!(format!("{}", term_graph.root_term(b).unwrap()), "(λv1 v1)");
+        let root_b = term_graph.root_cursor(b).unwrap();
+        assert_eq!(root_b.kind(), crate::vm::NodeKind::Lam);
+    }
+
+    #[test]
+    fn test_from_roots_errors() {
+        let a = "a".intern_static();
+        assert!(TermGraph::from_roots(&[], &[]).is_err());
+        assert!(TermGraph::from_roots(&[], &[(a, parse("x")), (a, parse("y"))]).is_err());
+        let defs = [(a, parse("x")), (a, parse("y"))];
+        assert!(TermGraph::from_roots(&defs, &[(a, parse("a"))]).is_err());
+    }
+
+    #[test]
+    fn test_from_roots_out_of_labels() {
+        let id = "id".intern_static();
+        let a = "a".intern_static();
+        let defs = [(id, parse("λx x"))];
+        let max = "#4294967295{id id}";
+        assert!(TermGraph::from_roots(&defs, &[(a, parse(max))]).is_err());
+        // Without a definition to share, no fresh label is needed.
+        let term_graph = TermGraph::from_roots(&[], &[(a, parse(max))]).unwrap();
+        assert_eq!(
+            format!("{}", term_graph.root_term(a).unwrap()),
+            "#4294967295{id id}"
+        );
+        // The last label is still free for a definition with a single use.
+        let term = parse("#4294967294{id y}");
+        assert!(TermGraph::from_roots(&defs, &[(a, term)]).is_ok());
+    }
+
+    #[test]
+    fn test_from_roots_deep() {
+        // Under Miri, which is far slower, only the memory accesses are checked.
+        let depth = if cfg!(miri) { 2_000 } else { 200_000 };
+        let mut term = Term::Var("id".into());
+        for _ in 0..depth {
+            term = Term::App(Box::new(term), Box::new(Term::Var("id".into())));
+        }
+        let defs = [("id".into(), parse("λx x"))];
+        let term_graph = TermGraph::from_roots(&defs, &[("main".into(), term)]).unwrap();
+        // One App per level, the shared Lam, and the dup chain sharing it.
+        assert_eq!(term_graph.1.arena.live().count(), depth + 1 + depth);
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+
+use super::{RedexSite, RuleKind, StrategyConfig, TermGraph};
+use crate::error::{Error, ParseError};
+use crate::syntax::{Label, Term};
+
+/// One step of a [`ReductionScript`]: the redex that was reduced, and the
+/// term read back afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptStep {
+    /// The index of the redex among the [`TermGraph::redex_sites`] of the
+    /// graph before the step.
+    pub index: usize,
+    pub site: RedexSite,
+    pub term: Term,
+}
+
+/// A recorded reduction that can be replayed against a graph with
+/// [`TermGraph::replay`], to check that the graph still reduces the same way.
+///
+/// Scripts have a text format, written by `Display` and read by
+/// [`ReductionScript::parse`]: a line `start term`, then for each step a line
+/// `step index kind depth binders dup_label sup_label` (with `-` for a
+/// missing label) followed by a line `  = term`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReductionScript {
+    pub start: Term,
+    pub steps: Vec<ScriptStep>,
+}
+
+impl ReductionScript {
+    /// Parses a script in the format written by `Display`.
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        let mut lines = src.lines().map(str::trim).filter(|line| !line.is_empty());
+        let end = &src[src.len()..];
+        let error = |line: &str, expected: &str| -> Error {
+            let found = match line {
+                "" => "end of input".to_string(),
+                line => format!("`{}`", line),
+            };
+            ParseError::at(src, line, expected.to_string(), found).into()
+        };
+        let parse_term = |term: &str| term.parse::<Term>().map_err(|err| err.within(src, term));
+        let line = lines.next().unwrap_or(end);
+        let start = match line.strip_prefix("start ") {
+            Some(term) => parse_term(term)?,
+            None => return Err(error(line, "`start term`")),
+        };
+        let mut steps = vec![];
+        while let Some(line) = lines.next() {
+            let (index, site) =
+                line.strip_prefix("step ")
+                    .and_then(parse_step)
+                    .ok_or_else(|| {
+                        error(line, "`step index kind depth binders dup_label sup_label`")
+                    })?;
+            let next = lines.next().unwrap_or(end);
+            let term = next
+                .strip_prefix("= ")
+                .ok_or_else(|| error(next, "`= term` after `step`"))?;
+            steps.push(ScriptStep {
+                index,
+                site,
+                term: parse_term(term)?,
+            });
+        }
+        Ok(ReductionScript { start, steps })
+    }
+}
+
+fn parse_step(step: &str) -> Option<(usize, RedexSite)> {
+    let fields: Vec<&str> = step.split_whitespace().collect();
+    let [index, kind, depth, binders, dup_label, sup_label] = fields[..] else {
+        return None;
+    };
+    let label = |field: &str| -> Option<Option<Label>> {
+        match field {
+            "-" => Some(None),
+            _ => field.parse().ok().map(Some),
+        }
+    };
+    let site = RedexSite {
+        kind: *RuleKind::ALL.iter().find(|k| format!("{:?}", k) == kind)?,
+        depth: depth.parse().ok()?,
+        binders: binders.parse().ok()?,
+        dup_label: label(dup_label)?,
+        sup_label: label(sup_label)?,
+    };
+    Some((index.parse().ok()?, site))
+}
+
+impl fmt::Display for ReductionScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = |label: Option<Label>| label.map_or("-".to_string(), |l| l.to_string());
+        writeln!(f, "start {}", self.start)?;
+        for step in &self.steps {
+            writeln!(
+                f,
+                "step {} {:?} {} {} {} {}\n  = {}",
+                step.index,
+                step.site.kind,
+                step.site.depth,
+                step.site.binders,
+                label(step.site.dup_label),
+                label(step.site.sup_label),
+                step.term
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a replayed graph stopped following its [`ReductionScript`], as
+/// returned by [`TermGraph::replay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The number of steps replayed before diverging.
+    pub step: usize,
+    /// The redex the script reduces next, which the graph does not have, or
+    /// `None` if the script has more steps but the graph is in normal form.
+    pub expected: Option<RedexSite>,
+    /// The redexes the graph has instead.
+    pub found: Vec<RedexSite>,
+    /// The term recorded before the step, and the term read back from the
+    /// graph instead, both canonical (see [`Term::canonicalize`]).
+    pub recorded: Term,
+    pub actual: Term,
+}
+
+impl Divergence {
+    /// Returns the path to the first subterm where `recorded` and `actual`
+    /// differ, in the order of [`Term::children`], along with both
+    /// subterms, or `None` if they are equal.
+    ///
+    /// Two subterms differ if they are different kinds of nodes, have
+    /// different labels or operators, or are different variables or numbers.
+    pub fn difference(&self) -> Option<(Vec<usize>, &Term, &Term)> {
+        let mut path = vec![];
+        let (mut a, mut b) = (&self.recorded, &self.actual);
+        loop {
+            // Binder names are not compared, since an extra binder on one
+            // side shifts the canonical names of the others.
+            let same_head = match (a, b) {
+                (Term::Var(x), Term::Var(y)) => x == y,
+                (Term::Lam(..), Term::Lam(..))
+                | (Term::App(..), Term::App(..))
+                | (Term::Let(..), Term::Let(..)) => true,
+                (Term::Sup(l, ..), Term::Sup(m, ..)) | (Term::Dup(l, ..), Term::Dup(m, ..)) => {
+                    l == m
+                }
+                (Term::Num(n), Term::Num(m)) => n == m,
+                (Term::Op2(op, ..), Term::Op2(other, ..)) => op == other,
+                _ => false,
+            };
+            if !same_head {
+                return Some((path, a, b));
+            }
+            let (index, (c, d)) = a
+                .children()
+                .zip(b.children())
+                .enumerate()
+                .find(|(_, (c, d))| c != d)?;
+            path.push(index);
+            (a, b) = (c, d);
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.expected {
+            Some(site) => writeln!(
+                f,
+                "step {}: the recorded {:?} redex at depth {} no longer exists",
+                self.step + 1,
+                site.kind,
+                site.depth
+            )?,
+            None => writeln!(
+                f,
+                "step {}: the graph is already in normal form",
+                self.step + 1
+            )?,
+        }
+        match self.difference() {
+            Some((path, recorded, actual)) => {
+                write!(f, "  graphs differ at /")?;
+                let path: Vec<String> = path.iter().map(|index| index.to_string()).collect();
+                writeln!(f, "{}", path.join("/"))?;
+                writeln!(f, "  recorded: {}", recorded)?;
+                write!(f, "    actual: {}", actual)
+            }
+            None => write!(f, "  graphs are equal: {}", self.actual),
+        }
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// recording the redex reduced in every step.
+    pub fn record_script(&mut self, config: &StrategyConfig) -> ReductionScript {
+        let start = Term::from(&*self);
+        let mut steps = vec![];
+        loop {
+            let mut chosen = None;
+            let rule = self.reduce_step_chosen_by(|sites| {
+                let index = config.choose(sites)?;
+                chosen = Some((index, sites[index]));
+                Some(index)
+            });
+            let (Some(_), Some((index, site))) = (rule, chosen) else {
+                break;
+            };
+            steps.push(ScriptStep {
+                index,
+                site,
+                term: Term::from(&*self),
+            });
+        }
+        ReductionScript { start, steps }
+    }
+
+    /// Reduces the graph by following `script`, returning the number of
+    /// steps taken.
+    ///
+    /// Each step reduces the redex at the recorded index if it is the
+    /// recorded redex, and otherwise the first redex that is, so that a
+    /// change in the order of the redexes alone does not break the replay.
+    /// If the graph has no such redex, returns the point of divergence,
+    /// leaving the graph as it was before that step.
+    pub fn replay(&mut self, script: &ReductionScript) -> Result<usize, Box<Divergence>> {
+        let mut recorded = &script.start;
+        for (step, ScriptStep { index, site, term }) in script.steps.iter().enumerate() {
+            let sites = self.redex_sites();
+            let found = if sites.get(*index) == Some(site) {
+                Some(*index)
+            } else {
+                sites.iter().position(|s| s == site)
+            };
+            match found {
+                Some(found) => {
+                    self.reduce_step_chosen_by(|_| Some(found));
+                }
+                None => {
+                    return Err(Box::new(Divergence {
+                        step,
+                        expected: (!sites.is_empty()).then_some(*site),
+                        found: sites,
+                        recorded: recorded.canonicalize().0,
+                        actual: Term::from(&*self).canonicalize().0,
+                    }))
+                }
+            }
+            recorded = term;
+        }
+        Ok(script.steps.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let config = StrategyConfig::default();
+        let script = TermGraph::from(&term).record_script(&config);
+        assert_eq!(script.steps.len(), 3);
+        assert_eq!(script.steps[0].site.kind, RuleKind::DupLam);
+
+        let parsed = ReductionScript::parse(&script.to_string()).unwrap();
+        assert_eq!(parsed, script);
+        assert_eq!(TermGraph::from(&term).replay(&parsed), Ok(3));
+        let err = ReductionScript::parse("start x\nstep 0 AppLam\n  = x\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("parse error: line 2, column 1: expected `step index kind"));
+        assert!(ReductionScript::parse("step 0 AppLam 0 0 - -\n").is_err());
+    }
+
+    #[test]
+    fn test_replay_divergence() {
+        let config = StrategyConfig::default();
+        let script =
+            TermGraph::from(&"dup #0{a b} = λx x; (a b)".parse().unwrap()).record_script(&config);
+        // The same term with a superposition where the lambda was.
+        let term: Term = "dup #0{a b} = #1{y z}; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let divergence = term_graph.replay(&script).unwrap_err();
+        assert_eq!(divergence.step, 0);
+        assert_eq!(divergence.found[0].kind, RuleKind::DupSupDiff);
+        assert_eq!(
+            divergence.to_string(),
+            "step 1: the recorded DupLam redex at depth 1 no longer exists\n  \
+             graphs differ at /0\n  \
+             recorded: (λx0 x0)\n    \
+             actual: #1{y z}"
+        );
+        // The graph is left as it was.
+        assert_eq!(Term::from(&term_graph), Term::from(&TermGraph::from(&term)));
+    }
+}
@@ -0,0 +1,182 @@
+use std::io;
+use std::mem::size_of;
+
+use super::{collect_redexes, App, Dup, Lam, Num, Op2, StrategyConfig, Sup, Tag, TermGraph};
+
+/// Measurements of a graph taken by a [`TimeSeries`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Sample {
+    /// The number of steps taken when the sample was taken.
+    pub step: usize,
+    /// The number of live nodes.
+    pub nodes: usize,
+    /// The number of redexes.
+    pub redexes: usize,
+    /// The number of bytes taken by the live nodes.
+    pub memory: usize,
+}
+
+/// Samples of a graph taken every `interval` steps of a reduction, for
+/// plotting how its size changes over time.
+///
+/// A series can be reused: [`TimeSeries::clear`] drops the samples but keeps
+/// the interval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeSeries {
+    interval: usize,
+    samples: Vec<Sample>,
+}
+
+impl TimeSeries {
+    /// The header row of [`TimeSeries::write_csv`].
+    pub const CSV_HEADER: &'static str = "step,nodes,redexes,memory";
+
+    /// Returns an empty series that samples every `interval` steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is 0.
+    pub fn new(interval: usize) -> Self {
+        assert!(interval > 0, "sampling interval must be positive");
+        TimeSeries {
+            interval,
+            samples: vec![],
+        }
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Returns the samples, in the order they were taken.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Samples `graph` as it is after `step` steps, if `step` is a multiple
+    /// of the interval. Returns whether a sample was taken.
+    pub fn observe(&mut self, graph: &TermGraph, step: usize) -> bool {
+        if !step.is_multiple_of(self.interval) {
+            return false;
+        }
+        self.record(graph, step);
+        true
+    }
+
+    /// Samples `graph` as it is after `step` steps, whatever the interval.
+    pub fn record(&mut self, graph: &TermGraph, step: usize) {
+        let mut sample = Sample {
+            step,
+            redexes: unsafe { collect_redexes(&graph.root_slots()) }.len(),
+            ..Sample::default()
+        };
+        for node in graph.1.arena.live() {
+            sample.nodes += 1;
+            sample.memory += match unsafe { node.tag() } {
+                Tag::LamPtr => size_of::<Lam>(),
+                Tag::AppPtr => size_of::<App>(),
+                Tag::SupPtr => size_of::<Sup>(),
+                Tag::DupPtr => size_of::<Dup>(),
+                Tag::NumPtr => size_of::<Num>(),
+                Tag::Op2Ptr => size_of::<Op2>(),
+                _ => unreachable!(),
+            };
+        }
+        self.samples.push(sample);
+    }
+
+    /// Writes the samples to `out` as CSV: a header row, then one row per
+    /// sample.
+    pub fn write_csv(&self, out: &mut impl io::Write) -> io::Result<()> {
+        writeln!(out, "{}", Self::CSV_HEADER)?;
+        for sample in &self.samples {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                sample.step, sample.nodes, sample.redexes, sample.memory
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the series to `out` as a JSON object with the interval and an
+    /// array of samples, one per line.
+    pub fn write_json(&self, out: &mut impl io::Write) -> io::Result<()> {
+        write!(out, "{{\"interval\":{},\"samples\":[", self.interval)?;
+        for (i, sample) in self.samples.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(
+                out,
+                "{}\n{{\"step\":{},\"nodes\":{},\"redexes\":{},\"memory\":{}}}",
+                separator, sample.step, sample.nodes, sample.redexes, sample.memory
+            )?;
+        }
+        writeln!(out, "\n]}}")
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// adding a sample to `series` every interval steps (starting with the
+    /// initial graph) and one of the normal form. Returns the number of
+    /// steps taken.
+    pub fn naive_reduce_sampled(
+        &mut self,
+        config: &StrategyConfig,
+        series: &mut TimeSeries,
+    ) -> usize {
+        let mut steps = 0;
+        let mut sampled = series.observe(self, steps);
+        while self.naive_reduce_step_with(config).is_some() {
+            steps += 1;
+            sampled = series.observe(self, steps);
+        }
+        if !sampled {
+            series.record(self, steps);
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_sampled() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let mut series = TimeSeries::new(2);
+        let steps =
+            TermGraph::from(&term).naive_reduce_sampled(&StrategyConfig::default(), &mut series);
+        assert_eq!(steps, 3);
+        let steps: Vec<usize> = series.samples().iter().map(|s| s.step).collect();
+        assert_eq!(steps, [0, 2, 3]);
+        assert_eq!(series.samples()[0].nodes, 3);
+        assert_eq!(series.samples()[0].redexes, 1);
+        // A lambda, an application and a dup.
+        assert_eq!(series.samples()[0].memory, 16 + 16 + 32);
+        assert_eq!(series.samples()[2].redexes, 0);
+
+        let mut csv = vec![];
+        series.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert_eq!(csv.lines().nth(1), Some("0,3,1,64"));
+
+        let mut json = vec![];
+        series.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("{\"interval\":2,\"samples\":[\n{\"step\":0,\"nodes\":3,"));
+        assert!(json.ends_with("\"redexes\":0,\"memory\":16}\n]}\n"));
+
+        series.clear();
+        assert!(series.samples().is_empty());
+        assert_eq!(series.interval(), 2);
+    }
+}
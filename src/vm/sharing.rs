@@ -0,0 +1,174 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    next_redex, reduce_redex, DupPtrExt, Redex, Rule, StrategyConfig, Tag, Tagged, TermGraph,
+};
+
+/// How much work sharing saved during a reduction to normal form.
+///
+/// A tree evaluator copies a term every time it is used more than once, and
+/// then reduces each copy separately. The graph only copies a term as dups
+/// propagate through it, so a beta reduction performed on a shared `App`
+/// stands for one beta reduction per copy the tree evaluator would have made.
+/// The number of copies is estimated by counting the paths from the roots to
+/// the `App` node, where every dup with both variables used fans out into two.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharingReport {
+    /// The number of `AppLam` rewrites performed.
+    pub beta_steps: usize,
+    /// The estimated number of beta reductions a tree evaluator would perform.
+    pub tree_beta_steps: usize,
+    /// The number of `AppSup`, `DupLam`, `DupSup`, `OpSup`, and `DupNum`
+    /// rewrites performed.
+    pub duplication_steps: usize,
+}
+
+impl SharingReport {
+    /// Returns the estimated number of beta reductions saved by sharing.
+    pub fn beta_steps_saved(&self) -> usize {
+        self.tree_beta_steps - self.beta_steps
+    }
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// and reports how many beta reductions sharing saved.
+    pub fn naive_reduce_with_sharing_report(&mut self, config: &StrategyConfig) -> SharingReport {
+        let mut report = SharingReport::default();
+        let roots = self.root_slots();
+        unsafe {
+            loop {
+                let redex = match next_redex(&roots, config) {
+                    Some(redex) => redex,
+                    None => return report,
+                };
+                match Rule::from(redex) {
+                    Rule::AppLam => {
+                        report.beta_steps += 1;
+                        report.tree_beta_steps += self.copies(redex);
+                    }
+                    Rule::AppSup | Rule::DupLam | Rule::DupSup | Rule::OpSup | Rule::DupNum => {
+                        report.duplication_steps += 1
+                    }
+                    Rule::Ref | Rule::OpNum | Rule::Era => {}
+                }
+                reduce_redex(&mut self.1, redex);
+            }
+        }
+    }
+
+    /// Counts the paths from the roots to the `App` node of `redex`.
+    unsafe fn copies(&self, redex: Redex) -> usize {
+        unsafe {
+            let ptr_ptr = match redex {
+                Redex::AppLam { ptr_ptr, .. } => ptr_ptr,
+                _ => unreachable!(),
+            };
+            let roots: HashSet<*mut Tagged> = self.root_slots().into_iter().collect();
+            let mut owners = HashMap::new();
+            let mut parents = HashMap::new();
+            for node in self.node_iter() {
+                for slot in node.child_slots() {
+                    owners.insert(slot, node);
+                }
+            }
+            for slot in owners.keys().chain(roots.iter()).copied() {
+                let ptr = slot.read();
+                if matches!(
+                    ptr.tag(),
+                    Tag::LamPtr | Tag::AppPtr | Tag::SupPtr | Tag::Op2Ptr
+                ) {
+                    parents.insert(ptr, slot);
+                }
+            }
+            let paths = Paths {
+                roots,
+                owners,
+                parents,
+            };
+            paths.to_slot(ptr_ptr, &mut HashSet::new())
+        }
+    }
+}
+
+struct Paths {
+    roots: HashSet<*mut Tagged>,
+    /// The node owning each child slot.
+    owners: HashMap<*mut Tagged, Tagged>,
+    /// The slot pointing to each `Lam`, `App`, and `Sup` node.
+    parents: HashMap<Tagged, *mut Tagged>,
+}
+
+impl Paths {
+    unsafe fn to_slot(&self, slot: *mut Tagged, visiting: &mut HashSet<Tagged>) -> usize {
+        unsafe {
+            if self.roots.contains(&slot) {
+                return 1;
+            }
+            match self.owners.get(&slot) {
+                Some(owner) => self.to_node(*owner, visiting),
+                None => 0,
+            }
+        }
+    }
+
+    unsafe fn to_node(&self, node: Tagged, visiting: &mut HashSet<Tagged>) -> usize {
+        unsafe {
+            if !visiting.insert(node) {
+                return 0;
+            }
+            let count = match node.tag() {
+                Tag::DupPtr => [node.dup().a(), node.dup().b()]
+                    .into_iter()
+                    .map(|binder| binder.read())
+                    .filter(|x| x.tag() == Tag::VarUsePtr)
+                    .map(|x| self.to_slot(x.var_use(), visiting))
+                    .sum(),
+                _ => match self.parents.get(&node) {
+                    Some(slot) => self.to_slot(*slot, visiting),
+                    None => 0,
+                },
+            };
+            visiting.remove(&node);
+            count
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_no_sharing() {
+        let term: Term = "((λx x) (λy y))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let report = term_graph.naive_reduce_with_sharing_report(&StrategyConfig::default());
+        assert_eq!(report.beta_steps, 1);
+        assert_eq!(report.tree_beta_steps, 1);
+        assert_eq!(report.beta_steps_saved(), 0);
+        assert_eq!(report.duplication_steps, 0);
+    }
+
+    #[test]
+    fn test_shared_beta() {
+        // The inner beta reduction is shared by both copies of `a`.
+        let term: Term = "dup #0{a b} = ((λx x) λy y); #1{a b}".parse().unwrap();
+        let config = StrategyConfig {
+            priorities: vec![crate::vm::RuleKind::AppLam],
+        };
+        let mut term_graph = TermGraph::from(&term);
+        let report = term_graph.naive_reduce_with_sharing_report(&config);
+        assert_eq!(report.beta_steps, 1);
+        assert_eq!(report.tree_beta_steps, 2);
+        assert_eq!(report.beta_steps_saved(), 1);
+        // DupLam, then DupSup on the lambda's variable.
+        assert_eq!(report.duplication_steps, 2);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "#1{(λv1 v1) (λv2 v2)}"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use super::{ChildRecord, NodeRecord, RecordTag, TermGraph, UseRecord};
+use crate::error::Error;
+use crate::intern::{IStr, Intern};
+use crate::syntax::Op;
+
+/// The bytes every snapshot starts with: a magic number and the version of
+/// the format.
+const HEADER: &[u8] = b"ICTG\x01";
+
+impl TermGraph {
+    /// Encodes the graph as bytes, for example to write a partially reduced
+    /// graph to disk and resume reducing it later with
+    /// [`TermGraph::deserialize`].
+    ///
+    /// The snapshot holds the records of [`TermGraph::dump`]: after a header,
+    /// the number of nodes, then each node in id order, as its tag, its label
+    /// or value, its children and the uses of its variables. A child or use
+    /// refers to another node by the difference between their ids, which is
+    /// usually small, so most fit in a byte. Integers are LEB128 varints,
+    /// differences are zigzag encoded first, and names are their length
+    /// followed by their UTF-8 bytes.
+    ///
+    /// Only the graph is saved: not its statistics, nor the definitions of
+    /// its book, so a graph that still holds references cannot be restored.
+    pub fn serialize(&self) -> Vec<u8> {
+        let records = self.dump();
+        let mut out = HEADER.to_vec();
+        write_uint(&mut out, records.len() as u64);
+        for record in &records {
+            let id = record.id;
+            match record.tag {
+                RecordTag::Lam => out.push(0),
+                RecordTag::App => out.push(1),
+                RecordTag::Sup => out.push(2),
+                RecordTag::Dup => out.push(3),
+                RecordTag::Num(n) => {
+                    out.push(4);
+                    write_uint(&mut out, n);
+                }
+                RecordTag::Op2(op) => {
+                    out.push(5);
+                    out.push(Op::ALL.iter().position(|o| *o == op).unwrap() as u8);
+                }
+            }
+            if let Some(label) = record.label {
+                write_uint(&mut out, label);
+            }
+            for child in &record.children {
+                match child {
+                    ChildRecord::Node(to) => write_ref(&mut out, 0, id, *to),
+                    ChildRecord::LamVar(to) => write_ref(&mut out, 1, id, *to),
+                    ChildRecord::DupAVar(to) => write_ref(&mut out, 2, id, *to),
+                    ChildRecord::DupBVar(to) => write_ref(&mut out, 3, id, *to),
+                    ChildRecord::FreeVar(None) => out.push(4),
+                    ChildRecord::FreeVar(Some(name)) => {
+                        out.push(5);
+                        write_name(&mut out, *name);
+                    }
+                    ChildRecord::Ref(name) => {
+                        out.push(6);
+                        write_name(&mut out, *name);
+                    }
+                }
+            }
+            for use_ in &record.uses {
+                match use_ {
+                    UseRecord::Unused => out.push(0),
+                    UseRecord::Node(to) => write_ref(&mut out, 1, id, *to),
+                    UseRecord::Root => out.push(2),
+                }
+            }
+        }
+        out
+    }
+
+    /// Decodes a graph encoded by [`TermGraph::serialize`].
+    ///
+    /// Returns [`Error::Graph`] if `bytes` are not a snapshot, or do not
+    /// describe a graph (see [`TermGraph::from_dump`]).
+    pub fn deserialize(bytes: &[u8]) -> Result<TermGraph, Error> {
+        let Some(body) = bytes.strip_prefix(HEADER) else {
+            return Err(invalid("missing header"));
+        };
+        let mut reader = Reader { bytes: body };
+        let count = reader.usize()?;
+        // Every node takes at least one byte, which bounds the allocation.
+        let mut records = Vec::with_capacity(count.min(body.len()));
+        for id in 0..count {
+            let (tag, labelled, children, uses) = match reader.byte()? {
+                0 => (RecordTag::Lam, false, 1, 1),
+                1 => (RecordTag::App, false, 2, 0),
+                2 => (RecordTag::Sup, true, 2, 0),
+                3 => (RecordTag::Dup, true, 1, 2),
+                4 => (RecordTag::Num(reader.uint()?), false, 0, 0),
+                5 => match Op::ALL.get(reader.byte()? as usize) {
+                    Some(op) => (RecordTag::Op2(*op), false, 2, 0),
+                    None => return Err(invalid("unknown operation")),
+                },
+                tag => return Err(invalid(&format!("unknown node tag {}", tag))),
+            };
+            let label = match labelled {
+                true => Some(reader.uint()?),
+                false => None,
+            };
+            let children = (0..children)
+                .map(|_| {
+                    Ok(match reader.byte()? {
+                        0 => ChildRecord::Node(reader.target(id)?),
+                        1 => ChildRecord::LamVar(reader.target(id)?),
+                        2 => ChildRecord::DupAVar(reader.target(id)?),
+                        3 => ChildRecord::DupBVar(reader.target(id)?),
+                        4 => ChildRecord::FreeVar(None),
+                        5 => ChildRecord::FreeVar(Some(reader.name()?)),
+                        6 => ChildRecord::Ref(reader.name()?),
+                        kind => return Err(invalid(&format!("unknown child kind {}", kind))),
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+            let uses = (0..uses)
+                .map(|_| {
+                    Ok(match reader.byte()? {
+                        0 => UseRecord::Unused,
+                        1 => UseRecord::Node(reader.target(id)?),
+                        2 => UseRecord::Root,
+                        kind => return Err(invalid(&format!("unknown use kind {}", kind))),
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+            records.push(NodeRecord {
+                id,
+                tag,
+                label,
+                children,
+                uses,
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        TermGraph::from_dump(&records)
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Graph(format!("invalid snapshot: {}", message))
+}
+
+fn write_uint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Writes `kind`, then the reference from node `from` to node `to`.
+fn write_ref(out: &mut Vec<u8>, kind: u8, from: usize, to: usize) {
+    out.push(kind);
+    let delta = to as i64 - from as i64;
+    write_uint(out, ((delta << 1) ^ (delta >> 63)) as u64);
+}
+
+fn write_name(out: &mut Vec<u8>, name: IStr) {
+    let name = name.to_string();
+    write_uint(out, name.len() as u64);
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Reads the parts of a snapshot from the front of `bytes`.
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        let (first, rest) = self
+            .bytes
+            .split_first()
+            .ok_or_else(|| invalid("truncated"))?;
+        self.bytes = rest;
+        Ok(*first)
+    }
+
+    fn uint(&mut self) -> Result<u64, Error> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(invalid("integer too large"))
+    }
+
+    fn usize(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.uint()?).map_err(|_| invalid("integer too large"))
+    }
+
+    /// Reads a reference from node `from`, and returns the id it refers to.
+    fn target(&mut self, from: usize) -> Result<usize, Error> {
+        let n = self.uint()?;
+        let delta = (n >> 1) as i64 ^ -((n & 1) as i64);
+        (from as i64)
+            .checked_add(delta)
+            .and_then(|to| usize::try_from(to).ok())
+            .ok_or_else(|| invalid("reference out of range"))
+    }
+
+    fn name(&mut self) -> Result<IStr, Error> {
+        let len = self.usize()?;
+        if len > self.bytes.len() {
+            return Err(invalid("truncated"));
+        }
+        let (name, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        match std::str::from_utf8(name) {
+            Ok(name) => Ok(name.intern()),
+            Err(_) => Err(invalid("name is not UTF-8")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_snapshot_resume() {
+        let src = "((λf dup #0{f1 f2} = f; λx (f1 (f2 x)) λy dup #1{a b} = y; #2{a (+ b 1)}) 7)";
+        let term: Term = src.parse().unwrap();
+        let mut uninterrupted = TermGraph::from(&term);
+        while uninterrupted.naive_reduce_step().is_some() {}
+
+        let mut term_graph = TermGraph::from(&term);
+        for _ in 0..3 {
+            term_graph.naive_reduce_step().unwrap();
+        }
+        let bytes = term_graph.serialize();
+        let mut resumed = TermGraph::deserialize(&bytes).unwrap();
+        assert_eq!(resumed.dump(), term_graph.dump());
+        assert_eq!(resumed.serialize(), bytes);
+        while resumed.naive_reduce_step().is_some() {}
+        assert_eq!(Term::from(&resumed), Term::from(&uninterrupted));
+    }
+
+    #[test]
+    fn test_snapshot_names() {
+        let term: Term = "λx (f #0{x y})".parse().unwrap();
+        let term_graph = TermGraph::deserialize(&TermGraph::from(&term).serialize()).unwrap();
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv2 (f #0{v2 y}))");
+    }
+
+    #[test]
+    fn test_snapshot_errors() {
+        let bytes = TermGraph::from(&"λx (x y)".parse().unwrap()).serialize();
+        let error = |bytes: &[u8]| TermGraph::deserialize(bytes).unwrap_err().to_string();
+        assert_eq!(
+            error(b"nope"),
+            "graph error: invalid snapshot: missing header"
+        );
+        assert_eq!(
+            error(&bytes[..bytes.len() - 1]),
+            "graph error: invalid snapshot: truncated"
+        );
+        assert_eq!(
+            error(&[&bytes[..], &[0]].concat()),
+            "graph error: invalid snapshot: trailing bytes"
+        );
+        // A node that refers to itself.
+        let mut cyclic = bytes.clone();
+        cyclic[HEADER.len() + 3] = 0;
+        assert!(error(&cyclic).starts_with("graph error: invalid dump: "));
+        assert!(TermGraph::deserialize(&[HEADER, &[0xff; 12]].concat()).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use super::{
+    op2_redex, reduce_redex, AppPtrExt, DupPtrExt, LamPtrExt, Op2PtrExt, Redex, Rule, SupPtrExt,
+    Tag, Tagged, TermGraph,
+};
+
+/// The result of walking the head spine of a term.
+pub(super) enum Spine {
+    /// The redex at the head of the term.
+    Redex(Redex),
+    /// The term is in head normal form. These are the slots of the subterms
+    /// hanging off of its spine (arguments of applications, branches of
+    /// superpositions, and operands not yet needed), outermost first.
+    Stuck(Vec<*mut Tagged>),
+}
+
+/// Walks the head spine of the term in `slot`: under lambdas (if
+/// `under_lambdas`), into the function of applications, into the first
+/// operand of operations (or the second, once the first is a number), and
+/// from dup variables into the duplicated expression.
+///
+/// Dups in `visited` are not entered again, and the dups that are entered are
+/// added to it, so that shared expressions are only walked once.
+pub(super) unsafe fn walk_spine(
+    slot: *mut Tagged,
+    visited: &mut HashSet<*mut ()>,
+    under_lambdas: bool,
+) -> Spine {
+    unsafe {
+        let mut slot = slot;
+        let mut hanging = vec![];
+        loop {
+            let ptr = slot.read();
+            match ptr.tag() {
+                Tag::LamPtr if under_lambdas => slot = ptr.lam().e(),
+                Tag::AppPtr => {
+                    let e1 = ptr.app().e1().read();
+                    match e1.tag() {
+                        Tag::LamPtr => {
+                            return Spine::Redex(Redex::AppLam {
+                                ptr_ptr: slot,
+                                app_ptr: ptr,
+                                lam_ptr: e1,
+                            })
+                        }
+                        Tag::SupPtr => {
+                            return Spine::Redex(Redex::AppSup {
+                                ptr_ptr: slot,
+                                app_ptr: ptr,
+                                sup_ptr: e1,
+                            })
+                        }
+                        Tag::Ref => {
+                            return Spine::Redex(Redex::Ref {
+                                ptr_ptr: ptr.app().e1(),
+                            })
+                        }
+                        _ => {
+                            hanging.push(ptr.app().e2());
+                            slot = ptr.app().e1();
+                        }
+                    }
+                }
+                Tag::SupPtr => {
+                    hanging.push(ptr.sup().e2());
+                    hanging.push(ptr.sup().e1());
+                    return Spine::Stuck(hanging);
+                }
+                Tag::Op2Ptr => {
+                    if let Some(redex) = op2_redex(slot, ptr) {
+                        return Spine::Redex(redex);
+                    }
+                    if ptr.op2().e1().read().tag() == Tag::NumPtr {
+                        slot = ptr.op2().e2();
+                    } else {
+                        hanging.push(ptr.op2().e2());
+                        slot = ptr.op2().e1();
+                    }
+                }
+                Tag::DupABoundVar | Tag::DupBBoundVar => {
+                    let e = ptr.dup().e().read();
+                    match e.tag() {
+                        Tag::LamPtr => {
+                            return Spine::Redex(Redex::DupLam {
+                                dup_ptr: ptr,
+                                lam_ptr: e,
+                            })
+                        }
+                        Tag::SupPtr => {
+                            return Spine::Redex(Redex::DupSup {
+                                dup_ptr: ptr,
+                                sup_ptr: e,
+                            })
+                        }
+                        Tag::NumPtr => {
+                            return Spine::Redex(Redex::DupNum {
+                                dup_ptr: ptr,
+                                num_ptr: e,
+                            })
+                        }
+                        Tag::Ref => {
+                            return Spine::Redex(Redex::Ref {
+                                ptr_ptr: ptr.dup().e(),
+                            })
+                        }
+                        _ => {
+                            if !visited.insert(ptr.ptr()) {
+                                return Spine::Stuck(hanging);
+                            }
+                            slot = ptr.dup().e();
+                        }
+                    }
+                }
+                _ => return Spine::Stuck(hanging),
+            }
+        }
+    }
+}
+
+/// Returns the redex unfolding the reference in the root slot `slot`, if it
+/// holds one. Other references are only unfolded once applied or duplicated,
+/// which `walk_spine` finds.
+pub(super) unsafe fn root_ref(slot: *mut Tagged) -> Option<Redex> {
+    unsafe { (slot.read().tag() == Tag::Ref).then_some(Redex::Ref { ptr_ptr: slot }) }
+}
+
+/// Finds the leftmost-outermost redex reachable from `roots`.
+///
+/// Rather than collecting every redex in the graph, this walks the head spine
+/// of each root, and only looks inside the subterms hanging off of a spine
+/// once the spine is known to be stuck.
+pub(super) unsafe fn normal_order_redex(roots: &[*mut Tagged]) -> Option<Redex> {
+    unsafe {
+        if let Some(redex) = roots.iter().find_map(|root| root_ref(*root)) {
+            return Some(redex);
+        }
+        let mut visited = HashSet::new();
+        let mut stack: Vec<*mut Tagged> = roots.iter().rev().copied().collect();
+        while let Some(slot) = stack.pop() {
+            match walk_spine(slot, &mut visited, true) {
+                Spine::Redex(redex) => return Some(redex),
+                // The innermost (i.e. leftmost) hanging subterm is popped first.
+                Spine::Stuck(hanging) => stack.extend(hanging),
+            }
+        }
+        None
+    }
+}
+
+impl TermGraph {
+    /// Reduces the leftmost-outermost redex of the graph, if there is one.
+    pub fn reduce_normal_order_step(&mut self) -> Option<Rule> {
+        let roots = self.root_slots();
+        unsafe {
+            let redex = normal_order_redex(&roots)?;
+            reduce_redex(&mut self.1, redex);
+            Some(redex.into())
+        }
+    }
+
+    /// Reduces the graph to normal form in normal order, returning the number
+    /// of steps taken.
+    ///
+    /// Normal order finds a normal form whenever one exists, even if some
+    /// subterm of the graph diverges.
+    pub fn reduce_normal_order(&mut self) -> usize {
+        let mut steps = 0;
+        while self.reduce_normal_order_step().is_some() {
+            steps += 1;
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_normal_order() {
+        // The discarded argument Ω is never reduced.
+        let omega = "(λd dup #0{a b} = d; (a b) λd dup #0{a b} = d; (a b))";
+        let term: Term = format!("λx ((λy λz z) {} ((λw w) x))", omega)
+            .parse()
+            .unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_normal_order(), 3);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 v1)");
+    }
+
+    #[test]
+    fn test_normal_order_under_stuck_head() {
+        let term: Term = "λx (x ((λy y) x2) #0{((λz z) a) b})".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_normal_order(), 2);
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 ((v1 x2) #0{a b}))"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use super::{read_back, SupPtrExt, Tag, TermGraph};
+
+impl TermGraph {
+    /// If the root of the graph is a superposition, returns a new graph for
+    /// each of its branches.
+    ///
+    /// The new graphs are independent of this one and of each other: structure
+    /// shared between the branches through dups is copied into both, and a
+    /// variable of one branch that is bound in the other becomes free.
+    pub fn split_root_sup(&self) -> Option<(TermGraph, TermGraph)> {
+        unsafe {
+            let root = self.0.read();
+            if root.tag() != Tag::SupPtr {
+                return None;
+            }
+            let left = read_back(root.sup().e1());
+            let right = read_back(root.sup().e2());
+            let book = &self.1.book;
+            Some((
+                TermGraph::with_book(book.clone(), &left),
+                TermGraph::with_book(book.clone(), &right),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_split_root_sup() {
+        let term: Term = "dup #0{a b} = λx x; #1{(a y) λz (b z)}".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let (mut left, mut right) = term_graph.split_root_sup().unwrap();
+        left.naive_random_order_reduce();
+        right.naive_random_order_reduce();
+        assert_eq!(format!("{}", Term::from(&left)), "y");
+        assert_eq!(format!("{}", Term::from(&right)), "(λv1 v1)");
+        // The original graph is untouched.
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(dup #0{v2 v4} = (λv1 v1); #1{(v2 y) (λv5 (v4 v5))})"
+        );
+    }
+
+    #[test]
+    fn test_split_root_not_sup() {
+        let term: Term = "λx #0{x x2}".parse().unwrap();
+        assert!(TermGraph::from(&term).split_root_sup().is_none());
+    }
+}
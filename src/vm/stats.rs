@@ -0,0 +1,155 @@
+use super::{RuleKind, TermGraph};
+
+/// Counts of the work done by a [`TermGraph`], as returned by
+/// [`TermGraph::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    rewrites: [usize; 15],
+    /// The number of nodes allocated, including those the graph was built
+    /// with.
+    pub allocated: usize,
+    /// The number of nodes deallocated, by rewrites or garbage collection.
+    pub freed: usize,
+    /// The largest number of nodes live at once.
+    pub peak_live: usize,
+    /// The nodes live when the stats were taken.
+    pub live: LiveNodes,
+}
+
+/// The number of live nodes of each type, as counted by [`Stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiveNodes {
+    /// The number of live `Lam` nodes.
+    pub lams: usize,
+    /// The number of live `App` nodes.
+    pub apps: usize,
+    /// The number of live `Sup` nodes.
+    pub sups: usize,
+    /// The number of live `Dup` nodes.
+    pub dups: usize,
+    /// The number of live `Num` nodes.
+    pub nums: usize,
+    /// The number of live `Op2` nodes.
+    pub ops: usize,
+}
+
+impl LiveNodes {
+    /// The number of live nodes of all types.
+    pub fn total(&self) -> usize {
+        self.lams + self.apps + self.sups + self.dups + self.nums + self.ops
+    }
+}
+
+impl Stats {
+    /// The number of rewrites of kind `kind`.
+    pub fn rewrites(&self, kind: RuleKind) -> usize {
+        self.rewrites[kind as usize]
+    }
+
+    /// The number of rewrites of all kinds.
+    pub fn total_rewrites(&self) -> usize {
+        self.rewrites.iter().sum()
+    }
+}
+
+impl TermGraph {
+    /// Returns the rewrites, allocations, and deallocations counted since the
+    /// graph was built, or since the last [`TermGraph::reset_stats`], and the
+    /// nodes live now. Counting is part of allocating and freeing nodes, so
+    /// this is cheap enough to call after every step.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            rewrites: self.1.rewrites,
+            allocated: self.1.allocations,
+            freed: self.1.freed,
+            peak_live: self.1.peak_live,
+            live: self.1.live_nodes(),
+        }
+    }
+
+    /// Resets the counts of [`TermGraph::stats`], so that the peak starts
+    /// from the nodes live now. The live nodes are still counted.
+    pub fn reset_stats(&mut self) {
+        self.1.rewrites = [0; 15];
+        self.1.allocations = 0;
+        self.1.freed = 0;
+        self.1.peak_live = self.1.live_count();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_stats() {
+        let term: Term = "dup #0{a b} = λx x; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let built = term_graph.stats();
+        assert_eq!(built.total_rewrites(), 0);
+        assert_eq!(built.allocated, 3);
+        assert_eq!(built.peak_live, 3);
+        assert_eq!(
+            built.live,
+            LiveNodes {
+                lams: 1,
+                apps: 1,
+                dups: 1,
+                ..LiveNodes::default()
+            }
+        );
+        while term_graph.naive_reduce_step().is_some() {}
+        let stats = term_graph.stats();
+        assert_eq!(stats.rewrites(RuleKind::DupLam), 1);
+        assert_eq!(stats.rewrites(RuleKind::DupSupSame), 1);
+        assert_eq!(stats.rewrites(RuleKind::AppLam), 1);
+        assert_eq!(stats.total_rewrites(), 3);
+        // λx x is all that is left.
+        assert_eq!(stats.allocated - stats.freed, 1);
+        assert_eq!(stats.live.lams, 1);
+        assert_eq!(stats.live.total(), 1);
+
+        term_graph.reset_stats();
+        assert_eq!(
+            term_graph.stats(),
+            Stats {
+                peak_live: 1,
+                live: stats.live,
+                ..Stats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_live() {
+        let term: Term = "((λf dup #0{f1 f2} = f; λx (f1 (f2 x))) λy (+ y 1))"
+            .parse()
+            .unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut peak = 0;
+        while term_graph.naive_reduce_step().is_some() {
+            let stats = term_graph.stats();
+            assert_eq!(stats.live.total(), stats.allocated - stats.freed);
+            peak = peak.max(stats.live.total());
+            assert_eq!(stats.live.total(), term_graph.1.arena.live().count());
+        }
+        let stats = term_graph.stats();
+        // Nodes may be allocated before others are freed within a step.
+        assert!(stats.peak_live >= peak);
+        assert_eq!(
+            stats.live,
+            LiveNodes {
+                lams: 1,
+                nums: 2,
+                ops: 2,
+                ..LiveNodes::default()
+            }
+        );
+        assert_eq!(term_graph.clone().stats().live, stats.live);
+
+        let mut parallel = TermGraph::from(&term);
+        parallel.parallel_reduce(2);
+        assert_eq!(parallel.stats().live, stats.live);
+    }
+}
@@ -0,0 +1,458 @@
+use super::spine::normal_order_redex;
+use super::{
+    collect_redex_sites, collect_redexes, reduce_redex, DupPtrExt, Redex, Rule, SupPtrExt,
+    TermGraph,
+};
+use crate::syntax::Label;
+
+/// The kind of rewrite a redex will perform.
+///
+/// This is a finer classification than [`Rule`]: `DupSup` interactions are
+/// split into annihilations (`DupSupSame`, where the labels match) and
+/// commutations (`DupSupDiff`, where they differ), and erasures by the type
+/// of the erased node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleKind {
+    AppLam,
+    AppSup,
+    DupLam,
+    DupSupSame,
+    DupSupDiff,
+    Ref,
+    OpNum,
+    OpSup,
+    DupNum,
+    EraLam,
+    EraApp,
+    EraSup,
+    EraDup,
+    EraNum,
+    EraOp2,
+}
+
+impl RuleKind {
+    /// All rule kinds, in declaration order.
+    pub const ALL: [RuleKind; 15] = [
+        RuleKind::AppLam,
+        RuleKind::AppSup,
+        RuleKind::DupLam,
+        RuleKind::DupSupSame,
+        RuleKind::DupSupDiff,
+        RuleKind::Ref,
+        RuleKind::OpNum,
+        RuleKind::OpSup,
+        RuleKind::DupNum,
+        RuleKind::EraLam,
+        RuleKind::EraApp,
+        RuleKind::EraSup,
+        RuleKind::EraDup,
+        RuleKind::EraNum,
+        RuleKind::EraOp2,
+    ];
+}
+
+impl From<RuleKind> for Rule {
+    fn from(kind: RuleKind) -> Self {
+        match kind {
+            RuleKind::AppLam => Rule::AppLam,
+            RuleKind::AppSup => Rule::AppSup,
+            RuleKind::DupLam => Rule::DupLam,
+            RuleKind::DupSupSame | RuleKind::DupSupDiff => Rule::DupSup,
+            RuleKind::Ref => Rule::Ref,
+            RuleKind::OpNum => Rule::OpNum,
+            RuleKind::OpSup => Rule::OpSup,
+            RuleKind::DupNum => Rule::DupNum,
+            RuleKind::EraLam
+            | RuleKind::EraApp
+            | RuleKind::EraSup
+            | RuleKind::EraDup
+            | RuleKind::EraNum
+            | RuleKind::EraOp2 => Rule::Era,
+        }
+    }
+}
+
+impl Redex {
+    /// Returns the kind of rewrite this redex will perform.
+    pub(super) unsafe fn kind(self) -> RuleKind {
+        unsafe {
+            match self {
+                Redex::AppLam { .. } => RuleKind::AppLam,
+                Redex::AppSup { .. } => RuleKind::AppSup,
+                Redex::DupLam { .. } => RuleKind::DupLam,
+                Redex::DupSup { dup_ptr, sup_ptr } => {
+                    if dup_ptr.dup().l().read() == sup_ptr.sup().l().read() {
+                        RuleKind::DupSupSame
+                    } else {
+                        RuleKind::DupSupDiff
+                    }
+                }
+                Redex::Ref { .. } => RuleKind::Ref,
+                Redex::OpNum { .. } => RuleKind::OpNum,
+                Redex::OpSup { .. } => RuleKind::OpSup,
+                Redex::DupNum { .. } => RuleKind::DupNum,
+            }
+        }
+    }
+}
+
+/// What a heuristic strategy knows about a redex, as returned by
+/// [`TermGraph::redex_sites`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RedexSite {
+    /// The kind of rewrite the redex will perform.
+    pub kind: RuleKind,
+    /// The number of nodes above the application or dup of the redex, on the
+    /// path by which the traversal of [`Strategy::Deterministic`] reached it.
+    pub depth: usize,
+    /// The number of lambdas on that path.
+    pub binders: usize,
+    /// The label of the dup, for `DupLam` and `DupSup` redexes.
+    pub dup_label: Option<Label>,
+    /// The label of the superposition, for `AppSup` and `DupSup` redexes.
+    pub sup_label: Option<Label>,
+}
+
+/// A built-in rule for choosing the redex to reduce in each step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// The first redex found by a depth-first, preorder traversal of the
+    /// roots, in order, among those with the highest priority in the
+    /// [`StrategyConfig`].
+    ///
+    /// The traversal visits a node before its children, and the children
+    /// last to first: the body of a lambda, then the argument and then the
+    /// function of an application, the second and then the first branch of a
+    /// superposition, and the expression of a dup, which is only visited
+    /// through the first of its variables to be reached. An application or a
+    /// dup is a redex when its function or expression is a lambda or a
+    /// superposition. Since the order only depends on the structure of the
+    /// graph, two graphs built from the same term are always reduced the same
+    /// way. This is the strategy of [`TermGraph::naive_reduce_step`].
+    #[default]
+    Deterministic,
+    /// The leftmost-outermost redex, as reduced by
+    /// [`TermGraph::reduce_normal_order_step`]. Ignores the priorities of the
+    /// [`StrategyConfig`].
+    NormalOrder,
+}
+
+impl TermGraph {
+    /// Reduces the redex chosen by `strategy`, if there is one.
+    pub fn reduce_step_by(&mut self, strategy: Strategy, config: &StrategyConfig) -> Option<Rule> {
+        match strategy {
+            Strategy::Deterministic => self.naive_reduce_step_with(config),
+            Strategy::NormalOrder => self.reduce_normal_order_step(),
+        }
+    }
+}
+
+impl TermGraph {
+    /// Returns whether the graph has no redexes left, without reducing it.
+    ///
+    /// The search stops at the first redex found, so this is cheaper than
+    /// [`TermGraph::redex_count`] for graphs that are far from normal form.
+    pub fn is_normal_form(&self) -> bool {
+        unsafe { normal_order_redex(&self.root_slots()) }.is_none()
+    }
+
+    /// Returns the number of redexes of the graph, without reducing it.
+    pub fn redex_count(&self) -> usize {
+        unsafe { collect_redexes(&self.root_slots()) }.len()
+    }
+
+    /// Returns a description of every redex of the graph, in the order of
+    /// [`Strategy::Deterministic`], ignoring priorities.
+    pub fn redex_sites(&self) -> Vec<RedexSite> {
+        unsafe { collect_redex_sites(&self.root_slots()) }
+            .into_iter()
+            .map(|(_, site)| site)
+            .collect()
+    }
+
+    /// Reduces the redex chosen by `choose`, if it chooses one.
+    ///
+    /// `choose` is given the same sites as [`TermGraph::redex_sites`], and
+    /// returns the index of the one to reduce, so that a heuristic strategy
+    /// (such as "prefer the shallowest annihilation") can be written without
+    /// walking the graph itself. It is not called if there are no redexes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the returned index is out of range.
+    pub fn reduce_step_chosen_by(
+        &mut self,
+        choose: impl FnOnce(&[RedexSite]) -> Option<usize>,
+    ) -> Option<Rule> {
+        let (redexes, sites): (Vec<Redex>, Vec<RedexSite>) =
+            unsafe { collect_redex_sites(&self.root_slots()) }
+                .into_iter()
+                .unzip();
+        if redexes.is_empty() {
+            return None;
+        }
+        let redex = redexes[choose(&sites)?];
+        unsafe { reduce_redex(&mut self.1, redex) };
+        Some(redex.into())
+    }
+}
+
+/// Configuration shared by the built-in reduction strategies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrategyConfig {
+    /// Rule kinds in decreasing order of priority.
+    ///
+    /// When choosing the next redex, a strategy only considers redexes whose
+    /// kind comes earliest in this list. Kinds that are not listed share the
+    /// lowest priority. An empty list gives every redex the same priority.
+    pub priorities: Vec<RuleKind>,
+}
+
+impl StrategyConfig {
+    /// Returns a config that always prefers annihilations (`DupSupSame` and
+    /// `AppLam`) over commutations, which tends to keep the graph small.
+    pub fn prefer_annihilations() -> Self {
+        StrategyConfig {
+            priorities: vec![RuleKind::DupSupSame, RuleKind::AppLam],
+        }
+    }
+
+    fn rank(&self, kind: RuleKind) -> usize {
+        self.priorities
+            .iter()
+            .position(|k| *k == kind)
+            .unwrap_or(self.priorities.len())
+    }
+
+    /// Returns the index of the first of `sites` with the highest priority,
+    /// which is the redex [`Strategy::Deterministic`] reduces when given the
+    /// sites of [`TermGraph::redex_sites`].
+    pub(super) fn choose(&self, sites: &[RedexSite]) -> Option<usize> {
+        let ranks = sites.iter().map(|site| self.rank(site.kind));
+        let best = ranks.clone().min()?;
+        ranks.into_iter().position(|rank| rank == best)
+    }
+
+    /// Keeps only the redexes with the highest priority, preserving their order.
+    pub(super) unsafe fn prioritize(&self, redexes: Vec<Redex>) -> Vec<Redex> {
+        unsafe {
+            if self.priorities.is_empty() {
+                return redexes;
+            }
+            let ranks: Vec<usize> = redexes.iter().map(|r| self.rank(r.kind())).collect();
+            let best = match ranks.iter().min() {
+                Some(best) => *best,
+                None => return redexes,
+            };
+            redexes
+                .into_iter()
+                .zip(ranks)
+                .filter(|(_, rank)| *rank == best)
+                .map(|(redex, _)| redex)
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+    use crate::vm::TermGraph;
+
+    #[test]
+    fn test_rule_kind_into_rule() {
+        assert_eq!(Rule::from(RuleKind::DupSupSame), Rule::DupSup);
+        assert_eq!(Rule::from(RuleKind::DupSupDiff), Rule::DupSup);
+        assert_eq!(Rule::from(RuleKind::AppSup), Rule::AppSup);
+    }
+
+    #[test]
+    fn test_priorities() {
+        // Both an AppLam and a DupSup redex are available.
+        let src = "((λx x) dup #0{a b} = #0{y z}; (a b))";
+        let term: Term = src.parse().unwrap();
+
+        let mut term_graph = TermGraph::from(&term);
+        let config = StrategyConfig {
+            priorities: vec![RuleKind::DupSupSame],
+        };
+        assert_eq!(
+            term_graph.naive_reduce_step_with(&config),
+            Some(Rule::DupSup)
+        );
+        assert_eq!(
+            term_graph.naive_reduce_step_with(&config),
+            Some(Rule::AppLam)
+        );
+        assert_eq!(term_graph.naive_reduce_step_with(&config), None);
+
+        let mut term_graph = TermGraph::from(&term);
+        let config = StrategyConfig {
+            priorities: vec![RuleKind::AppLam],
+        };
+        assert_eq!(
+            term_graph.naive_reduce_step_with(&config),
+            Some(Rule::AppLam)
+        );
+        assert_eq!(
+            term_graph.naive_reduce_step_with(&config),
+            Some(Rule::DupSup)
+        );
+    }
+
+    #[test]
+    fn test_deterministic_order() {
+        // The argument is visited before the function, so the first step
+        // reduces `((λx x) λy y)` rather than the head redex.
+        let src = "((λf (f λa a) λb b) ((λx x) λy y))";
+        let term: Term = src.parse().unwrap();
+        let config = StrategyConfig::default();
+        let mut derivations = vec![];
+        for _ in 0..2 {
+            // Allocating in between moves the nodes of the second graph.
+            let _padding = TermGraph::from(&term);
+            let mut term_graph = TermGraph::from(&term);
+            let mut sizes = vec![];
+            while term_graph
+                .reduce_step_by(Strategy::Deterministic, &config)
+                .is_some()
+            {
+                sizes.push(Term::from(&term_graph).size());
+            }
+            derivations.push(sizes);
+        }
+        assert_eq!(derivations[0], [10, 7, 4, 2]);
+        assert_eq!(derivations[0], derivations[1]);
+
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(
+            term_graph.reduce_step_by(Strategy::NormalOrder, &config),
+            Some(Rule::AppLam)
+        );
+        assert_eq!(Term::from(&term_graph).size(), 9);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_random_order_with_priorities() {
+        let term: Term = "λx λy dup #0{a b} = #1{x y}; (a b)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        term_graph.naive_random_order_reduce_with(&StrategyConfig::prefer_annihilations());
+        assert_eq!(
+            format!("{}", Term::from(&term_graph)),
+            "(λv1 (λv4 #1{(dup #0{v2 v3} = v1; (v2 v3)) (dup #0{v5 v6} = v4; (v5 v6))}))"
+        );
+    }
+
+    #[test]
+    fn test_redex_sites() {
+        let term: Term = "λx dup #0{a b} = #1{x λy y}; ((λz z) (λw (a w) b))"
+            .parse()
+            .unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let sites = term_graph.redex_sites();
+        assert_eq!(
+            sites,
+            [
+                RedexSite {
+                    kind: RuleKind::AppLam,
+                    depth: 1,
+                    binders: 1,
+                    dup_label: None,
+                    sup_label: None,
+                },
+                RedexSite {
+                    kind: RuleKind::AppLam,
+                    depth: 2,
+                    binders: 1,
+                    dup_label: None,
+                    sup_label: None,
+                },
+                RedexSite {
+                    kind: RuleKind::DupSupDiff,
+                    depth: 3,
+                    binders: 1,
+                    dup_label: Some(0),
+                    sup_label: Some(1),
+                },
+            ]
+        );
+        // The dup is reached through `b`, the argument of `(λw (a w))`.
+        // Prefer the deepest redex.
+        let deepest = |sites: &[RedexSite]| (0..sites.len()).max_by_key(|i| sites[*i].depth);
+        assert_eq!(
+            term_graph.reduce_step_chosen_by(deepest),
+            Some(Rule::DupSup)
+        );
+        assert_eq!(term_graph.reduce_step_chosen_by(|_| None), None);
+        // Commuting the dup leaves an `AppSup` and a `DupLam`.
+        assert_eq!(term_graph.redex_sites().len(), 4);
+    }
+
+    #[test]
+    fn test_is_normal_form() {
+        let term: Term = "dup #0{a b} = λx x; ((a b) ((λy y) 1))".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let before = format!("{:?}", term_graph);
+        assert!(!term_graph.is_normal_form());
+        assert_eq!(term_graph.redex_count(), 2);
+        // Neither query reduces the graph.
+        assert_eq!(format!("{:?}", term_graph), before);
+        while term_graph.naive_reduce_step().is_some() {}
+        assert!(term_graph.is_normal_form());
+        assert_eq!(term_graph.redex_count(), 0);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "1");
+    }
+
+    #[test]
+    fn test_redex_sites_escaped_var() {
+        use crate::vm::{ChildRecord, NodeRecord, RecordTag, UseRecord};
+
+        // `#0{(λx (let _ = 1; 2)) x}`, where the second branch, visited
+        // first, uses the variable of the lambda in the first.
+        let record = |id, tag, label, children, uses| NodeRecord {
+            id,
+            tag,
+            label,
+            children,
+            uses,
+        };
+        let records = [
+            record(
+                0,
+                RecordTag::Sup,
+                Some(0),
+                vec![ChildRecord::Node(1), ChildRecord::LamVar(1)],
+                vec![],
+            ),
+            record(
+                1,
+                RecordTag::Lam,
+                None,
+                vec![ChildRecord::Node(2)],
+                vec![UseRecord::Node(0)],
+            ),
+            record(
+                2,
+                RecordTag::App,
+                None,
+                vec![ChildRecord::Node(3), ChildRecord::Node(5)],
+                vec![],
+            ),
+            record(
+                3,
+                RecordTag::Lam,
+                None,
+                vec![ChildRecord::Node(4)],
+                vec![UseRecord::Unused],
+            ),
+            record(4, RecordTag::Num(2), None, vec![], vec![]),
+            record(5, RecordTag::Num(1), None, vec![], vec![]),
+        ];
+        let mut term_graph = TermGraph::from_dump(&records).unwrap();
+        assert_eq!(term_graph.redex_sites().len(), 1);
+        assert_eq!(term_graph.naive_reduce_step(), Some(Rule::AppLam));
+        assert_eq!(format!("{}", Term::from(&term_graph)), "#0{(λv1 2) v1}");
+    }
+}
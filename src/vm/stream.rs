@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use super::{
+    AppPtrExt, Dup, DupPtrExt, LamPtrExt, NodeIter, Op2PtrExt, SupPtrExt, Tag, Tagged, TermGraph,
+    VarNames,
+};
+use crate::intern::{IStr, InternStatic};
+use crate::syntax::DisplayLimits;
+
+/// Where the read-back term of a graph binds its names and places its dups,
+/// as computed by the same traversal as `Term::from`, but without building
+/// the term.
+struct Layout {
+    /// The name of each bound variable, keyed by the tagged pointer its use
+    /// holds (`LamBoundVar`, `DupABoundVar` or `DupBBoundVar`).
+    vars: HashMap<Tagged, IStr>,
+    /// The name of each binder whose variable is used within its scope, keyed
+    /// like `vars`.
+    binders: HashMap<Tagged, IStr>,
+    /// The name of each unbound variable, keyed by the slot that holds it.
+    unbound: HashMap<*mut Tagged, IStr>,
+    /// The dups placed around the subterm in each slot, innermost first.
+    wraps: HashMap<*mut Tagged, Vec<*mut Dup>>,
+}
+
+impl Layout {
+    /// Computes the layout of the term stored in `root_slot`.
+    ///
+    /// NOTE: This must visit the graph in exactly the order of `read_back`,
+    ///       so that the names and dups come out the same.
+    unsafe fn new(root_slot: *mut Tagged) -> Self {
+        unsafe {
+            enum Task {
+                Visit(*mut Tagged),
+                BuildVar(*mut Tagged),
+                /// Replaces the subterms on top of the stack by the node in
+                /// the slot, which has this many children.
+                Build(*mut Tagged, usize),
+                BuildDup(*mut Dup),
+            }
+            let root = root_slot.read();
+            let local_slots: HashSet<*mut Tagged> = NodeIter::new(root)
+                .flat_map(|ptr| ptr.child_slots())
+                .chain([root_slot])
+                .collect();
+            let is_unused = |binder: *mut Tagged| {
+                let x = binder.read();
+                x.tag() != Tag::VarUsePtr || !local_slots.contains(&x.var_use())
+            };
+            let mut var_names = VarNames::new(root);
+            let mut layout = Layout {
+                vars: HashMap::new(),
+                binders: HashMap::new(),
+                unbound: HashMap::new(),
+                wraps: HashMap::new(),
+            };
+            // The names of the variables visited but not yet bound. As in
+            // `read_back`, a binder built before its variable is visited is
+            // unused.
+            let mut names: HashMap<Tagged, IStr> = HashMap::new();
+            fn bind(names: &mut HashMap<Tagged, IStr>, layout: &mut Layout, binder: Tagged) {
+                if let Some(name) = names.remove(&binder) {
+                    layout.binders.insert(binder, name);
+                }
+            }
+            // The slots of the subterms built so far, and the double-use dups
+            // with a variable in each, as in `read_back`.
+            let mut built: Vec<*mut Tagged> = vec![];
+            let mut double_use_dups_var_tracker: Vec<HashMap<*mut Dup, usize>> = vec![];
+            let mut single_use_dups: HashSet<*mut Dup> = HashSet::new();
+            let merge_top_two = |tracker: &mut Vec<HashMap<*mut Dup, usize>>| {
+                let tmp = tracker.pop().unwrap();
+                let map = tracker.last_mut().unwrap();
+                for (dup, count) in tmp {
+                    *map.entry(dup).or_insert(0) += count;
+                }
+            };
+
+            let mut tasks = vec![Task::Visit(root_slot)];
+            while let Some(task) = tasks.pop() {
+                match task {
+                    Task::Visit(slot) => {
+                        let ptr = slot.read();
+                        match ptr.tag() {
+                            Tag::UnboundVar | Tag::LamBoundVar => {
+                                tasks.push(Task::BuildVar(slot));
+                            }
+                            Tag::Ref | Tag::NumPtr => {
+                                built.push(slot);
+                                double_use_dups_var_tracker.push(HashMap::new());
+                            }
+                            Tag::DupABoundVar => {
+                                tasks.push(Task::BuildVar(slot));
+                                if is_unused(ptr.dup().b()) {
+                                    single_use_dups.insert(ptr.dup());
+                                }
+                            }
+                            Tag::DupBBoundVar => {
+                                tasks.push(Task::BuildVar(slot));
+                                if is_unused(ptr.dup().a()) {
+                                    single_use_dups.insert(ptr.dup());
+                                }
+                            }
+                            Tag::LamPtr => {
+                                tasks.push(Task::Build(slot, 1));
+                                tasks.push(Task::Visit(ptr.lam().e()));
+                            }
+                            Tag::AppPtr => {
+                                let e1 = ptr.app().e1().read();
+                                tasks.push(Task::Build(slot, 2));
+                                if e1.tag() == Tag::LamPtr {
+                                    tasks.push(Task::Visit(ptr.app().e2()));
+                                    tasks.push(Task::Visit(e1.lam().e()));
+                                } else {
+                                    tasks.push(Task::Visit(ptr.app().e1()));
+                                    tasks.push(Task::Visit(ptr.app().e2()));
+                                }
+                            }
+                            Tag::SupPtr => {
+                                tasks.push(Task::Build(slot, 2));
+                                tasks.push(Task::Visit(ptr.sup().e1()));
+                                tasks.push(Task::Visit(ptr.sup().e2()));
+                            }
+                            Tag::Op2Ptr => {
+                                tasks.push(Task::Build(slot, 2));
+                                tasks.push(Task::Visit(ptr.op2().e1()));
+                                tasks.push(Task::Visit(ptr.op2().e2()));
+                            }
+                            _ => unreachable!("{:?}", ptr.tag()),
+                        }
+                    }
+                    Task::BuildVar(slot) => {
+                        let ptr = slot.read();
+                        let v = var_names.next(ptr);
+                        built.push(slot);
+                        double_use_dups_var_tracker.push(HashMap::new());
+                        if ptr.tag() == Tag::UnboundVar {
+                            layout.unbound.insert(slot, v);
+                            continue;
+                        }
+                        layout.vars.insert(ptr, v);
+                        names.insert(ptr, v);
+                        if ptr.tag() == Tag::LamBoundVar {
+                            continue;
+                        }
+                        if single_use_dups.remove(&ptr.dup()) {
+                            tasks.push(Task::BuildDup(ptr.dup()));
+                            tasks.push(Task::Visit(ptr.dup().e()));
+                        } else {
+                            double_use_dups_var_tracker
+                                .last_mut()
+                                .unwrap()
+                                .insert(ptr.dup(), 1);
+                        }
+                    }
+                    Task::Build(slot, children) => {
+                        let ptr = slot.read();
+                        if ptr.tag() == Tag::LamPtr {
+                            bind(&mut names, &mut layout, ptr.lam_bound_var());
+                        } else if ptr.tag() == Tag::AppPtr {
+                            let e1 = ptr.app().e1().read();
+                            if e1.tag() == Tag::LamPtr {
+                                bind(&mut names, &mut layout, e1.lam_bound_var());
+                            }
+                        }
+                        built.truncate(built.len() - children);
+                        built.push(slot);
+                        if children == 2 {
+                            merge_top_two(&mut double_use_dups_var_tracker);
+                        }
+                    }
+                    Task::BuildDup(dup) => {
+                        bind(
+                            &mut names,
+                            &mut layout,
+                            Tagged::new(dup as *mut (), Tag::DupABoundVar),
+                        );
+                        bind(
+                            &mut names,
+                            &mut layout,
+                            Tagged::new(dup as *mut (), Tag::DupBBoundVar),
+                        );
+                        built.pop();
+                        let cont = *built.last().unwrap();
+                        layout.wraps.entry(cont).or_default().push(dup);
+                        merge_top_two(&mut double_use_dups_var_tracker);
+                    }
+                }
+                if let Some(top) = double_use_dups_var_tracker.last_mut() {
+                    let dups_to_build: Vec<*mut Dup> = top
+                        .iter()
+                        .filter_map(|(dup, count)| if *count == 2 { Some(*dup) } else { None })
+                        .collect();
+                    for dup in dups_to_build {
+                        top.remove(&dup);
+                        tasks.push(Task::BuildDup(dup));
+                        tasks.push(Task::Visit(dup.e()));
+                    }
+                }
+            }
+            layout
+        }
+    }
+
+    fn wraps(&self, slot: *mut Tagged) -> &[*mut Dup] {
+        self.wraps.get(&slot).map_or(&[], Vec::as_slice)
+    }
+
+    fn name(&self, binder: Tagged) -> IStr {
+        self.binders
+            .get(&binder)
+            .copied()
+            .unwrap_or_else(|| "_".intern_static())
+    }
+
+    /// Returns the children of the subterm in `slot` without its outermost
+    /// `wrapped` dups, as pairs of a slot and a number of dups to skip.
+    unsafe fn children(&self, slot: *mut Tagged, wrapped: usize) -> Vec<(*mut Tagged, usize)> {
+        unsafe {
+            let wraps = self.wraps(slot);
+            if wrapped < wraps.len() {
+                let dup = wraps[wraps.len() - 1 - wrapped];
+                return vec![(dup.e(), 0), (slot, wrapped + 1)];
+            }
+            let ptr = slot.read();
+            match ptr.tag() {
+                Tag::LamPtr => vec![(ptr.lam().e(), 0)],
+                Tag::AppPtr if ptr.app().e1().read().tag() == Tag::LamPtr => {
+                    vec![(ptr.app().e2(), 0), (ptr.app().e1().read().lam().e(), 0)]
+                }
+                Tag::AppPtr => vec![(ptr.app().e1(), 0), (ptr.app().e2(), 0)],
+                Tag::SupPtr => vec![(ptr.sup().e1(), 0), (ptr.sup().e2(), 0)],
+                Tag::Op2Ptr => vec![(ptr.op2().e1(), 0), (ptr.op2().e2(), 0)],
+                _ => vec![],
+            }
+        }
+    }
+
+    /// Returns the number of nodes of the read-back subterm, as counted by
+    /// `Term::size`.
+    unsafe fn size(&self, slot: *mut Tagged, wrapped: usize) -> usize {
+        unsafe {
+            let mut size = 0;
+            let mut stack = vec![(slot, wrapped)];
+            while let Some((slot, wrapped)) = stack.pop() {
+                size += 1;
+                stack.extend(self.children(slot, wrapped));
+            }
+            size
+        }
+    }
+}
+
+impl TermGraph {
+    /// Writes the term in the graph to `out`, as `Term::from` followed by
+    /// [`Term::limited`](crate::syntax::Term::limited) would, but without
+    /// building the term or its string.
+    ///
+    /// The names and the placement of dups are computed first, which takes
+    /// memory in proportion to the number of variables and dups, and then the
+    /// term is written as the graph is walked, so that a normal form much
+    /// larger than the available memory for a `Term` can still be saved.
+    pub fn write_term(&self, out: &mut impl io::Write, limits: DisplayLimits) -> io::Result<()> {
+        enum Item {
+            /// A subterm, without its outermost dups, with its depth.
+            Term(*mut Tagged, usize, usize),
+            Text(&'static str),
+        }
+
+        unsafe {
+            let layout = Layout::new(self.0);
+            let mut stack = vec![Item::Term(self.0, 0, 1)];
+            let mut printed = 0;
+            while let Some(item) = stack.pop() {
+                let (slot, wrapped, depth) = match item {
+                    Item::Term(slot, wrapped, depth) => (slot, wrapped, depth),
+                    Item::Text(text) => {
+                        out.write_all(text.as_bytes())?;
+                        continue;
+                    }
+                };
+                if limits.depth.is_some_and(|d| depth > d)
+                    || limits.size.is_some_and(|n| printed >= n)
+                {
+                    write!(out, "…{}", layout.size(slot, wrapped))?;
+                    continue;
+                }
+                printed += 1;
+                let depth = depth + 1;
+                let wraps = layout.wraps(slot);
+                if wrapped < wraps.len() {
+                    let dup = wraps[wraps.len() - 1 - wrapped];
+                    write!(
+                        out,
+                        "(dup #{}{{{} {}}} = ",
+                        dup.l().read(),
+                        layout.name(Tagged::new(dup as *mut (), Tag::DupABoundVar)),
+                        layout.name(Tagged::new(dup as *mut (), Tag::DupBBoundVar)),
+                    )?;
+                    stack.extend([
+                        Item::Text(")"),
+                        Item::Term(slot, wrapped + 1, depth),
+                        Item::Text("; "),
+                        Item::Term(dup.e(), 0, depth),
+                    ]);
+                    continue;
+                }
+                let ptr = slot.read();
+                match ptr.tag() {
+                    Tag::UnboundVar => write!(out, "{}", layout.unbound[&slot])?,
+                    Tag::Ref => write!(out, "{}", ptr.def().name)?,
+                    Tag::NumPtr => write!(out, "{}", ptr.num().read().n)?,
+                    Tag::LamBoundVar | Tag::DupABoundVar | Tag::DupBBoundVar => {
+                        write!(out, "{}", layout.vars[&ptr])?
+                    }
+                    Tag::LamPtr => {
+                        write!(out, "(λ{} ", layout.name(ptr.lam_bound_var()))?;
+                        stack.extend([Item::Text(")"), Item::Term(ptr.lam().e(), 0, depth)]);
+                    }
+                    Tag::AppPtr => {
+                        let e1 = ptr.app().e1().read();
+                        if e1.tag() == Tag::LamPtr {
+                            // ((λx e1) e2) => (let x = e2; e1)
+                            write!(out, "(let {} = ", layout.name(e1.lam_bound_var()))?;
+                            stack.extend([
+                                Item::Text(")"),
+                                Item::Term(e1.lam().e(), 0, depth),
+                                Item::Text("; "),
+                                Item::Term(ptr.app().e2(), 0, depth),
+                            ]);
+                        } else {
+                            out.write_all(b"(")?;
+                            stack.extend([
+                                Item::Text(")"),
+                                Item::Term(ptr.app().e2(), 0, depth),
+                                Item::Text(" "),
+                                Item::Term(ptr.app().e1(), 0, depth),
+                            ]);
+                        }
+                    }
+                    Tag::SupPtr => {
+                        write!(out, "#{}{{", ptr.sup().l().read())?;
+                        stack.extend([
+                            Item::Text("}"),
+                            Item::Term(ptr.sup().e2(), 0, depth),
+                            Item::Text(" "),
+                            Item::Term(ptr.sup().e1(), 0, depth),
+                        ]);
+                    }
+                    Tag::Op2Ptr => {
+                        write!(out, "({} ", ptr.op2().op().read())?;
+                        stack.extend([
+                            Item::Text(")"),
+                            Item::Term(ptr.op2().e2(), 0, depth),
+                            Item::Text(" "),
+                            Item::Term(ptr.op2().e1(), 0, depth),
+                        ]);
+                    }
+                    _ => unreachable!("{:?}", ptr.tag()),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    fn streamed(term_graph: &TermGraph, limits: DisplayLimits) -> String {
+        let mut out = vec![];
+        term_graph.write_term(&mut out, limits).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_term_matches_read_back() {
+        let srcs = [
+            "x",
+            "λx λy (y x)",
+            "((λx x) (λy y) λz z)",
+            "dup #0{a b} = λx x; (a b)",
+            "λf dup #0{f1 f2} = f; λx (f1 (f2 x))",
+            "λx dup #0{a _} = x; #1{a (y z)}",
+            "(λf dup #1{f1 f2} = f; λx (f1 (f2 x)) λf dup #2{f1 f2} = f; λx (f1 (f2 x)))",
+            "dup #0{a b} = 7; (+ (* a #1{1 x}) b)",
+        ];
+        for src in srcs {
+            let term: Term = src.parse().unwrap();
+            let mut term_graph = TermGraph::from(&term);
+            loop {
+                let expected = Term::from(&term_graph);
+                let limits = DisplayLimits::default();
+                assert_eq!(
+                    streamed(&term_graph, limits),
+                    expected.to_string(),
+                    "{}",
+                    src
+                );
+                let limits = DisplayLimits {
+                    depth: Some(3),
+                    size: Some(5),
+                };
+                assert_eq!(
+                    streamed(&term_graph, limits),
+                    expected.limited(limits).to_string(),
+                    "{}",
+                    src
+                );
+                if term_graph.naive_reduce_step().is_none() {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    collect_redexes, reduce_redex, DupPtrExt, LamPtrExt, NodeIter, Op2PtrExt, Redex, Tag, Tagged,
+    TermGraph,
+};
+
+/// The wiring of a graph, used to find the nodes a rewrite can touch.
+struct Wiring {
+    /// The node holding each child slot.
+    owners: HashMap<*mut Tagged, *mut ()>,
+    /// The node holding the slot that points to each node other than a `Dup`.
+    parents: HashMap<*mut (), *mut ()>,
+}
+
+impl Wiring {
+    unsafe fn new(graph: &TermGraph) -> Self {
+        unsafe {
+            let mut owners = HashMap::new();
+            let mut parents = HashMap::new();
+            for node in graph.node_iter() {
+                for slot in node.child_slots() {
+                    owners.insert(slot, node.ptr());
+                    let child = slot.read();
+                    if matches!(
+                        child.tag(),
+                        Tag::LamPtr | Tag::AppPtr | Tag::SupPtr | Tag::NumPtr | Tag::Op2Ptr
+                    ) {
+                        parents.insert(child.ptr(), node.ptr());
+                    }
+                }
+            }
+            Wiring { owners, parents }
+        }
+    }
+
+    /// Adds the nodes connected to `node` by a wire to `out`.
+    unsafe fn add_neighbors(&self, node: Tagged, out: &mut HashSet<*mut ()>) {
+        unsafe {
+            out.extend(self.parents.get(&node.ptr()));
+            for slot in node.child_slots() {
+                let child = slot.read();
+                match child.tag() {
+                    Tag::LamPtr
+                    | Tag::AppPtr
+                    | Tag::SupPtr
+                    | Tag::NumPtr
+                    | Tag::Op2Ptr
+                    | Tag::LamBoundVar
+                    | Tag::DupABoundVar
+                    | Tag::DupBBoundVar => {
+                        out.insert(child.ptr());
+                    }
+                    _ => {}
+                }
+            }
+            let binders = match node.tag() {
+                Tag::LamPtr => vec![node.lam().x()],
+                Tag::DupPtr => vec![node.dup().a(), node.dup().b()],
+                _ => vec![],
+            };
+            for binder in binders {
+                let x = binder.read();
+                if x.tag() == Tag::VarUsePtr {
+                    out.extend(self.owners.get(&x.var_use()));
+                }
+            }
+        }
+    }
+
+    /// Returns the nodes that reducing `redex` may free (the nodes it consumes
+    /// and anything it may erase), and the other nodes whose slots it may
+    /// write (every node wired to one of those).
+    unsafe fn footprint(&self, redex: Redex) -> Footprint {
+        unsafe {
+            let (consumed, erasing) = match redex {
+                Redex::AppLam {
+                    app_ptr, lam_ptr, ..
+                } => (
+                    [app_ptr, lam_ptr],
+                    lam_ptr.lam().x().read().tag() == Tag::UnusedVar,
+                ),
+                Redex::AppSup {
+                    app_ptr, sup_ptr, ..
+                } => ([app_ptr, sup_ptr], false),
+                Redex::DupLam { dup_ptr, lam_ptr } => (
+                    [Tagged::new(dup_ptr.ptr(), Tag::DupPtr), lam_ptr],
+                    has_unused_var(dup_ptr),
+                ),
+                Redex::DupSup { dup_ptr, sup_ptr } => (
+                    [Tagged::new(dup_ptr.ptr(), Tag::DupPtr), sup_ptr],
+                    has_unused_var(dup_ptr),
+                ),
+                Redex::OpNum { op2_ptr, .. } => ([op2_ptr, op2_ptr.op2().e2().read()], false),
+                Redex::OpSup {
+                    op2_ptr, sup_ptr, ..
+                } => ([op2_ptr, sup_ptr], false),
+                // NOTE: The number is moved rather than freed, but it is
+                //       counted as consumed so that no other rewrite reads it.
+                Redex::DupNum { dup_ptr, num_ptr } => {
+                    ([Tagged::new(dup_ptr.ptr(), Tag::DupPtr), num_ptr], false)
+                }
+                Redex::Ref { ptr_ptr } => {
+                    // Unfolding a reference frees nothing, and only writes the
+                    // slot holding it.
+                    return Footprint {
+                        freed: HashSet::new(),
+                        touched: self.owners.get(&ptr_ptr).copied().into_iter().collect(),
+                    };
+                }
+            };
+            let mut region: HashSet<Tagged> = consumed.into_iter().collect();
+            if erasing {
+                let children = consumed.iter().flat_map(|node| node.child_slots());
+                region.extend(NodeIter::from_roots(children.map(|slot| slot.read())));
+            }
+            let freed: HashSet<*mut ()> = region.iter().map(|node| node.ptr()).collect();
+            let mut touched = HashSet::new();
+            for node in region {
+                self.add_neighbors(node, &mut touched);
+            }
+            touched.retain(|node| !freed.contains(node));
+            Footprint { freed, touched }
+        }
+    }
+}
+
+/// The nodes a rewrite may free, and the nodes it may write to.
+#[derive(Default)]
+struct Footprint {
+    freed: HashSet<*mut ()>,
+    touched: HashSet<*mut ()>,
+}
+
+impl Footprint {
+    /// Returns whether the rewrites of `self` and `other` are independent:
+    /// neither frees a node that the other frees or writes to.
+    fn is_independent(&self, other: &Footprint) -> bool {
+        self.freed.is_disjoint(&other.freed)
+            && self.freed.is_disjoint(&other.touched)
+            && self.touched.is_disjoint(&other.freed)
+    }
+
+    fn extend(&mut self, other: Footprint) {
+        self.freed.extend(other.freed);
+        self.touched.extend(other.touched);
+    }
+}
+
+unsafe fn has_unused_var(dup_ptr: Tagged) -> bool {
+    unsafe {
+        dup_ptr.dup().a().read().tag() == Tag::UnusedVar
+            || dup_ptr.dup().b().read().tag() == Tag::UnusedVar
+    }
+}
+
+impl TermGraph {
+    /// Reduces a maximal set of non-overlapping redexes, returning how many
+    /// were reduced (0 once the graph is in normal form).
+    ///
+    /// This models one parallel step of an interaction net: the redexes are
+    /// chosen greedily, in the order the naive strategies see them, skipping
+    /// any redex that may free a node that an already chosen one frees or
+    /// writes to, or the other way around. Since the chosen redexes cannot
+    /// affect each other, reducing them one after the other gives the same
+    /// graph as reducing them all at once.
+    pub fn superstep(&mut self) -> usize {
+        unsafe {
+            let chosen = self.independent_redexes();
+            for redex in &chosen {
+                reduce_redex(&mut self.1, *redex);
+            }
+            chosen.len()
+        }
+    }
+
+    /// Returns the redexes of the next [`TermGraph::superstep`].
+    pub(super) unsafe fn independent_redexes(&self) -> Vec<Redex> {
+        unsafe {
+            let redexes = collect_redexes(&self.root_slots());
+            if redexes.is_empty() {
+                return vec![];
+            }
+            let wiring = Wiring::new(self);
+            let mut claimed = Footprint::default();
+            let mut chosen = vec![];
+            for redex in redexes {
+                let footprint = wiring.footprint(redex);
+                if footprint.is_independent(&claimed) {
+                    claimed.extend(footprint);
+                    chosen.push(redex);
+                }
+            }
+            chosen
+        }
+    }
+
+    /// Reduces the graph to normal form in supersteps, returning the number of
+    /// redexes reduced in each.
+    ///
+    /// The number of supersteps is the parallel time of the reduction, and
+    /// their sum is its total work.
+    pub fn reduce_in_supersteps(&mut self) -> Vec<usize> {
+        let mut widths = vec![];
+        loop {
+            match self.superstep() {
+                0 => return widths,
+                width => widths.push(width),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[test]
+    fn test_superstep() {
+        // The two applications are independent.
+        let term: Term = "λx #0{((λy y) x) ((λz z) w)}".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_in_supersteps(), vec![2]);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(λv1 #0{v1 w})");
+    }
+
+    #[test]
+    fn test_superstep_overlapping() {
+        // The outer application consumes the lambda of the inner one.
+        let term: Term = "((λf (f a)) λx x)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        assert_eq!(term_graph.reduce_in_supersteps(), vec![1, 1]);
+        assert_eq!(format!("{}", Term::from(&term_graph)), "a");
+    }
+
+    #[test]
+    fn test_superstep_same_normal_form() {
+        let src = "(λf λx dup #0{f1 f2} = f; (f1 (f2 x)) λy dup #1{y1 y2} = y; #2{y1 y2})";
+        let term: Term = src.parse().unwrap();
+        let mut sequential = TermGraph::from(&term);
+        let mut steps = 0;
+        while sequential.naive_reduce_step().is_some() {
+            steps += 1;
+        }
+        let mut parallel = TermGraph::from(&term);
+        let widths = parallel.reduce_in_supersteps();
+        assert!(widths.len() < steps);
+        assert_eq!(widths.iter().sum::<usize>(), steps);
+        assert_eq!(parallel.structural_hash(), sequential.structural_hash());
+    }
+}
@@ -0,0 +1,89 @@
+use std::io;
+
+use super::{StrategyConfig, TermGraph};
+use crate::syntax::{ColorChoice, Term};
+
+/// What a traced reduction prints after each step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceMode {
+    /// A dump of the graph's nodes, as printed by `Debug`.
+    Nodes,
+    /// The term read back from the graph, in surface syntax.
+    Terms,
+    /// Like [`TraceMode::Terms`], but with ANSI colors (see [`Term::colored`]).
+    ColoredTerms,
+}
+
+impl TermGraph {
+    /// Reduces the graph to normal form, like
+    /// [`TermGraph::naive_reduce_step_with`] repeated until no redexes remain,
+    /// writing the initial graph and the graph after every step to `out`.
+    ///
+    /// Each step is introduced by a line with its number and the rule that
+    /// was applied. Returns the number of steps taken.
+    pub fn naive_reduce_traced(
+        &mut self,
+        config: &StrategyConfig,
+        mode: TraceMode,
+        out: &mut impl io::Write,
+    ) -> io::Result<usize> {
+        writeln!(out, "step 0")?;
+        self.write_trace(mode, out)?;
+        let mut steps = 0;
+        while let Some(rule) = self.naive_reduce_step_with(config) {
+            steps += 1;
+            writeln!(out, "step {}: {:?}", steps, rule)?;
+            self.write_trace(mode, out)?;
+        }
+        Ok(steps)
+    }
+
+    fn write_trace(&self, mode: TraceMode, out: &mut impl io::Write) -> io::Result<()> {
+        match mode {
+            TraceMode::Nodes => write!(out, "{:?}", self),
+            TraceMode::Terms => writeln!(out, "{}", Term::from(self)),
+            TraceMode::ColoredTerms => {
+                writeln!(out, "{}", Term::from(self).colored(ColorChoice::Always))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_trace_terms() {
+        let term: Term = "((λx x) (λy y) λz z)".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut out = vec![];
+        let steps = term_graph
+            .naive_reduce_traced(&StrategyConfig::default(), TraceMode::Terms, &mut out)
+            .unwrap();
+        assert_eq!(steps, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "step 0\n\
+             ((let v2 = (λv1 v1); v2) (λv3 v3))\n\
+             step 1: AppLam\n\
+             (let v2 = (λv1 v1); v2)\n\
+             step 2: AppLam\n\
+             (λv1 v1)\n"
+        );
+    }
+
+    #[test]
+    fn test_trace_nodes() {
+        let term: Term = "λx x".parse().unwrap();
+        let mut term_graph = TermGraph::from(&term);
+        let mut out = vec![];
+        let steps = term_graph
+            .naive_reduce_traced(&StrategyConfig::default(), TraceMode::Nodes, &mut out)
+            .unwrap();
+        assert_eq!(steps, 0);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("step 0\n"));
+        assert!(out.contains("Lam"));
+    }
+}
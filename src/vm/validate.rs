@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use super::MAX_LABEL;
+use crate::error::GraphBuildError;
+use crate::intern::IStr;
+use crate::syntax::Term;
+
+/// Checks that a graph can be built for `term`: every bound variable is used
+/// at most once, the two variables of a dup are distinct, and the variables of
+/// a dup are not used in the expression they are bound to, and every label
+/// fits in a node.
+///
+/// The variable of a `let` is only bound in its body, so a use of the same
+/// name in its expression refers to an outer binder, as in `λx let x = x; x`.
+///
+/// Free variables and references may be used any number of times, since they
+/// either stay unbound or refer to a shared definition.
+pub(crate) fn validate(term: &Term) -> Result<(), GraphBuildError> {
+    enum Task<'t> {
+        Visit(&'t Term),
+        Bind(IStr),
+        Unbind(IStr),
+        /// Marks the end of the expression of a dup, after which its
+        /// variables may be used.
+        EnterCont(IStr, IStr),
+    }
+
+    // The number of uses of each binder in scope, and whether it may be used
+    // yet.
+    let mut binders: HashMap<IStr, Vec<(usize, bool)>> = HashMap::new();
+    let mut stack = vec![Task::Visit(term)];
+    while let Some(task) = stack.pop() {
+        match task {
+            Task::Visit(Term::Var(x)) => {
+                if let Some((uses, usable)) = binders.get_mut(x).and_then(|b| b.last_mut()) {
+                    if !*usable {
+                        return Err(GraphBuildError::UsedInOwnExpression(*x));
+                    }
+                    *uses += 1;
+                    if *uses > 1 {
+                        return Err(GraphBuildError::UsedTwice(*x));
+                    }
+                }
+            }
+            Task::Visit(Term::Ref(_) | Term::Num(_)) => {}
+            Task::Visit(Term::Lam(x, e)) => {
+                stack.push(Task::Unbind(*x));
+                stack.push(Task::Visit(e));
+                stack.push(Task::Bind(*x));
+            }
+            Task::Visit(Term::Sup(l, _, _) | Term::Dup(l, _, _, _, _)) if *l > MAX_LABEL => {
+                return Err(GraphBuildError::LabelTooLarge(*l));
+            }
+            Task::Visit(Term::App(e1, e2))
+            | Task::Visit(Term::Sup(_, e1, e2))
+            | Task::Visit(Term::Op2(_, e1, e2)) => {
+                stack.push(Task::Visit(e2));
+                stack.push(Task::Visit(e1));
+            }
+            Task::Visit(Term::Dup(_, a, b, e, cont)) => {
+                if a == b {
+                    return Err(GraphBuildError::BoundTwice(*a));
+                }
+                binders.entry(*a).or_default().push((0, false));
+                binders.entry(*b).or_default().push((0, false));
+                stack.push(Task::Unbind(*b));
+                stack.push(Task::Unbind(*a));
+                stack.push(Task::Visit(cont));
+                stack.push(Task::EnterCont(*a, *b));
+                stack.push(Task::Visit(e));
+            }
+            Task::Visit(Term::Let(x, e, cont)) => {
+                stack.push(Task::Unbind(*x));
+                stack.push(Task::Visit(cont));
+                stack.push(Task::Bind(*x));
+                stack.push(Task::Visit(e));
+            }
+            Task::Bind(x) => {
+                binders.entry(x).or_default().push((0, true));
+            }
+            Task::Unbind(x) => {
+                binders.get_mut(&x).and_then(Vec::pop);
+            }
+            Task::EnterCont(a, b) => {
+                for x in [a, b] {
+                    if let Some((_, usable)) = binders.get_mut(&x).and_then(|b| b.last_mut()) {
+                        *usable = true;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::intern::Intern;
+    use crate::vm::TermGraph;
+
+    #[test]
+    fn test_validate() {
+        for src in [
+            "λx λx x",
+            "λx (f (f x))",
+            "λx dup #0{a b} = λa a; (a (b x))",
+            "λx let x = x; x",
+            "#4294967295{a b}",
+        ] {
+            assert!(validate(&src.parse().unwrap()).is_ok(), "{}", src);
+        }
+        let x = "x".intern();
+        let a = "a".intern();
+        for (src, expected) in [
+            ("λx (x x)", GraphBuildError::UsedTwice(x)),
+            ("dup #0{a b} = x; (a a)", GraphBuildError::UsedTwice(a)),
+            ("let x = y; (x x)", GraphBuildError::UsedTwice(x)),
+            ("dup #0{a a} = y; a", GraphBuildError::BoundTwice(a)),
+            (
+                "dup #0{a b} = a; b",
+                GraphBuildError::UsedInOwnExpression(a),
+            ),
+            ("λx let x = (x x); x", GraphBuildError::UsedTwice(x)),
+            (
+                "dup #4294967296{a b} = y; (a b)",
+                GraphBuildError::LabelTooLarge(1 << 32),
+            ),
+        ] {
+            let term: Term = src.parse().unwrap();
+            assert!(
+                matches!(TermGraph::try_from_term(&term), Err(Error::Build(err)) if err == expected),
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_shadowing_let() {
+        let term: Term = "(λx let x = (f x); (g x) y)".parse().unwrap();
+        let mut term_graph = TermGraph::try_from_term(&term).unwrap();
+        while term_graph.naive_reduce_step().is_some() {}
+        assert_eq!(format!("{}", Term::from(&term_graph)), "(g (f y))");
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use super::{Cursor, NodeKind, TermGraph};
+
+/// What [`TermGraph::visit`] should do after a [`GraphVisitor`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visit {
+    /// Visit the children of this position.
+    Continue,
+    /// Do not visit the children of this position, but carry on with the rest
+    /// of the graph.
+    Skip,
+    /// End the traversal.
+    Stop,
+}
+
+/// Callbacks for [`TermGraph::visit`], one per kind of position.
+///
+/// Each callback receives a cursor at the position and its depth, the number
+/// of steps from the root. All of them default to [`Visit::Continue`].
+pub trait GraphVisitor {
+    /// Positions deeper than this are not visited. Defaults to no limit.
+    fn max_depth(&self) -> Option<usize> {
+        None
+    }
+
+    fn visit_lam(&mut self, _lam: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    fn visit_app(&mut self, _app: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    fn visit_sup(&mut self, _sup: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for every variable, bound or free.
+    fn visit_var(&mut self, _var: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for every reference to a definition.
+    fn visit_ref(&mut self, _reference: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    fn visit_num(&mut self, _num: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    fn visit_op2(&mut self, _op2: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called the first time one of the variables of a dup is visited, with a
+    /// cursor at that variable, right after [`GraphVisitor::visit_var`]. The
+    /// child of a dup is the expression being duplicated.
+    fn visit_dup(&mut self, _var: Cursor<'_>, _depth: usize) -> Visit {
+        Visit::Continue
+    }
+}
+
+impl TermGraph {
+    /// Walks the graph depth first from the primary root, calling `visitor`
+    /// at each position.
+    ///
+    /// Children are visited in order: the body of a lambda, the function and
+    /// then the argument of an application, the two branches of a
+    /// superposition, the two operands of an operation, and the expression of
+    /// a dup. Shared structure is only
+    /// visited once, through the first variable of its dup to be reached.
+    /// Returns `false` if the visitor stopped the traversal early.
+    pub fn visit(&self, visitor: &mut impl GraphVisitor) -> bool {
+        let max_depth = visitor.max_depth().unwrap_or(usize::MAX);
+        let mut visited_dups = HashSet::new();
+        let mut stack = vec![(self.cursor(), 0)];
+        while let Some((cursor, depth)) = stack.pop() {
+            if depth > max_depth {
+                continue;
+            }
+            let mut children = vec![];
+            let mut action = match cursor.kind() {
+                NodeKind::Lam => {
+                    children.extend(cursor.lam_body());
+                    visitor.visit_lam(cursor, depth)
+                }
+                NodeKind::App => {
+                    children.extend(cursor.app_fun());
+                    children.extend(cursor.app_arg());
+                    visitor.visit_app(cursor, depth)
+                }
+                NodeKind::Sup => {
+                    children.extend(cursor.sup_left());
+                    children.extend(cursor.sup_right());
+                    visitor.visit_sup(cursor, depth)
+                }
+                NodeKind::LamVar | NodeKind::DupAVar | NodeKind::DupBVar | NodeKind::FreeVar => {
+                    visitor.visit_var(cursor, depth)
+                }
+                NodeKind::Ref => visitor.visit_ref(cursor, depth),
+                NodeKind::Num => visitor.visit_num(cursor, depth),
+                NodeKind::Op2 => {
+                    children.extend(cursor.op_left());
+                    children.extend(cursor.op_right());
+                    visitor.visit_op2(cursor, depth)
+                }
+            };
+            if action == Visit::Continue
+                && matches!(cursor.kind(), NodeKind::DupAVar | NodeKind::DupBVar)
+                && visited_dups.insert(cursor.node_id())
+            {
+                children.extend(cursor.dup_expr());
+                action = visitor.visit_dup(cursor, depth);
+            }
+            match action {
+                Visit::Continue => {
+                    stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)))
+                }
+                Visit::Skip => {}
+                Visit::Stop => return false,
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Term;
+
+    #[derive(Default)]
+    struct SizeByDepth {
+        sizes: Vec<usize>,
+        max_depth: Option<usize>,
+    }
+
+    impl SizeByDepth {
+        fn count(&mut self, depth: usize) -> Visit {
+            if self.sizes.len() <= depth {
+                self.sizes.resize(depth + 1, 0);
+            }
+            self.sizes[depth] += 1;
+            Visit::Continue
+        }
+    }
+
+    impl GraphVisitor for SizeByDepth {
+        fn max_depth(&self) -> Option<usize> {
+            self.max_depth
+        }
+
+        fn visit_lam(&mut self, _: Cursor<'_>, depth: usize) -> Visit {
+            self.count(depth)
+        }
+
+        fn visit_app(&mut self, _: Cursor<'_>, depth: usize) -> Visit {
+            self.count(depth)
+        }
+
+        fn visit_sup(&mut self, _: Cursor<'_>, depth: usize) -> Visit {
+            self.count(depth)
+        }
+
+        fn visit_var(&mut self, _: Cursor<'_>, depth: usize) -> Visit {
+            self.count(depth)
+        }
+    }
+
+    #[test]
+    fn test_visit_size_by_depth() {
+        let term: Term = "λx dup #0{a b} = λy y; (a (b x))".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let mut visitor = SizeByDepth::default();
+        assert!(term_graph.visit(&mut visitor));
+        // The shared `λy y` is visited once, under `a`.
+        assert_eq!(visitor.sizes, [1, 1, 2, 3, 1]);
+        let mut visitor = SizeByDepth {
+            max_depth: Some(2),
+            ..SizeByDepth::default()
+        };
+        assert!(term_graph.visit(&mut visitor));
+        assert_eq!(visitor.sizes, [1, 1, 2]);
+    }
+
+    #[test]
+    fn test_visit_skip_and_stop() {
+        struct FindSup {
+            found: Option<usize>,
+            vars: usize,
+        }
+
+        impl GraphVisitor for FindSup {
+            fn visit_lam(&mut self, _: Cursor<'_>, _: usize) -> Visit {
+                Visit::Skip
+            }
+
+            fn visit_sup(&mut self, _: Cursor<'_>, depth: usize) -> Visit {
+                self.found = Some(depth);
+                Visit::Stop
+            }
+
+            fn visit_var(&mut self, _: Cursor<'_>, _: usize) -> Visit {
+                self.vars += 1;
+                Visit::Continue
+            }
+        }
+
+        let term: Term = "((λx #0{x w}) (f #1{y z}))".parse().unwrap();
+        let term_graph = TermGraph::from(&term);
+        let mut visitor = FindSup {
+            found: None,
+            vars: 0,
+        };
+        assert!(!term_graph.visit(&mut visitor));
+        // The sup under the lambda is skipped.
+        assert_eq!(visitor.found, Some(2));
+        assert_eq!(visitor.vars, 1);
+    }
+}